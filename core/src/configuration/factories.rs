@@ -1,20 +1,45 @@
+use crate::data_providers::audit::JsonlAuditLog;
+use crate::data_providers::auth::AuthChain;
 use crate::data_providers::bus::LocalBus;
 use crate::data_providers::cipher::Chacha20Poly1305Cipher;
 use crate::data_providers::config::{FsConfigLoader, FsConfigResolver};
 use crate::data_providers::extractor::ExtractorFactoryImpl;
 use crate::data_providers::fs::LocalFs;
+use crate::data_providers::links::{HmacSigner, JsonLinkStore};
+use crate::data_providers::metrics::PrometheusMetrics;
+use crate::data_providers::ocr::JsonOverrideStore;
+use crate::data_providers::pages::DocumentPages;
+use crate::data_providers::quota::FsUsageMeter;
 use crate::data_providers::receiver::FsEventReceiver;
+use crate::data_providers::scanner::EdgeScanner;
+use crate::data_providers::sharing::JsonShareStore;
 use crate::data_providers::state::TantivyState;
 use crate::data_providers::thumbnailer::ThumbnailerFactoryImpl;
-use crate::result::{BusErr, EventReceiverErr, SetupErr, StateErr};
+use crate::data_providers::unpacker::SafeUnpacker;
+use crate::data_providers::users::JsonAccountStore;
+use crate::result::{
+    AuditErr, BusErr, EventReceiverErr, LinkErr, MetricsErr, OcrErr, SetupErr, SharingErr,
+    StateErr, UsersErr,
+};
+use crate::use_cases::audit::AuditLog;
+use crate::use_cases::auth::Auth;
 use crate::use_cases::bus::EventBus;
 use crate::use_cases::cipher::Cipher;
 use crate::use_cases::config::{CfgLoader, CfgResolver, Config};
 use crate::use_cases::fs::Fs;
+use crate::use_cases::links::{Links, Signer};
+use crate::use_cases::metrics::Metrics;
+use crate::use_cases::ocr::LanguageOverrides;
+use crate::use_cases::pages::Pages;
+use crate::use_cases::quota::Meter;
 use crate::use_cases::receiver::EventRecv;
+use crate::use_cases::scanner::Scanner;
 use crate::use_cases::services::extractor::ExtractorCreator;
 use crate::use_cases::services::thumbnailer::ThumbnailerCreator;
+use crate::use_cases::sharing::Sharing;
 use crate::use_cases::state::State;
+use crate::use_cases::unpacker::Unpacker;
+use crate::use_cases::users::Accounts;
 
 use std::sync::Arc;
 
@@ -27,20 +52,45 @@ pub struct Runtime {
     pub extractor_factory: ExtractorCreator,
     pub state: State,
     pub cipher: Cipher,
+    pub metrics: Metrics,
+    pub auth: Auth,
+    pub sharing: Sharing,
+    pub links: Links,
+    pub link_signer: Signer,
+    pub audit: AuditLog,
+    pub meter: Meter,
+    pub accounts: Accounts,
+    pub ocr_overrides: LanguageOverrides,
+    pub scanner: Scanner,
+    pub unpacker: Unpacker,
+    pub pages: Pages,
 }
 
 impl Runtime {
     pub fn new<C: AsRef<Config>>(cfg: C) -> Result<Self, SetupErr> {
         let cfg = cfg.as_ref();
+        let ocr_overrides = ocr_overrides(cfg)?;
         Ok(Self {
             cfg: cfg.clone(),
             bus: event_bus()?,
             fs: fs(),
             event_watcher: event_watcher(cfg)?,
-            thumbnailer_factory: thumbnailer_factory(),
-            extractor_factory: extractor_factory(),
+            thumbnailer_factory: thumbnailer_factory(cfg),
+            extractor_factory: extractor_factory(cfg, ocr_overrides.clone()),
             state: state(cfg)?,
             cipher: cipher(),
+            metrics: metrics(cfg)?,
+            auth: authenticator(cfg),
+            sharing: sharing(cfg)?,
+            links: links(cfg)?,
+            link_signer: link_signer(cfg)?,
+            audit: audit_log(cfg)?,
+            meter: usage_meter(cfg),
+            accounts: accounts(cfg)?,
+            ocr_overrides,
+            scanner: scanner(cfg),
+            unpacker: unpacker(cfg),
+            pages: pages(cfg),
         })
     }
 }
@@ -57,12 +107,12 @@ pub fn event_bus() -> Result<EventBus, BusErr> {
     Ok(Arc::new(LocalBus::new()?))
 }
 
-pub fn thumbnailer_factory() -> ThumbnailerCreator {
-    Box::new(ThumbnailerFactoryImpl)
+pub fn thumbnailer_factory(cfg: &Config) -> ThumbnailerCreator {
+    Box::new(ThumbnailerFactoryImpl::new(cfg))
 }
 
-pub fn extractor_factory() -> ExtractorCreator {
-    Box::new(ExtractorFactoryImpl)
+pub fn extractor_factory(cfg: &Config, overrides: LanguageOverrides) -> ExtractorCreator {
+    Box::new(ExtractorFactoryImpl::new(cfg, overrides))
 }
 
 pub fn state<C: AsRef<Config>>(cfg: &C) -> Result<State, StateErr> {
@@ -78,7 +128,55 @@ pub fn cipher() -> Cipher {
     Chacha20Poly1305Cipher::create()
 }
 
+pub fn metrics(cfg: &Config) -> Result<Metrics, MetricsErr> {
+    PrometheusMetrics::create(cfg)
+}
+
 pub fn event_watcher(cfg: &Config) -> Result<EventRecv, EventReceiverErr> {
     let watched_dir = cfg.watched_dir.clone();
-    Ok(Box::new(FsEventReceiver::new(watched_dir)?))
+    Ok(Box::new(FsEventReceiver::new(watched_dir, &cfg.watcher)?))
+}
+
+pub fn authenticator(cfg: &Config) -> Auth {
+    AuthChain::create(&cfg.auth)
+}
+
+pub fn sharing(cfg: &Config) -> Result<Sharing, SharingErr> {
+    JsonShareStore::create(cfg)
+}
+
+pub fn links(cfg: &Config) -> Result<Links, LinkErr> {
+    JsonLinkStore::create(cfg)
+}
+
+pub fn link_signer(cfg: &Config) -> Result<Signer, LinkErr> {
+    HmacSigner::create(cfg)
+}
+
+pub fn audit_log(cfg: &Config) -> Result<AuditLog, AuditErr> {
+    JsonlAuditLog::create(cfg)
+}
+
+pub fn usage_meter(cfg: &Config) -> Meter {
+    FsUsageMeter::create(cfg)
+}
+
+pub fn accounts(cfg: &Config) -> Result<Accounts, UsersErr> {
+    JsonAccountStore::create(cfg)
+}
+
+pub fn ocr_overrides(cfg: &Config) -> Result<LanguageOverrides, OcrErr> {
+    JsonOverrideStore::create(cfg)
+}
+
+pub fn scanner(cfg: &Config) -> Scanner {
+    EdgeScanner::create(cfg)
+}
+
+pub fn unpacker(cfg: &Config) -> Unpacker {
+    SafeUnpacker::create(cfg)
+}
+
+pub fn pages(cfg: &Config) -> Pages {
+    DocumentPages::create(cfg)
 }
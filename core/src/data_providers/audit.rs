@@ -0,0 +1,313 @@
+//! This is concrete implementation of [`crate::use_cases::audit`] abstractions.
+//!
+//! Records are appended to a file in [`Config::data_dir`], one JSON object per line. The file is
+//! never rewritten, only a broken last line, left by a crash while appending, is cut off when the
+//! log is opened. Offsets of the records of each user are kept in memory, so reading them doesn't
+//! require parsing the whole log.
+use crate::entities::user::User;
+use crate::result::AuditErr;
+use crate::use_cases::audit::{AuditEntry, AuditLog, AuditRecord, AuditStore, GENESIS_HASH};
+use crate::use_cases::config::Config;
+
+use std::collections::HashMap;
+use std::fs::{create_dir_all, File, OpenOptions};
+use std::io::{BufRead, BufReader, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tracing::{debug, error, instrument, warn};
+
+pub const AUDIT_FILE: &str = "audit.jsonl";
+
+pub struct JsonlAuditLog {
+    path: PathBuf,
+    tail: Mutex<Tail>,
+    /// Sequence number of the record which was torn and cut off when the log was opened.
+    torn: Option<u64>,
+}
+
+impl JsonlAuditLog {
+    pub fn create(cfg: &Config) -> Result<AuditLog, AuditErr> {
+        create_dir_all(&cfg.data_dir)?;
+        let path = cfg.data_dir.join(AUDIT_FILE);
+        let Loaded { records, len, torn } = load(&path)?;
+        debug!(
+            "loaded {} audit records from '{}'",
+            records.len(),
+            path.display()
+        );
+        let mut tail = Tail {
+            seq: 0,
+            hash: GENESIS_HASH.into(),
+            len,
+            offsets: HashMap::new(),
+        };
+        for (offset, record) in &records {
+            tail.add(*offset, record);
+        }
+        let torn = torn.then_some(tail.seq);
+        if let Some(seq) = torn {
+            warn!("cutting off torn audit record {} at byte {}", seq, len);
+            OpenOptions::new().write(true).open(&path)?.set_len(len)?;
+        }
+        let log = Self {
+            path,
+            tail: Mutex::new(tail),
+            torn,
+        };
+        // NOTE: broken chain is reported, but it shouldn't stop recording new actions
+        if let Err(e) = log.verify() {
+            error!("audit log is not intact: '{}'", e);
+        }
+        Ok(Arc::new(log))
+    }
+
+    fn records(&self) -> Result<Vec<AuditRecord>, AuditErr> {
+        Ok(load(&self.path)?
+            .records
+            .into_iter()
+            .map(|(_, record)| record)
+            .collect())
+    }
+}
+
+/// End of the log, where the next record is appended.
+struct Tail {
+    /// Sequence number of the next record.
+    seq: u64,
+    /// Hash of the last record.
+    hash: String,
+    /// Length of the file, in bytes.
+    len: u64,
+    /// Offsets of the records made by each user or concerning their documents.
+    offsets: HashMap<User, Vec<u64>>,
+}
+
+impl Tail {
+    fn add(&mut self, offset: u64, record: &AuditRecord) {
+        for user in record.entry.users() {
+            self.offsets.entry(user.clone()).or_default().push(offset);
+        }
+        self.seq = record.seq + 1;
+        self.hash = record.hash.clone();
+    }
+}
+
+/// Content of the log file.
+#[derive(Default)]
+struct Loaded {
+    /// Records with their offsets in the file.
+    records: Vec<(u64, AuditRecord)>,
+    /// Length, in bytes, of the complete records.
+    len: u64,
+    /// Tells if the last line is a record which wasn't fully written.
+    torn: bool,
+}
+
+/// Reads all records. The last line is skipped when it can't be parsed and doesn't end with the
+/// new line, as it's a record which wasn't fully written. A broken line elsewhere is an error.
+fn load(path: &Path) -> Result<Loaded, AuditErr> {
+    let mut loaded = Loaded::default();
+    if !path.exists() {
+        return Ok(loaded);
+    }
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        let read = reader.read_until(b'\n', &mut line)?;
+        if read == 0 {
+            break;
+        }
+        if !line.iter().all(u8::is_ascii_whitespace) {
+            match serde_json::from_slice(&line) {
+                Ok(record) => loaded.records.push((loaded.len, record)),
+                Err(e) if line.ends_with(b"\n") => return Err(e.into()),
+                Err(_) => {
+                    loaded.torn = true;
+                    break;
+                }
+            }
+        }
+        loaded.len += read as u64;
+    }
+    Ok(loaded)
+}
+
+impl AuditStore for JsonlAuditLog {
+    #[instrument(skip(self))]
+    fn append(&self, entry: AuditEntry) -> Result<AuditRecord, AuditErr> {
+        let mut tail = self.tail.lock().expect("poisoned mutex");
+        let record = AuditRecord::new(tail.seq, entry, tail.hash.clone())?;
+        let mut line = serde_json::to_vec(&record)?;
+        line.push(b'\n');
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(&line)?;
+        file.sync_data()?;
+        let offset = tail.len;
+        tail.add(offset, &record);
+        tail.len += line.len() as u64;
+        Ok(record)
+    }
+
+    fn records_of(&self, user: &User) -> Result<Vec<AuditRecord>, AuditErr> {
+        let offsets = self
+            .tail
+            .lock()
+            .expect("poisoned mutex")
+            .offsets
+            .get(user)
+            .cloned()
+            .unwrap_or_default();
+        // NOTE: records are never rewritten, so they can be read while new ones are appended
+        let mut reader = BufReader::new(File::open(&self.path)?);
+        let mut line = String::new();
+        let mut records = Vec::with_capacity(offsets.len());
+        for offset in offsets {
+            reader.seek(SeekFrom::Start(offset))?;
+            line.clear();
+            reader.read_line(&mut line)?;
+            records.push(serde_json::from_str(&line)?);
+        }
+        Ok(records)
+    }
+
+    fn verify(&self) -> Result<(), AuditErr> {
+        // NOTE: appending is blocked, so the last record is not written while checking
+        let _tail = self.tail.lock().expect("poisoned mutex");
+        if let Some(seq) = self.torn {
+            return Err(AuditErr::Torn(seq));
+        }
+        let records = self.records()?;
+        let mut prev = None;
+        for record in &records {
+            if !record.follows(prev)? {
+                return Err(AuditErr::Tampered(record.seq));
+            }
+            prev = Some(record);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::testingtools::TestConfig;
+    use crate::use_cases::audit::Action;
+
+    use anyhow::Result;
+    use claim::{assert_matches, assert_ok};
+    use std::fs;
+
+    fn entry(user: &str, action: Action) -> AuditEntry {
+        AuditEntry::new(&User::new(user), action)
+    }
+
+    #[test]
+    fn chain_is_continued_after_restart() -> Result<()> {
+        // given
+        let cfg = TestConfig::new()?;
+        let log = JsonlAuditLog::create(cfg.as_ref())?;
+        let first = log.append(entry("me@email.com", Action::Upload))?;
+
+        // when
+        let log = JsonlAuditLog::create(cfg.as_ref())?;
+        let second = log.append(entry("me@email.com", Action::Download))?;
+
+        // then
+        assert_eq!(second.seq, 1);
+        assert_eq!(second.prev_hash, first.hash);
+        assert_ok!(log.verify());
+
+        Ok(())
+    }
+
+    #[test]
+    fn modified_record_is_detected() -> Result<()> {
+        // given
+        let cfg = TestConfig::new()?;
+        let log = JsonlAuditLog::create(cfg.as_ref())?;
+        log.append(entry("me@email.com", Action::Upload))?;
+        log.append(entry("me@email.com", Action::Delete).document("a.pdf"))?;
+        log.append(entry("me@email.com", Action::Search))?;
+        let path = cfg.as_ref().data_dir.join(AUDIT_FILE);
+
+        // when
+        let audit = fs::read_to_string(&path)?;
+        fs::write(&path, audit.replace("a.pdf", "b.pdf"))?;
+
+        // then
+        assert_matches!(log.verify(), Err(AuditErr::Tampered(1)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn removed_record_is_detected() -> Result<()> {
+        // given
+        let cfg = TestConfig::new()?;
+        let log = JsonlAuditLog::create(cfg.as_ref())?;
+        log.append(entry("me@email.com", Action::Upload))?;
+        log.append(entry("me@email.com", Action::Delete))?;
+        log.append(entry("me@email.com", Action::Search))?;
+        let path = cfg.as_ref().data_dir.join(AUDIT_FILE);
+
+        // when
+        let audit = fs::read_to_string(&path)?;
+        let without_delete: Vec<&str> = audit.lines().filter(|l| !l.contains("delete")).collect();
+        fs::write(&path, without_delete.join("\n"))?;
+
+        // then
+        assert_matches!(log.verify(), Err(AuditErr::Tampered(2)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn torn_last_record_is_cut_off_and_reported() -> Result<()> {
+        // given
+        let cfg = TestConfig::new()?;
+        let log = JsonlAuditLog::create(cfg.as_ref())?;
+        log.append(entry("me@email.com", Action::Upload))?;
+        let path = cfg.as_ref().data_dir.join(AUDIT_FILE);
+        let mut file = OpenOptions::new().append(true).open(&path)?;
+        file.write_all(br#"{"seq":1,"at":16"#)?;
+
+        // when
+        let log = JsonlAuditLog::create(cfg.as_ref())?;
+        let appended = log.append(entry("me@email.com", Action::Download))?;
+
+        // then
+        assert_matches!(log.verify(), Err(AuditErr::Torn(1)));
+        assert_eq!(appended.seq, 1);
+        assert_eq!(log.records_of(&User::new("me@email.com"))?.len(), 2);
+        assert_ok!(JsonlAuditLog::create(cfg.as_ref())?.verify());
+
+        Ok(())
+    }
+
+    #[test]
+    fn user_gets_only_records_concerning_them() -> Result<()> {
+        // given
+        let cfg = TestConfig::new()?;
+        let log = JsonlAuditLog::create(cfg.as_ref())?;
+        let me = User::new("me@email.com");
+        log.append(entry("me@email.com", Action::Upload))?;
+        log.append(entry("other@email.com", Action::Upload))?;
+        log.append(entry("other@email.com", Action::Download).owner(&me))?;
+
+        // when
+        let records = log.records_of(&me)?;
+
+        // then
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].entry.action, Action::Upload);
+        assert_eq!(records[1].entry.user, Some(User::new("other@email.com")));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,122 @@
+//! Users with passwords kept in the configuration, for setups without an identity provider.
+use crate::entities::user::User;
+use crate::result::AuthErr;
+use crate::use_cases::auth::{Authenticator, Credentials};
+use crate::use_cases::config::LocalConfig;
+
+use argon2::password_hash::PasswordHash;
+use argon2::{Argon2, PasswordVerifier};
+use tracing::{debug, instrument, warn};
+
+pub struct LocalUsers {
+    cfg: LocalConfig,
+}
+
+impl LocalUsers {
+    pub fn new(cfg: LocalConfig) -> Self {
+        Self { cfg }
+    }
+}
+
+#[rocket::async_trait]
+impl Authenticator for LocalUsers {
+    #[instrument(skip(self, credentials))]
+    async fn authenticate(&self, credentials: &Credentials) -> Result<Option<User>, AuthErr> {
+        let Credentials::Basic { username, password } = credentials else {
+            return Ok(None);
+        };
+        let Some(user) = self.cfg.users.iter().find(|u| &u.email == username) else {
+            debug!("no local user '{}'", username);
+            return Ok(None);
+        };
+        let hash = PasswordHash::new(&user.password_hash)?;
+        // NOTE: Argon2 parameters are read from the hash, so defaults don't matter here
+        if Argon2::default()
+            .verify_password(password.as_bytes(), &hash)
+            .is_err()
+        {
+            warn!("invalid password of '{}'", username);
+            return Err(AuthErr::InvalidCredentials);
+        }
+        Ok(Some(User::new(&user.email)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::use_cases::config::LocalUser;
+
+    use anyhow::Result;
+    use argon2::password_hash::rand_core::OsRng;
+    use argon2::password_hash::{PasswordHasher, SaltString};
+    use claim::assert_matches;
+
+    fn local_users() -> Result<LocalUsers> {
+        let salt = SaltString::generate(&mut OsRng);
+        let password_hash = Argon2::default()
+            .hash_password(b"secret", &salt)
+            .map_err(|e| anyhow::anyhow!("{e}"))?
+            .to_string();
+        Ok(LocalUsers::new(LocalConfig {
+            users: vec![LocalUser {
+                email: "local@email.com".into(),
+                password_hash,
+            }],
+        }))
+    }
+
+    fn basic(username: &str, password: &str) -> Credentials {
+        Credentials::Basic {
+            username: username.into(),
+            password: password.into(),
+        }
+    }
+
+    #[rocket::async_test]
+    async fn correct_password_returns_user() -> Result<()> {
+        // given
+        let auth = local_users()?;
+
+        // when
+        let user = auth
+            .authenticate(&basic("local@email.com", "secret"))
+            .await?;
+
+        // then
+        assert_eq!(user, Some(User::new("local@email.com")));
+
+        Ok(())
+    }
+
+    #[rocket::async_test]
+    async fn wrong_password_is_rejected() -> Result<()> {
+        // given
+        let auth = local_users()?;
+
+        // when
+        let res = auth.authenticate(&basic("local@email.com", "wrong")).await;
+
+        // then
+        assert_matches!(res, Err(AuthErr::InvalidCredentials));
+
+        Ok(())
+    }
+
+    #[rocket::async_test]
+    async fn unknown_user_is_not_handled() -> Result<()> {
+        // given
+        let auth = local_users()?;
+
+        // when
+        let user = auth
+            .authenticate(&basic("other@email.com", "secret"))
+            .await?;
+
+        // then
+        assert_eq!(user, None);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,162 @@
+//! This is concrete implementation of [`crate::use_cases::auth`] abstractions.
+//!
+//! Every provider configured in [`crate::use_cases::config::Config::auth`] is represented by
+//! separate [`Authenticator`]. They are combined using [`AuthChain`].
+use crate::data_providers::auth::local::LocalUsers;
+use crate::data_providers::auth::oidc::OidcProvider;
+use crate::data_providers::auth::tokens::ApiTokens;
+use crate::entities::user::User;
+use crate::result::AuthErr;
+use crate::use_cases::auth::{Auth, Authenticator, Credentials};
+use crate::use_cases::config::AuthConfig;
+
+use std::sync::Arc;
+use tracing::{debug, instrument, warn};
+
+pub mod local;
+pub mod oidc;
+pub mod tokens;
+
+/// Asks configured providers, one by one, until one of them handles the credentials.
+///
+/// Password of unknown user is rejected with the same error as the wrong password, so the
+/// existing users can't be discovered.
+pub struct AuthChain {
+    providers: Vec<Auth>,
+}
+
+impl AuthChain {
+    pub fn create(cfg: &[AuthConfig]) -> Auth {
+        let providers = cfg
+            .iter()
+            .map(|provider| -> Auth {
+                match provider {
+                    AuthConfig::Oidc(cfg) => Arc::new(OidcProvider::new(cfg.clone())),
+                    AuthConfig::Tokens(cfg) => Arc::new(ApiTokens::new(cfg.clone())),
+                    AuthConfig::Local(cfg) => Arc::new(LocalUsers::new(cfg.clone())),
+                }
+            })
+            .collect();
+        Arc::new(Self { providers })
+    }
+}
+
+#[rocket::async_trait]
+impl Authenticator for AuthChain {
+    #[instrument(skip(self, credentials))]
+    async fn authenticate(&self, credentials: &Credentials) -> Result<Option<User>, AuthErr> {
+        if self.providers.is_empty() {
+            return Err(AuthErr::NotConfigured);
+        }
+        for provider in &self.providers {
+            if let Some(user) = provider.authenticate(credentials).await? {
+                debug!("authenticated '{}'", user);
+                return Ok(Some(user));
+            }
+        }
+        match credentials {
+            Credentials::Basic { username, .. } => {
+                warn!("no provider knows user '{}'", username);
+                Err(AuthErr::InvalidCredentials)
+            }
+            Credentials::Token(_) => Err(AuthErr::UnknownCredentials),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::use_cases::config::{ApiToken, LocalConfig, LocalUser, TokensConfig};
+
+    use anyhow::Result;
+    use argon2::password_hash::rand_core::OsRng;
+    use argon2::password_hash::{PasswordHasher, SaltString};
+    use argon2::Argon2;
+    use claim::assert_matches;
+    use sha2::{Digest, Sha256};
+
+    fn tokens(email: &str, token: &str) -> AuthConfig {
+        AuthConfig::Tokens(TokensConfig {
+            tokens: vec![ApiToken {
+                email: email.into(),
+                sha256: format!("{:x}", Sha256::digest(token)),
+            }],
+        })
+    }
+
+    #[rocket::async_test]
+    async fn next_provider_is_used_when_credentials_are_not_handled() -> Result<()> {
+        // given
+        let auth = AuthChain::create(&[
+            tokens("first@email.com", "first-token"),
+            tokens("second@email.com", "second-token"),
+        ]);
+        let credentials = Credentials::Token("second-token".into());
+
+        // when
+        let user = auth.authenticate(&credentials).await?;
+
+        // then
+        assert_eq!(user, Some(User::new("second@email.com")));
+
+        Ok(())
+    }
+
+    #[rocket::async_test]
+    async fn credentials_not_handled_by_any_provider_are_rejected() {
+        // given
+        let auth = AuthChain::create(&[tokens("first@email.com", "first-token")]);
+        let credentials = Credentials::Token("unknown-token".into());
+
+        // when
+        let res = auth.authenticate(&credentials).await;
+
+        // then
+        assert_matches!(res, Err(AuthErr::UnknownCredentials));
+    }
+
+    #[rocket::async_test]
+    async fn unknown_user_is_rejected_like_wrong_password() -> Result<()> {
+        // given
+        let salt = SaltString::generate(&mut OsRng);
+        let password_hash = Argon2::default()
+            .hash_password(b"secret", &salt)
+            .map_err(|e| anyhow::anyhow!("{e}"))?
+            .to_string();
+        let auth = AuthChain::create(&[AuthConfig::Local(LocalConfig {
+            users: vec![LocalUser {
+                email: "local@email.com".into(),
+                password_hash,
+            }],
+        })]);
+        let basic = |username: &str, password: &str| Credentials::Basic {
+            username: username.into(),
+            password: password.into(),
+        };
+
+        // when
+        let unknown = auth.authenticate(&basic("other@email.com", "secret")).await;
+        let wrong = auth.authenticate(&basic("local@email.com", "wrong")).await;
+
+        // then
+        assert_matches!(unknown, Err(AuthErr::InvalidCredentials));
+        assert_matches!(wrong, Err(AuthErr::InvalidCredentials));
+
+        Ok(())
+    }
+
+    #[rocket::async_test]
+    async fn no_providers_means_authentication_is_not_configured() {
+        // given
+        let auth = AuthChain::create(&[]);
+        let credentials = Credentials::Token("some-token".into());
+
+        // when
+        let res = auth.authenticate(&credentials).await;
+
+        // then
+        assert_matches!(res, Err(AuthErr::NotConfigured));
+    }
+}
@@ -0,0 +1,475 @@
+//! ID tokens issued by an OpenID Connect provider, e.g. Google.
+use crate::entities::user::User;
+use crate::result::AuthErr;
+use crate::use_cases::auth::{Authenticator, Credentials};
+use crate::use_cases::config::OidcConfig;
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD as b64url;
+use base64::Engine;
+use jsonwebtoken::jwk::{AlgorithmParameters, Jwk, JwkSet};
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use rocket::tokio::sync::RwLock;
+use serde::Deserialize;
+use std::time::{Duration, Instant};
+use tracing::{debug, instrument, warn};
+
+/// Keys are fetched again when the token is signed with unknown key, but not more often than this.
+const KEYS_MIN_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Algorithms of the keys accepted from the provider. Others, e.g. HMAC, are rejected, so the
+/// token can't choose how it's verified.
+const ALLOWED_ALGORITHMS: [Algorithm; 2] = [Algorithm::RS256, Algorithm::ES256];
+
+pub struct OidcProvider {
+    cfg: OidcConfig,
+    client: reqwest::Client,
+    keys: RwLock<Option<CachedKeys>>,
+}
+
+impl OidcProvider {
+    pub fn new(cfg: OidcConfig) -> Self {
+        Self {
+            cfg,
+            client: reqwest::Client::new(),
+            keys: RwLock::new(None),
+        }
+    }
+
+    /// Returns keys of the provider, fetching them when they are older than `max_age`.
+    ///
+    /// When the keys can't be fetched, previously fetched keys are used.
+    async fn keys(&self, max_age: Duration) -> Result<JwkSet, AuthErr> {
+        if let Some(keys) = fresh(&self.keys.read().await, max_age) {
+            return Ok(keys);
+        }
+        let mut cache = self.keys.write().await;
+        // NOTE: keys could be fetched by other request while waiting for the lock
+        if let Some(keys) = fresh(&cache, max_age) {
+            return Ok(keys);
+        }
+        match self.fetch_keys().await {
+            Ok(keys) => {
+                *cache = Some(CachedKeys {
+                    keys: keys.clone(),
+                    fetched: Instant::now(),
+                });
+                Ok(keys)
+            }
+            Err(e) => match cache.as_ref() {
+                Some(cached) => {
+                    warn!("failed to refresh keys, using previous ones: '{}'", e);
+                    Ok(cached.keys.clone())
+                }
+                None => Err(e),
+            },
+        }
+    }
+
+    async fn fetch_keys(&self) -> Result<JwkSet, AuthErr> {
+        debug!("fetching keys from '{}'", self.cfg.jwks_url);
+        Ok(self
+            .client
+            .get(&self.cfg.jwks_url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?)
+    }
+
+    /// Finds the key used to sign the token. Keys are fetched again when the key is unknown,
+    /// because the provider could rotate them before the cached ones expired.
+    async fn key(&self, kid: &str) -> Result<Jwk, AuthErr> {
+        let ttl = self.cfg.jwks_cache_ttl();
+        if let Some(jwk) = self.keys(ttl).await?.find(kid) {
+            return Ok(jwk.clone());
+        }
+        debug!("key '{}' not found in cached keys", kid);
+        let keys = self.keys(ttl.min(KEYS_MIN_REFRESH_INTERVAL)).await?;
+        keys.find(kid).cloned().ok_or_else(|| {
+            warn!("token signed with unknown key '{}'", kid);
+            AuthErr::UnknownKey(kid.into())
+        })
+    }
+
+    fn validation(&self, alg: Algorithm, audience: &str) -> Validation {
+        let mut validation = Validation::new(alg);
+        validation.set_issuer(&self.issuers());
+        validation.set_audience(&[audience]);
+        validation.set_required_spec_claims(&["exp", "iss", "aud"]);
+        validation.validate_nbf = true;
+        validation.leeway = self.cfg.clock_skew_secs;
+        validation
+    }
+
+    /// Some providers (e.g. Google) issue tokens with the issuer with or without the scheme.
+    fn issuers(&self) -> [&str; 2] {
+        let issuer = self.cfg.issuer.as_str();
+        [issuer, issuer.trim_start_matches("https://")]
+    }
+
+    fn check_domain(&self, email: &str) -> Result<(), AuthErr> {
+        if self.cfg.allowed_domains.is_empty() {
+            return Ok(());
+        }
+        let domain = email.rsplit_once('@').map(|(_, domain)| domain);
+        match domain {
+            Some(domain) if self.is_allowed(domain) => Ok(()),
+            _ => {
+                warn!("email '{}' is outside of allowed domains", email);
+                Err(AuthErr::DomainNotAllowed(email.into()))
+            }
+        }
+    }
+
+    fn is_allowed(&self, domain: &str) -> bool {
+        self.cfg
+            .allowed_domains
+            .iter()
+            .any(|allowed| allowed.eq_ignore_ascii_case(domain))
+    }
+}
+
+#[rocket::async_trait]
+impl Authenticator for OidcProvider {
+    #[instrument(skip(self, credentials))]
+    async fn authenticate(&self, credentials: &Credentials) -> Result<Option<User>, AuthErr> {
+        let Credentials::Token(token) = credentials else {
+            return Ok(None);
+        };
+        // NOTE: the issuer is read before verification only to pick the right provider, the
+        // signature and the claims are verified below
+        let issuer = unverified_issuer(token).unwrap_or_default();
+        if !self.issuers().contains(&issuer.as_str()) {
+            debug!("token not issued by '{}'", self.cfg.issuer);
+            return Ok(None);
+        }
+        // NOTE: the application doesn't start without the audience, see `Config::check_auth`
+        let audience = self.cfg.audience.as_deref().unwrap_or_default();
+        let header = decode_header(token)?;
+        if !ALLOWED_ALGORITHMS.contains(&header.alg) {
+            warn!("token signed with not allowed algorithm '{:?}'", header.alg);
+            return Err(AuthErr::AlgorithmNotAllowed(format!("{:?}", header.alg)));
+        }
+        let kid = header.kid.clone().unwrap_or_default();
+        let jwk = self.key(&kid).await?;
+        // NOTE: the algorithm comes from the key, the header of the token only has to agree
+        if key_algorithm(&jwk) != Some(header.alg) {
+            warn!("algorithm '{:?}' doesn't match key '{}'", header.alg, kid);
+            return Err(AuthErr::AlgorithmNotAllowed(format!("{:?}", header.alg)));
+        }
+        let key = DecodingKey::from_jwk(&jwk)?;
+        let validation = self.validation(header.alg, audience);
+        let claims = decode::<Claims>(token, &key, &validation)?.claims;
+        let email = claims.email.ok_or(AuthErr::MissingEmail)?;
+        self.check_domain(&email)?;
+        Ok(Some(User::new(email)))
+    }
+}
+
+/// Algorithm declared by the key, or implied by its type when it's not declared. `None` when the
+/// algorithm is not allowed.
+fn key_algorithm(jwk: &Jwk) -> Option<Algorithm> {
+    let implied = match jwk.algorithm {
+        AlgorithmParameters::RSA(_) => Some(Algorithm::RS256),
+        AlgorithmParameters::EllipticCurve(_) => Some(Algorithm::ES256),
+        AlgorithmParameters::OctetKey(_) | AlgorithmParameters::OctetKeyPair(_) => None,
+    };
+    jwk.common
+        .algorithm
+        .or(implied)
+        .filter(|alg| ALLOWED_ALGORITHMS.contains(alg))
+}
+
+fn fresh(cache: &Option<CachedKeys>, max_age: Duration) -> Option<JwkSet> {
+    let cached = cache.as_ref()?;
+    (cached.fetched.elapsed() < max_age).then(|| cached.keys.clone())
+}
+
+struct CachedKeys {
+    keys: JwkSet,
+    fetched: Instant,
+}
+
+#[derive(Debug, Deserialize)]
+struct Claims {
+    email: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct UnverifiedClaims {
+    iss: Option<String>,
+}
+
+fn unverified_issuer(token: &str) -> Option<String> {
+    let payload = token.split('.').nth(1)?;
+    let payload = b64url.decode(payload).ok()?;
+    serde_json::from_slice::<UnverifiedClaims>(&payload)
+        .ok()?
+        .iss
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::testingtools::auth::{sign, sign_with_kid, JwksStub, TEST_ISSUER};
+
+    use anyhow::Result;
+    use claim::assert_matches;
+    use serde_json::json;
+
+    fn provider(jwks: &JwksStub) -> OidcProvider {
+        OidcProvider::new(OidcConfig {
+            issuer: TEST_ISSUER.into(),
+            jwks_url: jwks.url(),
+            audience: Some("dox".into()),
+            allowed_domains: Vec::new(),
+            jwks_cache_ttl_secs: 3600,
+            clock_skew_secs: 60,
+        })
+    }
+
+    async fn age_keys(auth: &OidcProvider, age: Duration) {
+        if let Some(cached) = auth.keys.write().await.as_mut() {
+            cached.fetched = Instant::now().checked_sub(age).unwrap_or(cached.fetched);
+        }
+    }
+
+    fn token(claims: &serde_json::Value) -> Result<Credentials> {
+        Ok(Credentials::Token(sign(claims)?))
+    }
+
+    fn valid_claims(email: &str) -> serde_json::Value {
+        json!({
+            "iss": TEST_ISSUER,
+            "aud": "dox",
+            "email": email,
+            "exp": jsonwebtoken::get_current_timestamp() + 600,
+        })
+    }
+
+    #[rocket::async_test]
+    async fn valid_token_returns_user_from_email_claim() -> Result<()> {
+        // given
+        let jwks = JwksStub::start()?;
+        let auth = provider(&jwks);
+
+        // when
+        let user = auth
+            .authenticate(&token(&valid_claims("some@email.com"))?)
+            .await?;
+
+        // then
+        assert_eq!(user, Some(User::new("some@email.com")));
+
+        Ok(())
+    }
+
+    #[rocket::async_test]
+    async fn token_for_other_audience_is_rejected() -> Result<()> {
+        // given
+        let jwks = JwksStub::start()?;
+        let auth = provider(&jwks);
+        let mut claims = valid_claims("some@email.com");
+        claims["aud"] = json!("other-app");
+
+        // when
+        let res = auth.authenticate(&token(&claims)?).await;
+
+        // then
+        assert_matches!(res, Err(ref e @ AuthErr::InvalidAudience) if e.status().code == 401);
+
+        Ok(())
+    }
+
+    #[rocket::async_test]
+    async fn expired_token_is_rejected() -> Result<()> {
+        // given
+        let jwks = JwksStub::start()?;
+        let auth = provider(&jwks);
+        let mut claims = valid_claims("some@email.com");
+        claims["exp"] = json!(jsonwebtoken::get_current_timestamp() - 3600);
+
+        // when
+        let res = auth.authenticate(&token(&claims)?).await;
+
+        // then
+        assert_matches!(res, Err(AuthErr::TokenExpired));
+
+        Ok(())
+    }
+
+    #[rocket::async_test]
+    async fn token_expired_within_clock_skew_is_accepted() -> Result<()> {
+        // given
+        let jwks = JwksStub::start()?;
+        let auth = provider(&jwks);
+        let mut claims = valid_claims("some@email.com");
+        claims["exp"] = json!(jsonwebtoken::get_current_timestamp() - 10);
+
+        // when
+        let user = auth.authenticate(&token(&claims)?).await?;
+
+        // then
+        assert_eq!(user, Some(User::new("some@email.com")));
+
+        Ok(())
+    }
+
+    #[rocket::async_test]
+    async fn token_used_before_its_not_before_time_is_rejected() -> Result<()> {
+        // given
+        let jwks = JwksStub::start()?;
+        let auth = provider(&jwks);
+        let mut claims = valid_claims("some@email.com");
+        claims["nbf"] = json!(jsonwebtoken::get_current_timestamp() + 300);
+
+        // when
+        let res = auth.authenticate(&token(&claims)?).await;
+
+        // then
+        assert_matches!(res, Err(AuthErr::TokenNotYetValid));
+
+        Ok(())
+    }
+
+    #[rocket::async_test]
+    async fn token_without_audience_claim_is_rejected() -> Result<()> {
+        // given
+        let jwks = JwksStub::start()?;
+        let auth = provider(&jwks);
+        let claims = json!({
+            "iss": TEST_ISSUER,
+            "email": "some@email.com",
+            "exp": jsonwebtoken::get_current_timestamp() + 600,
+        });
+
+        // when
+        let res = auth.authenticate(&token(&claims)?).await;
+
+        // then
+        assert_matches!(res, Err(AuthErr::InvalidToken(_)));
+
+        Ok(())
+    }
+
+    #[rocket::async_test]
+    async fn token_choosing_other_algorithm_than_key_is_rejected() -> Result<()> {
+        // given
+        let jwks = JwksStub::start()?;
+        let auth = provider(&jwks);
+        let mut header = jsonwebtoken::Header::new(Algorithm::HS256);
+        header.kid = Some("test-key".into());
+        let key = jsonwebtoken::EncodingKey::from_secret(b"public part of the key");
+        let token = jsonwebtoken::encode(&header, &valid_claims("some@email.com"), &key)?;
+
+        // when
+        let res = auth.authenticate(&Credentials::Token(token)).await;
+
+        // then
+        assert_matches!(res, Err(ref e @ AuthErr::AlgorithmNotAllowed(_)) if e.status().code == 401);
+
+        Ok(())
+    }
+
+    #[rocket::async_test]
+    async fn token_of_other_issuer_is_not_handled() -> Result<()> {
+        // given
+        let jwks = JwksStub::start()?;
+        let auth = provider(&jwks);
+        let mut claims = valid_claims("some@email.com");
+        claims["iss"] = json!("https://other.issuer.com");
+
+        // when
+        let user = auth.authenticate(&token(&claims)?).await?;
+
+        // then
+        assert_eq!(user, None);
+        assert_eq!(jwks.requests(), 0);
+
+        Ok(())
+    }
+
+    #[rocket::async_test]
+    async fn email_outside_of_allowed_domains_is_forbidden() -> Result<()> {
+        // given
+        let jwks = JwksStub::start()?;
+        let auth = OidcProvider::new(OidcConfig {
+            allowed_domains: vec!["company.com".into()],
+            ..provider(&jwks).cfg
+        });
+
+        // when
+        let res = auth
+            .authenticate(&token(&valid_claims("some@email.com"))?)
+            .await;
+
+        // then
+        assert_matches!(res, Err(ref e @ AuthErr::DomainNotAllowed(_)) if e.status().code == 403);
+
+        Ok(())
+    }
+
+    #[rocket::async_test]
+    async fn keys_are_cached_for_configured_time() -> Result<()> {
+        // given
+        let jwks = JwksStub::start()?;
+        let auth = provider(&jwks);
+        let credentials = token(&valid_claims("some@email.com"))?;
+
+        // when
+        auth.authenticate(&credentials).await?;
+        auth.authenticate(&credentials).await?;
+
+        // then
+        assert_eq!(jwks.requests(), 1);
+
+        Ok(())
+    }
+
+    #[rocket::async_test]
+    async fn keys_are_fetched_again_when_cache_expires() -> Result<()> {
+        // given
+        let jwks = JwksStub::start()?;
+        let auth = OidcProvider::new(OidcConfig {
+            jwks_cache_ttl_secs: 0,
+            ..provider(&jwks).cfg
+        });
+        let credentials = token(&valid_claims("some@email.com"))?;
+
+        // when
+        auth.authenticate(&credentials).await?;
+        auth.authenticate(&credentials).await?;
+
+        // then
+        assert_eq!(jwks.requests(), 2);
+
+        Ok(())
+    }
+
+    #[rocket::async_test]
+    async fn keys_are_fetched_again_when_key_is_unknown() -> Result<()> {
+        // given
+        let jwks = JwksStub::start()?;
+        let auth = provider(&jwks);
+        auth.authenticate(&token(&valid_claims("some@email.com"))?)
+            .await?;
+        age_keys(&auth, Duration::from_secs(120)).await;
+        let credentials = Credentials::Token(sign_with_kid(
+            "rotated-key",
+            &valid_claims("some@email.com"),
+        )?);
+
+        // when
+        let first = auth.authenticate(&credentials).await;
+        let second = auth.authenticate(&credentials).await;
+
+        // then
+        assert_matches!(first, Err(AuthErr::UnknownKey(_)));
+        assert_matches!(second, Err(AuthErr::UnknownKey(_)));
+        assert_eq!(jwks.requests(), 2); // second refresh is throttled
+
+        Ok(())
+    }
+}
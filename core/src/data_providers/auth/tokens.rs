@@ -0,0 +1,102 @@
+//! Static API tokens, useful for scripts which can't go through the login flow.
+use crate::entities::user::User;
+use crate::result::AuthErr;
+use crate::use_cases::auth::{Authenticator, Credentials};
+use crate::use_cases::config::TokensConfig;
+
+use sha2::{Digest, Sha256};
+use tracing::{debug, instrument};
+
+pub struct ApiTokens {
+    cfg: TokensConfig,
+}
+
+impl ApiTokens {
+    pub fn new(cfg: TokensConfig) -> Self {
+        Self { cfg }
+    }
+}
+
+#[rocket::async_trait]
+impl Authenticator for ApiTokens {
+    #[instrument(skip(self, credentials))]
+    async fn authenticate(&self, credentials: &Credentials) -> Result<Option<User>, AuthErr> {
+        let Credentials::Token(token) = credentials else {
+            return Ok(None);
+        };
+        let hash = format!("{:x}", Sha256::digest(token));
+        let owner = self
+            .cfg
+            .tokens
+            .iter()
+            .find(|api_token| api_token.sha256.eq_ignore_ascii_case(&hash));
+        debug!("api token found: {}", owner.is_some());
+        Ok(owner.map(|api_token| User::new(&api_token.email)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::use_cases::config::ApiToken;
+
+    use anyhow::Result;
+
+    fn api_tokens() -> ApiTokens {
+        ApiTokens::new(TokensConfig {
+            tokens: vec![ApiToken {
+                email: "script@email.com".into(),
+                sha256: format!("{:x}", Sha256::digest("secret-token")),
+            }],
+        })
+    }
+
+    #[rocket::async_test]
+    async fn known_token_returns_its_owner() -> Result<()> {
+        // given
+        let auth = api_tokens();
+        let credentials = Credentials::Token("secret-token".into());
+
+        // when
+        let user = auth.authenticate(&credentials).await?;
+
+        // then
+        assert_eq!(user, Some(User::new("script@email.com")));
+
+        Ok(())
+    }
+
+    #[rocket::async_test]
+    async fn unknown_token_is_not_handled() -> Result<()> {
+        // given
+        let auth = api_tokens();
+        let credentials = Credentials::Token("other-token".into());
+
+        // when
+        let user = auth.authenticate(&credentials).await?;
+
+        // then
+        assert_eq!(user, None);
+
+        Ok(())
+    }
+
+    #[rocket::async_test]
+    async fn password_credentials_are_not_handled() -> Result<()> {
+        // given
+        let auth = api_tokens();
+        let credentials = Credentials::Basic {
+            username: "script@email.com".into(),
+            password: "secret-token".into(),
+        };
+
+        // when
+        let user = auth.authenticate(&credentials).await?;
+
+        // then
+        assert_eq!(user, None);
+
+        Ok(())
+    }
+}
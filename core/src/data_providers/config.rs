@@ -113,18 +113,12 @@ fn check_watched_dir(config: &Config) -> Result<(), ConfigurationErr> {
 fn check_thumnbails_dir(config: &Config) -> Result<(), ConfigurationErr> {
     debug!("checking thumbnails dir");
     if config.thumbnails_dir.exists() && !config.thumbnails_dir.is_dir() {
-        return Err(ConfigurationErr::InvalidIndexPath(format!(
+        return Err(ConfigurationErr::InvalidThumbnailPath(format!(
             "It needs to be a directory: '{}'",
             config.thumbnails_dir.str()
         )));
     }
     create_dir_all(&config.thumbnails_dir)?;
-    if config.thumbnails_dir.read_dir()?.next().is_some() {
-        return Err(ConfigurationErr::InvalidThumbnailPath(format!(
-            "Directory needs to be empty: '{}'",
-            config.thumbnails_dir.str()
-        )));
-    }
     Ok(())
 }
 
@@ -149,6 +143,11 @@ mod test {
     use crate::configuration::telemetry::init_tracing;
     use crate::data_providers::config::default_config_path;
     use crate::testingtools::Spy;
+    use crate::use_cases::config::{
+        ApiToken, ArchivesConfig, AuthConfig, LinksConfig, LocalConfig, LocalUser, OcrConfig,
+        QuotaConfig, RateLimitConfig, ScannerConfig, ServerConfig, ThumbnailsConfig, TokensConfig,
+        UsersConfig, WatcherBackend, WatcherConfig,
+    };
 
     use anyhow::Result;
     use claim::assert_matches;
@@ -178,6 +177,19 @@ mod test {
             docs_dir: PathBuf::from("/home/zbyniu/.local/share/dox/docs"),
             thumbnails_dir: PathBuf::from("/home/zbyniu/.local/share/dox/thumbnails"),
             index_dir: PathBuf::from("/home/zbyniu/.local/share/dox/index"),
+            data_dir: Config::default().data_dir,
+            remove_orphan_thumbnails: false,
+            watcher: WatcherConfig::default(),
+            links: LinksConfig::default(),
+            quota: QuotaConfig::default(),
+            users: UsersConfig::default(),
+            rate_limit: RateLimitConfig::default(),
+            server: ServerConfig::default(),
+            ocr: OcrConfig::default(),
+            scanner: ScannerConfig::default(),
+            archives: ArchivesConfig::default(),
+            thumbnails: ThumbnailsConfig::default(),
+            auth: Config::default().auth,
         };
         let loader = FsConfigLoader;
 
@@ -190,6 +202,91 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn watcher_config_is_loaded_properly_from_a_file() -> Result<()> {
+        // given
+        let tmp_cfg = tempdir()?;
+        let cfg_path = tmp_cfg.path().join("dox.toml");
+        create_config(
+            &cfg_path,
+            r#"
+            watched_dir = "/home/zbyniu/Tests/notify"
+            docs_dir = "/home/zbyniu/.local/share/dox/docs"
+            thumbnails_dir = "/home/zbyniu/.local/share/dox/thumbnails"
+            index_dir = "/home/zbyniu/.local/share/dox/index"
+
+            [watcher]
+            backend = "both"
+            poll_interval_ms = 10000
+            "#,
+        )?;
+        let loader = FsConfigLoader;
+
+        // when
+        let read_cfg = loader.load(&cfg_path)?;
+
+        // then
+        assert_eq!(
+            read_cfg.watcher,
+            WatcherConfig {
+                backend: WatcherBackend::Both,
+                poll_interval_ms: 10000,
+                ..WatcherConfig::default()
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn auth_providers_are_loaded_in_order_from_a_file() -> Result<()> {
+        // given
+        let tmp_cfg = tempdir()?;
+        let cfg_path = tmp_cfg.path().join("dox.toml");
+        create_config(
+            &cfg_path,
+            r#"
+            watched_dir = "/home/zbyniu/Tests/notify"
+            docs_dir = "/home/zbyniu/.local/share/dox/docs"
+            thumbnails_dir = "/home/zbyniu/.local/share/dox/thumbnails"
+            index_dir = "/home/zbyniu/.local/share/dox/index"
+
+            [[auth]]
+            type = "tokens"
+            tokens = [{ email = "script@email.com", sha256 = "abc" }]
+
+            [[auth]]
+            type = "local"
+            users = [{ email = "local@email.com", password_hash = "$argon2id$hash" }]
+            "#,
+        )?;
+        let loader = FsConfigLoader;
+
+        // when
+        let read_cfg = loader.load(&cfg_path)?;
+
+        // then
+        assert_eq!(
+            read_cfg.auth,
+            vec![
+                AuthConfig::Tokens(TokensConfig {
+                    tokens: vec![ApiToken {
+                        email: "script@email.com".into(),
+                        sha256: "abc".into(),
+                    }],
+                }),
+                AuthConfig::Local(LocalConfig {
+                    users: vec![LocalUser {
+                        email: "local@email.com".into(),
+                        password_hash: "$argon2id$hash".into(),
+                    }],
+                }),
+            ]
+        );
+
+        Ok(())
+    }
+
     fn create_config<A: AsRef<Path>, S: Into<String>>(path: A, content: S) -> Result<()> {
         let path = path.as_ref();
         let mut cfg_file = File::create(path)?;
@@ -303,6 +400,19 @@ mod test {
             docs_dir: PathBuf::from("/docs_dir"),
             thumbnails_dir: PathBuf::from("/thumbnails_dir"),
             index_dir: PathBuf::from("/index_dir"),
+            data_dir: PathBuf::from("/data_dir"),
+            remove_orphan_thumbnails: false,
+            watcher: WatcherConfig::default(),
+            links: LinksConfig::default(),
+            quota: QuotaConfig::default(),
+            users: UsersConfig::default(),
+            rate_limit: RateLimitConfig::default(),
+            server: ServerConfig::default(),
+            ocr: OcrConfig::default(),
+            scanner: ScannerConfig::default(),
+            archives: ArchivesConfig::default(),
+            thumbnails: ThumbnailsConfig::default(),
+            auth: Config::default().auth,
         };
         let loader = FsConfigLoader;
 
@@ -316,6 +426,97 @@ mod test {
 docs_dir = "/docs_dir"
 thumbnails_dir = "/thumbnails_dir"
 index_dir = "/index_dir"
+data_dir = "/data_dir"
+remove_orphan_thumbnails = false
+
+[watcher]
+backend = "native"
+poll_interval_ms = 2000
+settle_time_ms = 500
+
+[links]
+default_ttl_secs = 86400
+max_ttl_secs = 604800
+
+[quota]
+max_docs = 10000
+max_bytes = 10737418240
+max_upload_bytes = 104857600
+
+[users]
+admins = []
+require_approval = false
+
+[rate_limit]
+max_auth_failures_per_minute = 20
+
+[rate_limit.search]
+user_per_minute = 60
+ip_per_minute = 120
+burst = 20
+
+[rate_limit.upload]
+user_per_minute = 20
+ip_per_minute = 40
+burst = 10
+
+[rate_limit.download]
+user_per_minute = 300
+ip_per_minute = 600
+burst = 50
+
+[server]
+address = "0.0.0.0"
+port = 8000
+
+[server.tls]
+enabled = false
+self_signed = true
+
+[ocr]
+languages = ["pol"]
+page_segmentation_mode = 3
+dpi = 300
+
+[ocr.preprocessing]
+enabled = true
+exif_rotation = true
+deskew = true
+max_skew_degrees = 10
+denoise = true
+binarize = true
+binarization_radius = 15
+target_dpi = 300
+
+[scanner]
+enabled = true
+min_page_area = 20
+max_page_area = 95
+
+[archives]
+enabled = true
+max_members = 1000
+max_member_bytes = 104857600
+max_total_bytes = 1073741824
+max_ratio = 100
+
+[thumbnails]
+format = "webp"
+quality = 80
+tile_px = 320
+preview_px = 1280
+pdf_width = 320
+pdf_height = 452
+pixel_ratios = [2]
+cover_page = 1
+
+[[auth]]
+type = "oidc"
+issuer = "https://accounts.google.com"
+jwks_url = "https://www.googleapis.com/oauth2/v3/certs"
+allowed_domains = []
+jwks_cache_ttl_secs = 3600
+clock_skew_secs = 60
 "#
         );
 
@@ -345,6 +546,19 @@ index_dir = "/index_dir"
             docs_dir: tmp_cfg.path().join("docs_dir"),
             thumbnails_dir: tmp_cfg.path().join("thumbnails_dir"),
             index_dir: tmp_cfg.path().join("index_dir"),
+            data_dir: tmp_cfg.path().join("data_dir"),
+            remove_orphan_thumbnails: false,
+            watcher: WatcherConfig::default(),
+            links: LinksConfig::default(),
+            quota: QuotaConfig::default(),
+            users: UsersConfig::default(),
+            rate_limit: RateLimitConfig::default(),
+            server: ServerConfig::default(),
+            ocr: OcrConfig::default(),
+            scanner: ScannerConfig::default(),
+            archives: ArchivesConfig::default(),
+            thumbnails: ThumbnailsConfig::default(),
+            auth: Config::default().auth,
         };
         let config_content = toml::to_string(&config)?;
         create_config(&cfg_path, config_content)?;
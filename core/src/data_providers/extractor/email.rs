@@ -0,0 +1,83 @@
+//! Allows to extract text from emails.
+use crate::data_providers::extractor::extract_pages;
+use crate::entities::document::DocDetails;
+use crate::entities::extension::Ext;
+use crate::entities::location::Location;
+use crate::result::{ExtractorErr, GeneralErr};
+use crate::use_cases::services::extractor::DataExtractor;
+
+use mail_parser::{HeaderValue, Message};
+use tracing::instrument;
+
+/// Extracts text from EML files.
+///
+/// The sender and the subject are extracted together with the text parts of the body. HTML parts
+/// are converted to text. All files pointed by `paths` are processed in parallel.
+#[derive(Debug)]
+pub struct FromEmail;
+
+impl DataExtractor for FromEmail {
+    #[instrument(skip(self))]
+    fn extract_data(&self, location: &Location) -> Result<Vec<DocDetails>, ExtractorErr> {
+        extract_pages(location, email_pages)
+    }
+}
+
+/// Reads the text of the email. The whole email is a single page.
+pub fn email_pages(doc: &[u8], ext: &Ext) -> Result<Vec<String>, ExtractorErr> {
+    if *ext != Ext::Eml {
+        return Err(GeneralErr::InvalidExtension.into());
+    }
+    let message = Message::parse(doc).ok_or(ExtractorErr::Email)?;
+    let mut lines = Vec::new();
+    if let HeaderValue::Address(from) = message.from() {
+        let name = from.name.as_deref().unwrap_or_default();
+        let address = from.address.as_deref().unwrap_or_default();
+        lines.push(format!("{name} <{address}>"));
+    }
+    if let Some(subject) = message.subject() {
+        lines.push(subject.to_string());
+    }
+    lines.extend((0..).map_while(|pos| message.body_text(pos).map(|text| text.into_owned())));
+    Ok(vec![lines.join("\n")])
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use anyhow::Result;
+    use claim::assert_err;
+    use std::fs;
+
+    #[test]
+    fn sender_subject_and_body_of_email_are_extracted() -> Result<()> {
+        // given
+        let doc = fs::read("res/doc16.eml")?;
+
+        // when
+        let pages = email_pages(&doc, &Ext::Eml)?;
+
+        // then
+        assert_eq!(pages.len(), 1);
+        assert!(pages[0].contains("Biuro <biuro@example.com>"));
+        assert!(pages[0].contains("Faktura za październik"));
+        assert!(pages[0].contains("przesyłamy fakturę za usługi księgowe"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn other_documents_are_not_read_as_emails() -> Result<()> {
+        // given
+        let doc = fs::read("res/doc13.txt")?;
+
+        // when
+        let res = email_pages(&doc, &Ext::Txt);
+
+        // then
+        assert_err!(res);
+
+        Ok(())
+    }
+}
@@ -1,22 +1,241 @@
 //! Allows to extract text from image using OCR.
-use crate::entities::document::DocDetails;
+use crate::data_providers::extractor::preprocess::Preprocessor;
+use crate::data_providers::raster::decode_pages;
+use crate::entities::document::{DocDetails, Page};
+use crate::entities::extension::Ext;
 use crate::entities::file::{Filename, Thumbnailname};
 use crate::entities::location::{Location, SafePathBuf};
 use crate::entities::user::User;
+use crate::helpers::PathRefExt;
 use crate::result::ExtractorErr;
+use crate::use_cases::config::{OcrConfig, ThumbnailFormat};
+use crate::use_cases::ocr::{LanguageOverrides, OcrLanguages};
 use crate::use_cases::services::extractor::DataExtractor;
 
-use leptess::LepTess;
+use ::image::{DynamicImage, ImageOutputFormat};
+use leptess::{LepTess, Variable};
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 use std::convert::TryFrom;
-use tracing::{debug, instrument};
+use std::fs;
+use std::io::Cursor;
+use std::path::Path;
+use tracing::{debug, instrument, warn};
+
+/// Tesseract treats lower resolutions as missing.
+const MIN_DPI: i32 = 70;
 
 /// Extracts text from the image.
 ///
 /// It's using [`LepTess`] to extract text from the image. All images pointed by `paths` are
-/// processed in parallel thanks to [`ParallelIterator`].
-#[derive(Debug, Default)]
-pub struct FromImage;
+/// processed in parallel thanks to [`ParallelIterator`]. Languages requested during the upload
+/// of the document are used instead of the configured ones. Pages of multi-page TIFF are
+/// recognized one by one, HEIC is decoded before the recognition. Thumbnails of all images are
+/// named after the document, in the configured `format`.
+pub struct FromImage {
+    ocr: Ocr,
+    overrides: LanguageOverrides,
+    format: ThumbnailFormat,
+}
+
+impl FromImage {
+    pub fn new(cfg: OcrConfig, overrides: LanguageOverrides, format: ThumbnailFormat) -> Self {
+        Self {
+            ocr: Ocr::new(cfg),
+            overrides,
+            format,
+        }
+    }
+
+    fn extract_details(&self, path: &SafePathBuf) -> Result<DocDetails, ExtractorErr> {
+        debug!("executing OCR on {:?}", path);
+        let filename = Filename::from(path);
+        let user = User::try_from(path)?;
+        let requested = self.overrides.take(&user, &filename)?;
+        let mut lt = self.ocr.tesseract(requested.as_ref())?;
+        let ext = path.ext()?;
+        let thumbnailname = Thumbnailname::of(path, self.format.ext())?;
+        let recognized = match ext {
+            Ext::Tiff | Ext::Heic => self.recognize_pages(&mut lt, path, &ext),
+            _ => self.recognize_file(&mut lt, path),
+        }?;
+        let confidence = mean_confidence(&recognized);
+        let pages = recognized
+            .into_iter()
+            .zip(1..)
+            .map(|((text, _), number)| Page::new(number, text))
+            .collect();
+        Ok(DocDetails::new(filename, "", thumbnailname, user)
+            .pages(pages)
+            .confidence(confidence))
+    }
+
+    fn recognize_file(
+        &self,
+        lt: &mut LepTess,
+        path: &SafePathBuf,
+    ) -> Result<Vec<(String, u8)>, ExtractorErr> {
+        let dpi = self.ocr.load_file(lt, path)?;
+        Ok(vec![self.ocr.recognize(lt, dpi)?])
+    }
+
+    /// Recognizes the text of each page of the image, e.g. multi-page TIFF from a scanner. Falls
+    /// back to the first page read by Tesseract itself, when the image can't be decoded.
+    fn recognize_pages(
+        &self,
+        lt: &mut LepTess,
+        path: &SafePathBuf,
+        ext: &Ext,
+    ) -> Result<Vec<(String, u8)>, ExtractorErr> {
+        let pages = match decode_pages(&fs::read(path)?, ext) {
+            Ok(pages) => pages,
+            Err(e) => {
+                warn!("can't decode pages of {:?}: '{}'", path, e);
+                return self.recognize_file(lt, path);
+            }
+        };
+        let mut recognized = Vec::new();
+        for (idx, page) in pages.into_iter().enumerate() {
+            debug!("executing OCR on page {} of {:?}", idx + 1, path);
+            let dpi = self.ocr.load_image(lt, page)?;
+            recognized.push(self.ocr.recognize(lt, dpi)?);
+        }
+        Ok(recognized)
+    }
+}
+
+fn mean_confidence(recognized: &[(String, u8)]) -> u8 {
+    let sum: usize = recognized.iter().map(|(_, conf)| usize::from(*conf)).sum();
+    u8::try_from(sum / recognized.len().max(1)).unwrap_or_default()
+}
+
+/// Recognizes text with Tesseract, using the configured settings.
+#[derive(Debug, Clone)]
+pub struct Ocr {
+    cfg: OcrConfig,
+    preprocessor: Preprocessor,
+}
+
+impl Ocr {
+    pub fn new(cfg: OcrConfig) -> Self {
+        let preprocessor = Preprocessor::new(cfg.preprocessing.clone());
+        Self { cfg, preprocessor }
+    }
+
+    /// Resolution of the images rendered for the recognition, e.g. from PDF pages.
+    pub fn render_dpi(&self) -> u16 {
+        if self.cfg.dpi > 0 {
+            self.cfg.dpi
+        } else {
+            OcrConfig::default().dpi
+        }
+    }
+
+    /// Creates Tesseract for the `requested` languages. Configured languages are used when none
+    /// were requested, or when the requested ones can't be loaded.
+    // NOTE: it's actually more efficient to create LepTess
+    // each time than sharing it between threads
+    pub fn tesseract(&self, requested: Option<&OcrLanguages>) -> Result<LepTess, ExtractorErr> {
+        let Some(languages) = requested else {
+            return self.tesseract_for(&self.cfg.languages());
+        };
+        self.tesseract_for(languages.as_ref()).or_else(|e| {
+            warn!(
+                "can't use requested languages '{}': '{}', using configured ones",
+                languages, e
+            );
+            self.tesseract_for(&self.cfg.languages())
+        })
+    }
+
+    fn tesseract_for(&self, languages: &str) -> Result<LepTess, ExtractorErr> {
+        let tessdata = self.cfg.tessdata_dir.as_ref().map(|dir| dir.str());
+        let mut lt = LepTess::new(tessdata, languages)?;
+        let psm = self.cfg.page_segmentation_mode.to_string();
+        lt.set_variable(Variable::TesseditPagesegMode, &psm)?;
+        Ok(lt)
+    }
+
+    /// Sets the image from the file in `lt`, preprocessing it when enabled. Returns the resolution
+    /// of the image, when it's known after the preprocessing.
+    pub fn load_file<P: AsRef<Path>>(
+        &self,
+        lt: &mut LepTess,
+        path: P,
+    ) -> Result<Option<u16>, ExtractorErr> {
+        let path = path.as_ref();
+        if self.preprocessor.is_enabled() {
+            match self.preprocessor.prepare_file(path) {
+                Ok(prepared) => {
+                    lt.set_image_from_mem(&prepared.png)?;
+                    return Ok(prepared.dpi);
+                }
+                Err(e) => warn!("can't preprocess '{}': '{}'", path.display(), e),
+            }
+        }
+        lt.set_image(path)?;
+        Ok(None)
+    }
+
+    /// Sets the decoded image in `lt`, preprocessing it when enabled. Returns the resolution of the
+    /// image, when it's known after the preprocessing.
+    pub fn load_image(
+        &self,
+        lt: &mut LepTess,
+        img: DynamicImage,
+    ) -> Result<Option<u16>, ExtractorErr> {
+        if self.preprocessor.is_enabled() {
+            match self.preprocessor.prepare(img.clone(), None) {
+                Ok(prepared) => {
+                    lt.set_image_from_mem(&prepared.png)?;
+                    return Ok(prepared.dpi);
+                }
+                Err(e) => warn!("can't preprocess the page: '{}'", e),
+            }
+        }
+        let mut png = Vec::new();
+        img.write_to(&mut Cursor::new(&mut png), ImageOutputFormat::Png)?;
+        lt.set_image_from_mem(&png)?;
+        Ok(None)
+    }
+
+    /// Sets the PNG image of `dpi` resolution in `lt`, preprocessing it when enabled. Returns the
+    /// resolution of the image set.
+    pub fn load_png(&self, lt: &mut LepTess, png: &[u8], dpi: u16) -> Result<u16, ExtractorErr> {
+        if self.preprocessor.is_enabled() {
+            let prepared = ::image::load_from_memory(png)
+                .map_err(ExtractorErr::from)
+                .and_then(|img| self.preprocessor.prepare(img, Some(dpi)));
+            match prepared {
+                Ok(prepared) => {
+                    lt.set_image_from_mem(&prepared.png)?;
+                    return Ok(prepared.dpi.unwrap_or(dpi));
+                }
+                Err(e) => warn!("can't preprocess the page: '{}'", e),
+            }
+        }
+        lt.set_image_from_mem(png)?;
+        Ok(dpi)
+    }
+
+    /// Recognizes text of the image set in `lt`. Returns the text and its mean confidence.
+    ///
+    /// Configured resolution is assumed when the `dpi` is unknown and the image doesn't specify it.
+    pub fn recognize(
+        &self,
+        lt: &mut LepTess,
+        dpi: Option<u16>,
+    ) -> Result<(String, u8), ExtractorErr> {
+        let dpi = dpi.or_else(|| {
+            (self.cfg.dpi > 0 && lt.get_source_y_resolution() < MIN_DPI).then(|| self.cfg.dpi)
+        });
+        if let Some(dpi) = dpi {
+            lt.set_source_resolution(i32::from(dpi));
+        }
+        let text = lt.get_utf8_text()?;
+        let confidence = u8::try_from(lt.mean_text_conf().clamp(0, 100)).unwrap_or_default();
+        Ok((text, confidence))
+    }
+}
 
 impl DataExtractor for FromImage {
     #[instrument(skip(self))]
@@ -24,39 +243,32 @@ impl DataExtractor for FromImage {
         let Location::FS(paths) = location;
         Ok(paths
             .par_iter()
-            .map(extract_details)
+            .map(|path| self.extract_details(path))
             .filter_map(Result::ok)
             .collect::<Vec<DocDetails>>())
     }
 }
 
-fn extract_details(path: &SafePathBuf) -> Result<DocDetails, ExtractorErr> {
-    debug!("executing OCR on {:?}", path);
-    // NOTE: it's actually more efficient to create LepTess
-    // each time than sharing it between threads
-    let filename = Filename::from(path);
-    let mut lt = LepTess::new(None, "pol")?;
-    lt.set_image(path)?;
-    let body = lt.get_utf8_text()?;
-    let thumbnailname = Thumbnailname::from(path);
-    let user = User::try_from(path)?;
-    Ok(DocDetails::new(filename, body, thumbnailname, user))
-}
-
 #[cfg(test)]
 mod test {
     use super::*;
 
     use crate::entities::location::SafePathBuf;
+    use crate::entities::user::FAKE_USER_EMAIL;
+    use crate::testingtools::services::ocr::stub;
+    use crate::use_cases::config::PreprocessingConfig;
+
     use anyhow::Result;
+    use claim::assert_none;
 
     #[test]
     fn test_extract_text() -> Result<()> {
         // given
-        let ocr = FromImage;
+        let ocr = FromImage::new(OcrConfig::default(), stub(), ThumbnailFormat::default());
         let paths = vec![
             SafePathBuf::from("res/doc1.png"),
             SafePathBuf::from("res/doc3.jpg"),
+            SafePathBuf::from("res/doc18.bmp"),
         ];
 
         // when
@@ -66,14 +278,97 @@ mod test {
         // then
         let first_doc = &result[0];
         let second_doc = &result[1];
+        let third_doc = &result[2];
 
-        assert!(first_doc.body.contains("W odpowiedzi na pismo"));
+        assert!(first_doc.body().contains("W odpowiedzi na pismo"));
         assert_eq!(first_doc.filename, Filename::new("doc1.png")?);
-        assert_eq!(first_doc.thumbnail, Thumbnailname::new("doc1.png")?);
+        assert_eq!(first_doc.thumbnail, Thumbnailname::new("doc1.png.webp")?);
+        assert!(first_doc.confidence.is_some());
+
+        assert!(second_doc.body().contains("W odpowiedzi na pismo"));
+        assert_eq!(second_doc.filename, Filename::new("doc18.bmp")?);
+        assert_eq!(second_doc.thumbnail, Thumbnailname::new("doc18.bmp.webp")?);
+
+        assert!(third_doc.body().contains("Szanowny Panie"));
+        assert_eq!(third_doc.filename, Filename::new("doc3.jpg")?);
+        assert_eq!(third_doc.thumbnail, Thumbnailname::new("doc3.jpg.webp")?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn each_page_of_tiff_is_recognized() -> Result<()> {
+        // given
+        let ocr = FromImage::new(OcrConfig::default(), stub(), ThumbnailFormat::default());
+        let paths = vec![SafePathBuf::from("res/doc17.tiff")];
+
+        // when
+        let result = ocr.extract_data(&Location::FS(paths))?;
 
-        assert!(second_doc.body.contains("Szanowny Panie"));
-        assert_eq!(second_doc.filename, Filename::new("doc3.jpg")?);
-        assert_eq!(second_doc.thumbnail, Thumbnailname::new("doc3.jpg")?);
+        // then
+        let doc = &result[0];
+        let numbers: Vec<u32> = doc.pages.iter().map(|page| page.number).collect();
+        assert_eq!(numbers, vec![1, 2]);
+        assert!(doc.pages[1].text.contains("W odpowiedzi na pismo"));
+        assert_eq!(doc.thumbnail, Thumbnailname::new("doc17.tiff.webp")?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn configured_languages_are_used_when_requested_ones_are_not_available() -> Result<()> {
+        // given
+        let overrides = stub();
+        let user = User::new(FAKE_USER_EMAIL);
+        let filename = Filename::new("doc1.png")?;
+        overrides.set(&user, &filename, OcrLanguages::new("not_installed")?)?;
+        let ocr = FromImage::new(
+            OcrConfig::default(),
+            overrides.clone(),
+            ThumbnailFormat::default(),
+        );
+        let paths = vec![SafePathBuf::from("res/doc1.png")];
+
+        // when
+        let result = ocr.extract_data(&Location::FS(paths))?;
+
+        // then
+        assert!(result[0].body().contains("W odpowiedzi na pismo"));
+        assert_none!(overrides.take(&user, &filename)?);
+
+        Ok(())
+    }
+
+    #[test]
+    #[ignore = "slow, measures the effect of the preprocessing, run with `cargo make ocr-report`"]
+    fn ocr_report() -> Result<()> {
+        let raw = Ocr::new(OcrConfig {
+            preprocessing: PreprocessingConfig {
+                enabled: false,
+                ..PreprocessingConfig::default()
+            },
+            ..OcrConfig::default()
+        });
+        let preprocessed = Ocr::new(OcrConfig::default());
+        for fixture in [
+            "res/doc1.png",
+            "res/doc2.jpg",
+            "res/doc3.jpg",
+            "res/doc4.webp",
+            "res/doc5.jpg",
+        ] {
+            let mut confidences = Vec::new();
+            for ocr in [&raw, &preprocessed] {
+                let mut lt = ocr.tesseract(None)?;
+                let dpi = ocr.load_file(&mut lt, fixture)?;
+                let (_, confidence) = ocr.recognize(&mut lt, dpi)?;
+                confidences.push(confidence);
+            }
+            println!(
+                "{}: confidence {}% without preprocessing, {}% with preprocessing",
+                fixture, confidences[0], confidences[1]
+            );
+        }
 
         Ok(())
     }
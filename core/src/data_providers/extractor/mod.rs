@@ -1,12 +1,29 @@
-use tracing::instrument;
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use std::convert::TryFrom;
+use std::fs;
+use tracing::{debug, instrument};
 
+use crate::data_providers::extractor::email::{email_pages, FromEmail};
 use crate::data_providers::extractor::image::FromImage;
+use crate::data_providers::extractor::office::{office_pages, FromOffice};
 use crate::data_providers::extractor::pdf::FromPdf;
+use crate::data_providers::extractor::text::{text_pages, FromText};
+use crate::entities::document::{DocDetails, Page};
 use crate::entities::extension::Ext;
+use crate::entities::file::{Filename, Thumbnailname};
+use crate::entities::location::{Location, SafePathBuf};
+use crate::entities::user::User;
+use crate::result::{ExtractorErr, GeneralErr};
+use crate::use_cases::config::{ArchivesConfig, Config, OcrConfig, ThumbnailFormat};
+use crate::use_cases::ocr::LanguageOverrides;
 use crate::use_cases::services::extractor::{Extractor, ExtractorFactory};
 
+pub mod email;
 pub mod image;
+pub mod office;
 pub mod pdf;
+pub mod preprocess;
+pub mod text;
 
 /// Creates specific [`Extractor`] based on the extension.
 ///
@@ -16,24 +33,107 @@ pub mod pdf;
 /// [`FromImage`](crate::data_providers::extractor::image::FromImage)).
 ///
 /// The type of a file is decided based on the file extension.
-#[derive(Debug)]
-pub struct ExtractorFactoryImpl;
+pub struct ExtractorFactoryImpl {
+    ocr: OcrConfig,
+    overrides: LanguageOverrides,
+    thumbnails: ThumbnailFormat,
+    archives: ArchivesConfig,
+}
+
+impl ExtractorFactoryImpl {
+    pub fn new(cfg: &Config, overrides: LanguageOverrides) -> Self {
+        Self {
+            ocr: cfg.ocr.clone(),
+            overrides,
+            thumbnails: cfg.thumbnails.format,
+            archives: cfg.archives.clone(),
+        }
+    }
+}
 
 impl ExtractorFactory for ExtractorFactoryImpl {
     #[instrument(skip(self))]
     fn make(&self, ext: &Ext) -> Extractor {
         match ext {
-            Ext::Png | Ext::Jpg | Ext::Webp => Box::new(FromImage),
-            Ext::Pdf => Box::new(FromPdf),
+            Ext::Png | Ext::Jpg | Ext::Webp | Ext::Tiff | Ext::Heic | Ext::Bmp | Ext::Gif => {
+                Box::new(FromImage::new(
+                    self.ocr.clone(),
+                    self.overrides.clone(),
+                    self.thumbnails,
+                ))
+            }
+            Ext::Pdf => Box::new(FromPdf::new(
+                self.ocr.clone(),
+                self.overrides.clone(),
+                self.thumbnails,
+            )),
+            Ext::Docx | Ext::Odt | Ext::Xlsx => Box::new(FromOffice::new(self.archives.clone())),
+            Ext::Rtf | Ext::Txt | Ext::Md | Ext::Html => Box::new(FromText),
+            Ext::Eml => Box::new(FromEmail),
         }
     }
 }
 
+/// Reads the text of the document, which is neither an image nor PDF, page by page.
+///
+/// Such documents have no own rendering, their text is used to render the thumbnails and the
+/// pages. Office documents are read within the `limits` of the archives.
+pub fn read_pages(
+    doc: &[u8],
+    ext: &Ext,
+    limits: &ArchivesConfig,
+) -> Result<Vec<String>, ExtractorErr> {
+    match ext {
+        Ext::Docx | Ext::Odt | Ext::Xlsx => office_pages(doc, ext, limits),
+        Ext::Rtf | Ext::Txt | Ext::Md | Ext::Html => text_pages(doc, ext),
+        Ext::Eml => email_pages(doc, ext),
+        Ext::Png
+        | Ext::Jpg
+        | Ext::Webp
+        | Ext::Tiff
+        | Ext::Heic
+        | Ext::Bmp
+        | Ext::Gif
+        | Ext::Pdf => Err(GeneralErr::InvalidExtension.into()),
+    }
+}
+
+/// Extracts the documents, which text is read with `read`, page by page. All files pointed by
+/// `location` are processed in parallel.
+pub fn extract_pages<F>(location: &Location, read: F) -> Result<Vec<DocDetails>, ExtractorErr>
+where
+    F: Fn(&[u8], &Ext) -> Result<Vec<String>, ExtractorErr> + Sync,
+{
+    let Location::FS(paths) = location;
+    Ok(paths
+        .par_iter()
+        .map(|path| text_details(path, &read))
+        .filter_map(Result::ok)
+        .collect::<Vec<DocDetails>>())
+}
+
+fn text_details<F>(path: &SafePathBuf, read: &F) -> Result<DocDetails, ExtractorErr>
+where
+    F: Fn(&[u8], &Ext) -> Result<Vec<String>, ExtractorErr>,
+{
+    debug!("reading text of {:?}", path);
+    let filename = Filename::from(path);
+    let thumbnailname = Thumbnailname::of(path, "png")?;
+    let user = User::try_from(path)?;
+    let pages = read(&fs::read(path)?, &path.ext()?)?
+        .into_iter()
+        .zip(1..)
+        .map(|(text, number)| Page::new(number, text))
+        .collect();
+    Ok(DocDetails::new(filename, "", thumbnailname, user).pages(pages))
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
 
     use crate::entities::location::{Location, SafePathBuf};
+    use crate::testingtools::services::ocr::stub;
 
     use anyhow::Result;
 
@@ -44,9 +144,19 @@ mod test {
             (Ext::Png, "res/doc1.png", "W dalszym ciągu uważamy"),
             (Ext::Jpg, "res/doc3.jpg", "Szanowny Panie"),
             (Ext::Webp, "res/doc4.webp", "Trybunału Konstytucyjnego"),
+            (Ext::Tiff, "res/doc17.tiff", "W dalszym ciągu uważamy"),
+            (Ext::Bmp, "res/doc18.bmp", "W dalszym ciągu uważamy"),
             (Ext::Pdf, "res/doc1.pdf", "Jak zainstalować scaner"),
+            (Ext::Docx, "res/doc9.docx", "Umowa najmu lokalu"),
+            (Ext::Odt, "res/doc10.odt", "Protokół zebrania wspólnoty"),
+            (Ext::Xlsx, "res/doc11.xlsx", "Ubezpieczenie"),
+            (Ext::Rtf, "res/doc12.rtf", "Zawiadomienie o zmianie taryfy"),
+            (Ext::Txt, "res/doc13.txt", "masło orzechowe"),
+            (Ext::Md, "res/doc14.md", "blokady transportowe"),
+            (Ext::Html, "res/doc15.html", "trzy noce"),
+            (Ext::Eml, "res/doc16.eml", "usługi księgowe"),
         ];
-        let extractor_factory = ExtractorFactoryImpl;
+        let extractor_factory = ExtractorFactoryImpl::new(&Config::default(), stub());
 
         for test_case in test_cases {
             let ext = test_case.0;
@@ -58,7 +168,7 @@ mod test {
             let doc = &docs[0];
 
             // then
-            assert!(doc.body.contains(test_case.2));
+            assert!(doc.body().contains(test_case.2));
         }
 
         Ok(())
@@ -68,7 +178,7 @@ mod test {
     fn test_extractor_factory_with_wrong_file() -> Result<()> {
         // given
         let ext = Ext::Pdf;
-        let extractor_factory = ExtractorFactoryImpl;
+        let extractor_factory = ExtractorFactoryImpl::new(&Config::default(), stub());
         let paths = vec![SafePathBuf::from("res/doc1.png")];
 
         // when
@@ -0,0 +1,240 @@
+//! Allows to extract text from office documents.
+use crate::data_providers::extractor::extract_pages;
+use crate::data_providers::extractor::text::FORM_FEED;
+use crate::entities::document::DocDetails;
+use crate::entities::extension::Ext;
+use crate::entities::location::Location;
+use crate::result::{ExtractorErr, GeneralErr};
+use crate::use_cases::config::ArchivesConfig;
+use crate::use_cases::services::extractor::DataExtractor;
+
+use calamine::{Reader as _, Xlsx};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use std::io::{self, Cursor, Read, Write};
+use tracing::instrument;
+use zip::read::ZipFile;
+use zip::ZipArchive;
+
+/// Extracts text from DOCX, ODT and XLSX documents.
+///
+/// The documents are ZIP archives keeping the content as XML. Explicit page breaks of DOCX
+/// documents separate the pages, each sheet of XLSX workbook is a separate page. All files pointed
+/// by `paths` are processed in parallel.
+#[derive(Debug)]
+pub struct FromOffice {
+    limits: ArchivesConfig,
+}
+
+impl FromOffice {
+    pub fn new(limits: ArchivesConfig) -> Self {
+        Self { limits }
+    }
+}
+
+impl DataExtractor for FromOffice {
+    #[instrument(skip(self))]
+    fn extract_data(&self, location: &Location) -> Result<Vec<DocDetails>, ExtractorErr> {
+        extract_pages(location, |doc, ext| office_pages(doc, ext, &self.limits))
+    }
+}
+
+/// Reads the text of the office document, page by page.
+///
+/// The document is a ZIP archive, so it's read within the `limits` of the archives, the same way
+/// as the archives sent by the users.
+pub fn office_pages(
+    doc: &[u8],
+    ext: &Ext,
+    limits: &ArchivesConfig,
+) -> Result<Vec<String>, ExtractorErr> {
+    match ext {
+        Ext::Docx => xml_pages(
+            &archive_entry(doc, "word/document.xml", limits)?,
+            Some(b"t"),
+        ),
+        Ext::Odt => xml_pages(&archive_entry(doc, "content.xml", limits)?, None),
+        Ext::Xlsx => {
+            // NOTE: calamine reads the entries without any limits, so all of them are checked first
+            check_entries(doc, limits)?;
+            xlsx_pages(doc)
+        }
+        _ => Err(GeneralErr::InvalidExtension.into()),
+    }
+}
+
+fn archive_entry(doc: &[u8], name: &str, limits: &ArchivesConfig) -> Result<Vec<u8>, ExtractorErr> {
+    let mut archive = ZipArchive::new(Cursor::new(doc))?;
+    let mut xml = Vec::new();
+    read_limited(archive.by_name(name)?, limits, &mut xml)?;
+    Ok(xml)
+}
+
+/// Unpacks all entries of the archive, without keeping them, as the sizes declared in the archive
+/// can't be trusted.
+fn check_entries(doc: &[u8], limits: &ArchivesConfig) -> Result<(), ExtractorErr> {
+    let mut archive = ZipArchive::new(Cursor::new(doc))?;
+    let mut total = 0_u64;
+    for i in 0..archive.len() {
+        let entry = archive.by_index(i)?;
+        let name = entry.name().to_string();
+        total = total.saturating_add(read_limited(entry, limits, &mut io::sink())?);
+        if total > limits.max_total_bytes {
+            return Err(ExtractorErr::TooLarge(name));
+        }
+    }
+    Ok(())
+}
+
+/// Writes the unpacked `entry` to `out`, failing when it's larger than allowed or suspiciously
+/// well compressed. Returns the number of unpacked bytes.
+fn read_limited<W: Write>(
+    entry: ZipFile,
+    limits: &ArchivesConfig,
+    out: &mut W,
+) -> Result<u64, ExtractorErr> {
+    let name = entry.name().to_string();
+    let max_bytes = limits
+        .max_member_bytes
+        .min(entry.compressed_size().saturating_mul(limits.max_ratio));
+    let len = io::copy(&mut entry.take(max_bytes.saturating_add(1)), out)?;
+    if len > max_bytes {
+        return Err(ExtractorErr::TooLarge(name));
+    }
+    Ok(len)
+}
+
+/// Collects the text of the document, ending lines with the paragraphs and headings.
+///
+/// When `text_tag` is given, only the text inside of such elements is collected. DOCX keeps e.g.
+/// field codes outside of them.
+fn xml_pages(xml: &[u8], text_tag: Option<&[u8]>) -> Result<Vec<String>, ExtractorErr> {
+    let mut reader = Reader::from_reader(xml);
+    let mut buf = Vec::new();
+    let mut text = String::new();
+    let mut in_text = text_tag.is_none();
+    loop {
+        match reader.read_event_into(&mut buf)? {
+            Event::Start(e) if Some(e.local_name().as_ref()) == text_tag => in_text = true,
+            Event::End(e) if Some(e.local_name().as_ref()) == text_tag => in_text = false,
+            Event::End(e) if matches!(e.local_name().as_ref(), b"p" | b"h") => text.push('\n'),
+            Event::Empty(e) => match e.local_name().as_ref() {
+                b"tab" => text.push('\t'),
+                b"s" => text.push(' '),
+                b"br" if is_page_break(&e)? => text.push(FORM_FEED),
+                b"br" | b"line-break" => text.push('\n'),
+                _ => {}
+            },
+            Event::Text(e) if in_text => text.push_str(&e.unescape()?),
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+    Ok(text.split(FORM_FEED).map(ToString::to_string).collect())
+}
+
+fn is_page_break(br: &BytesStart) -> Result<bool, ExtractorErr> {
+    let kind = br.try_get_attribute("w:type")?;
+    Ok(kind.map_or(false, |kind| kind.value.as_ref() == b"page"))
+}
+
+/// Each sheet starts with its name, cells of the rows are separated with tabs.
+fn xlsx_pages(doc: &[u8]) -> Result<Vec<String>, ExtractorErr> {
+    let mut workbook: Xlsx<_> = Xlsx::new(Cursor::new(doc))?;
+    Ok(workbook
+        .worksheets()
+        .into_iter()
+        .map(|(name, range)| {
+            let rows = range.rows().map(|row| {
+                row.iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<String>>()
+                    .join("\t")
+            });
+            std::iter::once(name)
+                .chain(rows)
+                .collect::<Vec<String>>()
+                .join("\n")
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use anyhow::Result;
+    use claim::assert_matches;
+    use std::fs;
+
+    #[test]
+    fn docx_is_split_into_pages_at_page_breaks() -> Result<()> {
+        // given
+        let doc = fs::read("res/doc9.docx")?;
+
+        // when
+        let pages = office_pages(&doc, &Ext::Docx, &ArchivesConfig::default())?;
+
+        // then
+        assert_eq!(pages.len(), 2);
+        assert!(pages[0].contains("Wynajmujący oddaje najemcy lokal."));
+        assert!(pages[1].contains("Czynsz płatny do dziesiątego dnia"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn text_of_odt_paragraphs_is_extracted() -> Result<()> {
+        // given
+        let doc = fs::read("res/doc10.odt")?;
+
+        // when
+        let pages = office_pages(&doc, &Ext::Odt, &ArchivesConfig::default())?;
+
+        // then
+        assert_eq!(pages.len(), 1);
+        assert!(pages[0].contains("Protokół zebrania wspólnoty\n"));
+        assert!(pages[0].contains("Zebranie otwarto o godzinie"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn each_xlsx_sheet_is_a_page() -> Result<()> {
+        // given
+        let doc = fs::read("res/doc11.xlsx")?;
+
+        // when
+        let pages = office_pages(&doc, &Ext::Xlsx, &ArchivesConfig::default())?;
+
+        // then
+        assert_eq!(pages.len(), 2);
+        assert!(pages[0].starts_with("Wydatki"));
+        assert!(pages[0].contains("Ogrzewanie\t420"));
+        assert!(pages[1].contains("Wynagrodzenie\t5200"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn document_exceeding_limits_of_archives_is_not_read() -> Result<()> {
+        // given
+        let docx = fs::read("res/doc9.docx")?;
+        let xlsx = fs::read("res/doc11.xlsx")?;
+        let limits = ArchivesConfig {
+            max_member_bytes: 64,
+            ..ArchivesConfig::default()
+        };
+
+        // when
+        let docx_pages = office_pages(&docx, &Ext::Docx, &limits);
+        let xlsx_pages = office_pages(&xlsx, &Ext::Xlsx, &limits);
+
+        // then
+        assert_matches!(docx_pages, Err(ExtractorErr::TooLarge(_)));
+        assert_matches!(xlsx_pages, Err(ExtractorErr::TooLarge(_)));
+
+        Ok(())
+    }
+}
@@ -1,23 +1,77 @@
 //! Allows to extract text from PDF.
-use crate::entities::document::DocDetails;
+use crate::data_providers::extractor::image::Ocr;
+use crate::data_providers::pdf::{render_page, POINTS_PER_INCH};
+use crate::entities::document::{DocDetails, Page};
 use crate::entities::file::{Filename, Thumbnailname};
 use crate::entities::location::{Location, SafePathBuf};
 use crate::entities::user::User;
 use crate::result::ExtractorErr;
+use crate::use_cases::config::{OcrConfig, ThumbnailFormat};
+use crate::use_cases::ocr::{LanguageOverrides, OcrLanguages};
 use crate::use_cases::services::extractor::DataExtractor;
 
-use pdf_extract::extract_text;
+use leptess::LepTess;
+use poppler::{PopplerDocument, PopplerPage};
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 use std::convert::TryFrom;
-use std::fmt::Debug;
-use tracing::{instrument, trace};
+use tracing::{debug, instrument, trace, warn};
+
+/// Pages with less characters are considered to have no text layer, e.g. scanned ones.
+const MIN_PAGE_CHARS: usize = 16;
 
 /// Extracts text from PDF file.
 ///
-/// It uses [`extract_text`] to extract text. All files pointed by `paths` are processed in
-/// parallel.
-#[derive(Debug, Default)]
-pub struct FromPdf;
+/// It uses the text layer of the pages. Pages without it, e.g. produced by scanners, are rendered
+/// and their text is recognized with OCR. All files pointed by `paths` are processed in parallel.
+pub struct FromPdf {
+    ocr: Ocr,
+    overrides: LanguageOverrides,
+    format: ThumbnailFormat,
+}
+
+impl FromPdf {
+    pub fn new(cfg: OcrConfig, overrides: LanguageOverrides, format: ThumbnailFormat) -> Self {
+        Self {
+            ocr: Ocr::new(cfg),
+            overrides,
+            format,
+        }
+    }
+
+    #[instrument(skip(self))]
+    fn extract(&self, path: &SafePathBuf) -> Result<DocDetails, ExtractorErr> {
+        let filename = Filename::from(path);
+        let thumbnailname = Thumbnailname::of(path, self.format.ext())?;
+        let user = User::try_from(path)?;
+        let requested = self.overrides.take(&user, &filename)?;
+        let doc = PopplerDocument::new_from_file(path, "")?;
+        let mut scanned = ScannedPages::new(&self.ocr, requested);
+        let pages: Vec<Page> = (0..doc.get_n_pages())
+            .filter_map(|idx| Some((page_number(idx)?, doc.get_page(idx)?)))
+            .map(|(number, page)| {
+                let text = page.get_text().unwrap_or_default();
+                if has_text_layer(text) {
+                    return Page::new(number, text);
+                }
+                debug!(
+                    "page {} of '{}' has no text, executing OCR",
+                    number, filename
+                );
+                let text = scanned.recognize(&page).unwrap_or_else(|e| {
+                    warn!("OCR of page {} of '{}' failed: '{}'", number, filename, e);
+                    text.to_string()
+                });
+                Page::new(number, text)
+            })
+            .collect();
+        trace!("extracted pages: '{:?}'", pages);
+        let details = DocDetails::new(filename, "", thumbnailname, user).pages(pages);
+        Ok(match scanned.confidence() {
+            Some(confidence) => details.confidence(confidence),
+            None => details,
+        })
+    }
+}
 
 impl DataExtractor for FromPdf {
     #[instrument(skip(self))]
@@ -25,24 +79,65 @@ impl DataExtractor for FromPdf {
         let Location::FS(paths) = location;
         Ok(paths
             .par_iter()
-            .map(extract)
+            .map(|path| self.extract(path))
             .filter_map(Result::ok)
             .collect::<Vec<DocDetails>>())
     }
 }
 
-#[instrument]
-fn extract(path: &SafePathBuf) -> Result<DocDetails, ExtractorErr> {
-    let filename = Filename::from(path);
-    let text = extract_text(path)?;
-    let thumbnailname = Thumbnailname::new(thumbnail_name(path))?;
-    let user = User::try_from(path)?;
-    trace!("extracted text: '{}'", text);
-    Ok(DocDetails::new(filename, text, thumbnailname, user))
+/// Pages are numbered from 1, poppler counts them from 0.
+fn page_number(idx: usize) -> Option<u32> {
+    u32::try_from(idx + 1).ok()
+}
+
+fn has_text_layer(text: &str) -> bool {
+    text.chars().filter(|c| !c.is_whitespace()).count() >= MIN_PAGE_CHARS
 }
 
-fn thumbnail_name(path: &SafePathBuf) -> String {
-    format!("{}.png", path.filestem())
+/// Recognizes the text of the pages without text layer. Tesseract is created only when the
+/// document has such pages.
+struct ScannedPages<'a> {
+    ocr: &'a Ocr,
+    languages: Option<OcrLanguages>,
+    tesseract: Option<LepTess>,
+    confidences: Vec<u8>,
+}
+
+impl<'a> ScannedPages<'a> {
+    fn new(ocr: &'a Ocr, languages: Option<OcrLanguages>) -> Self {
+        Self {
+            ocr,
+            languages,
+            tesseract: None,
+            confidences: Vec::new(),
+        }
+    }
+
+    fn recognize(&mut self, page: &PopplerPage) -> Result<String, ExtractorErr> {
+        let dpi = self.ocr.render_dpi();
+        let surface = render_page(page, f64::from(dpi) / POINTS_PER_INCH)?;
+        let mut png = Vec::new();
+        surface.write_to_png(&mut png)?;
+        let lt = match &mut self.tesseract {
+            Some(lt) => lt,
+            None => self
+                .tesseract
+                .insert(self.ocr.tesseract(self.languages.as_ref())?),
+        };
+        let dpi = self.ocr.load_png(lt, &png, dpi)?;
+        let (text, confidence) = self.ocr.recognize(lt, Some(dpi))?;
+        self.confidences.push(confidence);
+        Ok(text)
+    }
+
+    /// Mean confidence of the recognized pages. `None` when no page was recognized.
+    fn confidence(&self) -> Option<u8> {
+        if self.confidences.is_empty() {
+            return None;
+        }
+        let sum: u64 = self.confidences.iter().copied().map(u64::from).sum();
+        u8::try_from(sum / self.confidences.len() as u64).ok()
+    }
 }
 
 #[cfg(test)]
@@ -50,12 +145,14 @@ mod test {
     use super::*;
 
     use crate::entities::location::SafePathBuf;
+    use crate::testingtools::services::ocr::stub;
+
     use anyhow::Result;
 
     #[test]
     fn test_extract_text() -> Result<()> {
         // given
-        let pdf = FromPdf;
+        let pdf = FromPdf::new(OcrConfig::default(), stub(), ThumbnailFormat::default());
         let paths = vec![
             SafePathBuf::from("res/doc1.pdf"),
             SafePathBuf::from("res/doc2.pdf"),
@@ -69,13 +166,45 @@ mod test {
         let first_doc = &result[0];
         let second_doc = &result[1];
 
-        assert!(first_doc.body.contains("Jak zainstalować scaner"));
+        assert!(first_doc.body().contains("Jak zainstalować scaner"));
         assert_eq!(first_doc.filename, Filename::new("doc1.pdf")?);
-        assert_eq!(first_doc.thumbnail, Thumbnailname::new("doc1.png")?);
+        assert_eq!(first_doc.thumbnail, Thumbnailname::new("doc1.pdf.webp")?);
 
-        assert!(second_doc.body.contains("Podmiot powierzający"));
+        assert!(second_doc.body().contains("Podmiot powierzający"));
         assert_eq!(second_doc.filename, Filename::new("doc2.pdf")?);
-        assert_eq!(second_doc.thumbnail, Thumbnailname::new("doc2.png")?);
+        assert_eq!(second_doc.thumbnail, Thumbnailname::new("doc2.pdf.webp")?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn text_of_scanned_pdf_is_recognized() -> Result<()> {
+        // given
+        let pdf = FromPdf::new(OcrConfig::default(), stub(), ThumbnailFormat::default());
+        let paths = vec![SafePathBuf::from("res/scanned.pdf")];
+
+        // when
+        let result = pdf.extract_data(&Location::FS(paths))?;
+
+        // then
+        assert!(result[0].body().contains("Szanowny Panie"));
+        assert!(result[0].confidence.is_some());
+
+        Ok(())
+    }
+
+    #[test]
+    fn text_is_extracted_page_by_page() -> Result<()> {
+        // given
+        let pdf = FromPdf::new(OcrConfig::default(), stub(), ThumbnailFormat::default());
+        let paths = vec![SafePathBuf::from("res/doc2.pdf")];
+
+        // when
+        let result = pdf.extract_data(&Location::FS(paths))?;
+
+        // then
+        let numbers: Vec<u32> = result[0].pages.iter().map(|page| page.number).collect();
+        assert_eq!(numbers, vec![1, 2]);
 
         Ok(())
     }
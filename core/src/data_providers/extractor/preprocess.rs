@@ -0,0 +1,246 @@
+//! Cleanup of the images before the text recognition.
+//!
+//! Each step can be disabled in [`PreprocessingConfig`]. The effect of the preprocessing on the
+//! fixtures from `res/` can be compared with `cargo make ocr-report`.
+use crate::result::ExtractorErr;
+use crate::use_cases::config::PreprocessingConfig;
+
+use exif::{In, Tag};
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, GrayImage, ImageOutputFormat, Luma};
+use imageproc::contrast::{adaptive_threshold, otsu_level, threshold};
+use imageproc::filter::median_filter;
+use imageproc::geometric_transformations::{rotate_about_center, Interpolation};
+use std::fs::File;
+use std::io::{BufReader, Cursor};
+use std::path::Path;
+use tracing::{debug, trace};
+
+/// Width of A4 page. Photos of documents are assumed to show a single page.
+const A4_WIDTH_INCHES: f64 = 8.27;
+/// Small images are not upscaled more, the text wouldn't get more readable anyway.
+const MAX_UPSCALE: f64 = 4.0;
+/// Skew is estimated on a smaller copy of the image, which is much faster.
+const SKEW_SAMPLE_WIDTH: f64 = 600.0;
+const WHITE: Luma<u8> = Luma([255]);
+
+/// Image ready for the text recognition, encoded as PNG.
+#[derive(Debug)]
+pub struct Prepared {
+    pub png: Vec<u8>,
+    /// Resolution of the image, when it's known after the preprocessing.
+    pub dpi: Option<u16>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Preprocessor {
+    cfg: PreprocessingConfig,
+}
+
+impl Preprocessor {
+    pub fn new(cfg: PreprocessingConfig) -> Self {
+        Self { cfg }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.cfg.enabled
+    }
+
+    /// Loads the image from the file, rotating it according to its EXIF orientation.
+    pub fn prepare_file<P: AsRef<Path>>(&self, path: P) -> Result<Prepared, ExtractorErr> {
+        let path = path.as_ref();
+        let mut img = image::open(path)?;
+        if self.cfg.exif_rotation {
+            img = rotate_by_exif(img, exif_orientation(path));
+        }
+        self.prepare(img, None)
+    }
+
+    /// Prepares the image of known resolution, e.g. rendered PDF page.
+    pub fn prepare(&self, img: DynamicImage, dpi: Option<u16>) -> Result<Prepared, ExtractorErr> {
+        let mut gray = img.to_luma8();
+        let mut dpi = dpi;
+        if let Some(factor) = self.upscale_factor(&gray, dpi) {
+            debug!("upscaling image {} times", factor);
+            gray = resize(&gray, factor, FilterType::CatmullRom);
+            dpi = Some(self.cfg.target_dpi);
+        }
+        if self.cfg.denoise {
+            gray = median_filter(&gray, 1, 1);
+        }
+        if self.cfg.deskew {
+            let angle = skew_angle(&gray, self.cfg.max_skew_degrees);
+            if angle.abs() > f32::EPSILON {
+                debug!("straightening image skewed by {} degrees", angle);
+                gray =
+                    rotate_about_center(&gray, -angle.to_radians(), Interpolation::Bilinear, WHITE);
+            }
+        }
+        if self.cfg.binarize {
+            gray = adaptive_threshold(&gray, self.cfg.binarization_radius);
+        }
+        let mut png = Vec::new();
+        DynamicImage::ImageLuma8(gray)
+            .write_to(&mut Cursor::new(&mut png), ImageOutputFormat::Png)?;
+        Ok(Prepared { png, dpi })
+    }
+
+    fn upscale_factor(&self, gray: &GrayImage, dpi: Option<u16>) -> Option<f64> {
+        if self.cfg.target_dpi == 0 {
+            return None;
+        }
+        let dpi = dpi.map_or_else(
+            || f64::from(gray.width().min(gray.height())) / A4_WIDTH_INCHES,
+            f64::from,
+        );
+        let factor = (f64::from(self.cfg.target_dpi) / dpi).min(MAX_UPSCALE);
+        (factor > 1.0).then(|| factor)
+    }
+}
+
+pub fn exif_orientation(path: &Path) -> u32 {
+    let orientation = File::open(path).ok().and_then(|file| {
+        let exif = exif::Reader::new()
+            .read_from_container(&mut BufReader::new(file))
+            .ok()?;
+        exif.get_field(Tag::Orientation, In::PRIMARY)?
+            .value
+            .get_uint(0)
+    });
+    trace!(
+        "EXIF orientation of '{}': {:?}",
+        path.display(),
+        orientation
+    );
+    orientation.unwrap_or(1)
+}
+
+/// Rotates the image, so it's displayed the same way as by the viewers respecting EXIF.
+pub fn rotate_by_exif(img: DynamicImage, orientation: u32) -> DynamicImage {
+    match orientation {
+        2 => img.fliph(),
+        3 => img.rotate180(),
+        4 => img.flipv(),
+        5 => img.rotate90().fliph(),
+        6 => img.rotate90(),
+        7 => img.rotate270().fliph(),
+        8 => img.rotate270(),
+        _ => img,
+    }
+}
+
+/// Finds the angle, in degrees, at which the lines of the text are horizontal.
+///
+/// Rows of the straightened text alternate between dark lines of text and white gaps, so the
+/// differences between the numbers of dark pixels in neighbouring rows are the biggest.
+fn skew_angle(gray: &GrayImage, max_degrees: u8) -> f32 {
+    let factor = (SKEW_SAMPLE_WIDTH / f64::from(gray.width())).min(1.0);
+    let sample = resize(gray, factor, FilterType::Triangle);
+    let sample = threshold(&sample, otsu_level(&sample));
+    let steps = i16::from(max_degrees) * 2;
+    let mut best = (0.0, rows_contrast(&sample));
+    for step in (-steps..=steps).filter(|step| *step != 0) {
+        let angle = f32::from(step) / 2.0;
+        let rotated =
+            rotate_about_center(&sample, angle.to_radians(), Interpolation::Nearest, WHITE);
+        let contrast = rows_contrast(&rotated);
+        if contrast > best.1 {
+            best = (angle, contrast);
+        }
+    }
+    // NOTE: rotating the sample by the angle straightens it, the image is skewed the other way
+    -best.0
+}
+
+fn rows_contrast(binary: &GrayImage) -> i64 {
+    let dark_in_rows: Vec<i64> = binary
+        .rows()
+        .map(|row| row.filter(|px| px.0[0] < 128).map(|_| 1).sum())
+        .collect();
+    dark_in_rows
+        .windows(2)
+        .map(|pair| (pair[0] - pair[1]).pow(2))
+        .sum()
+}
+
+fn resize(gray: &GrayImage, factor: f64, filter: FilterType) -> GrayImage {
+    if (factor - 1.0).abs() < f64::EPSILON {
+        return gray.clone();
+    }
+    imageops::resize(
+        gray,
+        scaled(gray.width(), factor),
+        scaled(gray.height(), factor),
+        filter,
+    )
+}
+
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn scaled(size: u32, factor: f64) -> u32 {
+    (f64::from(size) * factor).round().max(1.0) as u32
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use anyhow::Result;
+    use claim::{assert_ge, assert_le, assert_some_eq};
+
+    fn lines(width: u32, height: u32) -> GrayImage {
+        GrayImage::from_fn(width, height, |x, y| {
+            let margin = width / 8;
+            if x > margin && x < width - margin && (y / 10) % 3 == 0 {
+                Luma([0])
+            } else {
+                WHITE
+            }
+        })
+    }
+
+    #[test]
+    fn skew_of_lines_is_found() {
+        // given
+        let skewed = rotate_about_center(
+            &lines(800, 800),
+            3_f32.to_radians(),
+            Interpolation::Bilinear,
+            WHITE,
+        );
+
+        // when
+        let angle = skew_angle(&skewed, 10);
+
+        // then
+        assert_le!((angle - 3.0).abs(), 0.5);
+    }
+
+    #[test]
+    fn small_images_are_upscaled_to_target_dpi() -> Result<()> {
+        // given
+        let preprocessor = Preprocessor::new(PreprocessingConfig::default());
+        let small = DynamicImage::ImageLuma8(lines(400, 600));
+
+        // when
+        let prepared = preprocessor.prepare(small, Some(100))?;
+
+        // then
+        let img = image::load_from_memory(&prepared.png)?;
+        assert_some_eq!(prepared.dpi, 300);
+        assert_ge!(img.width(), 1199);
+
+        Ok(())
+    }
+
+    #[test]
+    fn image_is_rotated_according_to_exif_orientation() {
+        // given
+        let img = DynamicImage::ImageLuma8(lines(200, 100));
+
+        // when
+        let rotated = rotate_by_exif(img, 6);
+
+        // then
+        assert_eq!((rotated.width(), rotated.height()), (100, 200));
+    }
+}
@@ -0,0 +1,304 @@
+//! Allows to extract text from plain text, Markdown, HTML and RTF documents.
+use crate::data_providers::extractor::extract_pages;
+use crate::entities::document::DocDetails;
+use crate::entities::extension::Ext;
+use crate::entities::location::Location;
+use crate::result::{ExtractorErr, GeneralErr};
+use crate::use_cases::services::extractor::DataExtractor;
+
+use encoding_rs::{Encoding, WINDOWS_1252};
+use html2text::render::text_renderer::TrivialDecorator;
+use pulldown_cmark::{Event, Parser, Tag};
+use tracing::instrument;
+
+/// Separates the pages of plain text, the same way `pdftotext` does it.
+pub const FORM_FEED: char = '\u{c}';
+/// HTML is rendered to lines of this width. Longer lines are easier to search.
+const HTML_WIDTH: usize = 120;
+/// Groups of RTF document, which are not part of the text.
+const RTF_SKIPPED_GROUPS: [&[u8]; 9] = [
+    b"fonttbl",
+    b"colortbl",
+    b"stylesheet",
+    b"info",
+    b"pict",
+    b"listtable",
+    b"listoverridetable",
+    b"themedata",
+    b"datastore",
+];
+
+/// Extracts text from plain text, Markdown, HTML and RTF documents.
+///
+/// Markup is removed, so only the text is indexed. Plain text can be split into pages with form
+/// feeds. All files pointed by `paths` are processed in parallel.
+#[derive(Debug)]
+pub struct FromText;
+
+impl DataExtractor for FromText {
+    #[instrument(skip(self))]
+    fn extract_data(&self, location: &Location) -> Result<Vec<DocDetails>, ExtractorErr> {
+        extract_pages(location, text_pages)
+    }
+}
+
+/// Reads the text of the document, page by page.
+pub fn text_pages(doc: &[u8], ext: &Ext) -> Result<Vec<String>, ExtractorErr> {
+    let text = match ext {
+        Ext::Txt => String::from_utf8_lossy(doc).into_owned(),
+        Ext::Md => markdown_text(&String::from_utf8_lossy(doc)),
+        Ext::Html => html2text::from_read_with_decorator(doc, HTML_WIDTH, TrivialDecorator::new()),
+        Ext::Rtf => RtfReader::new(doc).text(),
+        _ => return Err(GeneralErr::InvalidExtension.into()),
+    };
+    Ok(text
+        .trim_end()
+        .split(FORM_FEED)
+        .map(ToString::to_string)
+        .collect())
+}
+
+fn markdown_text(md: &str) -> String {
+    let mut text = String::new();
+    for event in Parser::new(md) {
+        match event {
+            Event::Text(t) | Event::Code(t) => text.push_str(&t),
+            Event::SoftBreak
+            | Event::HardBreak
+            | Event::Rule
+            | Event::End(Tag::Paragraph | Tag::Heading(..) | Tag::Item | Tag::CodeBlock(_)) => {
+                text.push('\n');
+            }
+            _ => {}
+        }
+    }
+    text
+}
+
+/// Reads the text of RTF document, skipping the formatting.
+///
+/// Characters escaped as `\'hh` are decoded with the code page of the document, Unicode characters
+/// are used instead of their replacements.
+struct RtfReader<'a> {
+    doc: &'a [u8],
+    pos: usize,
+    text: String,
+    /// Bytes in the code page of the document, waiting to be decoded.
+    encoded: Vec<u8>,
+    encoding: &'static Encoding,
+    /// Number of replacement characters following each Unicode character.
+    unicode_replacements: usize,
+}
+
+impl<'a> RtfReader<'a> {
+    fn new(doc: &'a [u8]) -> Self {
+        Self {
+            doc,
+            pos: 0,
+            text: String::new(),
+            encoded: Vec::new(),
+            encoding: WINDOWS_1252,
+            unicode_replacements: 1,
+        }
+    }
+
+    fn text(mut self) -> String {
+        while let Some(byte) = self.advance() {
+            match byte {
+                b'{' if self.is_skipped_group() => self.skip_group(),
+                b'{' | b'}' | b'\r' | b'\n' => {}
+                b'\\' => self.control(),
+                _ => self.encoded.push(byte),
+            }
+        }
+        self.flush();
+        self.text
+    }
+
+    fn advance(&mut self) -> Option<u8> {
+        let byte = self.doc.get(self.pos).copied();
+        self.pos += 1;
+        byte
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.doc.get(self.pos).copied()
+    }
+
+    fn control(&mut self) {
+        match self.advance() {
+            Some(b'\'') => {
+                if let Some(byte) = self.hex_byte() {
+                    self.encoded.push(byte);
+                }
+            }
+            Some(byte @ (b'\\' | b'{' | b'}')) => self.encoded.push(byte),
+            Some(b'~') => self.push(' '),
+            Some(b'_') => self.push('-'),
+            Some(b'\r' | b'\n') => self.push('\n'),
+            Some(byte) if byte.is_ascii_alphabetic() => {
+                self.pos -= 1;
+                let (word, param) = self.control_word();
+                self.apply(&word, param);
+            }
+            _ => {}
+        }
+    }
+
+    fn control_word(&mut self) -> (Vec<u8>, Option<i32>) {
+        let mut word = Vec::new();
+        while let Some(byte) = self.peek().filter(u8::is_ascii_alphabetic) {
+            word.push(byte);
+            self.pos += 1;
+        }
+        let mut param = String::new();
+        while let Some(byte) = self.peek().filter(|b| b.is_ascii_digit() || *b == b'-') {
+            param.push(char::from(byte));
+            self.pos += 1;
+        }
+        if self.peek() == Some(b' ') {
+            self.pos += 1;
+        }
+        (word, param.parse().ok())
+    }
+
+    fn apply(&mut self, word: &[u8], param: Option<i32>) {
+        match (word, param) {
+            (b"par" | b"line", _) => self.push('\n'),
+            (b"tab", _) => self.push('\t'),
+            (b"page", _) => self.push(FORM_FEED),
+            (b"u", Some(code)) => {
+                let code = if code < 0 { code + 0x10000 } else { code };
+                if let Some(c) = u32::try_from(code).ok().and_then(char::from_u32) {
+                    self.push(c);
+                }
+                self.skip_replacements();
+            }
+            (b"uc", Some(count)) => self.unicode_replacements = usize::try_from(count).unwrap_or(1),
+            (b"ansicpg", Some(page)) => {
+                let label = format!("windows-{page}");
+                self.encoding = Encoding::for_label(label.as_bytes()).unwrap_or(WINDOWS_1252);
+            }
+            _ => {}
+        }
+    }
+
+    fn skip_replacements(&mut self) {
+        for _ in 0..self.unicode_replacements {
+            match self.peek() {
+                Some(b'\\') if self.doc.get(self.pos + 1) == Some(&b'\'') => self.pos += 4,
+                Some(b'\\' | b'{' | b'}') | None => break,
+                Some(_) => self.pos += 1,
+            }
+        }
+    }
+
+    fn hex_byte(&mut self) -> Option<u8> {
+        let hex = self.doc.get(self.pos..self.pos + 2)?;
+        self.pos += 2;
+        u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok()
+    }
+
+    /// Ignorable destinations (`{\*\...}`) and the tables of fonts, colors, etc. are skipped.
+    fn is_skipped_group(&self) -> bool {
+        let rest = &self.doc[self.pos..];
+        rest.starts_with(b"\\*")
+            || RTF_SKIPPED_GROUPS.iter().any(|group| {
+                rest.strip_prefix(b"\\")
+                    .and_then(|word| word.strip_prefix(*group))
+                    .map_or(false, |after| {
+                        !after.first().map_or(false, u8::is_ascii_alphabetic)
+                    })
+            })
+    }
+
+    fn skip_group(&mut self) {
+        let mut depth = 1;
+        while depth > 0 {
+            match self.advance() {
+                Some(b'\\') => self.pos += 1,
+                Some(b'{') => depth += 1,
+                Some(b'}') => depth -= 1,
+                Some(_) => {}
+                None => break,
+            }
+        }
+    }
+
+    fn push(&mut self, c: char) {
+        self.flush();
+        self.text.push(c);
+    }
+
+    fn flush(&mut self) {
+        if !self.encoded.is_empty() {
+            let (decoded, _) = self.encoding.decode_without_bom_handling(&self.encoded);
+            self.text.push_str(&decoded);
+            self.encoded.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use anyhow::Result;
+    use std::fs;
+
+    #[test]
+    fn text_is_split_into_pages_at_form_feeds() -> Result<()> {
+        // given
+        let doc = "first page\u{c}second page\n\u{c}\n";
+
+        // when
+        let pages = text_pages(doc.as_bytes(), &Ext::Txt)?;
+
+        // then
+        assert_eq!(pages, vec!["first page", "second page"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn markup_is_removed_from_markdown_and_html() -> Result<()> {
+        // given
+        let md = fs::read("res/doc14.md")?;
+        let html = fs::read("res/doc15.html")?;
+
+        // when
+        let md_pages = text_pages(&md, &Ext::Md)?;
+        let html_pages = text_pages(&html, &Ext::Html)?;
+
+        // then
+        assert!(md_pages[0].starts_with("Instrukcja obsługi pralki\n"));
+        assert!(md_pages[0].contains("Przed pierwszym uruchomieniem usuń"));
+        assert!(md_pages[0].contains("naciśnij start"));
+        assert!(html_pages[0].contains("Potwierdzenie rezerwacji"));
+        assert!(html_pages[0].contains("Pokój dwuosobowy"));
+        assert!(!html_pages[0].contains("<p>"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn rtf_text_is_decoded_with_document_code_page() -> Result<()> {
+        // given
+        let doc = fs::read("res/doc12.rtf")?;
+
+        // when
+        let pages = text_pages(&doc, &Ext::Rtf)?;
+
+        // then
+        assert_eq!(
+            pages,
+            vec![
+                "Zawiadomienie o zmianie taryfy\n\
+                 Od nowego roku opłata za wodę wzrośnie.\n\
+                 Prosimy o terminową wpłatę."
+            ]
+        );
+
+        Ok(())
+    }
+}
@@ -5,11 +5,10 @@ use crate::entities::location::SafePathBuf;
 use crate::result::FsErr;
 use crate::use_cases::fs::Filesystem;
 
-use std::fs::{self, create_dir_all};
+use std::fs::{self, create_dir_all, OpenOptions};
+use std::io::Write;
 use std::path::{Path, PathBuf};
-use std::thread;
-use std::time::Duration;
-use tracing::{debug, instrument};
+use tracing::{debug, error, instrument, warn};
 
 pub struct LocalFs;
 
@@ -17,13 +16,7 @@ impl Filesystem for LocalFs {
     #[instrument(skip(self, buf))]
     fn save(&self, uri: PathBuf, buf: &[u8]) -> Result<(), FsErr> {
         let parent_dir = uri.parent().expect("failed to get parent dir");
-        if !parent_dir.exists() {
-            create_dir_all(parent_dir)?;
-            // NOTE: this is needed because when file creation happens immediately after directory
-            // creation, then the file creation event is not yet registered by filesystem watching
-            // library
-            thread::sleep(Duration::from_secs(1)); // allow to start watching for new directory
-        }
+        create_dir_all(parent_dir)?;
         fs::write(uri, buf)?;
         Ok(())
     }
@@ -42,9 +35,67 @@ impl Filesystem for LocalFs {
 
     #[instrument(skip(self))]
     fn mv_file(&self, from: &SafePathBuf, to: &Path) -> Result<(), FsErr> {
+        let parent_dir = to.parent().expect("failed to get parent dir");
+        create_dir_all(parent_dir)?;
         fs::rename(from, to)?;
         Ok(())
     }
+
+    #[instrument(skip(self))]
+    fn rm_dirs(&self, dirs: &[PathBuf]) -> Result<(), FsErr> {
+        // NOTE: directories are renamed first, because renaming is atomic and can be reverted
+        // when renaming of any other directory fails
+        let mut renamed = Vec::new();
+        for dir in dirs.iter().filter(|dir| dir.exists()) {
+            let removed = removed_path(dir);
+            if let Err(e) = fs::rename(dir, &removed) {
+                restore(&renamed);
+                return Err(e.into());
+            }
+            renamed.push((dir, removed));
+        }
+        for (_, removed) in renamed {
+            debug!("removing '{}'", removed.display());
+            if let Err(e) = fs::remove_dir_all(&removed) {
+                // NOTE: directory is already out of the way, it's only left on the disk
+                warn!("failed to remove '{}': '{}'", removed.display(), e);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Writes the secret, e.g. a key, to a file readable only by its owner. The permissions are set
+/// when the file is created, so the secret is never readable by others, even for a moment.
+pub fn write_secret(path: &Path, secret: &[u8]) -> std::io::Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)?.write_all(secret)
+}
+
+/// Hidden sibling of the `dir`. Its name is not a base64 encoded email, so it's not mistaken
+/// for a directory of any user.
+fn removed_path(dir: &Path) -> PathBuf {
+    let name = dir.file_name().unwrap_or_default().to_string_lossy();
+    dir.with_file_name(format!(".{name}.removed"))
+}
+
+fn restore(renamed: &[(&PathBuf, PathBuf)]) {
+    for (dir, removed) in renamed.iter().rev() {
+        if let Err(e) = fs::rename(removed, dir) {
+            error!(
+                "failed to restore '{}' from '{}': '{}'",
+                dir.display(),
+                removed.display(),
+                e
+            );
+        }
+    }
 }
 
 #[cfg(test)]
@@ -52,13 +103,13 @@ mod test {
     use super::*;
 
     use anyhow::Result;
-    use claim::{assert_gt, assert_lt, assert_matches, assert_ok_eq};
+    use claim::{assert_lt, assert_matches, assert_ok_eq};
     use fake::faker::filesystem::en::FilePath;
     use fake::faker::lorem::en::Paragraph;
     use fake::Fake;
     use fs::read_to_string;
     use std::io::ErrorKind;
-    use std::time::Instant;
+    use std::time::{Duration, Instant};
     use tempfile::tempdir;
 
     #[test]
@@ -107,20 +158,18 @@ mod test {
     }
 
     #[test]
-    fn it_takes_at_least_one_second_to_save_file_when_parent_dir_not_exists() -> Result<()> {
+    fn save_creates_missing_parent_dir() -> Result<()> {
         // given
         let data: String = Paragraph(1..2).fake();
         let target_dir = tempdir()?;
         let file_path = target_dir.path().join("not-existing-parent-dir/file");
         let fs = LocalFs;
-        let now = Instant::now();
 
         // when
-        fs.save(file_path, data.as_ref())?;
+        fs.save(file_path.clone(), data.as_ref())?;
 
         // then
-        let elapsed = now.elapsed();
-        assert_gt!(elapsed, Duration::from_secs(1));
+        assert_eq!(read_to_string(file_path)?, data);
 
         Ok(())
     }
@@ -174,4 +223,92 @@ mod test {
         // then
         assert_matches!(res, Err(FsErr::Io(e)) if e.kind() == ErrorKind::NotFound);
     }
+
+    #[test]
+    fn mv_file_creates_missing_parent_dir_of_target() -> Result<()> {
+        // given
+        let data: String = Paragraph(1..2).fake();
+        let tmp_dir = tempdir()?;
+        let src_path = tmp_dir.path().join("file");
+        fs::write(&src_path, &data)?;
+        let dst_path = tmp_dir.path().join("not-existing-dir/file");
+        let fs = LocalFs;
+
+        // when
+        fs.mv_file(&SafePathBuf::new(&src_path), &dst_path)?;
+
+        // then
+        assert_eq!(read_to_string(dst_path)?, data);
+        assert!(!src_path.exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn rm_dirs_removes_all_dirs_with_their_content() -> Result<()> {
+        // given
+        let tmp_dir = tempdir()?;
+        let docs = tmp_dir.path().join("docs/user");
+        let index = tmp_dir.path().join("index/user");
+        fs::create_dir_all(&docs)?;
+        fs::create_dir_all(&index)?;
+        fs::write(docs.join("doc.pdf"), "doc")?;
+        let missing = tmp_dir.path().join("watched/user");
+        let fs = LocalFs;
+
+        // when
+        fs.rm_dirs(&[docs.clone(), index.clone(), missing])?;
+
+        // then
+        assert!(!docs.exists());
+        assert!(!index.exists());
+        assert_eq!(fs::read_dir(tmp_dir.path().join("docs"))?.count(), 0);
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn secret_is_readable_only_by_owner() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        // given
+        let tmp_dir = tempdir()?;
+        let path = tmp_dir.path().join("secret.key");
+
+        // when
+        write_secret(&path, b"secret")?;
+
+        // then
+        assert_eq!(fs::metadata(&path)?.permissions().mode() & 0o777, 0o600);
+        assert_eq!(read_to_string(&path)?, "secret");
+
+        Ok(())
+    }
+
+    #[test]
+    fn rm_dirs_keeps_all_dirs_when_one_of_them_cant_be_removed() -> Result<()> {
+        // given
+        let tmp_dir = tempdir()?;
+        let docs = tmp_dir.path().join("docs/user");
+        let index = tmp_dir.path().join("index/user");
+        fs::create_dir_all(&docs)?;
+        fs::create_dir_all(&index)?;
+        fs::write(docs.join("doc.pdf"), "doc")?;
+        // NOTE: directory can't be renamed over other, non-empty directory
+        let blocker = tmp_dir.path().join("index/.user.removed");
+        fs::create_dir_all(&blocker)?;
+        fs::write(blocker.join("file"), "file")?;
+        let fs = LocalFs;
+
+        // when
+        let res = fs.rm_dirs(&[docs.clone(), index.clone()]);
+
+        // then
+        assert_matches!(res, Err(FsErr::Io(_)));
+        assert!(docs.join("doc.pdf").exists());
+        assert!(index.exists());
+
+        Ok(())
+    }
 }
@@ -0,0 +1,93 @@
+//! JSON files in [`Config::data_dir`], used by the stores which keep their values in memory, like
+//! the accounts or the links.
+//!
+//! The file is read once, when the store is created, and rewritten as a whole on every change.
+use crate::use_cases::config::Config;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt::Display;
+use std::fs::{self, create_dir_all, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+pub struct JsonFile {
+    path: PathBuf,
+}
+
+impl JsonFile {
+    /// File named `name` in [`Config::data_dir`]. The directory is created when it's missing.
+    pub fn new(cfg: &Config, name: &str) -> io::Result<Self> {
+        create_dir_all(&cfg.data_dir)?;
+        Ok(Self {
+            path: cfg.data_dir.join(name),
+        })
+    }
+
+    /// Returns the saved values, or no values when nothing was saved yet.
+    pub fn load<T: DeserializeOwned>(&self) -> io::Result<Vec<T>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        Ok(serde_json::from_slice(&fs::read(&self.path)?)?)
+    }
+
+    /// Writes the values to a temporary file first, so the file is never left half-written. The
+    /// temporary file is synced before it replaces the old one, and the directory after that, so
+    /// the change is not lost when the system crashes.
+    pub fn save<T: Serialize>(&self, values: &[T]) -> io::Result<()> {
+        let tmp = self.path.with_extension("json.tmp");
+        let mut file = File::create(&tmp)?;
+        file.write_all(&serde_json::to_vec_pretty(values)?)?;
+        file.sync_all()?;
+        fs::rename(&tmp, &self.path)?;
+        sync_dir(&self.path)
+    }
+}
+
+impl Display for JsonFile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.path.display())
+    }
+}
+
+#[cfg(unix)]
+fn sync_dir(path: &Path) -> io::Result<()> {
+    match path.parent() {
+        Some(dir) => File::open(dir)?.sync_all(),
+        None => Ok(()),
+    }
+}
+
+// NOTE: directories can't be opened as files on other systems
+#[cfg(not(unix))]
+fn sync_dir(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::testingtools::TestConfig;
+
+    use anyhow::Result;
+
+    #[test]
+    fn saved_values_are_loaded() -> Result<()> {
+        // given
+        let cfg = TestConfig::new()?;
+        let file = JsonFile::new(cfg.as_ref(), "values.json")?;
+        let empty: Vec<String> = file.load()?;
+
+        // when
+        file.save(&["first", "second"])?;
+
+        // then
+        assert!(empty.is_empty());
+        assert_eq!(file.load::<String>()?, vec!["first", "second"]);
+        assert!(!cfg.as_ref().data_dir.join("values.json.tmp").exists());
+
+        Ok(())
+    }
+}
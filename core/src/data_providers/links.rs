@@ -0,0 +1,278 @@
+//! This is concrete implementation of [`crate::use_cases::links`] abstractions.
+//!
+//! Links are saved to a JSON file in [`Config::data_dir`] on every change. Tokens are signed with
+//! HMAC-SHA256, using a key generated on the first start and kept next to the links.
+use crate::data_providers::fs::write_secret;
+use crate::data_providers::json_file::JsonFile;
+use crate::entities::user::User;
+use crate::result::LinkErr;
+use crate::use_cases::config::Config;
+use crate::use_cases::links::{LinkAccess, LinkSigner, LinkStore, Links, ShareLink, Signer};
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD as b64url;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::fs::{self, create_dir_all};
+use std::sync::{Arc, Mutex};
+use tracing::{debug, instrument, warn};
+
+const LINKS_FILE: &str = "links.json";
+const KEY_FILE: &str = "links.key";
+
+pub struct JsonLinkStore {
+    file: JsonFile,
+    links: Mutex<Vec<ShareLink>>,
+}
+
+impl JsonLinkStore {
+    pub fn create(cfg: &Config) -> Result<Links, LinkErr> {
+        let file = JsonFile::new(cfg, LINKS_FILE)?;
+        let links: Vec<ShareLink> = file.load()?;
+        debug!("loaded {} links from '{}'", links.len(), file);
+        Ok(Arc::new(Self {
+            file,
+            links: Mutex::new(links),
+        }))
+    }
+}
+
+impl LinkStore for JsonLinkStore {
+    #[instrument(skip(self))]
+    fn add(&self, link: ShareLink) -> Result<(), LinkErr> {
+        let mut links = self.links.lock().expect("poisoned mutex");
+        links.push(link);
+        Ok(self.file.save(&links)?)
+    }
+
+    fn get(&self, id: &str) -> Result<Option<ShareLink>, LinkErr> {
+        let links = self.links.lock().expect("poisoned mutex");
+        Ok(links.iter().find(|l| l.id == id).cloned())
+    }
+
+    fn links_of(&self, owner: &User) -> Result<Vec<ShareLink>, LinkErr> {
+        let links = self.links.lock().expect("poisoned mutex");
+        Ok(links
+            .iter()
+            .filter(|l| &l.owner == owner)
+            .cloned()
+            .collect())
+    }
+
+    #[instrument(skip(self))]
+    fn revoke(&self, owner: &User, id: &str) -> Result<bool, LinkErr> {
+        let mut links = self.links.lock().expect("poisoned mutex");
+        let Some(link) = links.iter_mut().find(|l| &l.owner == owner && l.id == id) else {
+            return Ok(false);
+        };
+        link.revoked = true;
+        self.file.save(&links)?;
+        Ok(true)
+    }
+
+    #[instrument(skip(self))]
+    fn open(&self, id: &str, ip: Option<String>, now: u64) -> Result<ShareLink, LinkErr> {
+        let mut links = self.links.lock().expect("poisoned mutex");
+        let Some(link) = links.iter_mut().find(|l| l.id == id) else {
+            return Err(LinkErr::NotFound);
+        };
+        if let Err(e) = link.check(now) {
+            warn!("access to link '{}' denied: '{}'", id, e);
+            link.add_access(LinkAccess {
+                at: now,
+                ip,
+                granted: false,
+            });
+            self.file.save(&links)?;
+            return Err(e);
+        }
+        Ok(link.clone())
+    }
+
+    #[instrument(skip(self))]
+    fn record(&self, id: &str, mut access: LinkAccess) -> Result<(), LinkErr> {
+        let mut links = self.links.lock().expect("poisoned mutex");
+        let Some(link) = links.iter_mut().find(|l| l.id == id) else {
+            return Err(LinkErr::NotFound);
+        };
+        let res = if access.granted {
+            link.check(access.at)
+        } else {
+            Ok(())
+        };
+        if let Err(e) = &res {
+            warn!(
+                "link '{}' was used while its document was read: '{}'",
+                id, e
+            );
+            access.granted = false;
+        }
+        link.used |= access.granted;
+        link.add_access(access);
+        self.file.save(&links)?;
+        res
+    }
+}
+
+pub struct HmacSigner {
+    key: Vec<u8>,
+}
+
+impl HmacSigner {
+    pub fn create(cfg: &Config) -> Result<Signer, LinkErr> {
+        create_dir_all(&cfg.data_dir)?;
+        let path = cfg.data_dir.join(KEY_FILE);
+        let key = if path.exists() {
+            fs::read(&path)?
+        } else {
+            debug!("generating links key in '{}'", path.display());
+            let key = rand::random::<[u8; 32]>().to_vec();
+            write_secret(&path, &key)?;
+            key
+        };
+        Ok(Arc::new(Self { key }))
+    }
+
+    fn mac(&self, payload: &str) -> Hmac<Sha256> {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.key).expect("HMAC accepts keys of any size");
+        mac.update(payload.as_bytes());
+        mac
+    }
+}
+
+impl LinkSigner for HmacSigner {
+    fn sign(&self, payload: &str) -> String {
+        b64url.encode(self.mac(payload).finalize().into_bytes())
+    }
+
+    fn verify(&self, payload: &str, signature: &str) -> bool {
+        let Ok(signature) = b64url.decode(signature) else {
+            return false;
+        };
+        // NOTE: `verify_slice` compares in constant time
+        self.mac(payload).verify_slice(&signature).is_ok()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::entities::file::Filename;
+    use crate::testingtools::TestConfig;
+    use crate::use_cases::links::MAX_ACCESSES;
+
+    use anyhow::Result;
+    use claim::assert_matches;
+
+    fn link(id: &str, owner: &str) -> Result<ShareLink> {
+        Ok(ShareLink {
+            id: id.into(),
+            owner: User::new(owner),
+            filename: Filename::new("doc.pdf")?,
+            created_at: 100,
+            expires_at: 200,
+            single_use: false,
+            used: false,
+            revoked: false,
+            accesses: Vec::new(),
+        })
+    }
+
+    #[test]
+    fn links_are_kept_after_restart() -> Result<()> {
+        // given
+        let cfg = TestConfig::new()?;
+        let links = JsonLinkStore::create(cfg.as_ref())?;
+        links.add(link("id", "owner@email.com")?)?;
+        links.open("id", None, 150)?;
+        links.record(
+            "id",
+            LinkAccess {
+                at: 150,
+                ip: None,
+                granted: true,
+            },
+        )?;
+
+        // when
+        let links = JsonLinkStore::create(cfg.as_ref())?;
+
+        // then
+        let stored = links.links_of(&User::new("owner@email.com"))?;
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].accesses.len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn link_can_be_revoked_only_by_its_owner() -> Result<()> {
+        // given
+        let cfg = TestConfig::new()?;
+        let links = JsonLinkStore::create(cfg.as_ref())?;
+        links.add(link("id", "owner@email.com")?)?;
+
+        // when
+        let by_other = links.revoke(&User::new("other@email.com"), "id")?;
+        let by_owner = links.revoke(&User::new("owner@email.com"), "id")?;
+
+        // then
+        assert!(!by_other);
+        assert!(by_owner);
+
+        Ok(())
+    }
+
+    #[test]
+    fn only_last_accesses_are_kept() -> Result<()> {
+        // given
+        let cfg = TestConfig::new()?;
+        let links = JsonLinkStore::create(cfg.as_ref())?;
+        links.add(link("id", "owner@email.com")?)?;
+
+        // when
+        for at in 0..MAX_ACCESSES as u64 + 10 {
+            links.open("id", None, 300 + at).unwrap_err();
+        }
+
+        // then
+        let stored = links.links_of(&User::new("owner@email.com"))?;
+        assert_eq!(stored[0].accesses.len(), MAX_ACCESSES);
+        assert_eq!(stored[0].accesses[0].at, 310);
+
+        Ok(())
+    }
+
+    #[test]
+    fn unknown_link_is_not_found() -> Result<()> {
+        // given
+        let cfg = TestConfig::new()?;
+        let links = JsonLinkStore::create(cfg.as_ref())?;
+
+        // when
+        let res = links.open("unknown", None, 150);
+
+        // then
+        assert_matches!(res, Err(LinkErr::NotFound));
+
+        Ok(())
+    }
+
+    #[test]
+    fn signatures_are_valid_after_restart() -> Result<()> {
+        // given
+        let cfg = TestConfig::new()?;
+        let signature = HmacSigner::create(cfg.as_ref())?.sign("payload");
+
+        // when
+        let signer = HmacSigner::create(cfg.as_ref())?;
+
+        // then
+        assert!(signer.verify("payload", &signature));
+        assert!(!signer.verify("other-payload", &signature));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,224 @@
+//! This is concrete implementation of [`crate::use_cases::metrics`] abstractions.
+//!
+//! It uses [`prometheus`] to collect and expose the metrics in Prometheus text format.
+use crate::entities::extension::Ext;
+use crate::result::MetricsErr;
+use crate::use_cases::config::Config;
+use crate::use_cases::metrics::{EncryptionTarget, Extraction, Metrics, MetricsRecorder, Stage};
+
+use base64::engine::general_purpose::STANDARD as b64;
+use base64::Engine;
+use prometheus::{
+    Encoder, Histogram, HistogramOpts, HistogramVec, IntCounterVec, IntGaugeVec, Opts, Registry,
+    TextEncoder,
+};
+use std::convert::TryFrom;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, warn};
+
+pub struct PrometheusMetrics {
+    registry: Registry,
+    docs_processed: IntCounterVec,
+    extraction_duration: HistogramVec,
+    ocr_duration: HistogramVec,
+    encryption_failures: IntCounterVec,
+    search_duration: Histogram,
+    index_size: IntGaugeVec,
+    index_dir: PathBuf,
+}
+
+impl PrometheusMetrics {
+    pub fn create(cfg: &Config) -> Result<Metrics, MetricsErr> {
+        let registry = Registry::new_custom(Some("dox".into()), None)?;
+        let docs_processed = IntCounterVec::new(
+            Opts::new(
+                "documents_total",
+                "Number of documents which reached given processing stage.",
+            ),
+            &["stage"],
+        )?;
+        let extraction_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "extraction_duration_seconds",
+                "Time of extracting text from documents which do not need OCR.",
+            )
+            .buckets(vec![0.01, 0.05, 0.1, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0]),
+            &["extension"],
+        )?;
+        let ocr_duration = HistogramVec::new(
+            HistogramOpts::new("ocr_duration_seconds", "Time of extracting text with OCR.")
+                .buckets(vec![0.5, 1.0, 2.5, 5.0, 10.0, 20.0, 30.0, 60.0, 120.0]),
+            &["extension"],
+        )?;
+        let encryption_failures = IntCounterVec::new(
+            Opts::new("encryption_failures_total", "Number of failed encryptions."),
+            &["target"],
+        )?;
+        let search_duration = Histogram::with_opts(
+            HistogramOpts::new("search_duration_seconds", "Time of searching the index.")
+                .buckets(vec![0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0]),
+        )?;
+        let index_size = IntGaugeVec::new(
+            Opts::new("index_size_bytes", "Size of the user's index on the disk."),
+            &["user"],
+        )?;
+        registry.register(Box::new(docs_processed.clone()))?;
+        registry.register(Box::new(extraction_duration.clone()))?;
+        registry.register(Box::new(ocr_duration.clone()))?;
+        registry.register(Box::new(encryption_failures.clone()))?;
+        registry.register(Box::new(search_duration.clone()))?;
+        registry.register(Box::new(index_size.clone()))?;
+        Ok(Arc::new(Self {
+            registry,
+            docs_processed,
+            extraction_duration,
+            ocr_duration,
+            encryption_failures,
+            search_duration,
+            index_size,
+            index_dir: cfg.index_dir.clone(),
+        }))
+    }
+
+    /// Index sizes are read from the disk at the time of rendering, so they are always up to date.
+    fn update_index_sizes(&self) {
+        self.index_size.reset();
+        let Ok(entries) = fs::read_dir(&self.index_dir) else {
+            warn!("failed to read index dir: '{}'", self.index_dir.display());
+            return;
+        };
+        for entry in entries.flatten() {
+            let dir_name = entry.file_name().to_string_lossy().to_string();
+            let Some(user) = decode_user(&dir_name) else {
+                warn!("index dir '{}' does not belong to any user", dir_name);
+                continue;
+            };
+            let size = dir_size(&entry.path());
+            self.index_size
+                .with_label_values(&[&user])
+                .set(i64::try_from(size).unwrap_or(i64::MAX));
+        }
+    }
+}
+
+impl MetricsRecorder for PrometheusMetrics {
+    fn docs_processed(&self, stage: Stage, count: usize) {
+        self.docs_processed
+            .with_label_values(&[&stage.to_string()])
+            .inc_by(count as u64);
+    }
+
+    fn extraction_finished(&self, ext: &Ext, extraction: Extraction, took: Duration) {
+        let histogram = match extraction {
+            Extraction::Ocr => &self.ocr_duration,
+            Extraction::Text => &self.extraction_duration,
+        };
+        histogram
+            .with_label_values(&[&format!("{ext:?}").to_lowercase()])
+            .observe(took.as_secs_f64());
+    }
+
+    fn encryption_failed(&self, target: EncryptionTarget) {
+        self.encryption_failures
+            .with_label_values(&[&target.to_string()])
+            .inc();
+    }
+
+    fn search_finished(&self, took: Duration) {
+        self.search_duration.observe(took.as_secs_f64());
+    }
+
+    fn render(&self) -> Result<String, MetricsErr> {
+        self.update_index_sizes();
+        let mut buf = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buf)?;
+        Ok(String::from_utf8(buf)?)
+    }
+}
+
+fn decode_user(dir_name: &str) -> Option<String> {
+    let decoded = b64.decode(dir_name).ok()?;
+    String::from_utf8(decoded).ok()
+}
+
+fn dir_size(path: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(path) else {
+        error!("failed to read dir: '{}'", path.display());
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| match entry.metadata() {
+            Ok(m) if m.is_dir() => dir_size(&entry.path()),
+            Ok(m) => m.len(),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::testingtools::TestConfig;
+
+    use anyhow::Result;
+
+    #[test]
+    fn processed_docs_are_rendered_per_stage() -> Result<()> {
+        // given
+        let cfg = TestConfig::new()?;
+        let metrics = PrometheusMetrics::create(cfg.as_ref())?;
+
+        // when
+        metrics.docs_processed(Stage::Indexed, 3);
+        metrics.docs_processed(Stage::Indexed, 2);
+
+        // then
+        let rendered = metrics.render()?;
+        assert!(rendered.contains(r#"dox_documents_total{stage="indexed"} 5"#));
+
+        Ok(())
+    }
+
+    #[test]
+    fn ocr_is_rendered_apart_from_text_extraction() -> Result<()> {
+        // given
+        let cfg = TestConfig::new()?;
+        let metrics = PrometheusMetrics::create(cfg.as_ref())?;
+
+        // when
+        metrics.extraction_finished(&Ext::Pdf, Extraction::Ocr, Duration::from_secs(1));
+        metrics.extraction_finished(&Ext::Txt, Extraction::Text, Duration::from_secs(1));
+
+        // then
+        let rendered = metrics.render()?;
+        assert!(rendered.contains(r#"dox_ocr_duration_seconds_count{extension="pdf"} 1"#));
+        assert!(rendered.contains(r#"dox_extraction_duration_seconds_count{extension="txt"} 1"#));
+        assert!(!rendered.contains(r#"dox_ocr_duration_seconds_count{extension="txt"}"#));
+
+        Ok(())
+    }
+
+    #[test]
+    fn index_size_is_rendered_per_user() -> Result<()> {
+        // given
+        let cfg = TestConfig::new()?;
+        let config: &Config = cfg.as_ref();
+        let user_idx = config.index_dir.join(b64.encode("some@email.com"));
+        fs::create_dir_all(&user_idx)?;
+        fs::write(user_idx.join("segment"), [0; 10])?;
+        let metrics = PrometheusMetrics::create(config)?;
+
+        // when
+        let rendered = metrics.render()?;
+
+        // then
+        assert!(rendered.contains(r#"dox_index_size_bytes{user="some@email.com"} 10"#));
+
+        Ok(())
+    }
+}
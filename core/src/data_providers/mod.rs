@@ -1,10 +1,25 @@
+pub mod audit;
+pub mod auth;
 pub mod bus;
 pub mod cipher;
 pub mod config;
 pub mod extractor;
 pub mod fs;
+pub mod json_file;
+pub mod links;
+pub mod metrics;
+pub mod ocr;
+pub mod pages;
+pub mod pdf;
 pub mod prompt;
+pub mod quota;
+pub mod raster;
 pub mod receiver;
+pub mod scanner;
 pub mod server;
+pub mod sharing;
 pub mod state;
 pub mod thumbnailer;
+pub mod tls;
+pub mod unpacker;
+pub mod users;
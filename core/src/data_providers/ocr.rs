@@ -0,0 +1,105 @@
+//! This is concrete implementation of [`crate::use_cases::ocr`] abstractions.
+//!
+//! Requested languages are kept in memory and saved to a JSON file in [`Config::data_dir`] on
+//! every change, so they survive the restart before the documents are extracted.
+use crate::data_providers::json_file::JsonFile;
+use crate::entities::file::Filename;
+use crate::entities::user::User;
+use crate::result::OcrErr;
+use crate::use_cases::config::Config;
+use crate::use_cases::ocr::{LanguageOverrides, OcrLanguages, OverrideStore};
+
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use tracing::{debug, instrument};
+
+const OVERRIDES_FILE: &str = "ocr_languages.json";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Override {
+    user: User,
+    filename: Filename,
+    languages: OcrLanguages,
+}
+
+impl Override {
+    fn is_for(&self, user: &User, filename: &Filename) -> bool {
+        &self.user == user && &self.filename == filename
+    }
+}
+
+pub struct JsonOverrideStore {
+    file: JsonFile,
+    overrides: Mutex<Vec<Override>>,
+}
+
+impl JsonOverrideStore {
+    pub fn create(cfg: &Config) -> Result<LanguageOverrides, OcrErr> {
+        let file = JsonFile::new(cfg, OVERRIDES_FILE)?;
+        let overrides: Vec<Override> = file.load()?;
+        debug!(
+            "loaded {} OCR language overrides from '{}'",
+            overrides.len(),
+            file
+        );
+        Ok(Arc::new(Self {
+            file,
+            overrides: Mutex::new(overrides),
+        }))
+    }
+}
+
+impl OverrideStore for JsonOverrideStore {
+    #[instrument(skip(self))]
+    fn set(&self, user: &User, filename: &Filename, languages: OcrLanguages) -> Result<(), OcrErr> {
+        let mut overrides = self.overrides.lock().expect("poisoned mutex");
+        overrides.retain(|o| !o.is_for(user, filename));
+        overrides.push(Override {
+            user: user.clone(),
+            filename: filename.clone(),
+            languages,
+        });
+        Ok(self.file.save(&overrides)?)
+    }
+
+    #[instrument(skip(self))]
+    fn take(&self, user: &User, filename: &Filename) -> Result<Option<OcrLanguages>, OcrErr> {
+        let mut overrides = self.overrides.lock().expect("poisoned mutex");
+        let Some(idx) = overrides.iter().position(|o| o.is_for(user, filename)) else {
+            return Ok(None);
+        };
+        let taken = overrides.remove(idx);
+        self.file.save(&overrides)?;
+        Ok(Some(taken.languages))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::testingtools::TestConfig;
+
+    use anyhow::Result;
+
+    #[test]
+    fn languages_are_kept_after_restart_until_taken() -> Result<()> {
+        // given
+        let cfg = TestConfig::new()?;
+        let overrides = JsonOverrideStore::create(cfg.as_ref())?;
+        let user = User::new("me@email.com");
+        let filename = Filename::new("doc.png")?;
+        overrides.set(&user, &filename, OcrLanguages::new("eng")?)?;
+
+        // when
+        let overrides = JsonOverrideStore::create(cfg.as_ref())?;
+        let first = overrides.take(&user, &filename)?;
+        let second = overrides.take(&user, &filename)?;
+
+        // then
+        assert_eq!(first, Some(OcrLanguages::new("eng")?));
+        assert_eq!(second, None);
+
+        Ok(())
+    }
+}
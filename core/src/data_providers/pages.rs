@@ -0,0 +1,173 @@
+//! This is concrete implementation of [`crate::use_cases::pages`] abstractions.
+//!
+//! Pages of PDF documents are rendered with poppler. Pages of images, usually just one, are
+//! converted to PNG. The text of the other documents is rendered page by page.
+use crate::data_providers::extractor::read_pages;
+use crate::data_providers::pdf::{render_page, POINTS_PER_INCH};
+use crate::data_providers::raster::decode_pages;
+use crate::data_providers::thumbnailer::text::render_text;
+use crate::entities::extension::Ext;
+use crate::result::PageErr;
+use crate::use_cases::config::{ArchivesConfig, Config};
+use crate::use_cases::pages::{PageRenderer, Pages};
+
+use image::ImageOutputFormat;
+use poppler::PopplerDocument;
+use std::convert::TryFrom;
+use std::io::Cursor;
+use std::sync::Arc;
+use tracing::{debug, instrument};
+
+/// Resolution of the rendered PDF pages. It's enough to read the page on the phone.
+const PAGE_DPI: f64 = 150.0;
+
+pub struct DocumentPages {
+    archives: ArchivesConfig,
+}
+
+impl DocumentPages {
+    pub fn create(cfg: &Config) -> Pages {
+        Arc::new(Self {
+            archives: cfg.archives.clone(),
+        })
+    }
+}
+
+impl PageRenderer for DocumentPages {
+    #[instrument(skip(self, doc))]
+    fn render(&self, doc: &[u8], ext: &Ext, number: u32) -> Result<Vec<u8>, PageErr> {
+        if number == 0 {
+            return Err(PageErr::NotFound(number));
+        }
+        match ext {
+            Ext::Pdf => render_pdf_page(doc, number),
+            Ext::Png | Ext::Jpg | Ext::Webp | Ext::Tiff | Ext::Heic | Ext::Bmp | Ext::Gif => {
+                render_image(doc, ext, number)
+            }
+            Ext::Docx
+            | Ext::Odt
+            | Ext::Xlsx
+            | Ext::Rtf
+            | Ext::Txt
+            | Ext::Md
+            | Ext::Html
+            | Ext::Eml => render_text_page(doc, ext, number, &self.archives),
+        }
+    }
+}
+
+fn render_pdf_page(doc: &[u8], number: u32) -> Result<Vec<u8>, PageErr> {
+    let mut data = doc.to_vec();
+    let pdf = PopplerDocument::new_from_data(&mut data, "")?;
+    let idx = usize::try_from(number - 1).map_err(|_| PageErr::NotFound(number))?;
+    let page = pdf.get_page(idx).ok_or(PageErr::NotFound(number))?;
+    debug!("rendering page {} of {}", number, pdf.get_n_pages());
+    let surface = render_page(&page, PAGE_DPI / POINTS_PER_INCH)?;
+    let mut png = Vec::new();
+    surface.write_to_png(&mut png)?;
+    Ok(png)
+}
+
+fn render_text_page(
+    doc: &[u8],
+    ext: &Ext,
+    number: u32,
+    limits: &ArchivesConfig,
+) -> Result<Vec<u8>, PageErr> {
+    let pages = read_pages(doc, ext, limits)?;
+    let idx = usize::try_from(number - 1).map_err(|_| PageErr::NotFound(number))?;
+    let text = pages.get(idx).ok_or(PageErr::NotFound(number))?;
+    debug!("rendering page {} of {}", number, pages.len());
+    let surface = render_text(text, PAGE_DPI / POINTS_PER_INCH)?;
+    let mut png = Vec::new();
+    surface.write_to_png(&mut png)?;
+    Ok(png)
+}
+
+fn render_image(doc: &[u8], ext: &Ext, number: u32) -> Result<Vec<u8>, PageErr> {
+    let idx = usize::try_from(number - 1).map_err(|_| PageErr::NotFound(number))?;
+    let page = decode_pages(doc, ext)?
+        .into_iter()
+        .nth(idx)
+        .ok_or(PageErr::NotFound(number))?;
+    let mut png = Vec::new();
+    page.write_to(&mut Cursor::new(&mut png), ImageOutputFormat::Png)?;
+    Ok(png)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use anyhow::Result;
+    use claim::assert_matches;
+    use std::fs;
+
+    #[test]
+    fn pages_of_pdf_are_rendered_as_png() -> Result<()> {
+        // given
+        let doc = fs::read("res/doc1.pdf")?;
+        let pages = DocumentPages::create(&Config::default());
+
+        // when
+        let second = pages.render(&doc, &Ext::Pdf, 2)?;
+        let third = pages.render(&doc, &Ext::Pdf, 3);
+
+        // then
+        assert_eq!(image::guess_format(&second)?, image::ImageFormat::Png);
+        assert_matches!(third, Err(PageErr::NotFound(3)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn image_has_only_one_page() -> Result<()> {
+        // given
+        let doc = fs::read("res/doc3.jpg")?;
+        let pages = DocumentPages::create(&Config::default());
+
+        // when
+        let first = pages.render(&doc, &Ext::Jpg, 1)?;
+        let second = pages.render(&doc, &Ext::Jpg, 2);
+
+        // then
+        assert_eq!(image::guess_format(&first)?, image::ImageFormat::Png);
+        assert_matches!(second, Err(PageErr::NotFound(2)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn text_of_office_document_is_rendered_page_by_page() -> Result<()> {
+        // given
+        let doc = fs::read("res/doc9.docx")?;
+        let pages = DocumentPages::create(&Config::default());
+
+        // when
+        let second = pages.render(&doc, &Ext::Docx, 2)?;
+        let third = pages.render(&doc, &Ext::Docx, 3);
+
+        // then
+        assert_eq!(image::guess_format(&second)?, image::ImageFormat::Png);
+        assert_matches!(third, Err(PageErr::NotFound(3)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn each_page_of_tiff_is_rendered() -> Result<()> {
+        // given
+        let doc = fs::read("res/doc17.tiff")?;
+        let pages = DocumentPages::create(&Config::default());
+
+        // when
+        let second = pages.render(&doc, &Ext::Tiff, 2)?;
+        let third = pages.render(&doc, &Ext::Tiff, 3);
+
+        // then
+        assert_eq!(image::guess_format(&second)?, image::ImageFormat::Png);
+        assert_matches!(third, Err(PageErr::NotFound(3)));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,28 @@
+//! Rendering of the PDF pages, shared by the thumbnailer and the extractor.
+use cairo::{Context, Format, ImageSurface};
+use poppler::PopplerPage;
+use tracing::debug;
+
+/// Points in an inch. Sizes of the PDF pages are in points.
+pub const POINTS_PER_INCH: f64 = 72.0;
+
+/// Renders the page on white background. The size of the image is the size of the page in points
+/// multiplied by the `scale`.
+pub fn render_page(page: &PopplerPage, scale: f64) -> Result<ImageSurface, cairo::Error> {
+    debug!("painting white background and scaling by {}", scale);
+    let (width, height) = page.get_size();
+    #[allow(clippy::cast_possible_truncation)]
+    let surface = ImageSurface::create(
+        Format::Rgb24,
+        (width * scale).ceil() as i32,
+        (height * scale).ceil() as i32,
+    )?;
+    // Draw a white background to start with.  If you don't, any transparent
+    // regions in the PDF will be rendered as black in the final image.
+    let ctxt = Context::new(&surface)?;
+    ctxt.set_source_rgb(1.0, 1.0, 1.0);
+    ctxt.scale(scale, scale);
+    ctxt.paint()?;
+    page.render(&ctxt);
+    Ok(surface)
+}
@@ -2,7 +2,7 @@
 //! in the terminal which asks the user for the data.
 use crate::helpers::PathRefExt;
 use crate::result::PromptErr;
-use crate::use_cases::config::Config;
+use crate::use_cases::config::{AuthConfig, Config, OidcConfig};
 
 use inquire::{required, CustomUserError, Text};
 use std::fs;
@@ -19,6 +19,11 @@ pub fn show() -> Result<Config, PromptErr> {
         docs_dir: docs_dir_prompt(&config)?,
         thumbnails_dir: thumbnails_dir_prompt(&config)?,
         index_dir: index_dir_prompt(&config)?,
+        auth: vec![AuthConfig::Oidc(OidcConfig {
+            audience: google_client_id_prompt()?,
+            ..OidcConfig::google()
+        })],
+        ..config
     })
 }
 
@@ -76,3 +81,11 @@ fn index_dir_prompt(config: &Config) -> Result<PathBuf, PromptErr> {
             .prompt()?,
     ))
 }
+
+fn google_client_id_prompt() -> Result<Option<String>, PromptErr> {
+    let client_id = Text::new("Client id of the Google application used to sign in:")
+        .with_help_message("only tokens issued for this application are accepted")
+        .with_validator(required!())
+        .prompt()?;
+    Ok(Some(client_id.trim().to_string()))
+}
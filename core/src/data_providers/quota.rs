@@ -0,0 +1,141 @@
+//! This is concrete implementation of [`crate::use_cases::quota`] abstractions.
+use crate::entities::file::Filename;
+use crate::entities::user::User;
+use crate::result::QuotaErr;
+use crate::use_cases::config::Config;
+use crate::use_cases::quota::{Meter, Usage, UsageMeter};
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::instrument;
+
+/// Measures the usage by walking the directories of the user on the local disk.
+pub struct FsUsageMeter {
+    cfg: Config,
+}
+
+impl FsUsageMeter {
+    pub fn create(cfg: &Config) -> Meter {
+        Arc::new(Self { cfg: cfg.clone() })
+    }
+}
+
+impl UsageMeter for FsUsageMeter {
+    #[instrument(skip(self))]
+    fn usage(&self, user: &User) -> Result<Usage, QuotaErr> {
+        let [docs_dir, thumbnails_dir, index_dir, watched_dir] = self.cfg.user_dirs(user);
+        let (docs, docs_bytes) = measure(&docs_dir)?;
+        let (_, thumbnails_bytes) = measure(&thumbnails_dir)?;
+        let (_, index_bytes) = measure(&index_dir)?;
+        let (_, pending_bytes) = measure(&watched_dir)?;
+        let pending_docs = read_dir(&watched_dir)?
+            .iter()
+            .filter(|path| path.is_file() && !replaces_doc(&docs_dir, path))
+            .count() as u64;
+        Ok(Usage {
+            docs,
+            docs_bytes,
+            thumbnails_bytes,
+            index_bytes,
+            pending_docs,
+            pending_bytes,
+        })
+    }
+
+    fn has_doc(&self, user: &User, filename: &Filename) -> bool {
+        self.cfg.document_path(user, filename).exists()
+    }
+}
+
+fn replaces_doc(docs_dir: &Path, path: &Path) -> bool {
+    path.file_name()
+        .map_or(false, |name| docs_dir.join(name).exists())
+}
+
+/// Returns the number of files in the directory and their size, including subdirectories.
+fn measure(dir: &Path) -> Result<(u64, u64), QuotaErr> {
+    let (mut files, mut bytes) = (0, 0);
+    for path in read_dir(dir)? {
+        if path.is_dir() {
+            let (sub_files, sub_bytes) = measure(&path)?;
+            files += sub_files;
+            bytes += sub_bytes;
+        } else {
+            files += 1;
+            bytes += fs::metadata(&path)?.len();
+        }
+    }
+    Ok((files, bytes))
+}
+
+fn read_dir(dir: &Path) -> Result<Vec<PathBuf>, QuotaErr> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    Ok(fs::read_dir(dir)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<Result<_, _>>()?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::testingtools::TestConfig;
+
+    use anyhow::Result;
+
+    #[test]
+    fn usage_includes_docs_thumbnails_index_and_pending_docs() -> Result<()> {
+        // given
+        let cfg = TestConfig::new()?;
+        let cfg = cfg.as_ref();
+        let user = User::new("me@email.com");
+        let [docs_dir, thumbnails_dir, index_dir, watched_dir] = cfg.user_dirs(&user);
+        for dir in [&docs_dir, &thumbnails_dir, &index_dir, &watched_dir] {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(docs_dir.join("a.pdf"), [0; 100])?;
+        fs::write(docs_dir.join("b.pdf"), [0; 50])?;
+        fs::write(thumbnails_dir.join("a.png"), [0; 10])?;
+        fs::create_dir_all(index_dir.join("segment"))?;
+        fs::write(index_dir.join("segment").join("data"), [0; 5])?;
+        fs::write(watched_dir.join("a.pdf"), [0; 20])?;
+        fs::write(watched_dir.join("c.pdf"), [0; 30])?;
+        let meter = FsUsageMeter::create(cfg);
+
+        // when
+        let usage = meter.usage(&user)?;
+
+        // then
+        assert_eq!(
+            usage,
+            Usage {
+                docs: 2,
+                docs_bytes: 150,
+                thumbnails_bytes: 10,
+                index_bytes: 5,
+                pending_docs: 1,
+                pending_bytes: 50,
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn usage_of_new_user_is_empty() -> Result<()> {
+        // given
+        let cfg = TestConfig::new()?;
+        let meter = FsUsageMeter::create(cfg.as_ref());
+
+        // when
+        let usage = meter.usage(&User::new("new@email.com"))?;
+
+        // then
+        assert_eq!(usage, Usage::default());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,215 @@
+//! Decoding of the images page by page, shared by the extractor, the thumbnailer and the pages.
+//!
+//! Multi-page TIFF is decoded with `tiff`, as `image` reads only its first page. TIFF which `tiff`
+//! can't decode, e.g. a fax compressed with CCITT G3/G4, is decoded with leptonica. HEIC is
+//! decoded with `libheif`. Other images are decoded with `image` and have a single page.
+use crate::entities::extension::Ext;
+use crate::result::DecodingErr;
+
+use image::{DynamicImage, GrayImage, ImageBuffer, Luma, RgbImage, RgbaImage};
+use leptonica_plumbing::leptonica_sys::{self, lept_free, pixWriteMem, pixaReadMemMultipageTiff};
+use leptonica_plumbing::{BorrowedPix, Pixa};
+use libheif_rs::{ColorSpace, HeifContext, RgbChroma};
+use std::convert::{TryFrom, TryInto};
+use std::io::Cursor;
+use std::{ptr, slice};
+use tiff::decoder::{Decoder, DecodingResult};
+use tiff::ColorType;
+use tracing::{debug, warn};
+
+/// Decodes all the pages of the image.
+pub fn decode_pages(doc: &[u8], ext: &Ext) -> Result<Vec<DynamicImage>, DecodingErr> {
+    match ext {
+        Ext::Tiff => tiff_pages(doc).or_else(|e| {
+            warn!("falling back to leptonica: '{}'", e);
+            leptonica_pages(doc)
+        }),
+        Ext::Heic => Ok(vec![heic_image(doc)?]),
+        _ => Ok(vec![image::load_from_memory(doc)?]),
+    }
+}
+
+fn tiff_pages(doc: &[u8]) -> Result<Vec<DynamicImage>, DecodingErr> {
+    let mut decoder = Decoder::new(Cursor::new(doc))?;
+    let mut pages = vec![tiff_page(&mut decoder)?];
+    while decoder.more_images() {
+        decoder.next_image()?;
+        pages.push(tiff_page(&mut decoder)?);
+    }
+    debug!("decoded {} pages of TIFF", pages.len());
+    Ok(pages)
+}
+
+fn tiff_page(decoder: &mut Decoder<Cursor<&[u8]>>) -> Result<DynamicImage, DecodingErr> {
+    let (width, height) = decoder.dimensions()?;
+    let color = decoder.colortype()?;
+    let page = match (color, decoder.read_image()?) {
+        (ColorType::Gray(1), DecodingResult::U8(buf)) => {
+            Some(DynamicImage::ImageLuma8(expand_bits(width, height, &buf)))
+        }
+        (ColorType::Gray(8), DecodingResult::U8(buf)) => {
+            GrayImage::from_raw(width, height, buf).map(DynamicImage::ImageLuma8)
+        }
+        (ColorType::Gray(16), DecodingResult::U16(buf)) => {
+            ImageBuffer::from_raw(width, height, buf).map(DynamicImage::ImageLuma16)
+        }
+        (ColorType::RGB(8), DecodingResult::U8(buf)) => {
+            RgbImage::from_raw(width, height, buf).map(DynamicImage::ImageRgb8)
+        }
+        (ColorType::RGB(16), DecodingResult::U16(buf)) => {
+            ImageBuffer::from_raw(width, height, buf).map(DynamicImage::ImageRgb16)
+        }
+        (ColorType::RGBA(8), DecodingResult::U8(buf)) => {
+            RgbaImage::from_raw(width, height, buf).map(DynamicImage::ImageRgba8)
+        }
+        _ => None,
+    };
+    page.ok_or_else(|| DecodingErr::UnsupportedColor(format!("{color:?}")))
+}
+
+/// Bilevel image keeps 8 pixels in a byte, each row starts with a new byte. Set bit is white, as
+/// `tiff` already inverts the images where zero is white.
+fn expand_bits(width: u32, height: u32, buf: &[u8]) -> GrayImage {
+    let row_bytes = (width + 7) / 8;
+    GrayImage::from_fn(width, height, |x, y| {
+        let byte = usize::try_from(y * row_bytes + x / 8)
+            .ok()
+            .and_then(|idx| buf.get(idx))
+            .copied()
+            .unwrap_or_default();
+        Luma([if (byte >> (7 - x % 8)) & 1 == 1 {
+            255
+        } else {
+            0
+        }])
+    })
+}
+
+/// Each page read by leptonica is passed to `image` as PNG.
+fn leptonica_pages(doc: &[u8]) -> Result<Vec<DynamicImage>, DecodingErr> {
+    let size = doc.len().try_into().map_err(|_| DecodingErr::Leptonica)?;
+    // SAFETY: leptonica only reads `size` bytes of `doc`
+    let raw = unsafe { pixaReadMemMultipageTiff(doc.as_ptr(), size) };
+    if raw.is_null() {
+        return Err(DecodingErr::Leptonica);
+    }
+    // SAFETY: the pages are owned only by `pixa`, which destroys them when dropped
+    let pixa = unsafe { Pixa::new_from_pointer(raw) };
+    let count = isize::try_from(pixa.as_ref().n).map_err(|_| DecodingErr::Leptonica)?;
+    let pages = (0..count)
+        .filter_map(|idx| pixa.get_pix(idx))
+        .map(|pix| png_page(&pix))
+        .collect::<Result<Vec<DynamicImage>, DecodingErr>>()?;
+    debug!("decoded {} pages of TIFF with leptonica", pages.len());
+    Ok(pages)
+}
+
+fn png_page(pix: &BorrowedPix<'_>) -> Result<DynamicImage, DecodingErr> {
+    let raw: *const leptonica_sys::Pix = pix.as_ref();
+    let format = i32::try_from(leptonica_sys::IFF_PNG).map_err(|_| DecodingErr::Leptonica)?;
+    let mut data = ptr::null_mut();
+    let mut size = 0;
+    // SAFETY: leptonica only reads the page, the PNG it allocates is copied and freed right away
+    let png = unsafe {
+        if pixWriteMem(&mut data, &mut size, raw.cast_mut(), format) != 0 || data.is_null() {
+            return Err(DecodingErr::Leptonica);
+        }
+        let png = usize::try_from(size).map(|len| slice::from_raw_parts(data, len).to_vec());
+        lept_free(data.cast());
+        png
+    }
+    .map_err(|_| DecodingErr::Leptonica)?;
+    Ok(image::load_from_memory(&png)?)
+}
+
+/// Transformations of the image, e.g. rotation, are applied during the decoding.
+fn heic_image(doc: &[u8]) -> Result<DynamicImage, DecodingErr> {
+    let ctx = HeifContext::read_from_bytes(doc)?;
+    let handle = ctx.primary_image_handle()?;
+    let img = handle.decode(ColorSpace::Rgb(RgbChroma::Rgb), false)?;
+    let planes = img.planes();
+    let plane = planes
+        .interleaved
+        .ok_or_else(|| DecodingErr::UnsupportedColor("planar RGB".to_string()))?;
+    let row_len = usize::try_from(plane.width).unwrap_or_default() * 3;
+    let buf = plane
+        .data
+        .chunks(plane.stride)
+        .flat_map(|row| row.iter().take(row_len))
+        .copied()
+        .collect();
+    RgbImage::from_raw(plane.width, plane.height, buf)
+        .map(DynamicImage::ImageRgb8)
+        .ok_or_else(|| DecodingErr::UnsupportedColor("truncated RGB".to_string()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use anyhow::Result;
+    use std::fs;
+
+    #[test]
+    fn all_pages_of_tiff_are_decoded() -> Result<()> {
+        // given
+        let doc = fs::read("res/doc17.tiff")?;
+
+        // when
+        let pages = decode_pages(&doc, &Ext::Tiff)?;
+
+        // then
+        assert_eq!(pages.len(), 2);
+        assert_eq!((pages[1].width(), pages[1].height()), (791, 1024));
+
+        Ok(())
+    }
+
+    #[test]
+    fn pages_of_fax_are_decoded() -> Result<()> {
+        // given
+        let doc = fs::read("res/doc20.tiff")?;
+
+        // when
+        let pages = decode_pages(&doc, &Ext::Tiff)?;
+
+        // then
+        assert_eq!(pages.len(), 2);
+        assert_eq!((pages[0].width(), pages[0].height()), (256, 64));
+        let first = pages[0].to_luma8();
+        let second = pages[1].to_luma8();
+        assert_eq!((first[(80, 30)], first[(10, 10)]), (Luma([0]), Luma([255])));
+        assert_eq!(
+            (second[(150, 30)], second[(80, 30)]),
+            (Luma([0]), Luma([255]))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn bits_of_bilevel_image_are_expanded() {
+        // given
+        let buf = [0b1010_0000, 0b0100_0000];
+
+        // when
+        let img = expand_bits(3, 2, &buf);
+
+        // then
+        assert_eq!(img.as_raw(), &vec![255, 0, 255, 0, 255, 0]);
+    }
+
+    #[test]
+    fn other_images_have_single_page() -> Result<()> {
+        // given
+        let doc = fs::read("res/doc18.bmp")?;
+
+        // when
+        let pages = decode_pages(&doc, &Ext::Bmp)?;
+
+        // then
+        assert_eq!(pages.len(), 1);
+
+        Ok(())
+    }
+}
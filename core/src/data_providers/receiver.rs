@@ -1,70 +1,356 @@
+use crate::entities::file::Filename;
 use crate::entities::location::SafePathBuf;
 use crate::result::EventReceiverErr;
+use crate::use_cases::config::WatcherConfig;
 use crate::use_cases::receiver::{DocsEvent, EventReceiver};
 
-use notify::RecommendedWatcher;
 use notify::{watcher, DebouncedEvent, RecursiveMode, Watcher};
-use std::path::Path;
-use std::sync::mpsc::{channel, Receiver};
-use std::time::Duration;
+use notify::{PollWatcher, RecommendedWatcher};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, RecvError, RecvTimeoutError};
+use std::time::{Duration, Instant, SystemTime};
 use tracing::{debug, error, warn};
 
+/// Debounce delay of the native watcher.
+const DEBOUNCE_DELAY: Duration = Duration::from_millis(100);
+
+/// How often the sizes of files which are still being written are checked.
+const SETTLE_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Receives events from the watched directory using backend selected in [`WatcherConfig`].
+///
+/// New and modified documents are reported only after their size stops changing for
+/// [`WatcherConfig::settle_time`], so partially written files (e.g. still uploaded by a scanner)
+/// are not processed.
+///
+/// When both backends are used, each of them reports the same file. Reported files are remembered
+/// for a while, so the file is skipped when the other backend reports it unchanged.
 pub struct FsEventReceiver {
-    _watcher: RecommendedWatcher, // just keep watcher alive
+    _native: Option<RecommendedWatcher>, // just keep watcher alive
+    _polling: Option<PollWatcher>,       // just keep watcher alive
     watcher_rx: Receiver<DebouncedEvent>,
+    pending: RefCell<HashMap<PathBuf, Pending>>,
+    reported: RefCell<HashMap<PathBuf, Reported>>,
+    settle_time: Duration,
+    repeat_window: Duration,
 }
 
 impl FsEventReceiver {
-    pub fn new<P: AsRef<Path>>(watched_dir: P) -> Result<Self, EventReceiverErr> {
+    pub fn new<P: AsRef<Path>>(
+        watched_dir: P,
+        cfg: &WatcherConfig,
+    ) -> Result<Self, EventReceiverErr> {
+        let watched_dir = watched_dir.as_ref();
         let (watcher_tx, watcher_rx) = channel();
-        let mut watcher = watcher(watcher_tx, Duration::from_millis(100))?;
-        watcher.watch(watched_dir, RecursiveMode::Recursive)?;
+        let native = if cfg.backend.is_native() {
+            debug!("starting native watcher");
+            let mut native = watcher(watcher_tx.clone(), DEBOUNCE_DELAY)?;
+            native.watch(watched_dir, RecursiveMode::Recursive)?;
+            Some(native)
+        } else {
+            None
+        };
+        let polling = if cfg.backend.is_polling() {
+            debug!(
+                "starting polling watcher, interval: {:?}",
+                cfg.poll_interval()
+            );
+            let mut polling = PollWatcher::new(watcher_tx, cfg.poll_interval())?;
+            polling.watch(watched_dir, RecursiveMode::Recursive)?;
+            Some(polling)
+        } else {
+            None
+        };
+        // NOTE: the other backend reports the file at most one poll later, and then it settles
+        let repeat_window = if native.is_some() && polling.is_some() {
+            cfg.poll_interval() + cfg.settle_time() * 2
+        } else {
+            Duration::ZERO
+        };
         Ok(Self {
-            _watcher: watcher,
+            _native: native,
+            _polling: polling,
             watcher_rx,
+            pending: RefCell::new(HashMap::new()),
+            reported: RefCell::new(HashMap::new()),
+            settle_time: cfg.settle_time(),
+            repeat_window,
+        })
+    }
+
+    fn handle(&self, event: DebouncedEvent) -> Option<DocsEvent> {
+        match event {
+            DebouncedEvent::Create(path) if path.is_dir() => {
+                // NOTE: files created right after the directory may appear before the directory
+                // is watched, so they are looked up here
+                self.track_dir(&path);
+                Some(DocsEvent::Other)
+            }
+            DebouncedEvent::Create(path) => self.track(path, PendingKind::Created),
+            DebouncedEvent::Write(path) => self.track(path, PendingKind::Modified),
+            DebouncedEvent::Rename(from, to) => {
+                self.pending.borrow_mut().remove(&from);
+                if to.is_dir() {
+                    self.track_dir(&to);
+                    return Some(DocsEvent::Other);
+                }
+                Some(renamed(&from, to))
+            }
+            DebouncedEvent::Remove(path) => {
+                debug!("path removed: {}", path.display());
+                self.pending.borrow_mut().remove(&path);
+                Some(DocsEvent::Removed(path))
+            }
+            e => {
+                warn!("this FS event is not supported in FsEventReceiver: {:?}", e);
+                Some(DocsEvent::Other)
+            }
+        }
+    }
+
+    /// Starts waiting for the file to be fully written. Returns [`DocsEvent::Other`] when the
+    /// path doesn't point to a document.
+    fn track(&self, path: PathBuf, kind: PendingKind) -> Option<DocsEvent> {
+        let mut pending = self.pending.borrow_mut();
+        if let Some(file) = pending.get_mut(&path) {
+            file.touch();
+            return None;
+        }
+        let Some(doc) = to_doc(path) else {
+            return Some(DocsEvent::Other);
+        };
+        debug!("waiting until '{doc}' is fully written");
+        let path: &PathBuf = doc.as_ref();
+        pending.insert(path.clone(), Pending::new(kind));
+        None
+    }
+
+    fn track_dir(&self, dir: &Path) {
+        let Ok(entries) = fs::read_dir(dir) else {
+            warn!("failed to read dir: '{}'", dir.display());
+            return;
+        };
+        for path in entries.flatten().map(|entry| entry.path()) {
+            if path.is_dir() {
+                self.track_dir(&path);
+            } else {
+                self.track(path, PendingKind::Created);
+            }
+        }
+    }
+
+    /// Checks if the file was reported unchanged within the `repeat_window`, and remembers it
+    /// as reported otherwise.
+    fn is_repeated(&self, path: &Path) -> bool {
+        if self.repeat_window.is_zero() {
+            return false;
+        }
+        let mut reported = self.reported.borrow_mut();
+        reported.retain(|_, r| r.at.elapsed() < self.repeat_window);
+        let current = Reported::of(path);
+        let repeated = match (reported.get(path), &current) {
+            (Some(previous), Some(current)) => previous.is_same(current),
+            _ => false,
+        };
+        if !repeated {
+            if let Some(current) = current {
+                reported.insert(path.to_path_buf(), current);
+            }
+        }
+        repeated
+    }
+
+    /// Returns event of the first document which size didn't change for `settle_time`.
+    fn settled(&self) -> Option<DocsEvent> {
+        let mut pending = self.pending.borrow_mut();
+        let mut settled = None;
+        pending.retain(|path, file| {
+            if settled.is_some() {
+                return true;
+            }
+            match file.is_settled(path, self.settle_time) {
+                Some(false) => true,
+                Some(true) if self.is_repeated(path) => {
+                    debug!("'{}' was already reported", path.display());
+                    false
+                }
+                Some(true) => {
+                    settled = Some((path.clone(), file.kind));
+                    false
+                }
+                None => {
+                    debug!("'{}' disappeared before it was settled", path.display());
+                    false
+                }
+            }
+        });
+        let (path, kind) = settled?;
+        let doc = to_doc(path)?;
+        debug!("doc {kind:?}: {doc}");
+        Some(match kind {
+            PendingKind::Created => verified(doc, DocsEvent::Created),
+            PendingKind::Modified => verified(doc, DocsEvent::Modified),
         })
     }
 }
 
 impl EventReceiver for FsEventReceiver {
     fn recv(&self) -> Result<DocsEvent, EventReceiverErr> {
-        match self.watcher_rx.recv() {
-            Ok(DebouncedEvent::Create(path)) => {
-                let safepath = SafePathBuf::new(path);
-                if safepath.is_file() && safepath.has_valid_ext() && safepath.is_in_user_dir() {
-                    debug!("new doc detected: {safepath}");
-                    Ok(DocsEvent::Created(safepath))
-                } else {
-                    warn!("invalid path: {safepath}");
-                    Ok(DocsEvent::Other)
-                }
+        loop {
+            if let Some(event) = self.settled() {
+                return Ok(event);
             }
-            Ok(e) => {
-                warn!("this FS event is not supported in FsEventReceiver: {:?}", e);
-                Ok(DocsEvent::Other)
+            match self.watcher_rx.recv_timeout(SETTLE_CHECK_INTERVAL) {
+                Ok(event) => {
+                    if let Some(event) = self.handle(event) {
+                        return Ok(event);
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => {
+                    error!("watch error: all watchers disconnected");
+                    return Err(EventReceiverErr::Receive(RecvError));
+                }
             }
-            Err(e) => {
-                error!("watch error: {:?}", e);
-                Err(EventReceiverErr::Receive(e))
+        }
+    }
+}
+
+/// File which is still being written.
+struct Pending {
+    kind: PendingKind,
+    size: Option<u64>,
+    changed: Instant,
+}
+
+impl Pending {
+    fn new(kind: PendingKind) -> Self {
+        Self {
+            kind,
+            size: None,
+            changed: Instant::now(),
+        }
+    }
+
+    fn touch(&mut self) {
+        self.changed = Instant::now();
+    }
+
+    /// Returns `None` when the file doesn't exist anymore.
+    fn is_settled(&mut self, path: &Path, settle_time: Duration) -> Option<bool> {
+        let size = fs::metadata(path).ok()?.len();
+        if self.size != Some(size) {
+            self.size = Some(size);
+            self.touch();
+        }
+        Some(self.changed.elapsed() >= settle_time)
+    }
+}
+
+/// Size and modification time of the reported file.
+struct Reported {
+    size: u64,
+    modified: Option<SystemTime>,
+    at: Instant,
+}
+
+impl Reported {
+    fn of(path: &Path) -> Option<Self> {
+        let metadata = fs::metadata(path).ok()?;
+        Some(Self {
+            size: metadata.len(),
+            modified: metadata.modified().ok(),
+            at: Instant::now(),
+        })
+    }
+
+    fn is_same(&self, other: &Self) -> bool {
+        self.size == other.size && self.modified == other.modified
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum PendingKind {
+    Created,
+    Modified,
+}
+
+/// Returns document only if the path points to a supported file placed in a user directory.
+///
+/// The path is checked before creating [`SafePathBuf`], because the file could have been already
+/// moved from the watched directory when the event arrives.
+fn to_doc(path: PathBuf) -> Option<SafePathBuf> {
+    if !path.is_file() {
+        warn!("not a file: {}", path.display());
+        return None;
+    }
+    let doc = SafePathBuf::new(path);
+    if !doc.has_valid_ext() || !doc.is_in_user_dir() {
+        warn!("invalid path: {doc}");
+        return None;
+    }
+    Some(doc)
+}
+
+/// Returns the `event` of the document, or [`DocsEvent::Rejected`] when its content is not of the
+/// type claimed by its extension. The content is checked once the file is fully written.
+fn verified(doc: SafePathBuf, event: impl FnOnce(SafePathBuf) -> DocsEvent) -> DocsEvent {
+    match doc.verify_content() {
+        Ok(()) => event(doc),
+        Err(e) => {
+            warn!("rejecting '{doc}': {e}");
+            DocsEvent::Rejected {
+                path: doc,
+                reason: e.to_string(),
             }
         }
     }
 }
 
+/// Renaming within the same user directory is reported as [`DocsEvent::Renamed`]. In other cases
+/// the document under the new path is treated as a new one.
+fn renamed(from: &Path, to: PathBuf) -> DocsEvent {
+    let same_dir = from.parent() == to.parent();
+    let Some(to) = to_doc(to) else {
+        return DocsEvent::Other;
+    };
+    let from = from
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string());
+    match from.map(Filename::new) {
+        Some(Ok(from)) if same_dir => {
+            debug!("doc renamed from '{from}' to '{to}'");
+            verified(to, |to| DocsEvent::Renamed { from, to })
+        }
+        _ => {
+            debug!("doc moved to: {to}");
+            verified(to, DocsEvent::Created)
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
 
+    use crate::entities::extension::{Ext, ARCHIVE_EXT};
+    use crate::testingtools::{sample_content, EMPTY_ZIP};
+    use crate::use_cases::config::WatcherBackend;
+
     use anyhow::Result;
     use base64::engine::general_purpose::STANDARD as b64;
     use base64::Engine;
-    use claim::{assert_ok, assert_ok_eq};
+    use claim::{assert_ge, assert_ok, assert_ok_eq};
     use fake::faker::filesystem::en::FileName;
     use fake::faker::internet::en::SafeEmail;
     use fake::faker::lorem::en::Paragraph;
     use fake::{Fake, Faker};
-    use std::fs::{self, create_dir_all};
-    use std::path::PathBuf;
+    use std::fs::create_dir_all;
+    use std::io::Write;
+    use std::thread;
     use tempfile::tempdir;
 
     #[test]
@@ -73,7 +359,7 @@ mod test {
         let watched_dir = tempdir()?;
 
         // when
-        let receiver = FsEventReceiver::new(watched_dir);
+        let receiver = FsEventReceiver::new(watched_dir, &WatcherConfig::default());
 
         // then
         assert_ok!(receiver);
@@ -87,8 +373,11 @@ mod test {
         let watched_dir = tempdir()?;
         let user_email: String = SafeEmail().fake();
         let user_dir = mk_user_dir(&watched_dir, user_email)?;
-        let receiver = FsEventReceiver::new(&watched_dir)?;
-        let supported_extensions = vec!["png", "jpg", "jpeg", "webp", "pdf"];
+        let receiver = FsEventReceiver::new(&watched_dir, &WatcherConfig::default())?;
+        let supported_extensions = vec![
+            "png", "jpg", "jpeg", "webp", "tif", "tiff", "heic", "heif", "bmp", "gif", "pdf",
+            "docx", "odt", "xlsx", "rtf", "txt", "md", "html", "eml", "zip",
+        ];
 
         for extension in supported_extensions {
             let created_file: String = format!("some-file.{extension}");
@@ -110,7 +399,7 @@ mod test {
         let watched_dir = tempdir()?;
         let user_email: String = SafeEmail().fake();
         let user_dir = mk_user_dir(&watched_dir, user_email)?;
-        let receiver = FsEventReceiver::new(&watched_dir)?;
+        let receiver = FsEventReceiver::new(&watched_dir, &WatcherConfig::default())?;
         let extension: String = Faker.fake();
         let created_file: String = format!("some-file.{extension}");
         let file_path = user_dir.join(created_file);
@@ -132,8 +421,41 @@ mod test {
         Ok(user_dir)
     }
 
+    #[test]
+    fn rejected_event_appears_when_content_does_not_match_extension() -> Result<()> {
+        // given
+        let watched_dir = tempdir()?;
+        let user_email: String = SafeEmail().fake();
+        let user_dir = mk_user_dir(&watched_dir, user_email)?;
+        let receiver = FsEventReceiver::new(&watched_dir, &WatcherConfig::default())?;
+        let file_path = user_dir.join("scan.jpg");
+
+        // when
+        fs::write(&file_path, sample_content(&Ext::Pdf))?;
+
+        // then
+        assert_ok_eq!(
+            receiver.recv(),
+            DocsEvent::Rejected {
+                path: file_path.into(),
+                reason: "Content of the file looks like PDF, not JPG.".into()
+            }
+        );
+
+        Ok(())
+    }
+
+    /// Content of the file is of the type claimed by its extension, when it's supported.
     fn mk_file<P: AsRef<Path>>(path: P) -> Result<()> {
-        let content: String = Paragraph(0..2).fake();
+        let path = path.as_ref();
+        let ext = path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_string());
+        let content = match ext.map(Ext::try_from) {
+            Some(Ok(ext)) => sample_content(&ext),
+            _ if path.extension() == Some(ARCHIVE_EXT.as_ref()) => EMPTY_ZIP.to_vec(),
+            _ => Paragraph(0..2).fake::<String>().into_bytes(),
+        };
         fs::write(path, content)?;
         Ok(())
     }
@@ -144,7 +466,7 @@ mod test {
         let watched_dir = tempdir()?;
         let user_email: String = SafeEmail().fake();
         let user_dir = mk_user_dir(&watched_dir, user_email)?;
-        let receiver = FsEventReceiver::new(&watched_dir)?;
+        let receiver = FsEventReceiver::new(&watched_dir, &WatcherConfig::default())?;
         let created_dir: String = FileName().fake();
         let dir_path = user_dir.join(created_dir);
 
@@ -166,7 +488,7 @@ mod test {
     fn other_event_appears_when_directory_is_created_in_watched_dir() -> Result<()> {
         // given
         let watched_dir = tempdir()?;
-        let receiver = FsEventReceiver::new(&watched_dir)?;
+        let receiver = FsEventReceiver::new(&watched_dir, &WatcherConfig::default())?;
         let created_dir: String = FileName().fake();
         let dir_path = watched_dir.path().join(created_dir);
 
@@ -180,19 +502,39 @@ mod test {
     }
 
     #[test]
-    fn other_event_appears_when_user_dir_file_has_been_accessed() -> Result<()> {
+    fn modified_event_appears_when_user_dir_file_has_been_changed() -> Result<()> {
         // given
         let watched_dir = tempdir()?;
         let user_email: String = SafeEmail().fake();
         let user_dir = mk_user_dir(&watched_dir, user_email)?;
-        let receiver = FsEventReceiver::new(&watched_dir)?;
-        let created_file: String = FileName().fake();
-        let file_path = user_dir.join(created_file);
+        let receiver = FsEventReceiver::new(&watched_dir, &WatcherConfig::default())?;
+        let file_path = user_dir.join("some-file.pdf");
         mk_file(&file_path)?;
         let _event = receiver.recv(); // ignore Created event
 
         // when
-        touch_file(file_path)?;
+        touch_file(&file_path)?;
+
+        // then
+        assert_ok_eq!(receiver.recv(), DocsEvent::Modified(file_path.into()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn other_event_appears_when_file_with_unsupported_extension_has_been_changed() -> Result<()> {
+        // given
+        let watched_dir = tempdir()?;
+        let user_email: String = SafeEmail().fake();
+        let user_dir = mk_user_dir(&watched_dir, user_email)?;
+        let receiver = FsEventReceiver::new(&watched_dir, &WatcherConfig::default())?;
+        let extension: String = Faker.fake();
+        let file_path = user_dir.join(format!("some-file.{extension}"));
+        mk_file(&file_path)?;
+        let _event = receiver.recv(); // ignore Created event
+
+        // when
+        touch_file(&file_path)?;
 
         // then
         assert_ok_eq!(receiver.recv(), DocsEvent::Other);
@@ -201,28 +543,26 @@ mod test {
     }
 
     fn touch_file<P: AsRef<Path>>(path: P) -> Result<()> {
-        let content: String = Paragraph(0..2).fake();
-        fs::write(path, content)?;
-        Ok(())
+        mk_file(path)
     }
 
     #[test]
-    fn other_event_appears_when_user_dir_file_has_been_removed() -> Result<()> {
+    fn removed_event_appears_when_user_dir_file_has_been_removed() -> Result<()> {
         // given
         let watched_dir = tempdir()?;
         let user_email: String = SafeEmail().fake();
         let user_dir = mk_user_dir(&watched_dir, user_email)?;
-        let receiver = FsEventReceiver::new(&watched_dir)?;
+        let receiver = FsEventReceiver::new(&watched_dir, &WatcherConfig::default())?;
         let created_file: String = FileName().fake();
         let file_path = user_dir.join(created_file);
         mk_file(&file_path)?;
         let _event = receiver.recv(); // ignore Created event
 
         // when
-        rm_file(file_path)?;
+        rm_file(&file_path)?;
 
         // then
-        assert_ok_eq!(receiver.recv(), DocsEvent::Other);
+        assert_ok_eq!(receiver.recv(), DocsEvent::Removed(file_path));
 
         Ok(())
     }
@@ -231,4 +571,171 @@ mod test {
         fs::remove_file(path)?;
         Ok(())
     }
+
+    #[test]
+    fn renamed_event_appears_when_file_is_renamed_within_user_dir() -> Result<()> {
+        // given
+        let watched_dir = tempdir()?;
+        let user_email: String = SafeEmail().fake();
+        let user_dir = mk_user_dir(&watched_dir, user_email)?;
+        let receiver = FsEventReceiver::new(&watched_dir, &WatcherConfig::default())?;
+        let tmp_path = user_dir.join("scan.tmp");
+        fs::write(&tmp_path, sample_content(&Ext::Pdf))?;
+        let _event = receiver.recv(); // ignore Other event of unsupported file
+        let file_path = user_dir.join("scan.pdf");
+
+        // when
+        fs::rename(&tmp_path, &file_path)?;
+
+        // then
+        assert_ok_eq!(
+            receiver.recv(),
+            DocsEvent::Renamed {
+                from: Filename::new("scan.tmp")?,
+                to: file_path.into()
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn created_event_appears_when_file_is_moved_to_another_user_dir() -> Result<()> {
+        // given
+        let watched_dir = tempdir()?;
+        let first_user_dir = mk_user_dir(&watched_dir, SafeEmail().fake::<String>())?;
+        let second_user_dir = mk_user_dir(&watched_dir, SafeEmail().fake::<String>())?;
+        let receiver = FsEventReceiver::new(&watched_dir, &WatcherConfig::default())?;
+        let src_path = first_user_dir.join("scan.pdf");
+        mk_file(&src_path)?;
+        let _event = receiver.recv(); // ignore Created event
+        let dst_path = second_user_dir.join("scan.pdf");
+
+        // when
+        fs::rename(&src_path, &dst_path)?;
+
+        // then
+        assert_ok_eq!(receiver.recv(), DocsEvent::Created(dst_path.into()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn created_event_appears_when_file_size_stops_changing() -> Result<()> {
+        // given
+        let watched_dir = tempdir()?;
+        let user_email: String = SafeEmail().fake();
+        let user_dir = mk_user_dir(&watched_dir, user_email)?;
+        let receiver = FsEventReceiver::new(&watched_dir, &WatcherConfig::default())?;
+        let file_path = user_dir.join("scan.pdf");
+        let writer_path = file_path.clone();
+        let started = Instant::now();
+
+        // when
+        let writer = thread::spawn(move || -> Result<()> {
+            let mut file = fs::File::create(writer_path)?;
+            file.write_all(b"%PDF-1.4\n")?;
+            for _ in 0..10 {
+                file.write_all(b"part of the document")?;
+                file.flush()?;
+                thread::sleep(Duration::from_millis(150));
+            }
+            Ok(())
+        });
+
+        // then
+        assert_ok_eq!(receiver.recv(), DocsEvent::Created(file_path.into()));
+        assert_ge!(started.elapsed(), Duration::from_millis(1500));
+        assert!(writer.join().is_ok());
+
+        Ok(())
+    }
+
+    #[test]
+    fn created_event_appears_when_file_is_created_right_after_user_dir() -> Result<()> {
+        // given
+        let watched_dir = tempdir()?;
+        let receiver = FsEventReceiver::new(&watched_dir, &WatcherConfig::default())?;
+        let user_email: String = SafeEmail().fake();
+
+        // when
+        let user_dir = mk_user_dir(&watched_dir, user_email)?;
+        let file_path = user_dir.join("scan.pdf");
+        mk_file(&file_path)?;
+
+        // then
+        let event = receiver.recv()?;
+        let event = if event == DocsEvent::Other {
+            receiver.recv()? // user dir creation
+        } else {
+            event
+        };
+        assert_eq!(event, DocsEvent::Created(file_path.into()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn polling_backend_reports_created_file() -> Result<()> {
+        // given
+        let watched_dir = tempdir()?;
+        let user_email: String = SafeEmail().fake();
+        let user_dir = mk_user_dir(&watched_dir, user_email)?;
+        let cfg = WatcherConfig {
+            backend: WatcherBackend::Polling,
+            poll_interval_ms: 200,
+            ..WatcherConfig::default()
+        };
+        let receiver = FsEventReceiver::new(&watched_dir, &cfg)?;
+        let file_path = user_dir.join("scan.pdf");
+
+        // when
+        mk_file(&file_path)?;
+
+        // then
+        assert_ok_eq!(receiver.recv(), DocsEvent::Created(file_path.into()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn file_is_reported_once_when_both_backends_are_used() -> Result<()> {
+        // given
+        let watched_dir = tempdir()?;
+        let user_email: String = SafeEmail().fake();
+        let user_dir = mk_user_dir(&watched_dir, user_email)?;
+        let cfg = WatcherConfig {
+            backend: WatcherBackend::Both,
+            ..WatcherConfig::default()
+        };
+        let receiver = FsEventReceiver::new(&watched_dir, &cfg)?;
+        let file_path = user_dir.join("scan.pdf");
+
+        // when
+        mk_file(&file_path)?;
+
+        // then
+        assert_ok_eq!(receiver.recv(), DocsEvent::Created(file_path.into()));
+        assert!(receiver.pending.borrow().is_empty());
+        let (tx, rx) = channel();
+        thread::spawn(move || {
+            while let Ok(event) = receiver.recv() {
+                if tx.send(event).is_err() {
+                    break;
+                }
+            }
+        });
+        // the duplicate would be reported after the next poll, once the file settles again
+        let wait = cfg.poll_interval() + cfg.settle_time() * 2;
+        loop {
+            match rx.recv_timeout(wait) {
+                Ok(DocsEvent::Other) => continue,
+                Ok(event) => panic!("file reported twice: {event:?}"),
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => panic!("receiver failed"),
+            }
+        }
+
+        Ok(())
+    }
 }
@@ -0,0 +1,305 @@
+//! This is concrete implementation of [`crate::use_cases::scanner`] abstractions.
+//!
+//! The page is searched for on a smaller copy of the photo. Edges found there are joined into
+//! shapes, and the largest one is taken for the page when it has four corners. The corners are
+//! then used to warp the full photo into a rectangle.
+use crate::data_providers::extractor::preprocess::{exif_orientation, rotate_by_exif};
+use crate::entities::location::SafePathBuf;
+use crate::result::ScannerErr;
+use crate::use_cases::config::{Config, ScannerConfig};
+use crate::use_cases::scanner::{PageScanner, Scanner};
+
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::{self, FilterType};
+use image::{GrayImage, ImageFormat, Rgb, RgbImage};
+use imageproc::contours::{find_contours, BorderType};
+use imageproc::distance_transform::Norm;
+use imageproc::edges::canny;
+use imageproc::filter::gaussian_blur_f32;
+use imageproc::geometric_transformations::{warp_into, Interpolation, Projection};
+use imageproc::geometry::convex_hull;
+use imageproc::morphology::dilate;
+use imageproc::point::Point;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::Path;
+use std::sync::Arc;
+use tracing::{debug, instrument};
+
+/// Width of the copy of the photo used to find the page.
+const DETECTION_WIDTH: u32 = 512;
+/// Quality of the JPEG photos saved after the correction.
+const JPEG_QUALITY: u8 = 90;
+/// Shape is taken for the page when its corners cover most of it.
+const MIN_CORNERS_COVERAGE: f64 = 0.9;
+/// Page has to be brighter than the background by at least this many levels of gray.
+const MIN_CONTRAST: u64 = 10;
+const WHITE: Rgb<u8> = Rgb([255, 255, 255]);
+
+pub struct EdgeScanner {
+    cfg: ScannerConfig,
+}
+
+impl EdgeScanner {
+    pub fn create(cfg: &Config) -> Scanner {
+        Arc::new(Self {
+            cfg: cfg.scanner.clone(),
+        })
+    }
+
+    /// Returns corners of the page on the photo - top left, top right, bottom right and bottom
+    /// left, in that order.
+    fn find_page(&self, photo: &RgbImage) -> Option<[(f32, f32); 4]> {
+        let factor = f64::from(photo.width()) / f64::from(DETECTION_WIDTH);
+        let small = if factor > 1.0 {
+            let height = scaled(photo.height(), 1.0 / factor);
+            imageops::resize(photo, DETECTION_WIDTH, height, FilterType::Triangle)
+        } else {
+            photo.clone()
+        };
+        let factor = factor.max(1.0);
+        let gray = gaussian_blur_f32(&imageops::grayscale(&small), 2.0);
+        // NOTE: edges are thickened, so small gaps don't split the border of the page
+        let edges = dilate(&canny(&gray, 20.0, 60.0), Norm::LInf, 2);
+        let hull = largest_shape(&edges)?;
+        let corners = corners(&hull);
+        let photo_area = f64::from(small.width()) * f64::from(small.height());
+        let page_area = area(&corners);
+        let coverage = page_area / photo_area * 100.0;
+        debug!("largest shape covers {:.1}% of the photo", coverage);
+        if coverage < f64::from(self.cfg.min_page_area)
+            || coverage > f64::from(self.cfg.max_page_area)
+            || page_area < area(&hull) * MIN_CORNERS_COVERAGE
+            || !stands_out(&gray, &corners)
+        {
+            return None;
+        }
+        Some(corners.map(|p| {
+            (
+                to_f32(f64::from(p.x) * factor),
+                to_f32(f64::from(p.y) * factor),
+            )
+        }))
+    }
+}
+
+impl PageScanner for EdgeScanner {
+    #[instrument(skip(self))]
+    fn flatten(&self, path: &SafePathBuf) -> Result<bool, ScannerErr> {
+        // NOTE: multi-page TIFF would lose its pages when saved after the correction
+        let is_photo = path.ext().map_or(false, |ext| ext.is_displayable());
+        if !self.cfg.enabled || !is_photo {
+            return Ok(false);
+        }
+        let path: &Path = path.as_ref();
+        let format = ImageFormat::from_path(path)?;
+        if !format.can_write() {
+            debug!("can't save '{}' after correction, skipping", path.display());
+            return Ok(false);
+        }
+        let photo = rotate_by_exif(image::open(path)?, exif_orientation(path)).to_rgb8();
+        let Some(corners) = self.find_page(&photo) else {
+            debug!("page not found on '{}'", path.display());
+            return Ok(false);
+        };
+        let Some(page) = warp(&photo, corners) else {
+            return Ok(false);
+        };
+        save(&page, path, format)?;
+        Ok(true)
+    }
+}
+
+/// Returns the convex hull of the largest shape outlined by the edges.
+fn largest_shape(edges: &GrayImage) -> Option<Vec<Point<i32>>> {
+    find_contours::<i32>(edges)
+        .into_iter()
+        .filter(|contour| contour.border_type == BorderType::Outer && contour.points.len() > 2)
+        .map(|contour| convex_hull(&contour.points))
+        .max_by(|a, b| area(a).total_cmp(&area(b)))
+}
+
+/// Corners are the points closest to the corners of the photo.
+fn corners(hull: &[Point<i32>]) -> [Point<i32>; 4] {
+    let sum = |p: &&Point<i32>| p.x + p.y;
+    let diff = |p: &&Point<i32>| p.x - p.y;
+    [
+        hull.iter().min_by_key(sum),
+        hull.iter().max_by_key(diff),
+        hull.iter().max_by_key(sum),
+        hull.iter().min_by_key(diff),
+    ]
+    .map(|corner| *corner.expect("hull has points"))
+}
+
+/// Tells if the shape is brighter than its surroundings. Blocks of text or pictures on the page
+/// are darker than the margins of the page, so they are not taken for the page itself.
+fn stands_out(gray: &GrayImage, corners: &[Point<i32>; 4]) -> bool {
+    let (mut inside, mut outside) = ((0, 0), (0, 0));
+    for (x, y, px) in gray.enumerate_pixels() {
+        let sums = if contains(corners, i64::from(x), i64::from(y)) {
+            &mut inside
+        } else {
+            &mut outside
+        };
+        sums.0 += u64::from(px.0[0]);
+        sums.1 += 1;
+    }
+    let ((in_sum, in_count), (out_sum, out_count)) = (inside, outside);
+    out_count == 0 || in_sum * out_count > (out_sum + MIN_CONTRAST * out_count) * in_count
+}
+
+/// Tells if the point lies within the convex polygon with clockwise ordered corners.
+fn contains(polygon: &[Point<i32>], x: i64, y: i64) -> bool {
+    polygon
+        .iter()
+        .zip(polygon.iter().cycle().skip(1))
+        .all(|(from, to)| {
+            let (from_x, from_y) = (i64::from(from.x), i64::from(from.y));
+            let (to_x, to_y) = (i64::from(to.x), i64::from(to.y));
+            (to_x - from_x) * (y - from_y) - (to_y - from_y) * (x - from_x) >= 0
+        })
+}
+
+/// Area of the polygon, using the shoelace formula.
+fn area(polygon: &[Point<i32>]) -> f64 {
+    let doubled: i64 = polygon
+        .iter()
+        .zip(polygon.iter().cycle().skip(1))
+        .map(|(a, b)| i64::from(a.x) * i64::from(b.y) - i64::from(b.x) * i64::from(a.y))
+        .sum();
+    #[allow(clippy::cast_precision_loss)]
+    let doubled = doubled.abs() as f64;
+    doubled / 2.0
+}
+
+/// Warps the page with given corners into a rectangle, sized after the longer of opposite edges.
+fn warp(photo: &RgbImage, [tl, tr, br, bl]: [(f32, f32); 4]) -> Option<RgbImage> {
+    let distance = |a: (f32, f32), b: (f32, f32)| (a.0 - b.0).hypot(a.1 - b.1);
+    let width = distance(tl, tr).max(distance(bl, br)).round();
+    let height = distance(tl, bl).max(distance(tr, br)).round();
+    let projection = Projection::from_control_points(
+        [tl, tr, br, bl],
+        [(0.0, 0.0), (width, 0.0), (width, height), (0.0, height)],
+    )?;
+    let mut page = RgbImage::new(to_u32(width), to_u32(height));
+    warp_into(
+        photo,
+        &projection,
+        Interpolation::Bilinear,
+        WHITE,
+        &mut page,
+    );
+    Some(page)
+}
+
+/// Writes the page to a temporary file first, so the photo is never left half-written.
+fn save(page: &RgbImage, path: &Path, format: ImageFormat) -> Result<(), ScannerErr> {
+    let tmp = path.with_extension("scanned.tmp");
+    {
+        let mut writer = BufWriter::new(File::create(&tmp)?);
+        if format == ImageFormat::Jpeg {
+            JpegEncoder::new_with_quality(&mut writer, JPEG_QUALITY).encode_image(page)?;
+        } else {
+            page.write_to(&mut writer, format)?;
+        }
+    }
+    fs::rename(tmp, path)?;
+    Ok(())
+}
+
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn scaled(size: u32, factor: f64) -> u32 {
+    (f64::from(size) * factor).round().max(1.0) as u32
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn to_f32(value: f64) -> f32 {
+    value as f32
+}
+
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn to_u32(len: f32) -> u32 {
+    len.max(1.0) as u32
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use anyhow::Result;
+    use imageproc::drawing::draw_polygon_mut;
+    use tempfile::tempdir;
+
+    fn photo_with_page(path: &Path) -> Result<()> {
+        let mut photo = RgbImage::from_pixel(800, 600, Rgb([60, 50, 40]));
+        let page = [
+            Point::new(150, 100),
+            Point::new(650, 140),
+            Point::new(620, 520),
+            Point::new(120, 480),
+        ];
+        draw_polygon_mut(&mut photo, &page, WHITE);
+        photo.save(path)?;
+        Ok(())
+    }
+
+    fn brightness(img: &RgbImage) -> u64 {
+        let sum: u64 = img.pixels().map(|p| u64::from(p.0[0])).sum();
+        sum / u64::from(img.width() * img.height())
+    }
+
+    #[test]
+    fn page_on_photo_is_flattened() -> Result<()> {
+        // given
+        let dir = tempdir()?;
+        let path = dir.path().join("photo.png");
+        photo_with_page(&path)?;
+        let scanner = EdgeScanner::create(&Config::default());
+
+        // when
+        let flattened = scanner.flatten(&SafePathBuf::new(&path))?;
+
+        // then
+        let page = image::open(&path)?.to_rgb8();
+        assert!(flattened);
+        assert!((480..=530).contains(&page.width()));
+        assert!((360..=410).contains(&page.height()));
+        assert!(brightness(&page) > 200);
+
+        Ok(())
+    }
+
+    #[test]
+    fn photo_without_page_is_left_intact() -> Result<()> {
+        // given
+        let dir = tempdir()?;
+        let path = dir.path().join("photo.png");
+        RgbImage::from_pixel(800, 600, Rgb([60, 50, 40])).save(&path)?;
+        let before = fs::read(&path)?;
+        let scanner = EdgeScanner::create(&Config::default());
+
+        // when
+        let flattened = scanner.flatten(&SafePathBuf::new(&path))?;
+
+        // then
+        assert!(!flattened);
+        assert_eq!(fs::read(&path)?, before);
+
+        Ok(())
+    }
+
+    #[test]
+    fn documents_other_than_photos_are_ignored() -> Result<()> {
+        // given
+        let scanner = EdgeScanner::create(&Config::default());
+
+        // when
+        let flattened = scanner.flatten(&SafePathBuf::from("res/doc1.pdf"))?;
+
+        // then
+        assert!(!flattened);
+
+        Ok(())
+    }
+}
@@ -1,90 +1,680 @@
-use crate::entities::extension::supported_extensions;
-use crate::entities::file::{Filename, Thumbnailname};
-use crate::entities::user::User;
-use crate::result::{DocumentReadErr, DocumentSaveErr, SearchErr, ThumbnailReadErr};
+use crate::entities::extension::supported_names;
+use crate::entities::file::{Filename, ThumbnailSize, Thumbnailname};
+use crate::entities::user::{Admin, User};
+use crate::result::{
+    AuditErr, DocumentReadErr, DocumentSaveErr, FsErr, LinkErr, MetricsErr, QuotaErr, SearchErr,
+    SharingErr, ThumbnailReadErr, UsersErr,
+};
+use crate::use_cases::audit::{Action, AuditEntry, AuditRecord, AuditTrail};
+use crate::use_cases::auth::AuthFailure;
 use crate::use_cases::cipher::CipherReader;
 use crate::use_cases::config::Config;
 use crate::use_cases::fs::Fs as Filesystem;
+use crate::use_cases::health::{HealthCheck, HealthReport};
+use crate::use_cases::links::{MintedLink, PublicLinks, ShareLink};
+use crate::use_cases::metrics::Metrics;
+use crate::use_cases::ocr::{LanguageOverrides, OcrLanguages};
+use crate::use_cases::pages::Pages;
+use crate::use_cases::quota::{Quotas, UsageReport};
+use crate::use_cases::sharing::{DocAccess, Grant, Sharing};
 use crate::use_cases::state::{SearchResult, StateReader};
+use crate::use_cases::throttle::{ClientIp, Downloads, RetryAfter, Searches, Throttle, Uploads};
+use crate::use_cases::users::{Account, AccountStatus, UserDirectory, UserRemover};
 
 use anyhow::Context;
 use base64::engine::general_purpose::STANDARD as b64;
 use base64::Engine;
-use rocket::http::Status;
+use rocket::http::{ContentType, Header, Status};
 use rocket::serde::json::Json;
-use rocket::serde::Deserialize;
-use rocket::{get, post, State};
-use tracing::instrument;
+use rocket::serde::{Deserialize, Serialize};
+use rocket::{catch, delete, get, post, put, Request, Responder, State};
+use std::io::ErrorKind;
+use std::time::Instant;
+use tracing::{debug, instrument, warn};
 
 type Cfg = State<Config>;
 type Fs = State<Filesystem>;
 type Cipher = State<CipherReader>;
 type AppState = State<StateReader>;
+type Health = State<HealthCheck>;
+type Mtr = State<Metrics>;
+type Access = State<DocAccess>;
+type Shares = State<Sharing>;
 type Doc = Json<Document>;
+type ShareReq = Json<ShareRequest>;
+type PubLinks = State<PublicLinks>;
+type LinkReq = Json<LinkRequest>;
+type Trail = State<AuditTrail>;
+type Qts = State<Quotas>;
+type Directory = State<UserDirectory>;
+type Remover = State<UserRemover>;
+type Ocr = State<LanguageOverrides>;
+type DocPages = State<Pages>;
+type StatusReq = Json<StatusRequest>;
 
 type SearchRes = Result<Json<SearchResult>, SearchErr>;
 type GetThumbRes = Result<Option<Vec<u8>>, ThumbnailReadErr>;
 type GetAllThumbsRes = Result<Json<SearchResult>, ThumbnailReadErr>;
 type GetDocRes = Result<Option<Vec<u8>>, DocumentReadErr>;
+type GetPageRes = Result<Option<(ContentType, Vec<u8>)>, DocumentReadErr>;
 type PostDocRes = Result<(Status, String), DocumentSaveErr>;
+type HealthRes = (Status, Json<HealthReport>);
+type MetricsRes = Result<(ContentType, String), MetricsErr>;
+type ShareRes = Result<Status, SharingErr>;
+type GetSharesRes = Result<Json<Vec<Grant>>, SharingErr>;
+type CreateLinkRes = Result<(Status, Json<MintedLink>), LinkErr>;
+type GetLinksRes = Result<Json<Vec<ShareLink>>, LinkErr>;
+type RevokeLinkRes = Result<Status, LinkErr>;
+type OpenLinkRes = Result<Vec<u8>, LinkErr>;
+type GetAuditRes = Result<Json<Vec<AuditRecord>>, AuditErr>;
+type UsageRes = Result<Json<UsageReport>, QuotaErr>;
+type GetUsersRes = Result<Json<Vec<Account>>, UsersErr>;
+type SetUserStatusRes = Result<Json<Account>, UsersErr>;
+type RemoveUserRes = Result<Status, UsersErr>;
 
-#[instrument(skip(state))]
+/// Returns documents of the user and documents shared with them.
+#[instrument(skip(_rate, access, metrics, trail))]
+#[allow(clippy::needless_pass_by_value)] // rocket requires pass by value here
 #[get("/search?<q>")]
-pub fn search(user: User, q: String, state: &AppState) -> SearchRes {
-    Ok(Json(state.search(user, q).context("Searching failed.")?))
+pub fn search(
+    user: User,
+    _rate: Throttle<Searches>,
+    q: String,
+    access: &Access,
+    metrics: &Mtr,
+    trail: &Trail,
+) -> SearchRes {
+    let start = Instant::now();
+    let res = access.search(&user, &q).context("Searching failed.");
+    trail.record(AuditEntry::new(&user, Action::Search).outcome(&res));
+    metrics.search_finished(start.elapsed());
+    Ok(Json(res?))
+}
+
+/// Thumbnail of other user's document is returned when the `owner` shared the document.
+///
+/// The `size` selects one of the [`ThumbnailSize`]s and `dpr` the device pixel ratio of the
+/// screen. The closest existing thumbnail is returned when there is none for requested ones, down
+/// to the tile for standard screens.
+///
+/// Thumbnails are fetched for every tile of the listing, which is already recorded, so only the
+/// views which failed, e.g. because of no access, are recorded in the audit log.
+#[instrument(skip(cfg, fs, cipher, access, trail))]
+#[allow(clippy::too_many_arguments)]
+#[get("/thumbnail/<name>?<owner>&<size>&<dpr>")]
+pub fn thumbnail(
+    user: User,
+    name: String,
+    owner: Option<String>,
+    size: Option<ThumbnailSize>,
+    dpr: Option<u32>,
+    cfg: &Cfg,
+    fs: &Fs,
+    cipher: &Cipher,
+    access: &Access,
+    trail: &Trail,
+) -> GetThumbRes {
+    let owner = owner.map_or_else(|| user.clone(), User::new);
+    let entry = AuditEntry::new(&user, Action::View)
+        .owner(&owner)
+        .document(&name);
+    let size = size.unwrap_or_default();
+    let dpr = dpr.unwrap_or(1);
+    let res = read_thumbnail(&user, &owner, name, size, dpr, cfg, fs, cipher, access);
+    if res.is_err() {
+        trail.record(entry.outcome(&res));
+    }
+    res
 }
 
-#[instrument(skip(fs, cipher))]
-#[get("/thumbnail/<name>")]
-pub fn thumbnail(user: User, name: String, cfg: &Cfg, fs: &Fs, cipher: &Cipher) -> GetThumbRes {
+#[allow(clippy::too_many_arguments)]
+fn read_thumbnail(
+    user: &User,
+    owner: &User,
+    name: String,
+    size: ThumbnailSize,
+    dpr: u32,
+    cfg: &Cfg,
+    fs: &Fs,
+    cipher: &Cipher,
+    access: &Access,
+) -> GetThumbRes {
     let filename = Thumbnailname::new(name)?;
-    let buf = fs.load(cfg.thumbnail_path(&user, &filename))?;
+    if !access
+        .can_read_thumbnail(user, owner, &filename)
+        .context("Access check failed.")?
+    {
+        return Err(ThumbnailReadErr::NoAccess);
+    }
+    let sized = filename.sized(size);
+    let mut candidates = vec![sized.at_ratio(dpr), sized, filename];
+    candidates.dedup();
+    let (last, fallbacks) = candidates
+        .split_last()
+        .context("No thumbnail candidates.")?;
+    for candidate in fallbacks {
+        match fs.load(cfg.thumbnail_path(owner, candidate)) {
+            Ok(buf) => return Ok(Some(cipher.decrypt(&buf).context("Image decrypt failed.")?)),
+            Err(FsErr::Io(e)) if e.kind() == ErrorKind::NotFound => {
+                debug!("no thumbnail '{}', trying next one", candidate);
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+    let buf = fs.load(cfg.thumbnail_path(owner, last))?;
     Ok(Some(cipher.decrypt(&buf).context("Image decrypt failed.")?))
 }
 
-#[instrument(skip(state))]
+#[instrument(skip(state, trail))]
 #[get("/thumbnails/all")]
-pub fn all_thumbnails(user: User, state: &AppState) -> GetAllThumbsRes {
-    Ok(Json(state.all_docs(user).context("Failed to read docs.")?))
+pub fn all_thumbnails(user: User, state: &AppState, trail: &Trail) -> GetAllThumbsRes {
+    let res = state.all_docs(user.clone()).context("Failed to read docs.");
+    trail.record(AuditEntry::new(&user, Action::ListThumbnails).outcome(&res));
+    Ok(Json(res?))
 }
 
-#[instrument(skip(fs, cipher))]
+/// Document of other user is returned when the `owner` shared it.
+#[instrument(skip(_rate, cfg, fs, cipher, access, trail))]
+#[allow(clippy::too_many_arguments)]
 #[allow(clippy::needless_pass_by_value)] // rocket requires pass by value here
-#[get("/document/<name>")]
-pub fn document(user: User, name: String, cfg: &Cfg, fs: &Fs, cipher: &Cipher) -> GetDocRes {
+#[get("/document/<name>?<owner>")]
+pub fn document(
+    user: User,
+    _rate: Throttle<Downloads>,
+    name: String,
+    owner: Option<String>,
+    cfg: &Cfg,
+    fs: &Fs,
+    cipher: &Cipher,
+    access: &Access,
+    trail: &Trail,
+) -> GetDocRes {
+    let owner = owner.map_or_else(|| user.clone(), User::new);
+    let entry = AuditEntry::new(&user, Action::Download)
+        .owner(&owner)
+        .document(&name);
+    let res = read_document(&user, &owner, name, cfg, fs, cipher, access);
+    trail.record(entry.outcome(&res));
+    res
+}
+
+fn read_document(
+    user: &User,
+    owner: &User,
+    name: String,
+    cfg: &Cfg,
+    fs: &Fs,
+    cipher: &Cipher,
+    access: &Access,
+) -> GetDocRes {
     let filename = Filename::new(name)?;
-    let buf = fs.load(cfg.document_path(&user, &filename))?;
+    if !access
+        .can_read_doc(user, owner, &filename)
+        .context("Access check failed.")?
+    {
+        return Err(DocumentReadErr::NoAccess);
+    }
+    let buf = fs.load(cfg.document_path(owner, &filename))?;
     Ok(Some(cipher.decrypt(&buf).context("Doc decrypt failed.")?))
 }
 
-#[instrument(skip(doc, fs))]
+/// Image of a single page of the document, e.g. the one which matched the search.
+#[instrument(skip(_rate, cfg, fs, cipher, access, pages, trail))]
+#[allow(clippy::too_many_arguments)]
+#[allow(clippy::needless_pass_by_value)] // rocket requires pass by value here
+#[get("/document/<name>/page/<number>?<owner>")]
+pub fn document_page(
+    user: User,
+    _rate: Throttle<Downloads>,
+    name: String,
+    number: u32,
+    owner: Option<String>,
+    cfg: &Cfg,
+    fs: &Fs,
+    cipher: &Cipher,
+    access: &Access,
+    pages: &DocPages,
+    trail: &Trail,
+) -> GetPageRes {
+    let owner = owner.map_or_else(|| user.clone(), User::new);
+    let entry = AuditEntry::new(&user, Action::View)
+        .owner(&owner)
+        .document(&name)
+        .reason(format!("page {number}"));
+    let res = read_page(&user, &owner, name, number, cfg, fs, cipher, access, pages);
+    trail.record(entry.outcome(&res));
+    res
+}
+
+#[allow(clippy::too_many_arguments)]
+fn read_page(
+    user: &User,
+    owner: &User,
+    name: String,
+    number: u32,
+    cfg: &Cfg,
+    fs: &Fs,
+    cipher: &Cipher,
+    access: &Access,
+    pages: &DocPages,
+) -> GetPageRes {
+    let ext = Filename::new(name.clone())?
+        .ext()
+        .context("Unsupported document.")?;
+    let Some(doc) = read_document(user, owner, name, cfg, fs, cipher, access)? else {
+        return Ok(None);
+    };
+    let png = pages.render(&doc, &ext, number)?;
+    Ok(Some((ContentType::PNG, png)))
+}
+
+#[instrument(skip(cfg, sharing, trail))]
+#[allow(clippy::needless_pass_by_value)] // rocket requires pass by value here
+#[post("/share", data = "<req>")]
+pub fn share(user: User, req: ShareReq, cfg: &Cfg, sharing: &Shares, trail: &Trail) -> ShareRes {
+    let ShareRequest { filename, grantee } = req.into_inner();
+    let entry = AuditEntry::new(&user, Action::Share)
+        .document(&filename)
+        .reason(format!("shared with '{grantee}'"));
+    let res = if cfg.document_path(&user, &filename).exists() {
+        sharing.grant(Grant::new(user, User::new(grantee), filename))
+    } else {
+        Err(SharingErr::DocumentNotFound(filename.to_string()))
+    };
+    trail.record(entry.outcome(&res));
+    res?;
+    Ok(Status::Created)
+}
+
+#[instrument(skip(sharing, trail))]
+#[allow(clippy::needless_pass_by_value)] // rocket requires pass by value here
+#[delete("/share", data = "<req>")]
+pub fn unshare(user: User, req: ShareReq, sharing: &Shares, trail: &Trail) -> ShareRes {
+    let ShareRequest { filename, grantee } = req.into_inner();
+    let entry = AuditEntry::new(&user, Action::Unshare)
+        .document(&filename)
+        .reason(format!("unshared with '{grantee}'"));
+    let res = sharing.revoke(&Grant::new(user, User::new(grantee), filename));
+    trail.record(entry.outcome(&res));
+    if res? {
+        Ok(Status::NoContent)
+    } else {
+        Ok(Status::NotFound)
+    }
+}
+
+/// Returns documents shared by the user.
+#[instrument(skip(sharing, trail))]
+#[allow(clippy::needless_pass_by_value)] // rocket requires pass by value here
+#[get("/shares")]
+pub fn shares(user: User, sharing: &Shares, trail: &Trail) -> GetSharesRes {
+    let res = sharing.granted_by(&user);
+    trail.record(AuditEntry::new(&user, Action::ListShares).outcome(&res));
+    Ok(Json(res?))
+}
+
+/// Document exceeding the quota of the user is rejected with 413 when it's too large on its own,
+/// or with 507 when there is no space left for it. Invalid OCR languages are rejected with 422.
+/// Document which content is not of the type claimed by its extension is rejected with 415.
+/// ZIP archives are accepted, their documents are unpacked while processing.
+#[instrument(skip(_rate, doc, fs, quotas, overrides, trail))]
+#[allow(clippy::too_many_arguments)]
 #[allow(clippy::needless_pass_by_value)] // rocket requires pass by value here
 #[post("/document/upload", data = "<doc>")]
-pub fn receive_document(user: User, doc: Doc, cfg: &Cfg, fs: &Fs) -> PostDocRes {
+pub fn receive_document(
+    user: User,
+    _rate: Throttle<Uploads>,
+    doc: Doc,
+    cfg: &Cfg,
+    fs: &Fs,
+    quotas: &Qts,
+    overrides: &Ocr,
+    trail: &Trail,
+) -> PostDocRes {
+    let entry = AuditEntry::new(&user, Action::Upload).document(&doc.filename);
     if !doc.filename.has_supported_extension() {
-        return Ok((
-            Status::UnsupportedMediaType,
-            wrong_extension_msg(&doc.filename),
-        ));
-    }
-    let to = cfg.watched_path(&user, &doc.filename);
-    let doc = b64.decode(&doc.body).context("Failed to decode body.")?;
-    fs.save(to, &doc).context("Failed to save document.")?;
+        let msg = wrong_extension_msg(&doc.filename);
+        trail.record(entry.failed(&msg));
+        return Ok((Status::UnsupportedMediaType, msg));
+    }
+    let languages = match doc.languages.clone().map(OcrLanguages::new).transpose() {
+        Ok(languages) => languages,
+        Err(e) => {
+            let msg = e.to_string();
+            trail.record(entry.failed(&msg));
+            return Ok((Status::UnprocessableEntity, msg));
+        }
+    };
+    let res = save_document(&user, &doc, languages, cfg, fs, quotas, overrides);
+    trail.record(entry.outcome(&res));
+    res?;
     Ok((Status::Created, String::new()))
 }
 
+fn save_document(
+    user: &User,
+    doc: &Document,
+    languages: Option<OcrLanguages>,
+    cfg: &Cfg,
+    fs: &Fs,
+    quotas: &Qts,
+    overrides: &Ocr,
+) -> Result<(), DocumentSaveErr> {
+    let buf = b64.decode(&doc.body).context("Failed to decode body.")?;
+    doc.filename.verify(&buf)?;
+    quotas.check_upload(user, &doc.filename, buf.len() as u64)?;
+    // NOTE: languages are ignored for archives and documents which text is not recognized
+    let recognized = doc.filename.ext().map_or(false, |ext| ext.is_recognized());
+    let languages = languages.filter(|_| recognized);
+    if let Some(languages) = &languages {
+        overrides
+            .set(user, &doc.filename, languages.clone())
+            .context("Failed to save OCR languages.")?;
+    }
+    let to = cfg.watched_path(user, &doc.filename);
+    let saved = fs.save(to, &buf).context("Failed to save document.");
+    if saved.is_err() && languages.is_some() {
+        if let Err(e) = overrides.take(user, &doc.filename) {
+            warn!("failed to forget OCR languages: '{}'", e);
+        }
+    }
+    Ok(saved?)
+}
+
+#[instrument(skip(cfg, public_links, trail))]
+#[allow(clippy::needless_pass_by_value)] // rocket requires pass by value here
+#[post("/link", data = "<req>")]
+pub fn create_link(
+    user: User,
+    req: LinkReq,
+    cfg: &Cfg,
+    public_links: &PubLinks,
+    trail: &Trail,
+) -> CreateLinkRes {
+    let LinkRequest {
+        filename,
+        ttl_secs,
+        single_use,
+    } = req.into_inner();
+    let entry = AuditEntry::new(&user, Action::CreateLink).document(&filename);
+    let res = if cfg.document_path(&user, &filename).exists() {
+        public_links.mint(user, filename, ttl_secs, single_use)
+    } else {
+        Err(LinkErr::DocumentNotFound(filename.to_string()))
+    };
+    trail.record(match &res {
+        Ok(minted) => entry.reason(format!("link '{}'", minted.id)),
+        Err(e) => entry.failed(e),
+    });
+    Ok((Status::Created, Json(res?)))
+}
+
+/// Returns links created by the user, together with the record of their accesses.
+#[instrument(skip(public_links, trail))]
+#[allow(clippy::needless_pass_by_value)] // rocket requires pass by value here
+#[get("/links")]
+pub fn links(user: User, public_links: &PubLinks, trail: &Trail) -> GetLinksRes {
+    let res = public_links.links_of(&user);
+    trail.record(AuditEntry::new(&user, Action::ListLinks).outcome(&res));
+    Ok(Json(res?))
+}
+
+#[instrument(skip(public_links, trail))]
+#[allow(clippy::needless_pass_by_value)] // rocket requires pass by value here
+#[delete("/link/<id>")]
+pub fn revoke_link(
+    user: User,
+    id: String,
+    public_links: &PubLinks,
+    trail: &Trail,
+) -> RevokeLinkRes {
+    let res = public_links.revoke(&user, &id);
+    let entry = AuditEntry::new(&user, Action::RevokeLink).reason(format!("link '{id}'"));
+    trail.record(entry.outcome(&res));
+    if res? {
+        Ok(Status::NoContent)
+    } else {
+        Ok(Status::NotFound)
+    }
+}
+
+/// Returns the document of the link. It doesn't need the `authorization` header, the signed
+/// token is enough. The access is recorded in the audit log of the owner of the link. Tokens
+/// which don't point to any link are recorded as well, together with the address they came from.
+///
+/// Links stop working when the account of their owner isn't active anymore.
+#[instrument(skip(_rate, token, cfg, fs, cipher, public_links, directory, trail))]
+#[allow(clippy::too_many_arguments)]
+#[allow(clippy::needless_pass_by_value)] // rocket requires pass by value here
+#[get("/link/<token>")]
+pub fn open_link(
+    token: String,
+    ip: ClientIp,
+    _rate: Throttle<Downloads>,
+    cfg: &Cfg,
+    fs: &Fs,
+    cipher: &Cipher,
+    public_links: &PubLinks,
+    directory: &Directory,
+    trail: &Trail,
+) -> OpenLinkRes {
+    let ip = ip.0.map(|ip| ip.to_string());
+    let from = ip.clone().unwrap_or_else(|| "unknown".into());
+    let link = match public_links.find(&token) {
+        Ok(link) => link,
+        Err(e) => {
+            let entry = AuditEntry::anonymous(Action::OpenLink)
+                .failed(format!("link not opened from '{from}': {e}"));
+            trail.record(entry);
+            return Err(e);
+        }
+    };
+    let entry = AuditEntry::anonymous(Action::OpenLink)
+        .owner(&link.owner)
+        .document(&link.filename)
+        .reason(format!("link '{}' opened from '{}'", link.id, from));
+    let res = match directory.check_active(&link.owner) {
+        Ok(()) => read_link(&token, ip, cfg, fs, cipher, public_links),
+        Err(e) => Err(e.into()),
+    };
+    trail.record(entry.outcome(&res));
+    res
+}
+
+fn read_link(
+    token: &str,
+    ip: Option<String>,
+    cfg: &Cfg,
+    fs: &Fs,
+    cipher: &Cipher,
+    public_links: &PubLinks,
+) -> OpenLinkRes {
+    public_links.open(token, ip, |link| {
+        let buf = fs.load(cfg.document_path(&link.owner, &link.filename))?;
+        Ok(cipher.decrypt(&buf).context("Doc decrypt failed.")?)
+    })
+}
+
+/// Returns the actions of the user and the actions made on their documents by other users.
+#[instrument(skip(trail))]
+#[get("/audit")]
+pub fn audit(user: User, trail: &Trail) -> GetAuditRes {
+    Ok(Json(trail.records_of(&user)?))
+}
+
+/// Returns the storage used by the user and the limits of it.
+#[instrument(skip(quotas))]
+#[get("/usage")]
+pub fn usage(user: User, quotas: &Qts) -> UsageRes {
+    Ok(Json(quotas.usage(&user)?))
+}
+
+/// Returns accounts of all the users who logged in or were approved or denied in advance.
+#[instrument(skip(directory, trail))]
+#[get("/admin/users")]
+pub fn users(admin: Admin, directory: &Directory, trail: &Trail) -> GetUsersRes {
+    let Admin(admin) = admin;
+    let res = directory.accounts();
+    trail.record(AuditEntry::new(&admin, Action::ListUsers).outcome(&res));
+    Ok(Json(res?))
+}
+
+/// Approves, denies or disables the account of the user. The user doesn't need to have an
+/// account yet, so this is also the way to manage the allow-list.
+#[instrument(skip(directory, trail))]
+#[allow(clippy::needless_pass_by_value)] // rocket requires pass by value here
+#[put("/admin/users/<email>", data = "<req>")]
+pub fn set_user_status(
+    admin: Admin,
+    email: String,
+    req: StatusReq,
+    directory: &Directory,
+    trail: &Trail,
+) -> SetUserStatusRes {
+    let Admin(admin) = admin;
+    let StatusRequest { status } = req.into_inner();
+    let res = directory.set_status(&User::new(&email), status);
+    let entry = AuditEntry::new(&admin, Action::SetUserStatus)
+        .reason(format!("status of '{email}' set to '{status:?}'"));
+    trail.record(entry.outcome(&res));
+    Ok(Json(res?))
+}
+
+/// Removes the user together with their documents, thumbnails and index.
+#[instrument(skip(remover, trail))]
+#[delete("/admin/users/<email>")]
+pub fn remove_user(admin: Admin, email: String, remover: &Remover, trail: &Trail) -> RemoveUserRes {
+    let Admin(admin) = admin;
+    let res = remover.remove(&User::new(&email));
+    let entry = AuditEntry::new(&admin, Action::RemoveUser).reason(format!("user '{email}'"));
+    trail.record(entry.outcome(&res));
+    res?;
+    Ok(Status::NoContent)
+}
+
+#[instrument(skip(check))]
+#[get("/health")]
+pub fn health(check: &Health) -> HealthRes {
+    to_response(check.liveness())
+}
+
+#[instrument(skip(check))]
+#[get("/ready")]
+pub fn ready(check: &Health) -> HealthRes {
+    to_response(check.readiness())
+}
+
+fn to_response(report: HealthReport) -> HealthRes {
+    let status = if report.is_healthy() {
+        Status::Ok
+    } else {
+        Status::ServiceUnavailable
+    };
+    (status, Json(report))
+}
+
+/// Metrics are available only to administrators, they contain e.g. the sizes of the indexes of
+/// all users. Prometheus can authenticate with the API token of an administrator.
+#[instrument(skip(_admin, metrics))]
+#[get("/metrics")]
+pub fn metrics(_admin: Admin, metrics: &Mtr) -> MetricsRes {
+    Ok((ContentType::Plain, metrics.render()?))
+}
+
+/// Adds the reason of the failed authentication to the response, so the client knows if it
+/// should e.g. refresh the token.
+#[catch(401)]
+pub fn unauthorized(req: &Request) -> Unauthorized {
+    let error = auth_error(req, "Missing or invalid credentials.");
+    let challenge = format!(
+        r#"Bearer realm="dox", error="invalid_token", error_description="{}""#,
+        error.error.replace('"', "'")
+    );
+    Unauthorized {
+        inner: Json(error),
+        challenge: Header::new("WWW-Authenticate", challenge),
+    }
+}
+
+#[catch(403)]
+pub fn forbidden(req: &Request) -> Json<ErrorBody> {
+    Json(auth_error(req, "Access denied."))
+}
+
+/// Tells the client when it can send the request again.
+#[catch(429)]
+pub fn too_many_requests(req: &Request) -> TooManyRequests {
+    let RetryAfter(secs) = req.local_cache(RetryAfter::default);
+    TooManyRequests {
+        inner: Json(ErrorBody {
+            error: "Too many requests.".into(),
+        }),
+        retry_after: Header::new("Retry-After", secs.unwrap_or(1).to_string()),
+    }
+}
+
+fn auth_error(req: &Request, default: &str) -> ErrorBody {
+    let AuthFailure(reason) = req.local_cache(AuthFailure::default);
+    ErrorBody {
+        error: reason.clone().unwrap_or_else(|| default.into()),
+    }
+}
+
 fn wrong_extension_msg(filename: &Filename) -> String {
     format!(
-        "File '{}' has unsupported extension. Those are supported: {:?}.",
+        "File '{}' has unsupported extension. Those are supported: {}.",
         filename,
-        supported_extensions()
+        supported_names().join(", ")
     )
 }
 
+#[derive(Responder)]
+#[response(status = 401)]
+pub struct Unauthorized {
+    inner: Json<ErrorBody>,
+    challenge: Header<'static>,
+}
+
+#[derive(Responder)]
+#[response(status = 429)]
+pub struct TooManyRequests {
+    inner: Json<ErrorBody>,
+    retry_after: Header<'static>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorBody {
+    error: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ShareRequest {
+    filename: Filename,
+    grantee: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LinkRequest {
+    filename: Filename,
+    /// Validity of the link, in seconds. Configured default is used when missing.
+    #[serde(default)]
+    ttl_secs: Option<u64>,
+    #[serde(default)]
+    single_use: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StatusRequest {
+    status: AccountStatus,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct Document {
     filename: Filename,
     body: String,
+    /// Tesseract languages used instead of the configured ones, e.g. `eng` or `pol+eng`.
+    #[serde(default)]
+    languages: Option<String>,
 }
 
 #[cfg(test)]
@@ -92,12 +682,17 @@ mod test {
     use std::fmt::Display;
 
     use crate::configuration::telemetry::init_tracing;
+    use crate::entities::user::{User, FAKE_USER_EMAIL};
     use crate::testingtools::api::doc;
     use crate::testingtools::app::{start_test_app, test_app};
+    use crate::testingtools::services::users;
+    use crate::use_cases::config::{LimitConfig, QuotaConfig, RateLimitConfig};
+    use crate::use_cases::users::{Account, AccountStatus};
 
     use anyhow::Result;
     use fake::{Fake, Faker};
     use rocket::http::Status;
+    use std::fs;
 
     #[test]
     fn empty_index_returns_200_and_empty_json_entries() -> Result<()> {
@@ -137,7 +732,29 @@ mod test {
         assert_eq!(res.status, Status::Ok);
         assert_eq!(
             res.body,
-            r#"{"entries":[{"filename":"doc1.pdf","thumbnail":"doc1.png"}]}"#
+            r#"{"entries":[{"filename":"doc1.pdf","thumbnail":"doc1.pdf.webp","pages":[1]}]}"#
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn documents_of_uploaded_zip_are_indexed_separately() -> Result<()> {
+        // given
+        init_tracing();
+        let mut app = test_app()?.with_tracked_state()?.start()?;
+
+        // when
+        let res = app.upload_doc(&doc("doc19.zip"))?;
+        app.wait_til_indexed();
+
+        let res_search = app.search("zdjęcie")?;
+
+        // then
+        assert_eq!(res.status, Status::Created);
+        assert_eq!(
+            res_search.body,
+            r#"{"entries":[{"filename":"doc19-doc1.pdf","thumbnail":"doc19-doc1.pdf.webp","pages":[1]}]}"#
         );
 
         Ok(())
@@ -166,7 +783,7 @@ mod test {
         assert_eq!(res.status, Status::Ok);
         assert_eq!(
             res.body,
-            r#"{"entries":[{"filename":"doc1.png","thumbnail":"doc1.png"}]}"#
+            r#"{"entries":[{"filename":"doc1.png","thumbnail":"doc1.png.webp","pages":[1]}]}"#
         );
 
         Ok(())
@@ -190,7 +807,10 @@ mod test {
     }
 
     fn wrong_extension_msg<D: Display>(filename: D) -> String {
-        format!("File '{filename}' has unsupported extension. Those are supported: [Png, Jpg, Webp, Pdf].")
+        format!(
+            "File '{filename}' has unsupported extension. Those are supported: {}.",
+            supported_names().join(", ")
+        )
     }
 
     #[test]
@@ -210,6 +830,53 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn uploading_document_with_mismatched_content_results_in_415_status_code() -> Result<()> {
+        // given
+        init_tracing();
+        let app = start_test_app()?;
+
+        // when
+        let res = app.upload_doc_as(&doc("doc1.pdf"), "doc1.jpg")?;
+
+        // then
+        assert_eq!(res.status, Status::UnsupportedMediaType);
+        assert_eq!(res.body, "Content of the file looks like PDF, not JPG.");
+
+        Ok(())
+    }
+
+    #[test]
+    fn uploading_document_with_invalid_ocr_languages_results_in_422_status_code() -> Result<()> {
+        // given
+        init_tracing();
+        let app = start_test_app()?;
+
+        // when
+        let res = app.upload_doc_in(&doc("doc1.png"), "../pol")?;
+
+        // then
+        assert_eq!(res.status, Status::UnprocessableEntity);
+
+        Ok(())
+    }
+
+    #[test]
+    fn ocr_languages_are_ignored_for_documents_which_are_not_recognized() -> Result<()> {
+        // given
+        init_tracing();
+        let app = start_test_app()?;
+
+        // when
+        let res = app.upload_doc_in(&doc("doc9.docx"), "eng")?;
+
+        // then
+        assert_eq!(res.status, Status::Created);
+        assert_eq!(app.ocr_languages("doc9.docx")?, None);
+
+        Ok(())
+    }
+
     #[test]
     fn fetching_not_existing_document_returns_404() -> Result<()> {
         // given
@@ -255,6 +922,63 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn thumbnail_is_returned_in_requested_size() -> Result<()> {
+        // given
+        init_tracing();
+        let app = test_app()?.with_noop_cipher().start()?;
+        app.put_thumbnail("doc1.webp", "tile")?;
+        app.put_thumbnail("doc1.preview.webp", "preview")?;
+
+        // when
+        let default = app.get_thumbnail("doc1.webp")?;
+        let tile = app.get_sized_thumbnail("doc1.webp", "tile")?;
+        let preview = app.get_sized_thumbnail("doc1.webp", "preview")?;
+
+        // then
+        assert_eq!(default.body, "tile");
+        assert_eq!(tile.body, "tile");
+        assert_eq!(preview.body, "preview");
+
+        Ok(())
+    }
+
+    #[test]
+    fn tile_is_returned_when_thumbnail_has_no_other_sizes() -> Result<()> {
+        // given
+        init_tracing();
+        let app = test_app()?.with_noop_cipher().start()?;
+        app.put_thumbnail("doc1.png", "tile")?;
+
+        // when
+        let res = app.get_sized_thumbnail("doc1.png", "preview")?;
+
+        // then
+        assert_eq!(res.status, Status::Ok);
+        assert_eq!(res.body, "tile");
+
+        Ok(())
+    }
+
+    #[test]
+    fn thumbnail_for_high_density_screen_is_returned_when_requested() -> Result<()> {
+        // given
+        init_tracing();
+        let app = test_app()?.with_noop_cipher().start()?;
+        app.put_thumbnail("doc1.webp", "standard")?;
+        app.put_thumbnail("doc1@2x.webp", "double")?;
+
+        // when
+        let double = app.get_thumbnail_at_ratio("doc1.webp", 2)?;
+        let missing = app.get_thumbnail_at_ratio("doc1.webp", 3)?;
+
+        // then
+        assert_eq!(double.body, "double");
+        assert_eq!(missing.body, "standard");
+
+        Ok(())
+    }
+
     #[test]
     fn when_fs_fails_to_load_thumbnail_internal_server_error_is_returned() -> Result<()> {
         // given
@@ -307,7 +1031,489 @@ mod test {
         app.wait_til_file_removed(); // removal of document or thumbnail
 
         // then
-        assert!(!app.thumbnail_exists("doc1.png"));
+        assert!(!app.thumbnail_exists("doc1.pdf.webp"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn health_returns_200_when_all_services_are_running() -> Result<()> {
+        // given
+        init_tracing();
+        let app = start_test_app()?;
+
+        // when
+        let res = app.health()?;
+
+        // then
+        assert_eq!(res.status, Status::Ok);
+        assert!(res.body.contains(r#""service:indexer":true"#));
+
+        Ok(())
+    }
+
+    #[test]
+    fn ready_returns_200_when_all_resources_are_available() -> Result<()> {
+        // given
+        init_tracing();
+        let app = start_test_app()?;
+
+        // when
+        let res = app.ready()?;
+
+        // then
+        assert_eq!(res.status, Status::Ok);
+
+        Ok(())
+    }
+
+    #[test]
+    fn ready_returns_503_when_key_is_not_available() -> Result<()> {
+        // given
+        init_tracing();
+        let app = test_app()?.with_tracked_failing_cipher().start()?;
+
+        // when
+        let res = app.ready()?;
+
+        // then
+        assert_eq!(res.status, Status::ServiceUnavailable);
+        assert!(res.body.contains(r#""key":false"#));
+
+        Ok(())
+    }
+
+    #[test]
+    fn searching_is_visible_in_metrics() -> Result<()> {
+        // given
+        init_tracing();
+        let app = test_app()?.with_admin().start()?;
+        app.search(Faker.fake::<String>())?;
+
+        // when
+        let res = app.metrics()?;
+
+        // then
+        assert_eq!(res.status, Status::Ok);
+        assert!(res.body.contains("dox_search_duration_seconds_count 1"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn metrics_are_available_only_to_admin() -> Result<()> {
+        // given
+        init_tracing();
+        let app = start_test_app()?;
+
+        // when
+        let res = app.metrics()?;
+
+        // then
+        assert_eq!(res.status, Status::Forbidden);
+        assert!(!res.body.contains("index_size_bytes"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn sharing_not_existing_document_results_in_404_status_code() -> Result<()> {
+        // given
+        init_tracing();
+        let app = start_test_app()?;
+
+        // when
+        let res = app.share_doc("missing.pdf", "other@email.com")?;
+
+        // then
+        assert_eq!(res.status, Status::NotFound);
+
+        Ok(())
+    }
+
+    #[test]
+    fn shared_document_is_listed_until_sharing_is_revoked() -> Result<()> {
+        // given
+        init_tracing();
+        let mut app = test_app()?.with_tracked_state()?.start()?;
+        app.upload_doc(&doc("doc1.pdf"))?;
+        app.wait_til_indexed();
+
+        // when
+        let shared = app.share_doc("doc1.pdf", "other@email.com")?;
+        let shares = app.shares()?;
+        let revoked = app.unshare_doc("doc1.pdf", "other@email.com")?;
+        let revoked_again = app.unshare_doc("doc1.pdf", "other@email.com")?;
+
+        // then
+        assert_eq!(shared.status, Status::Created);
+        assert_eq!(
+            shares.body,
+            r#"[{"owner":"some@email.com","grantee":"other@email.com","filename":"doc1.pdf"}]"#
+        );
+        assert_eq!(revoked.status, Status::NoContent);
+        assert_eq!(revoked_again.status, Status::NotFound);
+        assert_eq!(app.shares()?.body, "[]");
+
+        Ok(())
+    }
+
+    #[test]
+    fn document_of_other_user_is_not_returned_when_not_shared() -> Result<()> {
+        // given
+        init_tracing();
+        let app = start_test_app()?;
+
+        // when
+        let res = app.get_shared_doc("doc1.pdf", "other@email.com")?;
+
+        // then
+        assert_eq!(res.status, Status::NotFound);
+
+        Ok(())
+    }
+
+    #[test]
+    fn link_to_not_existing_document_results_in_404_status_code() -> Result<()> {
+        // given
+        init_tracing();
+        let app = start_test_app()?;
+
+        // when
+        let res = app.create_link("missing.pdf", false)?;
+
+        // then
+        assert_eq!(res.status, Status::NotFound);
+
+        Ok(())
+    }
+
+    #[test]
+    fn not_existing_page_results_in_404_status_code() -> Result<()> {
+        // given
+        init_tracing();
+        let mut app = test_app()?
+            .with_tracked_state()?
+            .with_noop_cipher()
+            .start()?;
+        app.upload_doc(&doc("doc1.pdf"))?;
+        app.wait_til_indexed();
+
+        // when
+        let missing_page = app.get_page("doc1.pdf", 3)?;
+        let missing_doc = app.get_page("missing.pdf", 1)?;
+
+        // then
+        assert_eq!(missing_page.status, Status::NotFound);
+        assert_eq!(missing_doc.status, Status::NotFound);
+
+        Ok(())
+    }
+
+    #[test]
+    fn single_use_link_returns_document_only_once() -> Result<()> {
+        // given
+        init_tracing();
+        let mut app = test_app()?
+            .with_tracked_state()?
+            .with_noop_cipher()
+            .start()?;
+        app.upload_doc(&doc("doc1.pdf"))?;
+        app.wait_til_indexed();
+        let created = app.create_link("doc1.pdf", true)?;
+        let created_body: serde_json::Value = serde_json::from_str(&created.body)?;
+        let url = created_body["url"].as_str().unwrap_or_default();
+
+        // when
+        let first = app.open_link(url)?;
+        let second = app.open_link(url)?;
+
+        // then
+        assert_eq!(created.status, Status::Created);
+        assert_eq!(first.status, Status::Ok);
+        assert_eq!(second.status, Status::Gone);
+
+        Ok(())
+    }
+
+    #[test]
+    fn link_of_disabled_user_can_not_be_opened() -> Result<()> {
+        // given
+        init_tracing();
+        let accounts = users::stub(Vec::new());
+        let mut app = test_app()?
+            .with_tracked_state()?
+            .with_noop_cipher()
+            .with_accounts(accounts.clone())
+            .start()?;
+        app.upload_doc(&doc("doc1.pdf"))?;
+        app.wait_til_indexed();
+        let created = app.create_link("doc1.pdf", false)?;
+        let created_body: serde_json::Value = serde_json::from_str(&created.body)?;
+        let url = created_body["url"].as_str().unwrap_or_default();
+        let owner = User::new(FAKE_USER_EMAIL);
+
+        // when
+        accounts.put(Account::new(owner, AccountStatus::Disabled))?;
+        let res = app.open_link(url)?;
+
+        // then
+        assert_eq!(res.status, Status::Gone);
+
+        Ok(())
+    }
+
+    #[test]
+    fn opening_unknown_link_is_recorded_in_audit_log() -> Result<()> {
+        // given
+        init_tracing();
+        let app = start_test_app()?;
+
+        // when
+        let res = app.open_link("/link/not-a-token")?;
+
+        // then
+        assert_ne!(res.status, Status::Ok);
+        let records = app.audit_log()?;
+        let last = records.last().unwrap();
+        assert_eq!(last["action"], "open_link");
+        assert_eq!(last["outcome"], "failure");
+        assert!(last["user"].is_null());
+        assert!(!last["reason"]
+            .as_str()
+            .unwrap_or_default()
+            .contains("Some("));
+
+        Ok(())
+    }
+
+    #[test]
+    fn actions_of_user_are_recorded_in_audit_log() -> Result<()> {
+        // given
+        init_tracing();
+        let app = start_test_app()?;
+        app.upload_doc(&doc("doc1.pdf"))?;
+        app.get_doc("not-existing-doc.pdf")?;
+
+        // when
+        let res = app.audit()?;
+
+        // then
+        assert_eq!(res.status, Status::Ok);
+        let records: Vec<serde_json::Value> = serde_json::from_str(&res.body)?;
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0]["action"], "upload");
+        assert_eq!(records[0]["outcome"], "success");
+        assert_eq!(records[1]["action"], "download");
+        assert_eq!(records[1]["document"], "not-existing-doc.pdf");
+        assert_eq!(records[1]["outcome"], "failure");
+        assert_eq!(records[1]["prev_hash"], records[0]["hash"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn only_failed_thumbnail_views_are_recorded_in_audit_log() -> Result<()> {
+        // given
+        init_tracing();
+        let app = test_app()?.with_noop_cipher().start()?;
+        app.put_thumbnail("doc1.webp", "tile")?;
+        app.get_thumbnail("doc1.webp")?;
+        app.get_thumbnail("not-existing-thumbnail.webp")?;
+
+        // when
+        let res = app.audit()?;
+
+        // then
+        let records: Vec<serde_json::Value> = serde_json::from_str(&res.body)?;
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0]["action"], "view");
+        assert_eq!(records[0]["document"], "not-existing-thumbnail.webp");
+        assert_eq!(records[0]["outcome"], "failure");
+
+        Ok(())
+    }
+
+    #[test]
+    fn too_large_document_results_in_413_status_code() -> Result<()> {
+        // given
+        init_tracing();
+        let quota = QuotaConfig {
+            max_upload_bytes: 10,
+            ..QuotaConfig::default()
+        };
+        let app = test_app()?.with_quota(quota).start()?;
+
+        // when
+        let res = app.upload_doc(&doc("doc1.pdf"))?;
+
+        // then
+        assert_eq!(res.status, Status::PayloadTooLarge);
+
+        Ok(())
+    }
+
+    #[test]
+    fn document_over_quota_results_in_507_status_code() -> Result<()> {
+        // given
+        init_tracing();
+        let quota = QuotaConfig {
+            max_docs: 0,
+            ..QuotaConfig::default()
+        };
+        let app = test_app()?.with_quota(quota).start()?;
+
+        // when
+        let res = app.upload_doc(&doc("doc1.pdf"))?;
+
+        // then
+        assert_eq!(res.status, Status::InsufficientStorage);
+
+        Ok(())
+    }
+
+    #[test]
+    fn usage_of_storage_is_returned_with_limits() -> Result<()> {
+        // given
+        init_tracing();
+        let app = start_test_app()?;
+
+        // when
+        let res = app.usage()?;
+
+        // then
+        assert_eq!(res.status, Status::Ok);
+        let usage: serde_json::Value = serde_json::from_str(&res.body)?;
+        assert_eq!(usage["total_docs"], 0);
+        assert_eq!(usage["max_docs"], QuotaConfig::default().max_docs);
+
+        Ok(())
+    }
+
+    #[test]
+    fn search_over_the_limit_results_in_429_status_code() -> Result<()> {
+        // given
+        init_tracing();
+        let rate_limit = RateLimitConfig {
+            search: LimitConfig::new(1, 0, 1),
+            ..RateLimitConfig::default()
+        };
+        let app = test_app()?.with_rate_limit(rate_limit).start()?;
+
+        // when
+        let first = app.search("term")?;
+        let second = app.search("term")?;
+
+        // then
+        assert_eq!(first.status, Status::Ok);
+        assert_eq!(second.status, Status::TooManyRequests);
+        assert_eq!(second.header("Retry-After"), Some("60"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn client_cannot_escape_ip_limit_with_real_ip_header() -> Result<()> {
+        // given
+        init_tracing();
+        let rate_limit = RateLimitConfig {
+            search: LimitConfig::new(0, 1, 1),
+            ..RateLimitConfig::default()
+        };
+        let app = test_app()?.with_rate_limit(rate_limit).start()?;
+        let remote = "10.0.0.1:4000".parse()?;
+
+        // when
+        let first = app.search_from("term", remote, "192.168.0.1")?;
+        let second = app.search_from("term", remote, "192.168.0.2")?;
+
+        // then
+        assert_eq!(first.status, Status::Ok);
+        assert_eq!(second.status, Status::TooManyRequests);
+
+        Ok(())
+    }
+
+    #[test]
+    fn real_ip_header_of_trusted_proxy_is_used_for_ip_limit() -> Result<()> {
+        // given
+        init_tracing();
+        let rate_limit = RateLimitConfig {
+            search: LimitConfig::new(0, 1, 1),
+            trusted_proxy: Some("10.0.0.1".parse()?),
+            ..RateLimitConfig::default()
+        };
+        let app = test_app()?.with_rate_limit(rate_limit).start()?;
+        let proxy = "10.0.0.1:4000".parse()?;
+
+        // when
+        let first = app.search_from("term", proxy, "192.168.0.1")?;
+        let second = app.search_from("term", proxy, "192.168.0.2")?;
+        let third = app.search_from("term", proxy, "192.168.0.2")?;
+
+        // then
+        assert_eq!(first.status, Status::Ok);
+        assert_eq!(second.status, Status::Ok);
+        assert_eq!(third.status, Status::TooManyRequests);
+
+        Ok(())
+    }
+
+    #[test]
+    fn users_can_be_managed_only_by_admin() -> Result<()> {
+        // given
+        init_tracing();
+        let app = start_test_app()?;
+
+        // when
+        let res = app.users()?;
+
+        // then
+        assert_eq!(res.status, Status::Forbidden);
+
+        Ok(())
+    }
+
+    #[test]
+    fn email_approved_by_admin_is_listed_with_its_status() -> Result<()> {
+        // given
+        init_tracing();
+        let app = test_app()?.with_admin().start()?;
+
+        // when
+        let approved = app.set_user_status("other@email.com", "active")?;
+        let res = app.users()?;
+
+        // then
+        assert_eq!(approved.status, Status::Ok);
+        let accounts: Vec<serde_json::Value> = serde_json::from_str(&res.body)?;
+        assert_eq!(accounts.len(), 1);
+        assert_eq!(accounts[0]["user"], "other@email.com");
+        assert_eq!(accounts[0]["status"], "active");
+
+        Ok(())
+    }
+
+    #[test]
+    fn removed_user_has_no_files_left() -> Result<()> {
+        // given
+        init_tracing();
+        let app = test_app()?.with_admin().start()?;
+        let dirs = app.user_dirs("other@email.com");
+        // NOTE: watched dir is skipped, so the document isn't picked up by the mover
+        for dir in &dirs[..3] {
+            fs::create_dir_all(dir)?;
+            fs::write(dir.join("doc1.pdf"), "doc")?;
+        }
+
+        // when
+        let removed = app.remove_user("other@email.com")?;
+        let removed_again = app.remove_user("other@email.com")?;
+
+        // then
+        assert_eq!(removed.status, Status::NoContent);
+        assert_eq!(removed_again.status, Status::NotFound);
+        assert!(dirs.iter().all(|dir| !dir.exists()));
 
         Ok(())
     }
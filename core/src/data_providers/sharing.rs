@@ -0,0 +1,192 @@
+//! This is concrete implementation of [`crate::use_cases::sharing`] abstractions.
+//!
+//! Grants are kept in memory and saved to a JSON file in [`Config::data_dir`] on every change.
+use crate::data_providers::json_file::JsonFile;
+use crate::entities::user::User;
+use crate::result::SharingErr;
+use crate::use_cases::config::Config;
+use crate::use_cases::sharing::{Grant, ShareStore, Sharing};
+
+use std::sync::{Arc, Mutex};
+use tracing::{debug, instrument};
+
+const SHARES_FILE: &str = "shares.json";
+
+pub struct JsonShareStore {
+    file: JsonFile,
+    grants: Mutex<Vec<Grant>>,
+}
+
+impl JsonShareStore {
+    pub fn create(cfg: &Config) -> Result<Sharing, SharingErr> {
+        let file = JsonFile::new(cfg, SHARES_FILE)?;
+        let grants: Vec<Grant> = file.load()?;
+        debug!("loaded {} grants from '{}'", grants.len(), file);
+        Ok(Arc::new(Self {
+            file,
+            grants: Mutex::new(grants),
+        }))
+    }
+
+    fn filtered<F: Fn(&Grant) -> bool>(&self, predicate: F) -> Vec<Grant> {
+        let grants = self.grants.lock().expect("poisoned mutex");
+        grants.iter().filter(|g| predicate(g)).cloned().collect()
+    }
+}
+
+impl ShareStore for JsonShareStore {
+    #[instrument(skip(self))]
+    fn grant(&self, grant: Grant) -> Result<(), SharingErr> {
+        if grant.owner == grant.grantee {
+            return Err(SharingErr::SharedWithOwner);
+        }
+        let mut grants = self.grants.lock().expect("poisoned mutex");
+        if grants.contains(&grant) {
+            return Ok(());
+        }
+        grants.push(grant);
+        Ok(self.file.save(&grants)?)
+    }
+
+    #[instrument(skip(self))]
+    fn revoke(&self, grant: &Grant) -> Result<bool, SharingErr> {
+        let mut grants = self.grants.lock().expect("poisoned mutex");
+        let count = grants.len();
+        grants.retain(|g| g != grant);
+        if grants.len() == count {
+            return Ok(false);
+        }
+        self.file.save(&grants)?;
+        Ok(true)
+    }
+
+    fn granted_by(&self, owner: &User) -> Result<Vec<Grant>, SharingErr> {
+        Ok(self.filtered(|g| &g.owner == owner))
+    }
+
+    fn shared_with(&self, grantee: &User) -> Result<Vec<Grant>, SharingErr> {
+        Ok(self.filtered(|g| &g.grantee == grantee))
+    }
+
+    #[instrument(skip(self))]
+    fn forget(&self, user: &User) -> Result<(), SharingErr> {
+        let mut grants = self.grants.lock().expect("poisoned mutex");
+        grants.retain(|g| &g.owner != user && &g.grantee != user);
+        Ok(self.file.save(&grants)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::entities::file::Filename;
+    use crate::testingtools::TestConfig;
+
+    use anyhow::Result;
+    use claim::assert_matches;
+
+    fn grant(owner: &str, grantee: &str, filename: &str) -> Result<Grant> {
+        Ok(Grant::new(
+            User::new(owner),
+            User::new(grantee),
+            Filename::new(filename)?,
+        ))
+    }
+
+    #[test]
+    fn grants_are_kept_after_restart() -> Result<()> {
+        // given
+        let cfg = TestConfig::new()?;
+        let shares = JsonShareStore::create(cfg.as_ref())?;
+        shares.grant(grant("owner@email.com", "me@email.com", "a.pdf")?)?;
+
+        // when
+        let shares = JsonShareStore::create(cfg.as_ref())?;
+
+        // then
+        assert_eq!(
+            shares.shared_with(&User::new("me@email.com"))?,
+            vec![grant("owner@email.com", "me@email.com", "a.pdf")?]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn granting_the_same_access_twice_keeps_single_grant() -> Result<()> {
+        // given
+        let cfg = TestConfig::new()?;
+        let shares = JsonShareStore::create(cfg.as_ref())?;
+
+        // when
+        shares.grant(grant("owner@email.com", "me@email.com", "a.pdf")?)?;
+        shares.grant(grant("owner@email.com", "me@email.com", "a.pdf")?)?;
+
+        // then
+        assert_eq!(shares.granted_by(&User::new("owner@email.com"))?.len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn revoked_grant_is_removed() -> Result<()> {
+        // given
+        let cfg = TestConfig::new()?;
+        let shares = JsonShareStore::create(cfg.as_ref())?;
+        let to_revoke = grant("owner@email.com", "me@email.com", "a.pdf")?;
+        shares.grant(to_revoke.clone())?;
+        shares.grant(grant("owner@email.com", "me@email.com", "b.pdf")?)?;
+
+        // when
+        let revoked = shares.revoke(&to_revoke)?;
+        let revoked_again = shares.revoke(&to_revoke)?;
+
+        // then
+        assert!(revoked);
+        assert!(!revoked_again);
+        assert_eq!(
+            shares.shared_with(&User::new("me@email.com"))?,
+            vec![grant("owner@email.com", "me@email.com", "b.pdf")?]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn document_can_not_be_shared_with_its_owner() -> Result<()> {
+        // given
+        let cfg = TestConfig::new()?;
+        let shares = JsonShareStore::create(cfg.as_ref())?;
+
+        // when
+        let res = shares.grant(grant("owner@email.com", "owner@email.com", "a.pdf")?);
+
+        // then
+        assert_matches!(res, Err(SharingErr::SharedWithOwner));
+
+        Ok(())
+    }
+
+    #[test]
+    fn forgotten_user_has_no_grants_given_or_received() -> Result<()> {
+        // given
+        let cfg = TestConfig::new()?;
+        let shares = JsonShareStore::create(cfg.as_ref())?;
+        shares.grant(grant("owner@email.com", "me@email.com", "a.pdf")?)?;
+        shares.grant(grant("me@email.com", "other@email.com", "b.pdf")?)?;
+        shares.grant(grant("owner@email.com", "other@email.com", "c.pdf")?)?;
+
+        // when
+        shares.forget(&User::new("me@email.com"))?;
+
+        // then
+        assert_eq!(
+            shares.granted_by(&User::new("owner@email.com"))?,
+            vec![grant("owner@email.com", "other@email.com", "c.pdf")?]
+        );
+        assert_eq!(shares.shared_with(&User::new("other@email.com"))?.len(), 1);
+
+        Ok(())
+    }
+}
@@ -1,7 +1,9 @@
 //! This is concrete implementation of [`crate::use_cases::state`] abstractions.
 //!
-//! It uses [`tantivy`] as full text search library.
+//! It uses [`tantivy`] as full text search library. Each page of the document is indexed as
+//! a separate tantivy document, so search can tell which pages matched.
 use crate::entities::document::DocDetails;
+use crate::entities::file::Filename;
 use crate::entities::location::Location;
 use crate::entities::user::User;
 use crate::result::{IndexerErr, SearchErr, StateErr};
@@ -15,20 +17,25 @@ use base64::engine::general_purpose::STANDARD as b64;
 use base64::Engine;
 use core::fmt;
 use dashmap::DashMap;
-use std::convert::TryInto;
+use std::collections::{BTreeMap, HashSet};
+use std::convert::{TryFrom, TryInto};
 use std::fmt::Debug;
-use std::fs::create_dir_all;
-use std::path::PathBuf;
+use std::fs::{create_dir_all, read_dir, remove_dir_all};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tantivy::collector::TopDocs;
 use tantivy::directory::MmapDirectory;
-use tantivy::query::{AllQuery, FuzzyTermQuery, Query};
-use tantivy::schema::{Field, Schema, Value, STORED, TEXT};
-use tantivy::{doc, DocAddress, Index, ReloadPolicy, Searcher, Term};
+use tantivy::query::{AllQuery, BooleanQuery, FuzzyTermQuery, Query, TermSetQuery};
+use tantivy::schema::{Field, Schema, Value, STORED, STRING, TEXT};
+use tantivy::{doc, DocAddress, Index, ReloadPolicy, Searcher, TantivyError, Term};
 use tracing::{debug, error, instrument, warn};
 
 type TantivyDocs = Vec<(f32, DocAddress)>;
 
+/// Most pages returned by the search. Pages of the same document count separately.
+const MAX_HITS: usize = 1000;
+type SearchRes = Result<SearchResult, SearchErr>;
+
 pub struct TantivyState {
     read: StateReader,
     write: StateWriter,
@@ -44,11 +51,13 @@ impl TantivyState {
         }
         create_dir_all(&cfg.index_dir)?;
         let mut schema_builder = Schema::builder();
-        schema_builder.add_text_field(&Fields::Filename.to_string(), TEXT | STORED);
+        // NOTE: names are not tokenized, so documents can be deleted by their exact names
+        schema_builder.add_text_field(&Fields::Filename.to_string(), STRING | STORED);
         schema_builder.add_text_field(&Fields::Body.to_string(), TEXT);
-        schema_builder.add_text_field(&Fields::Thumbnail.to_string(), TEXT | STORED);
+        schema_builder.add_text_field(&Fields::Thumbnail.to_string(), STRING | STORED);
+        schema_builder.add_u64_field(&Fields::Page.to_string(), STORED);
         let schema = schema_builder.build();
-        let indexes = Arc::new(DashMap::new());
+        let indexes = Arc::new(load_indexes(&cfg.index_dir, &schema)?);
         Ok(Box::new(Self {
             read: Arc::new(TantivyStateReader::new(indexes.clone(), schema.clone())),
             write: Arc::new(TantivyStateWriter::new(
@@ -60,6 +69,53 @@ impl TantivyState {
     }
 }
 
+/// Opens indexes of all the users which were created before the application started.
+fn load_indexes(idx_root: &Path, schema: &Schema) -> Result<DashMap<User, Index>, StateErr> {
+    let indexes = DashMap::new();
+    for entry in read_dir(idx_root)? {
+        let idx_dir = entry?.path();
+        if !idx_dir.is_dir() {
+            continue;
+        }
+        let Some(user) = dir_owner(&idx_dir) else {
+            warn!(
+                "index dir '{}' does not belong to any user",
+                idx_dir.display()
+            );
+            continue;
+        };
+        debug!("loading index of '{}'", user);
+        indexes.insert(user, open_index(&idx_dir, schema)?);
+    }
+    Ok(indexes)
+}
+
+/// Index created with a different schema is dropped and created from scratch. Its documents are
+/// indexed again by the reconciler, because they are missing in the new index.
+fn open_index(idx_dir: &Path, schema: &Schema) -> Result<Index, StateErr> {
+    let dir = MmapDirectory::open(idx_dir)?;
+    match Index::open_or_create(dir, schema.clone()) {
+        Err(TantivyError::SchemaError(e)) => {
+            warn!(
+                "recreating index '{}' because of schema change: '{}'",
+                idx_dir.display(),
+                e
+            );
+            remove_dir_all(idx_dir)?;
+            create_dir_all(idx_dir)?;
+            let dir = MmapDirectory::open(idx_dir)?;
+            Ok(Index::open_or_create(dir, schema.clone())?)
+        }
+        res => Ok(res?),
+    }
+}
+
+fn dir_owner(dir: &Path) -> Option<User> {
+    let dir_name = dir.file_name()?.to_str()?;
+    let email = String::from_utf8(b64.decode(dir_name).ok()?).ok()?;
+    Some(User::new(email))
+}
+
 impl AppState for TantivyState {
     fn reader(&self) -> StateReader {
         self.read.clone()
@@ -104,31 +160,57 @@ impl TantivyStateReader {
         self.schema.get_field(&field.to_string()).unwrap()
     }
 
+    /// Pages of the same document are merged into one entry.
     #[instrument(skip(self, searcher))]
     fn to_search_result(
         &self,
         searcher: &Searcher,
         docs: TantivyDocs,
     ) -> Result<SearchResult, SearchErr> {
-        let mut results = Vec::new();
+        let mut pages: BTreeMap<(String, String), Vec<u32>> = BTreeMap::new();
         for (_score, doc_address) in docs {
             let retrieved_doc = searcher.doc(doc_address)?;
-            let filenames = retrieved_doc.get_all(self.field(&Fields::Filename));
-            let thumbnails = retrieved_doc.get_all(self.field(&Fields::Thumbnail));
-            results.extend(to_search_entries(filenames, thumbnails));
+            let filename = retrieved_doc.get_first(self.field(&Fields::Filename));
+            let thumbnail = retrieved_doc.get_first(self.field(&Fields::Thumbnail));
+            let (Some(filename), Some(thumbnail)) = (filename, thumbnail) else {
+                warn!("skipping indexed page without document name");
+                continue;
+            };
+            let numbers = pages
+                .entry((filename.text(), thumbnail.text()))
+                .or_default();
+            let page = retrieved_doc.get_first(self.field(&Fields::Page));
+            if let Some(number) = page.and_then(Value::as_u64) {
+                numbers.extend(u32::try_from(number).ok());
+            }
         }
-        Ok(results.into())
+        Ok(pages
+            .into_iter()
+            .map(|(names, mut numbers)| {
+                numbers.sort_unstable();
+                numbers.dedup();
+                SearchEntry::new(names).pages(numbers)
+            })
+            .collect::<Vec<SearchEntry>>()
+            .into())
     }
 
     #[instrument(skip(self))]
-    fn search_for(&self, user: User, query: &impl Query) -> Result<SearchResult, SearchErr> {
+    fn search_for(&self, user: User, query: &impl Query, limit: Limit) -> SearchRes {
         let searcher = self.create_searcher(user);
         if let Err(SearchErr::MissingIndex(email)) = searcher {
             debug!("No index for user: '{}'", email);
             return Ok(SearchResult::default());
         }
         let searcher = searcher.unwrap(); // can unwrap because it's checked above
-        let top_docs = searcher.search(query, &TopDocs::with_limit(100))?;
+        let limit = match limit {
+            Limit::Top(n) => n,
+            // NOTE: `TopDocs` panics when the limit is 0
+            Limit::All => usize::try_from(searcher.num_docs())
+                .unwrap_or(usize::MAX)
+                .max(1),
+        };
+        let top_docs = searcher.search(query, &TopDocs::with_limit(limit))?;
         self.to_search_result(&searcher, top_docs)
     }
 }
@@ -137,17 +219,54 @@ impl AppStateReader for TantivyStateReader {
     #[instrument(skip(self))]
     fn search(&self, user: User, term: String) -> Result<SearchResult, SearchErr> {
         debug!("search of user: '{}', for: '{}'", user.email, term);
-        let res = self.search_for(user, &self.make_query(term))?;
+        let res = self.search_for(user, &self.make_query(term), Limit::Top(MAX_HITS))?;
+        debug!("found docs: '{:?}'", res);
+        Ok(res)
+    }
+
+    /// Names are matched within the query, so the other documents don't take the hits.
+    #[instrument(skip(self, filenames))]
+    fn search_in(
+        &self,
+        user: User,
+        term: String,
+        filenames: &HashSet<String>,
+    ) -> Result<SearchResult, SearchErr> {
+        debug!(
+            "search of user: '{}', for: '{}', in {} docs",
+            user.email,
+            term,
+            filenames.len()
+        );
+        if filenames.is_empty() {
+            return Ok(SearchResult::default());
+        }
+        let names = filenames
+            .iter()
+            .map(|name| Term::from_field_text(self.field(&Fields::Filename), name));
+        let query = BooleanQuery::intersection(vec![
+            self.make_query(term),
+            Box::new(TermSetQuery::new(names)),
+        ]);
+        let res = self.search_for(user, &query, Limit::Top(MAX_HITS))?;
         debug!("found docs: '{:?}'", res);
         Ok(res)
     }
 
     #[instrument(skip(self))]
     fn all_docs(&self, user: User) -> Result<SearchResult, SearchErr> {
-        self.search_for(user, &AllQuery)
+        Ok(self
+            .search_for(user, &AllQuery, Limit::All)?
+            .without_pages())
     }
 }
 
+#[derive(Debug)]
+enum Limit {
+    Top(usize),
+    All,
+}
+
 #[derive(Debug, Clone)]
 struct TantivyStateWriter {
     indexes: Arc<DashMap<User, Index>>,
@@ -202,12 +321,18 @@ impl AppStateWriter for TantivyStateWriter {
             let filename = schema.get_field(&Fields::Filename.to_string()).unwrap();
             let body = schema.get_field(&Fields::Body.to_string()).unwrap();
             let thumbnail = schema.get_field(&Fields::Thumbnail.to_string()).unwrap();
+            let page = schema.get_field(&Fields::Page.to_string()).unwrap();
             debug!("indexing {:?}", doc_detail.filename);
-            index_writer.add_document(doc!(
-                    filename => doc_detail.filename.clone(),
-                    body => doc_detail.body.clone(),
-                    thumbnail => doc_detail.thumbnail.clone(),
-            ))?;
+            // NOTE: document could be indexed before, e.g. when it was modified
+            index_writer.delete_term(term(filename, doc_detail.filename.clone()));
+            for doc_page in &doc_detail.pages {
+                index_writer.add_document(doc!(
+                        filename => doc_detail.filename.clone(),
+                        body => doc_page.text.clone(),
+                        thumbnail => doc_detail.thumbnail.clone(),
+                        page => u64::from(doc_page.number),
+                ))?;
+            }
             debug!("commiting new doc");
             index_writer.commit()?;
         }
@@ -240,6 +365,29 @@ impl AppStateWriter for TantivyStateWriter {
         }
         Ok(())
     }
+
+    #[instrument(skip(self))]
+    fn delete_doc(&self, user: &User, filename: &Filename) -> Result<(), IndexerErr> {
+        let Some(index) = self.indexes.get(user) else {
+            debug!("no index for user: '{}', nothing to delete", user);
+            return Ok(());
+        };
+        let mut writer = index.writer(50_000_000)?;
+        debug!("deleting '{}' as a doc name", filename);
+        writer.delete_term(term(self.field(&Fields::Filename), filename.clone()));
+        debug!("commiting deletion");
+        writer.commit()?;
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    fn remove_user(&self, user: &User) -> Result<(), IndexerErr> {
+        // NOTE: index is opened again from its directory when a document of the user is indexed
+        if self.indexes.remove(user).is_none() {
+            debug!("no index for user: '{}', nothing to remove", user);
+        }
+        Ok(())
+    }
 }
 
 fn term<S: Into<String>>(field: Field, filename: S) -> Term {
@@ -250,6 +398,7 @@ enum Fields {
     Filename,
     Body,
     Thumbnail,
+    Page,
 }
 
 impl fmt::Display for Fields {
@@ -258,25 +407,11 @@ impl fmt::Display for Fields {
             Fields::Filename => write!(f, "filename"),
             Fields::Body => write!(f, "body"),
             Fields::Thumbnail => write!(f, "thumbnail"),
+            Fields::Page => write!(f, "page"),
         }
     }
 }
 
-pub fn to_search_entries<'a, V: Iterator<Item = &'a Value>>(
-    filenames: V,
-    thumbnails: V,
-) -> Vec<SearchEntry> {
-    filenames
-        .zip(thumbnails)
-        .map(to_text)
-        .map(SearchEntry::new)
-        .collect::<Vec<SearchEntry>>()
-}
-
-fn to_text((filename, thumbnail): (&Value, &Value)) -> (String, String) {
-    (filename.text(), thumbnail.text())
-}
-
 trait ValueExt {
     fn text(&self) -> String;
 }
@@ -294,10 +429,15 @@ mod test {
     use super::*;
 
     use crate::configuration::telemetry::init_tracing;
+    use crate::entities::document::Page;
     use crate::entities::file::{Filename, Thumbnailname};
     use crate::entities::user::FAKE_USER_EMAIL;
     use crate::testingtools::{
-        docs_dir_path, index_dir_path, thumbnails_dir_path, watched_dir_path,
+        data_dir_path, docs_dir_path, index_dir_path, thumbnails_dir_path, watched_dir_path,
+    };
+    use crate::use_cases::config::{
+        ArchivesConfig, LinksConfig, OcrConfig, QuotaConfig, RateLimitConfig, ScannerConfig,
+        ServerConfig, ThumbnailsConfig, UsersConfig, WatcherConfig,
     };
 
     use anyhow::Result;
@@ -332,11 +472,25 @@ mod test {
         let docs_dir = docs_dir_path()?;
         let watched_dir = watched_dir_path()?;
         let thumbnails_dir = thumbnails_dir_path()?;
+        let data_dir = data_dir_path()?;
         Ok(Config {
             watched_dir: watched_dir.path().to_path_buf(),
             docs_dir: docs_dir.path().to_path_buf(),
             thumbnails_dir: thumbnails_dir.path().to_path_buf(),
             index_dir: index_dir.path().to_path_buf(),
+            data_dir: data_dir.path().to_path_buf(),
+            remove_orphan_thumbnails: false,
+            watcher: WatcherConfig::default(),
+            links: LinksConfig::default(),
+            quota: QuotaConfig::default(),
+            users: UsersConfig::default(),
+            rate_limit: RateLimitConfig::default(),
+            server: ServerConfig::default(),
+            ocr: OcrConfig::default(),
+            scanner: ScannerConfig::default(),
+            archives: ArchivesConfig::default(),
+            thumbnails: ThumbnailsConfig::default(),
+            auth: Config::default().auth,
         })
     }
 
@@ -450,7 +604,7 @@ mod test {
         // then
         assert_eq!(
             results,
-            vec![SearchEntry::new(("filename5".into(), "thumbnail5".into())),].into()
+            vec![SearchEntry::new(("filename5".into(), "thumbnail5".into())).pages(vec![1])].into()
         );
 
         Ok(())
@@ -494,7 +648,7 @@ mod test {
         // then
         assert_eq!(
             first_results,
-            vec![SearchEntry::new(("filename3".into(), "thumbnail3".into())),].into()
+            vec![SearchEntry::new(("filename3".into(), "thumbnail3".into())).pages(vec![1])].into()
         );
         assert_eq!(second_results, Vec::new().into());
 
@@ -545,9 +699,9 @@ mod test {
         assert_eq!(
             res,
             vec![
-                SearchEntry::new(("filename3".into(), "thumbnail3".into())),
-                SearchEntry::new(("filename3".into(), "thumbnail3".into())),
-                SearchEntry::new(("filename3".into(), "thumbnail3".into()))
+                SearchEntry::new(("filename3".into(), "thumbnail3".into())).pages(vec![1]),
+                SearchEntry::new(("filename3".into(), "thumbnail3".into())).pages(vec![1]),
+                SearchEntry::new(("filename3".into(), "thumbnail3".into())).pages(vec![1])
             ]
             .into()
         );
@@ -608,9 +762,9 @@ mod test {
         assert_eq!(
             res,
             vec![
-                SearchEntry::new(("filename3".into(), "thumbnail3".into())),
-                SearchEntry::new(("filename3".into(), "thumbnail3".into())),
-                SearchEntry::new(("filename3".into(), "thumbnail3".into()))
+                SearchEntry::new(("filename3".into(), "thumbnail3".into())).pages(vec![1]),
+                SearchEntry::new(("filename3".into(), "thumbnail3".into())).pages(vec![1]),
+                SearchEntry::new(("filename3".into(), "thumbnail3".into())).pages(vec![1])
             ]
             .into()
         );
@@ -646,4 +800,215 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn indexes_created_before_restart_are_loaded() -> Result<()> {
+        // given
+        init_tracing();
+        let config = create_config()?;
+        let user = User::new(FAKE_USER_EMAIL);
+        let details = DocDetails::new(
+            Filename::new("filename")?,
+            "body",
+            Thumbnailname::new("thumbnail")?,
+            user.clone(),
+        );
+        TantivyState::create(&config)?.writer().index(&[details])?;
+
+        // when
+        let state = TantivyState::create(&config)?;
+        let res = state.reader().all_docs(user)?;
+
+        // then
+        assert_eq!(
+            res,
+            vec![SearchEntry::new(("filename".into(), "thumbnail".into()))].into()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn all_docs_returns_all_indexed_documents() -> Result<()> {
+        // given
+        init_tracing();
+        let config = create_config()?;
+        let state = TantivyState::create(&config)?;
+        let user = User::new(FAKE_USER_EMAIL);
+        let mut details = Vec::new();
+        for i in 0..110 {
+            details.push(DocDetails::new(
+                Filename::new(format!("filename{i}"))?,
+                "body",
+                Thumbnailname::new(format!("thumbnail{i}"))?,
+                user.clone(),
+            ));
+        }
+        state.writer().index(&details)?;
+
+        // when
+        let res = state.reader().all_docs(user)?;
+
+        // then
+        assert_eq!(res.filenames().len(), 110);
+
+        Ok(())
+    }
+
+    #[test]
+    fn indexing_document_again_replaces_its_data() -> Result<()> {
+        // given
+        init_tracing();
+        let config = create_config()?;
+        let state = TantivyState::create(&config)?;
+        let user = User::new(FAKE_USER_EMAIL);
+        let doc = |body| -> Result<DocDetails> {
+            Ok(DocDetails::new(
+                Filename::new("doc1.pdf")?,
+                body,
+                Thumbnailname::new("doc1.png")?,
+                user.clone(),
+            ))
+        };
+        state.writer().index(&[doc("old content")?])?;
+
+        // when
+        state.writer().index(&[doc("new content")?])?;
+
+        // then
+        assert_eq!(
+            state.reader().search(user.clone(), "old".into())?,
+            Vec::new().into()
+        );
+        assert_eq!(
+            state.reader().search(user, "new".into())?,
+            vec![SearchEntry::new(("doc1.pdf".into(), "doc1.png".into())).pages(vec![1])].into()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn delete_doc_removes_document_with_given_name() -> Result<()> {
+        // given
+        init_tracing();
+        let config = create_config()?;
+        let state = TantivyState::create(&config)?;
+        let user = User::new(FAKE_USER_EMAIL);
+        state.writer().index(&[
+            DocDetails::new(
+                Filename::new("doc1.pdf")?,
+                "body",
+                Thumbnailname::new("doc1.png")?,
+                user.clone(),
+            ),
+            DocDetails::new(
+                Filename::new("doc2.pdf")?,
+                "body",
+                Thumbnailname::new("doc2.png")?,
+                user.clone(),
+            ),
+        ])?;
+
+        // when
+        state
+            .writer()
+            .delete_doc(&user, &Filename::new("doc1.pdf")?)?;
+
+        // then
+        assert_eq!(
+            state.reader().all_docs(user)?,
+            vec![SearchEntry::new(("doc2.pdf".into(), "doc2.png".into()))].into()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn delete_doc_of_user_without_index_does_nothing() -> Result<()> {
+        // given
+        init_tracing();
+        let config = create_config()?;
+        let state = TantivyState::create(&config)?;
+
+        // when
+        let res = state
+            .writer()
+            .delete_doc(&User::new(FAKE_USER_EMAIL), &Filename::new("doc1.pdf")?);
+
+        // then
+        assert!(res.is_ok());
+
+        Ok(())
+    }
+
+    #[test]
+    fn search_returns_matching_pages_of_document() -> Result<()> {
+        // given
+        init_tracing();
+        let config = create_config()?;
+        let state = TantivyState::create(&config)?;
+        let user = User::new(FAKE_USER_EMAIL);
+        let details = DocDetails::new(
+            Filename::new("contract.pdf")?,
+            "",
+            Thumbnailname::new("contract.png")?,
+            user.clone(),
+        )
+        .pages(vec![
+            Page::new(1, "parties of the contract"),
+            Page::new(2, "payment terms"),
+            Page::new(3, "termination of the contract"),
+        ]);
+        state.writer().index(&[details])?;
+
+        // when
+        let res = state.reader().search(user.clone(), "contract".into())?;
+        let all = state.reader().all_docs(user)?;
+
+        // then
+        let entry = SearchEntry::new(("contract.pdf".into(), "contract.png".into()));
+        assert_eq!(res, vec![entry.clone().pages(vec![1, 3])].into());
+        assert_eq!(all, vec![entry].into());
+
+        Ok(())
+    }
+
+    #[test]
+    fn search_in_documents_is_not_limited_by_hits_in_others() -> Result<()> {
+        // given
+        init_tracing();
+        let config = create_config()?;
+        let state = TantivyState::create(&config)?;
+        let user = User::new(FAKE_USER_EMAIL);
+        let pages: Vec<Page> = (1..=MAX_HITS + 1)
+            .filter_map(|number| u32::try_from(number).ok())
+            .map(|number| Page::new(number, "contract"))
+            .collect();
+        let other = DocDetails::new(
+            Filename::new("other.pdf")?,
+            "",
+            Thumbnailname::new("other.pdf.webp")?,
+            user.clone(),
+        )
+        .pages(pages);
+        let shared = DocDetails::new(
+            Filename::new("shared.pdf")?,
+            "contract",
+            Thumbnailname::new("shared.pdf.webp")?,
+            user.clone(),
+        );
+        state.writer().index(&[other, shared])?;
+        let filenames = HashSet::from(["shared.pdf".to_string()]);
+
+        // when
+        let res = state
+            .reader()
+            .search_in(user, "contract".into(), &filenames)?;
+
+        // then
+        assert_eq!(res.filenames(), filenames);
+
+        Ok(())
+    }
 }
@@ -1,17 +1,42 @@
-use crate::entities::location::Location;
+use crate::data_providers::extractor::preprocess::{exif_orientation, rotate_by_exif};
+use crate::data_providers::raster::decode_pages;
+use crate::data_providers::thumbnailer::save_thumbnail;
+use crate::entities::extension::Ext;
+use crate::entities::file::{ThumbnailSize, Thumbnailname};
+use crate::entities::location::{Location, SafePathBuf};
 use crate::result::{GeneralErr, ThumbnailerErr};
+use crate::use_cases::config::ThumbnailsConfig;
 use crate::use_cases::services::thumbnailer::ThumbnailMaker;
-use std::fs::{self, create_dir_all};
 
+use enum_iterator::all;
+use image::DynamicImage;
+use std::fs::{self, create_dir_all};
 use std::path::Path;
 use tracing::{debug, instrument};
 
-/// Puts copy of an image to thumbnails directory.
+/// Makes downscaled thumbnails of an image.
 ///
-/// It utilizes [`fs::copy`] function to move a copy to target directory. Thumbnails directory
-/// comes from the configuration - see [`Config`](crate::configuration::cfg::Config).
+/// The image is turned according to its EXIF orientation, scaled down so its longest side doesn't
+/// exceed the size from [`ThumbnailsConfig`] and encoded in the configured format. One thumbnail
+/// is made for each [`ThumbnailSize`] and each of the configured device pixel ratios, smaller
+/// images are not scaled up. The first page is used when the image has more of them.
 #[derive(Debug)]
-pub struct ImageThumbnailer;
+pub struct ImageThumbnailer {
+    cfg: ThumbnailsConfig,
+}
+
+impl ImageThumbnailer {
+    pub fn new(cfg: ThumbnailsConfig) -> Self {
+        Self { cfg }
+    }
+
+    fn max_side(&self, size: ThumbnailSize) -> u32 {
+        match size {
+            ThumbnailSize::Tile => self.cfg.tile_px,
+            ThumbnailSize::Preview => self.cfg.preview_px,
+        }
+    }
+}
 
 impl ThumbnailMaker for ImageThumbnailer {
     #[instrument(skip(self))]
@@ -25,40 +50,69 @@ impl ThumbnailMaker for ImageThumbnailer {
                     GeneralErr::InvalidExtension,
                 ));
             }
-            let target_path = target_dir.join(p.rel_path());
-            let parent_path = target_path.parent().expect("failed to get parent dir");
-            create_dir_all(parent_path)?;
-            debug!("moving '{:?}' to '{}'", p, target_path.display());
-            fs::copy(p, &target_path)?;
-            result_paths.push(target_path.into());
+            let img = upright_image(p, &ext)?;
+            let user_dir = target_dir.join(p.parent_name());
+            create_dir_all(&user_dir)?;
+            let name = Thumbnailname::of(p, self.cfg.format.ext())?;
+            for size in all::<ThumbnailSize>() {
+                for ratio in self.cfg.ratios() {
+                    let target_path = user_dir.join(name.sized(size).at_ratio(ratio).to_string());
+                    let max = self.max_side(size).saturating_mul(ratio);
+                    debug!(
+                        "writing {:?} thumbnail to '{}'",
+                        size,
+                        target_path.display()
+                    );
+                    if img.width() > max || img.height() > max {
+                        save_thumbnail(&img.thumbnail(max, max), &self.cfg, &target_path)?;
+                    } else {
+                        save_thumbnail(&img, &self.cfg, &target_path)?;
+                    }
+                    result_paths.push(target_path.into());
+                }
+            }
         }
         Ok(Location::FS(result_paths))
     }
 }
 
+fn upright_image(path: &SafePathBuf, ext: &Ext) -> Result<DynamicImage, ThumbnailerErr> {
+    let pages = decode_pages(&fs::read(path)?, ext)?;
+    let first = pages.into_iter().next().ok_or(ThumbnailerErr::NoPages)?;
+    Ok(rotate_by_exif(first, exif_orientation(path.as_ref())))
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
 
     use crate::data_providers::thumbnailer::test::DirEntryExt;
-    use crate::entities::location::SafePathBuf;
-    use crate::helpers::PathRefExt;
+    use crate::use_cases::config::ThumbnailFormat;
 
     use anyhow::Result;
     use claim::assert_err;
+    use image::ImageFormat;
     use tempfile::tempdir;
 
     #[test]
     fn image_thumbnailer_returns_generated_thumbnail_location() -> Result<()> {
         // given
         let tmp_dir = tempdir()?;
-        let thumbnailer = ImageThumbnailer;
+        let thumbnailer = ImageThumbnailer::new(ThumbnailsConfig::default());
         let paths = vec![SafePathBuf::from("res/doc1.png")];
-        let target_path = tmp_dir.path().join(format!("{}.png", paths[0].rel_stem()));
+        let tile_path = tmp_dir.path().join("res/doc1.png.webp");
+        let double_tile_path = tmp_dir.path().join("res/doc1.png@2x.webp");
+        let preview_path = tmp_dir.path().join("res/doc1.png.preview.webp");
+        let double_preview_path = tmp_dir.path().join("res/doc1.png.preview@2x.webp");
 
         // when
         let res = thumbnailer.mk_thumbnail(&Location::FS(paths), tmp_dir.path())?;
-        let target_loc = Location::FS(vec![SafePathBuf::from(target_path)]);
+        let target_loc = Location::FS(vec![
+            SafePathBuf::from(tile_path),
+            SafePathBuf::from(double_tile_path),
+            SafePathBuf::from(preview_path),
+            SafePathBuf::from(double_preview_path),
+        ]);
 
         // then
         assert_eq!(res, target_loc);
@@ -70,7 +124,7 @@ mod test {
     fn image_thumbnailer_puts_image_files_in_user_dir() -> Result<()> {
         // given
         let tmp_dir = tempdir()?;
-        let thumbnailer = ImageThumbnailer;
+        let thumbnailer = ImageThumbnailer::new(ThumbnailsConfig::default());
         let paths = vec![SafePathBuf::from("res/doc1.png")];
         let is_empty = tmp_dir.path().read_dir()?.next().is_none();
         assert!(is_empty);
@@ -78,10 +132,65 @@ mod test {
         // when
         thumbnailer.mk_thumbnail(&Location::FS(paths), tmp_dir.path())?;
         let user_dir = tmp_dir.path().read_dir()?.next().unwrap()?;
+        let mut thumbnails = user_dir
+            .path()
+            .read_dir()?
+            .map(|entry| entry.map(|e| e.name()))
+            .collect::<std::io::Result<Vec<_>>>()?;
+        thumbnails.sort();
 
         // then
         assert_eq!(user_dir.name(), "res");
-        assert_eq!(user_dir.path().first_filename(), "doc1.png");
+        assert_eq!(
+            thumbnails,
+            vec![
+                "doc1.png.preview.webp",
+                "doc1.png.preview@2x.webp",
+                "doc1.png.webp",
+                "doc1.png@2x.webp"
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn image_thumbnailer_scales_down_only_larger_images() -> Result<()> {
+        // given
+        let tmp_dir = tempdir()?;
+        let thumbnailer = ImageThumbnailer::new(ThumbnailsConfig::default());
+        let paths = vec![SafePathBuf::from("res/doc17.tiff")];
+
+        // when
+        thumbnailer.mk_thumbnail(&Location::FS(paths), tmp_dir.path())?;
+        let tile = image::open(tmp_dir.path().join("res/doc17.tiff.webp"))?;
+        let double_tile = image::open(tmp_dir.path().join("res/doc17.tiff@2x.webp"))?;
+        let preview = image::open(tmp_dir.path().join("res/doc17.tiff.preview.webp"))?;
+
+        // then
+        assert_eq!(tile.width().max(tile.height()), 320);
+        assert_eq!(double_tile.width().max(double_tile.height()), 640);
+        assert_eq!(preview.width(), 791);
+
+        Ok(())
+    }
+
+    #[test]
+    fn image_thumbnailer_writes_configured_format() -> Result<()> {
+        // given
+        let tmp_dir = tempdir()?;
+        let thumbnailer = ImageThumbnailer::new(ThumbnailsConfig {
+            format: ThumbnailFormat::Jpeg,
+            ..ThumbnailsConfig::default()
+        });
+        let paths = vec![SafePathBuf::from("res/doc1.png")];
+
+        // when
+        thumbnailer.mk_thumbnail(&Location::FS(paths), tmp_dir.path())?;
+        let thumbnail = fs::read(tmp_dir.path().join("res/doc1.png.jpg"))?;
+
+        // then
+        assert_eq!(image::guess_format(&thumbnail)?, ImageFormat::Jpeg);
 
         Ok(())
     }
@@ -90,7 +199,7 @@ mod test {
     fn image_thumbnailer_fails_with_non_image_files() {
         // given
         let tmp_dir = tempdir().unwrap();
-        let thumbnailer = ImageThumbnailer;
+        let thumbnailer = ImageThumbnailer::new(ThumbnailsConfig::default());
         let paths = vec![SafePathBuf::from("res/doc1.pdf")];
 
         // when
@@ -1,8 +1,17 @@
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::webp::{WebPEncoder, WebPQuality};
+use image::{ColorType, DynamicImage};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
 use tracing::instrument;
 
 use crate::data_providers::thumbnailer::image::ImageThumbnailer;
 use crate::data_providers::thumbnailer::pdf::PdfThumbnailer;
+use crate::data_providers::thumbnailer::text::TextThumbnailer;
 use crate::entities::extension::Ext;
+use crate::result::ThumbnailerErr;
+use crate::use_cases::config::{ArchivesConfig, Config, ThumbnailFormat, ThumbnailsConfig};
 use crate::use_cases::services::thumbnailer::{Thumbnailer, ThumbnailerFactory};
 
 #[cfg(test)]
@@ -10,6 +19,7 @@ pub use test::DirEntryExt;
 
 pub mod image;
 pub mod pdf;
+pub mod text;
 
 /// Creates specific [`Thumbnailer`] based on the extension.
 ///
@@ -20,18 +30,71 @@ pub mod pdf;
 ///
 /// The type of a file is decided based on the file extension.
 #[derive(Debug)]
-pub struct ThumbnailerFactoryImpl;
+pub struct ThumbnailerFactoryImpl {
+    cfg: ThumbnailsConfig,
+    archives: ArchivesConfig,
+}
+
+impl ThumbnailerFactoryImpl {
+    pub fn new(cfg: &Config) -> Self {
+        Self {
+            cfg: cfg.thumbnails.clone(),
+            archives: cfg.archives.clone(),
+        }
+    }
+}
 
 impl ThumbnailerFactory for ThumbnailerFactoryImpl {
     #[instrument(skip(self))]
     fn make(&self, ext: &Ext) -> Thumbnailer {
         match ext {
-            Ext::Png | Ext::Jpg | Ext::Webp => Box::new(ImageThumbnailer),
-            Ext::Pdf => Box::new(PdfThumbnailer),
+            Ext::Png | Ext::Jpg | Ext::Webp | Ext::Tiff | Ext::Heic | Ext::Bmp | Ext::Gif => {
+                Box::new(ImageThumbnailer::new(self.cfg.clone()))
+            }
+            Ext::Pdf => Box::new(PdfThumbnailer::new(self.cfg.clone())),
+            Ext::Docx
+            | Ext::Odt
+            | Ext::Xlsx
+            | Ext::Rtf
+            | Ext::Txt
+            | Ext::Md
+            | Ext::Html
+            | Ext::Eml => Box::new(TextThumbnailer::new(self.archives.clone())),
         }
     }
 }
 
+/// Writes the thumbnail to `path`, compressed in the configured format.
+pub fn save_thumbnail(
+    img: &DynamicImage,
+    cfg: &ThumbnailsConfig,
+    path: &Path,
+) -> Result<(), ThumbnailerErr> {
+    let mut out = BufWriter::new(File::create(path)?);
+    let quality = cfg.quality.clamp(1, 100);
+    match cfg.format {
+        ThumbnailFormat::Webp => {
+            let rgba = img.to_rgba8();
+            WebPEncoder::new_with_quality(&mut out, WebPQuality::lossy(quality)).encode(
+                &rgba,
+                rgba.width(),
+                rgba.height(),
+                ColorType::Rgba8,
+            )?;
+        }
+        ThumbnailFormat::Jpeg => {
+            let rgb = img.to_rgb8();
+            JpegEncoder::new_with_quality(&mut out, quality).encode(
+                &rgb,
+                rgb.width(),
+                rgb.height(),
+                ColorType::Rgb8,
+            )?;
+        }
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -47,12 +110,22 @@ mod test {
     fn test_thumbnailer_factory_with_correct_file() -> Result<()> {
         // given
         let test_cases = vec![
-            (Ext::Png, "res/doc1.png", "doc1.png"),
-            (Ext::Jpg, "res/doc3.jpg", "doc3.jpg"),
-            (Ext::Webp, "res/doc4.webp", "doc4.webp"),
-            (Ext::Pdf, "res/doc1.pdf", "doc1.png"),
+            (Ext::Png, "res/doc1.png", "doc1.png.webp"),
+            (Ext::Jpg, "res/doc3.jpg", "doc3.jpg.webp"),
+            (Ext::Webp, "res/doc4.webp", "doc4.webp.webp"),
+            (Ext::Tiff, "res/doc17.tiff", "doc17.tiff.webp"),
+            (Ext::Bmp, "res/doc18.bmp", "doc18.bmp.webp"),
+            (Ext::Pdf, "res/doc1.pdf", "doc1.pdf.webp"),
+            (Ext::Docx, "res/doc9.docx", "doc9.docx.png"),
+            (Ext::Odt, "res/doc10.odt", "doc10.odt.png"),
+            (Ext::Xlsx, "res/doc11.xlsx", "doc11.xlsx.png"),
+            (Ext::Rtf, "res/doc12.rtf", "doc12.rtf.png"),
+            (Ext::Txt, "res/doc13.txt", "doc13.txt.png"),
+            (Ext::Md, "res/doc14.md", "doc14.md.png"),
+            (Ext::Html, "res/doc15.html", "doc15.html.png"),
+            (Ext::Eml, "res/doc16.eml", "doc16.eml.png"),
         ];
-        let thumbnailer_factory = ThumbnailerFactoryImpl;
+        let thumbnailer_factory = ThumbnailerFactoryImpl::new(&Config::default());
 
         for test_case in test_cases {
             let ext = test_case.0;
@@ -66,12 +139,35 @@ mod test {
 
             // then
             assert_eq!(tmp_dir.path().first_filename(), "res");
-            assert_eq!(tmp_dir.path().join("res").first_filename(), test_case.2);
+            assert!(tmp_dir.path().join("res").join(test_case.2).exists());
         }
 
         Ok(())
     }
 
+    #[test]
+    fn documents_with_the_same_stem_have_separate_thumbnails() -> Result<()> {
+        // given
+        let tmp_dir = tempdir()?;
+        let thumbnailer_factory = ThumbnailerFactoryImpl::new(&Config::default());
+        let image = Location::FS(vec![SafePathBuf::from("res/doc1.png")]);
+        let pdf = Location::FS(vec![SafePathBuf::from("res/doc1.pdf")]);
+
+        // when
+        thumbnailer_factory
+            .make(&Ext::Png)
+            .mk_thumbnail(&image, tmp_dir.path())?;
+        thumbnailer_factory
+            .make(&Ext::Pdf)
+            .mk_thumbnail(&pdf, tmp_dir.path())?;
+
+        // then
+        assert!(tmp_dir.path().join("res/doc1.png.webp").is_file());
+        assert!(tmp_dir.path().join("res/doc1.pdf.webp").is_file());
+
+        Ok(())
+    }
+
     pub trait DirEntryExt {
         fn name(&self) -> String;
     }
@@ -1,59 +1,90 @@
+use crate::data_providers::pdf::render_page;
+use crate::data_providers::thumbnailer::save_thumbnail;
+use crate::entities::file::{ThumbnailSize, Thumbnailname};
 use crate::entities::location::{Location, SafePathBuf};
 use crate::helpers::PathRefExt;
 use crate::result::ThumbnailerErr;
+use crate::use_cases::config::ThumbnailsConfig;
 use crate::use_cases::services::thumbnailer::ThumbnailMaker;
 
-use cairo::{Context, Format, ImageSurface};
+use enum_iterator::all;
 use poppler::{PopplerDocument, PopplerPage};
+use std::convert::TryFrom;
 use std::fs::create_dir_all;
 use std::path::Path;
-use std::{fmt::Debug, fs::File};
 use tracing::{debug, instrument};
 
-const FIRST: usize = 0;
-
 /// Generates thumbnail of the PDF file.
 ///
-/// The thumbnail is used by the client application to display the document. Always the first page
-/// of the PDF document is used to generate the thumbnail.
+/// The thumbnail is used by the client application to display the document. The cover page, the
+/// first one by default, is fitted into the box from [`ThumbnailsConfig`] and compressed in the
+/// configured format. Its preview is rendered so the longest side of the page has the size of
+/// the image previews. Both are rendered also for each of the configured device pixel ratios, so
+/// they stay sharp on high density screens.
 #[derive(Debug)]
-pub struct PdfThumbnailer;
+pub struct PdfThumbnailer {
+    cfg: ThumbnailsConfig,
+}
 
 impl PdfThumbnailer {
+    pub fn new(cfg: ThumbnailsConfig) -> Self {
+        Self { cfg }
+    }
+
     #[instrument(skip(self))]
-    fn generate(&self, pdf_path: &SafePathBuf, out_path: &Path) -> Result<(), ThumbnailerErr> {
-        let parent_path = out_path.parent().expect("failed to get parent dir");
-        create_dir_all(parent_path)?;
-        let page = first_page(pdf_path)?;
-        let surface = paint_background_and_scale(&page)?;
-        debug!("writing thumbnail to: '{}'", out_path.display());
-        let mut f: File = File::create(out_path)?;
-        surface.write_to_png(&mut f)?;
-        Ok(())
+    fn generate(
+        &self,
+        pdf_path: &SafePathBuf,
+        target_dir: &Path,
+    ) -> Result<Vec<SafePathBuf>, ThumbnailerErr> {
+        let user_dir = target_dir.join(pdf_path.parent_name());
+        create_dir_all(&user_dir)?;
+        let page = cover_page(pdf_path, self.cfg.cover_page)?;
+        let name = Thumbnailname::of(pdf_path, self.cfg.format.ext())?;
+        let mut paths = Vec::new();
+        for size in all::<ThumbnailSize>() {
+            let fit = self.fit_scale(&page, size);
+            for ratio in self.cfg.ratios() {
+                let out_path = user_dir.join(name.sized(size).at_ratio(ratio).to_string());
+                let surface = render_page(&page, fit * f64::from(ratio))?;
+                let mut png = Vec::new();
+                surface.write_to_png(&mut png)?;
+                debug!("writing {:?} thumbnail to: '{}'", size, out_path.display());
+                save_thumbnail(&image::load_from_memory(&png)?, &self.cfg, &out_path)?;
+                paths.push(out_path.into());
+            }
+        }
+        Ok(paths)
     }
-}
 
-fn first_page<P: AsRef<Path>>(pdf_path: P) -> Result<PopplerPage, ThumbnailerErr> {
-    debug!("getting first page of PDF '{}'", pdf_path.str());
-    let doc: PopplerDocument = PopplerDocument::new_from_file(pdf_path, "")?;
-    Ok(doc
-        .get_page(FIRST)
-        .unwrap_or_else(|| panic!("failed to get page")))
+    /// Scale which fits the page into the configured box, or its longest side into the size of
+    /// the preview, keeping its proportions.
+    fn fit_scale(&self, page: &PopplerPage, size: ThumbnailSize) -> f64 {
+        let (width, height) = page.get_size();
+        let scale = match size {
+            ThumbnailSize::Tile => {
+                (f64::from(self.cfg.pdf_width) / width).min(f64::from(self.cfg.pdf_height) / height)
+            }
+            ThumbnailSize::Preview => f64::from(self.cfg.preview_px) / width.max(height),
+        };
+        if scale.is_finite() && scale > 0.0 {
+            scale
+        } else {
+            1.0
+        }
+    }
 }
 
-fn paint_background_and_scale(page: &PopplerPage) -> Result<ImageSurface, ThumbnailerErr> {
-    debug!("painting while backgroud and scaling");
-    let (width, height) = page.get_size();
-    #[allow(clippy::cast_possible_truncation)]
-    let surface = ImageSurface::create(Format::Rgb24, width as i32, height as i32)?;
-    // Draw a white background to start with.  If you don't, any transparent
-    // regions in the PDF will be rendered as black in the final image.
-    let ctxt = Context::new(&surface)?;
-    ctxt.set_source_rgb(1.0, 1.0, 1.0);
-    ctxt.scale(1.0, 1.0);
-    ctxt.paint()?;
-    page.render(&ctxt);
-    Ok(surface)
+/// Page with the `number`, counted from 1, or the last page when the document is shorter.
+fn cover_page<P: AsRef<Path>>(pdf_path: P, number: u32) -> Result<PopplerPage, ThumbnailerErr> {
+    debug!("getting page {} of PDF '{}'", number, pdf_path.str());
+    let doc: PopplerDocument = PopplerDocument::new_from_file(pdf_path, "")?;
+    let last = doc
+        .get_n_pages()
+        .checked_sub(1)
+        .ok_or(ThumbnailerErr::NoPages)?;
+    let index = usize::try_from(number.saturating_sub(1)).map_or(last, |index| index.min(last));
+    doc.get_page(index).ok_or(ThumbnailerErr::NoPages)
 }
 
 impl ThumbnailMaker for PdfThumbnailer {
@@ -62,9 +93,7 @@ impl ThumbnailMaker for PdfThumbnailer {
         let Location::FS(paths) = loc;
         let mut result_paths = Vec::new();
         for pdf_path in paths {
-            let thumbnail_path = target_dir.join(format!("{}.png", pdf_path.rel_stem()));
-            self.generate(pdf_path, &thumbnail_path)?;
-            result_paths.push(thumbnail_path.into());
+            result_paths.extend(self.generate(pdf_path, target_dir)?);
         }
         Ok(Location::FS(result_paths))
     }
@@ -78,19 +107,29 @@ mod test {
 
     use anyhow::Result;
     use claim::assert_err;
+    use image::ImageFormat;
+    use std::fs;
     use tempfile::tempdir;
 
     #[test]
     fn pdf_thumbnailer_returns_generated_thumbnail_location() -> Result<()> {
         // given
         let tmp_dir = tempdir()?;
-        let thumbnailer = PdfThumbnailer;
+        let thumbnailer = PdfThumbnailer::new(ThumbnailsConfig::default());
         let paths = vec![SafePathBuf::from("res/doc1.pdf")];
-        let target_path = tmp_dir.path().join(format!("{}.png", paths[0].rel_stem()));
+        let target_path = tmp_dir.path().join("res/doc1.pdf.webp");
+        let double_path = tmp_dir.path().join("res/doc1.pdf@2x.webp");
+        let preview_path = tmp_dir.path().join("res/doc1.pdf.preview.webp");
+        let double_preview_path = tmp_dir.path().join("res/doc1.pdf.preview@2x.webp");
 
         // when
         let res = thumbnailer.mk_thumbnail(&Location::FS(paths), tmp_dir.path())?;
-        let target_loc = Location::FS(vec![SafePathBuf::from(target_path)]);
+        let target_loc = Location::FS(vec![
+            SafePathBuf::from(target_path),
+            SafePathBuf::from(double_path),
+            SafePathBuf::from(preview_path),
+            SafePathBuf::from(double_preview_path),
+        ]);
 
         // then
         assert_eq!(res, target_loc);
@@ -102,7 +141,10 @@ mod test {
     fn pdf_thumbnailer_puts_pdf_files_under_user_dir() -> Result<()> {
         // given
         let tmp_dir = tempdir()?;
-        let thumbnailer = PdfThumbnailer;
+        let thumbnailer = PdfThumbnailer::new(ThumbnailsConfig {
+            pixel_ratios: Vec::new(),
+            ..ThumbnailsConfig::default()
+        });
         let paths = vec![SafePathBuf::from("res/doc1.pdf")];
         let is_empty = tmp_dir.path().read_dir()?.next().is_none();
         assert!(is_empty);
@@ -113,7 +155,72 @@ mod test {
 
         // then
         assert_eq!(user_dir.name(), "res");
-        assert_eq!(user_dir.path().first_filename(), "doc1.png");
+        assert!(user_dir.path().join("doc1.pdf.webp").is_file());
+
+        Ok(())
+    }
+
+    #[test]
+    fn pdf_page_is_fitted_into_configured_box_for_each_pixel_ratio() -> Result<()> {
+        // given
+        let tmp_dir = tempdir()?;
+        let thumbnailer = PdfThumbnailer::new(ThumbnailsConfig {
+            pdf_width: 100,
+            pdf_height: 1000,
+            pixel_ratios: vec![2],
+            ..ThumbnailsConfig::default()
+        });
+        let paths = vec![SafePathBuf::from("res/doc1.pdf")];
+
+        // when
+        thumbnailer.mk_thumbnail(&Location::FS(paths), tmp_dir.path())?;
+        let standard = fs::read(tmp_dir.path().join("res/doc1.pdf.webp"))?;
+        let double = image::open(tmp_dir.path().join("res/doc1.pdf@2x.webp"))?;
+
+        // then
+        assert_eq!(image::guess_format(&standard)?, ImageFormat::WebP);
+        assert!(image::load_from_memory(&standard)?.width().abs_diff(100) <= 1);
+        assert!(double.width().abs_diff(200) <= 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn pdf_preview_has_longest_side_of_image_previews() -> Result<()> {
+        // given
+        let tmp_dir = tempdir()?;
+        let thumbnailer = PdfThumbnailer::new(ThumbnailsConfig {
+            preview_px: 800,
+            pixel_ratios: Vec::new(),
+            ..ThumbnailsConfig::default()
+        });
+        let paths = vec![SafePathBuf::from("res/doc1.pdf")];
+
+        // when
+        thumbnailer.mk_thumbnail(&Location::FS(paths), tmp_dir.path())?;
+        let preview = image::open(tmp_dir.path().join("res/doc1.pdf.preview.webp"))?;
+
+        // then
+        assert!(preview.width().max(preview.height()).abs_diff(800) <= 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn last_page_is_used_when_cover_page_is_beyond_document() -> Result<()> {
+        // given
+        let tmp_dir = tempdir()?;
+        let thumbnailer = PdfThumbnailer::new(ThumbnailsConfig {
+            cover_page: 99,
+            ..ThumbnailsConfig::default()
+        });
+        let paths = vec![SafePathBuf::from("res/doc1.pdf")];
+
+        // when
+        thumbnailer.mk_thumbnail(&Location::FS(paths), tmp_dir.path())?;
+
+        // then
+        assert!(tmp_dir.path().join("res/doc1.pdf.webp").is_file());
 
         Ok(())
     }
@@ -122,7 +229,7 @@ mod test {
     fn pdf_thumbnailer_fails_with_non_pdf_files() {
         // given
         let tmp_dir = tempdir().unwrap();
-        let thumbnailer = PdfThumbnailer;
+        let thumbnailer = PdfThumbnailer::new(ThumbnailsConfig::default());
         let paths = vec![SafePathBuf::from("res/doc8.jpg")];
 
         // when
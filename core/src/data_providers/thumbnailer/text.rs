@@ -0,0 +1,168 @@
+use crate::data_providers::extractor::read_pages;
+use crate::entities::file::Thumbnailname;
+use crate::entities::location::{Location, SafePathBuf};
+use crate::result::ThumbnailerErr;
+use crate::use_cases::config::ArchivesConfig;
+use crate::use_cases::services::thumbnailer::ThumbnailMaker;
+
+use cairo::{Context, FontSlant, FontWeight, Format, ImageSurface};
+use std::fs::{self, create_dir_all, File};
+use std::path::Path;
+use tracing::{debug, instrument};
+
+/// Size of A4 page in points, the same as the size of the PDF thumbnails of such pages.
+const PAGE_WIDTH: f64 = 595.0;
+const PAGE_HEIGHT: f64 = 842.0;
+const MARGIN: f64 = 48.0;
+const FONT_SIZE: f64 = 11.0;
+const LINE_HEIGHT: f64 = 15.0;
+/// Lines are wrapped at this number of characters, so they fit the page in most cases.
+const LINE_CHARS: usize = 88;
+
+/// Generates thumbnail of the documents without own rendering, e.g. office documents or emails.
+///
+/// The text of the first page is rendered on a white page of A4 size, so the thumbnails look the
+/// same way as the thumbnails of PDF documents.
+#[derive(Debug)]
+pub struct TextThumbnailer {
+    limits: ArchivesConfig,
+}
+
+impl TextThumbnailer {
+    pub fn new(limits: ArchivesConfig) -> Self {
+        Self { limits }
+    }
+
+    #[instrument(skip(self))]
+    fn generate(&self, path: &SafePathBuf, out_path: &Path) -> Result<(), ThumbnailerErr> {
+        let parent_path = out_path.parent().expect("failed to get parent dir");
+        create_dir_all(parent_path)?;
+        let pages = read_pages(&fs::read(path)?, &path.ext()?, &self.limits)?;
+        let first = pages.first().map_or("", String::as_str);
+        let surface = render_text(first, 1.0)?;
+        debug!("writing thumbnail to: '{}'", out_path.display());
+        let mut f: File = File::create(out_path)?;
+        surface.write_to_png(&mut f)?;
+        Ok(())
+    }
+}
+
+impl ThumbnailMaker for TextThumbnailer {
+    #[instrument]
+    fn mk_thumbnail(&self, loc: &Location, target_dir: &Path) -> Result<Location, ThumbnailerErr> {
+        let Location::FS(paths) = loc;
+        let mut result_paths = Vec::new();
+        for path in paths {
+            let thumbnail_path = target_dir
+                .join(path.parent_name())
+                .join(Thumbnailname::of(path, "png")?.to_string());
+            self.generate(path, &thumbnail_path)?;
+            result_paths.push(thumbnail_path.into());
+        }
+        Ok(Location::FS(result_paths))
+    }
+}
+
+/// Renders the text on white page of A4 size. The size of the image is the size of the page in
+/// points multiplied by the `scale`. The text not fitting the page is cut.
+pub fn render_text(text: &str, scale: f64) -> Result<ImageSurface, cairo::Error> {
+    #[allow(clippy::cast_possible_truncation)]
+    let surface = ImageSurface::create(
+        Format::Rgb24,
+        (PAGE_WIDTH * scale).ceil() as i32,
+        (PAGE_HEIGHT * scale).ceil() as i32,
+    )?;
+    let ctxt = Context::new(&surface)?;
+    ctxt.scale(scale, scale);
+    ctxt.set_source_rgb(1.0, 1.0, 1.0);
+    ctxt.paint()?;
+    ctxt.set_source_rgb(0.0, 0.0, 0.0);
+    ctxt.select_font_face("Sans", FontSlant::Normal, FontWeight::Normal);
+    ctxt.set_font_size(FONT_SIZE);
+    let mut baseline = MARGIN + FONT_SIZE;
+    for line in text.lines().flat_map(wrap) {
+        if baseline > PAGE_HEIGHT - MARGIN {
+            break;
+        }
+        ctxt.move_to(MARGIN, baseline);
+        ctxt.show_text(&line)?;
+        baseline += LINE_HEIGHT;
+    }
+    Ok(surface)
+}
+
+fn wrap(line: &str) -> Vec<String> {
+    let mut lines = vec![String::new()];
+    for word in line.split_whitespace() {
+        let current = lines.last().map_or(0, |l| l.chars().count());
+        if current > 0 && current + word.chars().count() >= LINE_CHARS {
+            lines.push(String::new());
+        }
+        if let Some(last) = lines.last_mut() {
+            if !last.is_empty() {
+                last.push(' ');
+            }
+            last.push_str(word);
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::data_providers::thumbnailer::DirEntryExt;
+    use crate::helpers::PathRefExt;
+
+    use anyhow::Result;
+    use claim::assert_err;
+    use tempfile::tempdir;
+
+    #[test]
+    fn text_thumbnailer_renders_first_page_to_png() -> Result<()> {
+        // given
+        let tmp_dir = tempdir()?;
+        let thumbnailer = TextThumbnailer::new(ArchivesConfig::default());
+        let paths = vec![SafePathBuf::from("res/doc9.docx")];
+
+        // when
+        thumbnailer.mk_thumbnail(&Location::FS(paths), tmp_dir.path())?;
+        let user_dir = tmp_dir.path().read_dir()?.next().unwrap()?;
+        let thumbnail = image::open(user_dir.path().join("doc9.docx.png"))?;
+
+        // then
+        assert_eq!(user_dir.name(), "res");
+        assert_eq!(user_dir.path().first_filename(), "doc9.docx.png");
+        assert_eq!((thumbnail.width(), thumbnail.height()), (595, 842));
+
+        Ok(())
+    }
+
+    #[test]
+    fn long_lines_are_wrapped_between_words() {
+        // given
+        let line = "word ".repeat(40);
+
+        // when
+        let lines = wrap(&line);
+
+        // then
+        assert_eq!(lines.len(), 3);
+        assert!(lines.iter().all(|l| l.chars().count() < LINE_CHARS));
+    }
+
+    #[test]
+    fn text_thumbnailer_fails_with_images() {
+        // given
+        let tmp_dir = tempdir().unwrap();
+        let thumbnailer = TextThumbnailer::new(ArchivesConfig::default());
+        let paths = vec![SafePathBuf::from("res/doc1.png")];
+
+        // when
+        let res = thumbnailer.mk_thumbnail(&Location::FS(paths), tmp_dir.path());
+
+        // then
+        assert_err!(res);
+    }
+}
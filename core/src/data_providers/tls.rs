@@ -0,0 +1,172 @@
+//! Certificate used by the HTTP server to encrypt the connections.
+//!
+//! The certificate can be provided by the user, e.g. issued by Let's Encrypt, or generated on the
+//! first run. Self-signed certificate can't be verified by the client the usual way, so its
+//! fingerprint is printed and the client pins it instead.
+use crate::data_providers::fs::write_secret;
+use crate::result::TlsErr;
+use crate::use_cases::config::Config;
+
+use base64::engine::general_purpose::STANDARD as b64;
+use base64::Engine;
+use sha2::{Digest, Sha256};
+use std::fs::{self, create_dir_all};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+const CERT_BEGIN: &str = "-----BEGIN CERTIFICATE-----";
+const CERT_END: &str = "-----END CERTIFICATE-----";
+
+/// Files of the certificate used by the server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TlsFiles {
+    pub cert: PathBuf,
+    pub key: PathBuf,
+    /// SHA-256 fingerprint of the certificate, as colon separated hex.
+    pub fingerprint: String,
+}
+
+/// Returns the certificate of the server, generating self-signed one when there is none yet and
+/// it's allowed. Returns `None` when TLS is disabled.
+pub fn prepare(cfg: &Config) -> Result<Option<TlsFiles>, TlsErr> {
+    let tls = &cfg.server.tls;
+    if !tls.enabled {
+        return Ok(None);
+    }
+    let (cert, key) = cfg.tls_paths();
+    if !cert.exists() || !key.exists() {
+        if !tls.self_signed {
+            let missing = if cert.exists() { &key } else { &cert };
+            return Err(TlsErr::Missing(missing.display().to_string()));
+        }
+        generate(&cert, &key, cfg.server.address)?;
+    }
+    let fingerprint = fingerprint(&cert)?;
+    Ok(Some(TlsFiles {
+        cert,
+        key,
+        fingerprint,
+    }))
+}
+
+fn generate(cert: &Path, key: &Path, address: IpAddr) -> Result<(), TlsErr> {
+    debug!("generating self-signed certificate in '{}'", cert.display());
+    let mut names = vec!["localhost".to_string()];
+    if !address.is_unspecified() {
+        names.push(address.to_string());
+    }
+    let generated = rcgen::generate_simple_self_signed(names)?;
+    for path in [cert, key] {
+        if let Some(parent) = path.parent() {
+            create_dir_all(parent)?;
+        }
+    }
+    write_secret(key, generated.serialize_private_key_pem().as_bytes())?;
+    fs::write(cert, generated.serialize_pem()?)?;
+    Ok(())
+}
+
+/// Calculates the fingerprint of the first certificate in the PEM file - the one of the server.
+fn fingerprint(cert: &Path) -> Result<String, TlsErr> {
+    let invalid = || TlsErr::InvalidCertificate(cert.display().to_string());
+    let pem = fs::read_to_string(cert)?;
+    let body: String = pem
+        .lines()
+        .map(str::trim)
+        .skip_while(|line| *line != CERT_BEGIN)
+        .skip(1)
+        .take_while(|line| *line != CERT_END)
+        .collect();
+    if body.is_empty() {
+        return Err(invalid());
+    }
+    let der = b64.decode(body).map_err(|_| invalid())?;
+    let hex: Vec<String> = Sha256::digest(der)
+        .iter()
+        .map(|byte| format!("{:02X}", byte))
+        .collect();
+    Ok(hex.join(":"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::use_cases::config::{ServerConfig, TlsConfig};
+
+    use anyhow::Result;
+    use claim::{assert_matches, assert_none};
+    use tempfile::tempdir;
+
+    fn config(data_dir: &Path, tls: TlsConfig) -> Config {
+        Config {
+            data_dir: data_dir.to_path_buf(),
+            server: ServerConfig {
+                tls,
+                ..ServerConfig::default()
+            },
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn nothing_is_prepared_when_tls_is_disabled() -> Result<()> {
+        // given
+        let data_dir = tempdir()?;
+        let cfg = config(data_dir.path(), TlsConfig::default());
+
+        // when
+        let files = prepare(&cfg)?;
+
+        // then
+        assert_none!(files);
+        assert!(!data_dir.path().join("tls").exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn self_signed_certificate_is_generated_once() -> Result<()> {
+        // given
+        let data_dir = tempdir()?;
+        let tls = TlsConfig {
+            enabled: true,
+            ..TlsConfig::default()
+        };
+        let cfg = config(data_dir.path(), tls);
+
+        // when
+        let generated = prepare(&cfg)?;
+        let loaded = prepare(&cfg)?;
+
+        // then
+        let generated = generated.expect("TLS is enabled");
+        assert!(generated.cert.exists());
+        assert!(generated.key.exists());
+        assert_eq!(generated.fingerprint.len(), 32 * 3 - 1);
+        assert_eq!(loaded, Some(generated));
+
+        Ok(())
+    }
+
+    #[test]
+    fn missing_certificate_is_error_when_it_can_not_be_generated() -> Result<()> {
+        // given
+        let data_dir = tempdir()?;
+        let tls = TlsConfig {
+            enabled: true,
+            self_signed: false,
+            ..TlsConfig::default()
+        };
+        let cfg = config(data_dir.path(), tls);
+
+        // when
+        let res = prepare(&cfg);
+
+        // then
+        assert_matches!(res, Err(TlsErr::Missing(_)));
+
+        Ok(())
+    }
+}
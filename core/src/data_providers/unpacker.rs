@@ -0,0 +1,512 @@
+//! This is concrete implementation of [`crate::use_cases::unpacker`] abstractions.
+//!
+//! Nothing in the archive is trusted. Only the names of the members are used, never their
+//! directories, so documents can't be written outside of the target directory. Sizes declared in
+//! the archive are checked, but the amount of data actually read is limited as well.
+use crate::entities::extension::Ext;
+use crate::entities::location::SafePathBuf;
+use crate::result::UnpackerErr;
+use crate::use_cases::config::{ArchivesConfig, Config};
+use crate::use_cases::quota::Allowance;
+use crate::use_cases::unpacker::{DocsUnpacker, Unpacker};
+
+use mail_parser::{Message, MimeHeaders};
+use std::collections::HashSet;
+use std::convert::TryFrom;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::Path;
+use std::sync::Arc;
+use tracing::{debug, instrument, warn};
+use zip::ZipArchive;
+
+pub struct SafeUnpacker {
+    cfg: ArchivesConfig,
+}
+
+impl SafeUnpacker {
+    pub fn create(cfg: &Config) -> Unpacker {
+        Arc::new(Self {
+            cfg: cfg.archives.clone(),
+        })
+    }
+
+    fn unpack_zip(&self, archive: &SafePathBuf, out: &mut Output) -> Result<(), UnpackerErr> {
+        let mut zip = ZipArchive::new(File::open(archive)?)?;
+        for i in 0..zip.len() {
+            let mut member = zip.by_index(i)?;
+            if !member.is_file() {
+                continue;
+            }
+            let Some(name) = member.enclosed_name().and_then(file_name) else {
+                warn!("skipping member with unsafe name: '{}'", member.name());
+                continue;
+            };
+            let Some(ext) = supported(&name) else {
+                debug!("skipping unsupported member: '{}'", name);
+                continue;
+            };
+            if member.size() > member.compressed_size().saturating_mul(self.cfg.max_ratio) {
+                return Err(UnpackerErr::Bomb(name));
+            }
+            out.write(&name, &ext, &mut member)?;
+        }
+        Ok(())
+    }
+
+    fn unpack_email(&self, email: &SafePathBuf, out: &mut Output) -> Result<(), UnpackerErr> {
+        let doc = fs::read(email)?;
+        let message = Message::parse(&doc).ok_or(UnpackerErr::Email)?;
+        for attachment in message.attachments() {
+            let Some(name) = attachment
+                .attachment_name()
+                .and_then(|name| file_name(Path::new(name)))
+            else {
+                debug!("skipping attachment without name");
+                continue;
+            };
+            let Some(ext) = supported(&name) else {
+                debug!("skipping unsupported attachment: '{}'", name);
+                continue;
+            };
+            out.write(&name, &ext, attachment.contents())?;
+        }
+        Ok(())
+    }
+}
+
+impl DocsUnpacker for SafeUnpacker {
+    #[instrument(skip(self))]
+    fn unpack(
+        &self,
+        archive: &SafePathBuf,
+        dir: &Path,
+        allowance: Allowance,
+    ) -> Result<Vec<SafePathBuf>, UnpackerErr> {
+        if !self.cfg.enabled {
+            return Ok(Vec::new());
+        }
+        let mut out = Output::new(&self.cfg, allowance, archive.filestem(), dir);
+        let res = if archive.is_archive() {
+            self.unpack_zip(archive, &mut out)
+        } else if matches!(archive.ext(), Ok(Ext::Eml)) {
+            self.unpack_email(archive, &mut out)
+        } else {
+            Ok(())
+        };
+        match res {
+            Ok(()) => {
+                debug!("unpacked {} documents", out.written.len());
+                Ok(out.written)
+            }
+            Err(e) => {
+                out.discard();
+                Err(e)
+            }
+        }
+    }
+}
+
+/// Documents unpacked from a single archive so far, counted against the limits and the quota.
+struct Output<'a> {
+    cfg: &'a ArchivesConfig,
+    allowance: Allowance,
+    dir: &'a Path,
+    prefix: String,
+    names: HashSet<String>,
+    written: Vec<SafePathBuf>,
+    bytes: u64,
+}
+
+impl<'a> Output<'a> {
+    fn new(cfg: &'a ArchivesConfig, allowance: Allowance, prefix: String, dir: &'a Path) -> Self {
+        Self {
+            cfg,
+            allowance,
+            dir,
+            prefix,
+            names: HashSet::new(),
+            written: Vec::new(),
+            bytes: 0,
+        }
+    }
+
+    /// Document which content is not of the type claimed by its extension is skipped.
+    fn write<R: Read>(&mut self, name: &str, ext: &Ext, member: R) -> Result<(), UnpackerErr> {
+        let count = u32::try_from(self.written.len()).unwrap_or(u32::MAX);
+        if count >= self.cfg.max_members {
+            return Err(UnpackerErr::TooManyMembers(self.cfg.max_members));
+        }
+        if u64::from(count) >= self.allowance.docs {
+            return Err(UnpackerErr::QuotaExceeded);
+        }
+        let remaining = self.cfg.max_total_bytes.saturating_sub(self.bytes);
+        let allowed = self.allowance.bytes.saturating_sub(self.bytes);
+        let limit = self.cfg.max_member_bytes.min(remaining).min(allowed);
+        let mut content = Vec::new();
+        member
+            .take(limit.saturating_add(1))
+            .read_to_end(&mut content)?;
+        let len = u64::try_from(content.len()).unwrap_or(u64::MAX);
+        if len > self.cfg.max_member_bytes {
+            return Err(UnpackerErr::MemberTooLarge(name.to_string()));
+        }
+        if len > remaining {
+            return Err(UnpackerErr::ArchiveTooLarge(self.cfg.max_total_bytes));
+        }
+        if len > allowed {
+            return Err(UnpackerErr::QuotaExceeded);
+        }
+        if let Err(e) = ext.verify(&content) {
+            warn!("skipping '{}': {}", name, e);
+            return Ok(());
+        }
+        self.bytes += len;
+        fs::create_dir_all(self.dir)?;
+        let path = self.dir.join(self.unique_name(name));
+        // NOTE: never replaces a document stored in the meantime under the same name
+        OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)?
+            .write_all(&content)?;
+        self.written.push(SafePathBuf::new(path));
+        Ok(())
+    }
+
+    /// Names start with the name of the archive. Members of the same name, e.g. placed in
+    /// different directories of the archive, are numbered, and so are the members which would
+    /// replace documents already stored in the target directory.
+    fn unique_name(&mut self, name: &str) -> String {
+        let mut unique = format!("{}-{}", self.prefix, name);
+        let mut number = 1;
+        while self.names.contains(&unique) || self.dir.join(&unique).exists() {
+            number += 1;
+            unique = format!("{}-{}-{}", self.prefix, number, name);
+        }
+        self.names.insert(unique.clone());
+        unique
+    }
+
+    fn discard(&self) {
+        for path in &self.written {
+            if let Err(e) = fs::remove_file(path) {
+                warn!("failed to remove unpacked '{}': '{}'", path, e);
+            }
+        }
+    }
+}
+
+fn file_name(path: &Path) -> Option<String> {
+    path.file_name()?.to_str().map(str::to_string)
+}
+
+fn supported(name: &str) -> Option<Ext> {
+    let ext = Path::new(name).extension()?.to_str()?;
+    Ext::try_from(ext).ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::helpers::PathRefExt;
+    use crate::testingtools::sample_content;
+
+    use anyhow::Result;
+    use claim::assert_err;
+    use std::path::PathBuf;
+    use tempfile::tempdir;
+    use zip::write::FileOptions;
+    use zip::ZipWriter;
+
+    const EMAIL: &str = "From: Jan <jan@example.com>\r
+Subject: Invoice\r
+MIME-Version: 1.0\r
+Content-Type: multipart/mixed; boundary=\"sep\"\r
+\r
+--sep\r
+Content-Type: text/plain\r
+\r
+Invoice is attached.\r
+--sep\r
+Content-Type: application/pdf\r
+Content-Disposition: attachment; filename=\"invoice.pdf\"\r
+Content-Transfer-Encoding: base64\r
+\r
+JVBERi0xLjQKU29tZSB0ZXh0IG9mIHRoZSBkb2N1bWVudC4K\r
+--sep--\r
+";
+
+    #[test]
+    fn supported_members_of_zip_are_unpacked() -> Result<()> {
+        // given
+        let tmp_dir = tempdir()?;
+        let archive = mk_zip(
+            tmp_dir.path(),
+            &[
+                ("scans/invoice.pdf", &sample_content(&Ext::Pdf)),
+                ("notes.txt", &sample_content(&Ext::Txt)),
+                ("setup.exe", b"MZ"),
+            ],
+        )?;
+        let out_dir = tmp_dir.path().join("out");
+        let unpacker = SafeUnpacker::create(&Config::default());
+
+        // when
+        let docs = unpacker.unpack(&archive, &out_dir, Allowance::unlimited())?;
+
+        // then
+        let names: Vec<String> = docs.iter().map(SafePathBuf::filename).collect();
+        assert_eq!(names, vec!["archive-invoice.pdf", "archive-notes.txt"]);
+        assert_eq!(fs::read(&docs[0])?, sample_content(&Ext::Pdf));
+
+        Ok(())
+    }
+
+    #[test]
+    fn members_escaping_target_dir_are_skipped() -> Result<()> {
+        // given
+        let tmp_dir = tempdir()?;
+        let archive = mk_zip(
+            tmp_dir.path(),
+            &[("../../escaped.pdf", &sample_content(&Ext::Pdf))],
+        )?;
+        let out_dir = tmp_dir.path().join("a/b/out");
+        let unpacker = SafeUnpacker::create(&Config::default());
+
+        // when
+        let docs = unpacker.unpack(&archive, &out_dir, Allowance::unlimited())?;
+
+        // then
+        assert!(docs.is_empty());
+        assert!(!tmp_dir.path().join("escaped.pdf").exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn members_with_same_name_are_numbered() -> Result<()> {
+        // given
+        let tmp_dir = tempdir()?;
+        let content = sample_content(&Ext::Txt);
+        let archive = mk_zip(
+            tmp_dir.path(),
+            &[("jan/notes.txt", &content), ("feb/notes.txt", &content)],
+        )?;
+        let out_dir = tmp_dir.path().join("out");
+        let unpacker = SafeUnpacker::create(&Config::default());
+
+        // when
+        let docs = unpacker.unpack(&archive, &out_dir, Allowance::unlimited())?;
+
+        // then
+        let names: Vec<String> = docs.iter().map(SafePathBuf::filename).collect();
+        assert_eq!(names, vec!["archive-notes.txt", "archive-2-notes.txt"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn members_are_numbered_instead_of_replacing_stored_documents() -> Result<()> {
+        // given
+        let tmp_dir = tempdir()?;
+        let archive = mk_zip(tmp_dir.path(), &[("notes.txt", &sample_content(&Ext::Txt))])?;
+        let out_dir = tmp_dir.path().join("out");
+        fs::create_dir_all(&out_dir)?;
+        fs::write(out_dir.join("archive-notes.txt"), "stored document")?;
+        let unpacker = SafeUnpacker::create(&Config::default());
+
+        // when
+        let docs = unpacker.unpack(&archive, &out_dir, Allowance::unlimited())?;
+
+        // then
+        let names: Vec<String> = docs.iter().map(SafePathBuf::filename).collect();
+        assert_eq!(names, vec!["archive-2-notes.txt"]);
+        assert_eq!(
+            fs::read_to_string(out_dir.join("archive-notes.txt"))?,
+            "stored document"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn member_with_mismatched_content_is_skipped() -> Result<()> {
+        // given
+        let tmp_dir = tempdir()?;
+        let archive = mk_zip(tmp_dir.path(), &[("photo.jpg", &sample_content(&Ext::Pdf))])?;
+        let out_dir = tmp_dir.path().join("out");
+        let unpacker = SafeUnpacker::create(&Config::default());
+
+        // when
+        let docs = unpacker.unpack(&archive, &out_dir, Allowance::unlimited())?;
+
+        // then
+        assert!(docs.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn zip_bomb_is_rejected_and_nothing_is_left() -> Result<()> {
+        // given
+        let tmp_dir = tempdir()?;
+        let archive = mk_zip(
+            tmp_dir.path(),
+            &[
+                ("notes.txt", &sample_content(&Ext::Txt)),
+                ("bomb.txt", &vec![b'a'; 1024 * 1024]),
+            ],
+        )?;
+        let out_dir = tmp_dir.path().join("out");
+        let unpacker = SafeUnpacker::create(&Config::default());
+
+        // when
+        let res = unpacker.unpack(&archive, &out_dir, Allowance::unlimited());
+
+        // then
+        assert!(matches!(res, Err(UnpackerErr::Bomb(name)) if name == "bomb.txt"));
+        assert!(out_dir.read_dir()?.next().is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn archive_exceeding_limits_is_rejected() -> Result<()> {
+        // given
+        let tmp_dir = tempdir()?;
+        let content = sample_content(&Ext::Txt);
+        let archive = mk_zip(
+            tmp_dir.path(),
+            &[
+                ("1.txt", &content),
+                ("2.txt", &content),
+                ("3.txt", &content),
+            ],
+        )?;
+        let out_dir = tmp_dir.path().join("out");
+        let few_members = SafeUnpacker {
+            cfg: ArchivesConfig {
+                max_members: 2,
+                ..ArchivesConfig::default()
+            },
+        };
+        let few_bytes = SafeUnpacker {
+            cfg: ArchivesConfig {
+                max_total_bytes: 40,
+                ..ArchivesConfig::default()
+            },
+        };
+
+        // when
+        let too_many = few_members.unpack(&archive, &out_dir, Allowance::unlimited());
+        let too_large = few_bytes.unpack(&archive, &out_dir, Allowance::unlimited());
+
+        // then
+        assert!(matches!(too_many, Err(UnpackerErr::TooManyMembers(2))));
+        assert!(matches!(too_large, Err(UnpackerErr::ArchiveTooLarge(40))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn archive_exceeding_quota_is_rejected_and_nothing_is_left() -> Result<()> {
+        // given
+        let tmp_dir = tempdir()?;
+        let content = sample_content(&Ext::Txt);
+        let archive = mk_zip(tmp_dir.path(), &[("1.txt", &content), ("2.txt", &content)])?;
+        let out_dir = tmp_dir.path().join("out");
+        let unpacker = SafeUnpacker::create(&Config::default());
+        let one_doc = Allowance {
+            docs: 1,
+            ..Allowance::unlimited()
+        };
+        let few_bytes = Allowance {
+            bytes: 40,
+            ..Allowance::unlimited()
+        };
+
+        // when
+        let too_many = unpacker.unpack(&archive, &out_dir, one_doc);
+        let too_large = unpacker.unpack(&archive, &out_dir, few_bytes);
+
+        // then
+        assert!(matches!(too_many, Err(UnpackerErr::QuotaExceeded)));
+        assert!(matches!(too_large, Err(UnpackerErr::QuotaExceeded)));
+        assert!(out_dir.read_dir()?.next().is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn attachments_of_email_are_unpacked() -> Result<()> {
+        // given
+        let tmp_dir = tempdir()?;
+        let email = tmp_dir.path().join("message.eml");
+        fs::write(&email, EMAIL)?;
+        let out_dir = tmp_dir.path().join("out");
+        let unpacker = SafeUnpacker::create(&Config::default());
+
+        // when
+        let docs = unpacker.unpack(&SafePathBuf::new(email), &out_dir, Allowance::unlimited())?;
+
+        // then
+        assert_eq!(docs.len(), 1);
+        assert_eq!(docs[0].filename(), "message-invoice.pdf");
+        assert_eq!(out_dir.first_filename(), "message-invoice.pdf");
+
+        Ok(())
+    }
+
+    #[test]
+    fn other_documents_are_not_unpacked() -> Result<()> {
+        // given
+        let tmp_dir = tempdir()?;
+        let unpacker = SafeUnpacker::create(&Config::default());
+
+        // when
+        let docs = unpacker.unpack(
+            &SafePathBuf::from("res/doc1.pdf"),
+            tmp_dir.path(),
+            Allowance::unlimited(),
+        )?;
+
+        // then
+        assert!(docs.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn broken_zip_is_rejected() -> Result<()> {
+        // given
+        let tmp_dir = tempdir()?;
+        let archive = tmp_dir.path().join("archive.zip");
+        fs::write(&archive, b"PK\x03\x04 broken")?;
+        let unpacker = SafeUnpacker::create(&Config::default());
+
+        // when
+        let res = unpacker.unpack(
+            &SafePathBuf::new(archive),
+            tmp_dir.path(),
+            Allowance::unlimited(),
+        );
+
+        // then
+        assert_err!(res);
+
+        Ok(())
+    }
+
+    fn mk_zip(dir: &Path, members: &[(&str, &[u8])]) -> Result<SafePathBuf> {
+        let path: PathBuf = dir.join("archive.zip");
+        let mut zip = ZipWriter::new(File::create(&path)?);
+        for (name, content) in members {
+            zip.start_file(*name, FileOptions::default())?;
+            zip.write_all(content)?;
+        }
+        zip.finish()?;
+        Ok(SafePathBuf::new(path))
+    }
+}
@@ -0,0 +1,121 @@
+//! This is concrete implementation of [`crate::use_cases::users`] abstractions.
+//!
+//! Accounts are kept in memory and saved to a JSON file in [`Config::data_dir`] on every change.
+use crate::data_providers::json_file::JsonFile;
+use crate::entities::user::User;
+use crate::result::UsersErr;
+use crate::use_cases::config::Config;
+use crate::use_cases::users::{Account, AccountStore, Accounts};
+
+use std::sync::{Arc, Mutex};
+use tracing::{debug, instrument};
+
+const ACCOUNTS_FILE: &str = "accounts.json";
+
+pub struct JsonAccountStore {
+    file: JsonFile,
+    accounts: Mutex<Vec<Account>>,
+}
+
+impl JsonAccountStore {
+    pub fn create(cfg: &Config) -> Result<Accounts, UsersErr> {
+        let file = JsonFile::new(cfg, ACCOUNTS_FILE)?;
+        let accounts: Vec<Account> = file.load()?;
+        debug!("loaded {} accounts from '{}'", accounts.len(), file);
+        Ok(Arc::new(Self {
+            file,
+            accounts: Mutex::new(accounts),
+        }))
+    }
+}
+
+impl AccountStore for JsonAccountStore {
+    fn get(&self, user: &User) -> Result<Option<Account>, UsersErr> {
+        let accounts = self.accounts.lock().expect("poisoned mutex");
+        Ok(accounts.iter().find(|a| &a.user == user).cloned())
+    }
+
+    fn get_or_add(&self, account: Account) -> Result<Account, UsersErr> {
+        let mut accounts = self.accounts.lock().expect("poisoned mutex");
+        if let Some(existing) = accounts.iter().find(|a| a.user == account.user) {
+            return Ok(existing.clone());
+        }
+        debug!("adding account of '{}'", account.user);
+        accounts.push(account.clone());
+        self.file.save(&accounts)?;
+        Ok(account)
+    }
+
+    #[instrument(skip(self))]
+    fn put(&self, account: Account) -> Result<(), UsersErr> {
+        let mut accounts = self.accounts.lock().expect("poisoned mutex");
+        accounts.retain(|a| a.user != account.user);
+        accounts.push(account);
+        Ok(self.file.save(&accounts)?)
+    }
+
+    fn all(&self) -> Result<Vec<Account>, UsersErr> {
+        Ok(self.accounts.lock().expect("poisoned mutex").clone())
+    }
+
+    #[instrument(skip(self))]
+    fn remove(&self, user: &User) -> Result<bool, UsersErr> {
+        let mut accounts = self.accounts.lock().expect("poisoned mutex");
+        let count = accounts.len();
+        accounts.retain(|a| &a.user != user);
+        if accounts.len() == count {
+            return Ok(false);
+        }
+        self.file.save(&accounts)?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::testingtools::TestConfig;
+    use crate::use_cases::users::AccountStatus;
+
+    use anyhow::Result;
+
+    #[test]
+    fn accounts_are_kept_after_restart() -> Result<()> {
+        // given
+        let cfg = TestConfig::new()?;
+        let accounts = JsonAccountStore::create(cfg.as_ref())?;
+        let user = User::new("me@email.com");
+        accounts.get_or_add(Account::new(user.clone(), AccountStatus::Pending))?;
+        accounts.put(Account::new(user.clone(), AccountStatus::Active))?;
+
+        // when
+        let accounts = JsonAccountStore::create(cfg.as_ref())?;
+
+        // then
+        let all = accounts.all()?;
+        assert_eq!(all.len(), 1);
+        assert_eq!(all[0].status, AccountStatus::Active);
+
+        Ok(())
+    }
+
+    #[test]
+    fn existing_account_is_not_replaced_when_added_again() -> Result<()> {
+        // given
+        let cfg = TestConfig::new()?;
+        let accounts = JsonAccountStore::create(cfg.as_ref())?;
+        let user = User::new("me@email.com");
+        accounts.put(Account::new(user.clone(), AccountStatus::Denied))?;
+
+        // when
+        let account = accounts.get_or_add(Account::new(user.clone(), AccountStatus::Active))?;
+
+        // then
+        assert_eq!(account.status, AccountStatus::Denied);
+        assert!(accounts.remove(&user)?);
+        assert!(!accounts.remove(&user)?);
+
+        Ok(())
+    }
+}
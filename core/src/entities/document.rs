@@ -10,9 +10,13 @@ use fake::{Dummy, Fake};
 #[derive(Debug, PartialOrd, Clone, Ord, Eq, PartialEq, Dummy)]
 pub struct DocDetails {
     pub filename: Filename,
-    pub body: String,
+    /// Text of the document, page by page. Documents without pages, like images, have one.
+    pub pages: Vec<Page>,
     pub thumbnail: Thumbnailname,
     pub user: User,
+    /// Mean confidence of the text recognition, from 0 to 100. `None` when the text didn't need
+    /// to be recognized.
+    pub confidence: Option<u8>,
 }
 
 impl DocDetails {
@@ -24,9 +28,44 @@ impl DocDetails {
     ) -> Self {
         Self {
             filename,
-            body: body.into(),
+            pages: vec![Page::new(1, body)],
             thumbnail,
             user,
+            confidence: None,
+        }
+    }
+
+    pub fn confidence(mut self, confidence: u8) -> Self {
+        self.confidence = Some(confidence);
+        self
+    }
+
+    /// Replaces the text of the document with the text of its pages.
+    pub fn pages(mut self, pages: Vec<Page>) -> Self {
+        self.pages = pages;
+        self
+    }
+
+    /// Returns the text of all the pages.
+    pub fn body(&self) -> String {
+        let texts: Vec<&str> = self.pages.iter().map(|page| page.text.as_str()).collect();
+        texts.join("\n")
+    }
+}
+
+/// Text of a single page of the document.
+#[derive(Debug, PartialOrd, Clone, Ord, Eq, PartialEq, Dummy)]
+pub struct Page {
+    /// Number of the page, counted from 1.
+    pub number: u32,
+    pub text: String,
+}
+
+impl Page {
+    pub fn new<S: Into<String>>(number: u32, text: S) -> Self {
+        Self {
+            number,
+            text: text.into(),
         }
     }
 }
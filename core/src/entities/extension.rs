@@ -1,13 +1,36 @@
 //! Represents extension of the documents appearing in the system.
 //!
+//! This is the single source of truth of the supported types. The type of the document is decided
+//! by the extension and verified by the content, see [`Ext::verify`].
+//!
 //! Indexing and preprocessing strategies are based on the extension. See
 //! [`ExtractorFactoryImpl`](crate::data_providers::extractor::ExtractorFactoryImpl)
 //! and [`ThumbnailerFactoryImpl`](crate::data_providers::thumbnailer::ThumbnailerFactoryImpl).
 
-use crate::result::GeneralErr;
+use crate::result::{FileTypeErr, GeneralErr};
 
 use enum_iterator::{all, Sequence};
 use std::convert::TryFrom;
+use std::fmt::Display;
+
+/// Enough bytes of the document to detect its type.
+pub const SNIFF_LEN: usize = 64 * 1024;
+/// Extension of ZIP archives. Archives are not documents themselves, their supported members are
+/// unpacked and processed as separate documents.
+pub const ARCHIVE_EXT: &str = "zip";
+/// Header of PDF files, followed by the version.
+const PDF_HEADER: &[u8] = b"%PDF-";
+/// Brands of HEIF images in the `ftyp` box.
+const HEIF_BRANDS: [&[u8]; 6] = [b"heic", b"heix", b"hevc", b"hevx", b"mif1", b"msf1"];
+/// Sizes of the known BMP headers, as little endian.
+const BMP_HEADER_SIZES: [[u8; 4]; 6] = [
+    [12, 0, 0, 0],
+    [40, 0, 0, 0],
+    [52, 0, 0, 0],
+    [56, 0, 0, 0],
+    [108, 0, 0, 0],
+    [124, 0, 0, 0],
+];
 
 /// File extension.
 ///
@@ -17,15 +40,191 @@ pub enum Ext {
     Png,
     Jpg,
     Webp,
+    Tiff,
+    Heic,
+    Bmp,
+    Gif,
     Pdf,
+    Docx,
+    Odt,
+    Xlsx,
+    Rtf,
+    Txt,
+    Md,
+    Html,
+    Eml,
 }
 
 impl Ext {
     pub fn is_image(&self) -> bool {
         match self {
-            Ext::Png | Ext::Jpg | Ext::Webp => true,
-            Ext::Pdf => false,
+            Ext::Png | Ext::Jpg | Ext::Webp | Ext::Tiff | Ext::Heic | Ext::Bmp | Ext::Gif => true,
+            Ext::Pdf
+            | Ext::Docx
+            | Ext::Odt
+            | Ext::Xlsx
+            | Ext::Rtf
+            | Ext::Txt
+            | Ext::Md
+            | Ext::Html
+            | Ext::Eml => false,
+        }
+    }
+
+    /// Text of these documents is recognized with OCR, so OCR languages can be requested for them.
+    pub fn is_recognized(&self) -> bool {
+        self.is_image() || matches!(self, Ext::Pdf)
+    }
+
+    /// Images which browsers can display as they are.
+    pub fn is_displayable(&self) -> bool {
+        matches!(self, Ext::Png | Ext::Jpg | Ext::Webp)
+    }
+
+    /// Extensions of the files of this type. The first one is the canonical one.
+    pub fn names(&self) -> &'static [&'static str] {
+        match self {
+            Ext::Png => &["png"],
+            Ext::Jpg => &["jpg", "jpeg"],
+            Ext::Webp => &["webp"],
+            Ext::Tiff => &["tiff", "tif"],
+            Ext::Heic => &["heic", "heif"],
+            Ext::Bmp => &["bmp"],
+            Ext::Gif => &["gif"],
+            Ext::Pdf => &["pdf"],
+            Ext::Docx => &["docx"],
+            Ext::Odt => &["odt"],
+            Ext::Xlsx => &["xlsx"],
+            Ext::Rtf => &["rtf"],
+            Ext::Txt => &["txt"],
+            Ext::Md => &["md", "markdown"],
+            Ext::Html => &["html", "htm"],
+            Ext::Eml => &["eml"],
+        }
+    }
+
+    /// Text formats have no signature, their content is just checked to be text.
+    pub fn is_text(&self) -> bool {
+        matches!(self, Ext::Txt | Ext::Md | Ext::Html | Ext::Eml)
+    }
+
+    /// Detects the type of the document from its content. Documents of text formats are detected
+    /// as [`Ext::Txt`]. Returns `None` when the type is not supported.
+    ///
+    /// Only the first [`SNIFF_LEN`] bytes are needed to detect the type.
+    pub fn sniff(content: &[u8]) -> Option<Self> {
+        let content = &content[..content.len().min(SNIFF_LEN)];
+        let ext = match content {
+            [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n', ..] => Ext::Png,
+            [0xff, 0xd8, 0xff, ..] => Ext::Jpg,
+            [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => Ext::Webp,
+            [b'I', b'I', b'*', 0, ..] | [b'M', b'M', 0, b'*', ..] => Ext::Tiff,
+            [_, _, _, _, b'f', b't', b'y', b'p', brand @ ..] if is_heif_brand(brand) => Ext::Heic,
+            [b'B', b'M', header @ ..] if is_bmp_header(header) => Ext::Bmp,
+            [b'G', b'I', b'F', b'8', b'7' | b'9', b'a', ..] => Ext::Gif,
+            [b'{', b'\\', b'r', b't', b'f', ..] => Ext::Rtf,
+            [b'P', b'K', 3, 4, ..] => return zipped_document(content),
+            _ if is_pdf(content) => Ext::Pdf,
+            _ if is_text(content) => Ext::Txt,
+            _ => return None,
+        };
+        Some(ext)
+    }
+
+    /// Checks if the `content` is of this type.
+    ///
+    /// Text formats have no signature, so text claimed to be of such format is accepted, even
+    /// when it starts like a file of other type.
+    pub fn verify(&self, content: &[u8]) -> Result<(), FileTypeErr> {
+        if self.is_text() && is_text(&content[..content.len().min(SNIFF_LEN)]) {
+            return Ok(());
         }
+        match Self::sniff(content) {
+            Some(detected) if detected == *self || (detected.is_text() && self.is_text()) => Ok(()),
+            Some(detected) => Err(FileTypeErr::Mismatch {
+                claimed: self.clone(),
+                detected,
+            }),
+            None => Err(FileTypeErr::Unrecognized(self.clone())),
+        }
+    }
+}
+
+/// Office documents are ZIP archives, they are recognized by the names of the files inside.
+fn zipped_document(content: &[u8]) -> Option<Ext> {
+    if contains(content, b"mimetypeapplication/vnd.oasis.opendocument.text") {
+        Some(Ext::Odt)
+    } else if contains(content, b"word/") {
+        Some(Ext::Docx)
+    } else if contains(content, b"xl/") {
+        Some(Ext::Xlsx)
+    } else {
+        None
+    }
+}
+
+fn is_heif_brand(brand: &[u8]) -> bool {
+    HEIF_BRANDS.iter().any(|heif| brand.starts_with(heif))
+}
+
+/// The signature of BMP is short, so the size of the header following it is checked as well.
+fn is_bmp_header(header: &[u8]) -> bool {
+    header
+        .get(12..16)
+        .map_or(false, |size| BMP_HEADER_SIZES.iter().any(|s| s == size))
+}
+
+/// Text, in any encoding using single bytes for ASCII, has no control characters except the
+/// whitespace.
+fn is_text(content: &[u8]) -> bool {
+    content
+        .iter()
+        .all(|b| *b >= 0x20 || b"\t\n\r\x0c\x1b".contains(b))
+}
+
+/// PDF readers accept some binary garbage before the header, e.g. left by a scanner. Text which
+/// just mentions the header is not a PDF.
+fn is_pdf(content: &[u8]) -> bool {
+    let head = &content[..content.len().min(1024)];
+    head.windows(PDF_HEADER.len())
+        .position(|window| window == PDF_HEADER)
+        .map_or(false, |at| at == 0 || !is_text(&head[..at]))
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack
+        .windows(needle.len())
+        .any(|window| window == needle)
+}
+
+/// Checks if the `content` is a ZIP archive. Empty archives are accepted as well.
+pub fn verify_archive(content: &[u8]) -> Result<(), FileTypeErr> {
+    if content.starts_with(b"PK\x03\x04") || content.starts_with(b"PK\x05\x06") {
+        Ok(())
+    } else {
+        Err(FileTypeErr::NotArchive)
+    }
+}
+
+/// Checks if the extension, without the dot, is the extension of the archives.
+pub fn is_archive_ext(ext: &str) -> bool {
+    ext.eq_ignore_ascii_case(ARCHIVE_EXT)
+}
+
+impl Display for Ext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.names()[0].to_uppercase())
+    }
+}
+
+impl TryFrom<&str> for Ext {
+    type Error = GeneralErr;
+
+    fn try_from(ext: &str) -> Result<Self, Self::Error> {
+        let ext = ext.to_lowercase();
+        all::<Ext>()
+            .find(|supported| supported.names().contains(&ext.as_str()))
+            .ok_or(GeneralErr::InvalidExtension)
     }
 }
 
@@ -33,26 +232,7 @@ impl TryFrom<String> for Ext {
     type Error = GeneralErr;
 
     fn try_from(ext: String) -> Result<Self, Self::Error> {
-        let ext = ext.as_ref();
-        Ok(match ext {
-            "png" => Self::Png,
-            "jpg" | "jpeg" => Self::Jpg,
-            "webp" => Self::Webp,
-            "pdf" => Self::Pdf,
-            _ => return Err(GeneralErr::InvalidExtension),
-        })
-    }
-}
-
-impl From<&str> for Ext {
-    fn from(ext: &str) -> Self {
-        match ext {
-            "png" => Self::Png,
-            "jpg" | "jpeg" => Self::Jpg,
-            "webp" => Self::Webp,
-            "pdf" => Self::Pdf,
-            _ => panic!("failed to create extension from '{}'", ext),
-        }
+        Self::try_from(ext.as_str())
     }
 }
 
@@ -60,14 +240,43 @@ pub fn supported_extensions() -> Vec<Ext> {
     all::<Ext>().collect()
 }
 
+/// Names of all supported extensions, including the archives, e.g. to tell them the user.
+pub fn supported_names() -> Vec<&'static str> {
+    all::<Ext>()
+        .flat_map(|ext| ext.names().iter().copied())
+        .chain([ARCHIVE_EXT])
+        .collect()
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
 
+    use crate::testingtools::sample_content;
+
+    use claim::{assert_err, assert_ok, assert_ok_eq};
+
     #[test]
     fn supported_extensions_returns_all_extensions_from_enum() {
         // given
-        let all_extensions = vec![Ext::Png, Ext::Jpg, Ext::Webp, Ext::Pdf];
+        let all_extensions = vec![
+            Ext::Png,
+            Ext::Jpg,
+            Ext::Webp,
+            Ext::Tiff,
+            Ext::Heic,
+            Ext::Bmp,
+            Ext::Gif,
+            Ext::Pdf,
+            Ext::Docx,
+            Ext::Odt,
+            Ext::Xlsx,
+            Ext::Rtf,
+            Ext::Txt,
+            Ext::Md,
+            Ext::Html,
+            Ext::Eml,
+        ];
 
         // when
         let supported_extensions = supported_extensions();
@@ -75,4 +284,81 @@ mod test {
         // then
         assert_eq!(all_extensions, supported_extensions);
     }
+
+    #[test]
+    fn content_of_each_type_is_verified() {
+        for ext in supported_extensions() {
+            // given
+            let content = sample_content(&ext);
+
+            // when
+            let res = ext.verify(&content);
+
+            // then
+            assert_ok!(res, "{ext} not verified");
+        }
+    }
+
+    #[test]
+    fn content_of_other_type_is_rejected() {
+        // given
+        let content = sample_content(&Ext::Pdf);
+
+        // when
+        let res = Ext::Jpg.verify(&content);
+
+        // then
+        assert!(matches!(
+            res,
+            Err(FileTypeErr::Mismatch {
+                claimed: Ext::Jpg,
+                detected: Ext::Pdf
+            })
+        ));
+    }
+
+    #[test]
+    fn binary_content_claimed_as_text_is_rejected() {
+        // given
+        let content = sample_content(&Ext::Png);
+
+        // when
+        let res = Ext::Txt.verify(&content);
+
+        // then
+        assert_err!(res);
+    }
+
+    #[test]
+    fn pdf_header_is_recognized_only_at_start_or_after_binary_garbage() {
+        assert_eq!(Ext::sniff(b"%PDF-1.4\n"), Some(Ext::Pdf));
+        assert_eq!(Ext::sniff(b"\0\x01\x02%PDF-1.4\n"), Some(Ext::Pdf));
+        assert_eq!(Ext::sniff(b"notes about %PDF-1.4 headers"), Some(Ext::Txt));
+    }
+
+    #[test]
+    fn text_starting_with_pdf_header_is_verified_as_text() {
+        // given
+        let content = b"%PDF- is the header of PDF files";
+
+        // when
+        let res = Ext::Md.verify(content);
+
+        // then
+        assert_ok!(res);
+    }
+
+    #[test]
+    fn zip_archive_is_verified() {
+        assert_ok!(verify_archive(b"PK\x03\x04 first member"));
+        assert_err!(verify_archive(&sample_content(&Ext::Pdf)));
+    }
+
+    #[test]
+    fn extension_is_parsed_ignoring_case_and_aliases() {
+        assert_ok_eq!(Ext::try_from("PDF"), Ext::Pdf);
+        assert_ok_eq!(Ext::try_from("jpeg"), Ext::Jpg);
+        assert_ok_eq!(Ext::try_from("Tif"), Ext::Tiff);
+        assert_err!(Ext::try_from("exe"));
+    }
 }
@@ -1,12 +1,16 @@
+use crate::entities::extension::{is_archive_ext, verify_archive, Ext};
 use crate::entities::location::SafePathBuf;
-use crate::result::WrongNameErr;
+use crate::result::{FileTypeErr, GeneralErr, WrongNameErr};
 
+use enum_iterator::{all, Sequence};
 use fake::{Dummy, Fake};
-use serde::Deserialize;
-use std::{fmt::Display, path::Path};
+use rocket::FromFormField;
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::{fmt::Display, iter, path::Path};
 use tantivy::schema::Value;
 
-#[derive(Debug, Dummy, Clone, Deserialize, Eq, PartialEq, PartialOrd, Ord)]
+#[derive(Debug, Dummy, Clone, Deserialize, Serialize, Eq, PartialEq, PartialOrd, Ord, Hash)]
 #[serde(transparent)]
 pub struct Filename {
     filename: String,
@@ -22,14 +26,34 @@ impl Filename {
         }
     }
 
+    pub fn ext(&self) -> Result<Ext, GeneralErr> {
+        match Path::new(&self.filename).extension() {
+            Some(ext) => Ext::try_from(ext.to_string_lossy().to_string()),
+            None => Err(GeneralErr::InvalidExtension),
+        }
+    }
+
+    /// Archives are accepted as well, their members are the documents.
     pub fn has_supported_extension(&self) -> bool {
-        let path = Path::new(&self.filename);
-        let Some(extension) = path.extension() else {
-            return false;
-        };
-        match extension.to_str() {
-            Some("png" | "jpg" | "jpeg" | "webp" | "pdf") => true,
-            Some(_) | None => false,
+        self.ext().is_ok() || self.is_archive()
+    }
+
+    pub fn is_archive(&self) -> bool {
+        Path::new(&self.filename)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map_or(false, is_archive_ext)
+    }
+
+    /// Checks if the `content` is of the type claimed by the extension. It's the same check for
+    /// the uploaded documents and the ones found in the watched directory.
+    pub fn verify(&self, content: &[u8]) -> Result<(), FileTypeErr> {
+        if self.is_archive() {
+            return verify_archive(content);
+        }
+        match self.ext() {
+            Ok(ext) => ext.verify(content),
+            Err(_) => Err(FileTypeErr::Unsupported(self.filename.clone())),
         }
     }
 }
@@ -74,6 +98,66 @@ impl Thumbnailname {
             Ok(Self { thumbnail })
         }
     }
+
+    /// Name of the thumbnail of the `doc`, saved as `ext`. The whole filename of the document is
+    /// kept, e.g. `invoice.pdf.webp`, so documents with the same stem have separate thumbnails.
+    pub fn of<F: Into<Filename>>(doc: F, ext: &str) -> Result<Self, WrongNameErr> {
+        Self::new(format!("{}.{}", doc.into(), ext))
+    }
+
+    /// Name of the thumbnail in given `size`. The tile is the thumbnail itself, other sizes are
+    /// stored next to it, e.g. `doc1.preview.webp` for `doc1.webp`.
+    pub fn sized(&self, size: ThumbnailSize) -> Self {
+        match size {
+            ThumbnailSize::Tile => self.clone(),
+            ThumbnailSize::Preview => self.suffixed(".preview"),
+        }
+    }
+
+    /// Name of the thumbnail rendered for screens with given device pixel `ratio`, e.g.
+    /// `doc1@2x.webp` for `doc1.webp`. Ratio of 1 is the thumbnail itself.
+    pub fn at_ratio(&self, ratio: u32) -> Self {
+        if ratio <= 1 {
+            self.clone()
+        } else {
+            self.suffixed(&format!("@{ratio}x"))
+        }
+    }
+
+    /// Names of all the files of the thumbnail, in each size and for each of the pixel `ratios`.
+    pub fn variants(&self, ratios: &[u32]) -> Vec<Self> {
+        all::<ThumbnailSize>()
+            .map(|size| self.sized(size))
+            .flat_map(|sized| {
+                let higher = ratios
+                    .iter()
+                    .filter(|&&ratio| ratio > 1)
+                    .map(|&ratio| sized.at_ratio(ratio))
+                    .collect::<Vec<_>>();
+                iter::once(sized).chain(higher)
+            })
+            .collect()
+    }
+
+    fn suffixed(&self, suffix: &str) -> Self {
+        let path = Path::new(&self.thumbnail);
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        let thumbnail = match path.extension() {
+            Some(ext) => format!("{}{}.{}", stem, suffix, ext.to_string_lossy()),
+            None => format!("{stem}{suffix}"),
+        };
+        Self { thumbnail }
+    }
+}
+
+/// Sizes in which the thumbnails are made, selected with the `size` query parameter.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, FromFormField, Sequence)]
+pub enum ThumbnailSize {
+    /// Shown in the list of documents.
+    #[default]
+    Tile,
+    /// Shown when the document is opened.
+    Preview,
 }
 
 impl From<Thumbnailname> for Value {
@@ -205,4 +289,56 @@ mod test {
         // then
         assert_err!(thumbnailname);
     }
+
+    #[test]
+    fn sized_thumbnailname_is_stored_next_to_tile() -> anyhow::Result<()> {
+        // given
+        let thumbnailname = Thumbnailname::new("doc1.webp")?;
+
+        // when
+        let tile = thumbnailname.sized(ThumbnailSize::Tile);
+        let preview = thumbnailname.sized(ThumbnailSize::Preview);
+
+        // then
+        assert_eq!(tile, thumbnailname);
+        assert_eq!(preview, Thumbnailname::new("doc1.preview.webp")?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn thumbnailname_for_high_density_screens_has_ratio_in_name() -> anyhow::Result<()> {
+        // given
+        let thumbnailname = Thumbnailname::new("doc1.webp")?;
+
+        // when
+        let standard = thumbnailname.at_ratio(1);
+        let double = thumbnailname.at_ratio(2);
+
+        // then
+        assert_eq!(standard, thumbnailname);
+        assert_eq!(double, Thumbnailname::new("doc1@2x.webp")?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn variants_of_thumbnailname_cover_each_size_and_ratio() -> anyhow::Result<()> {
+        // given
+        let thumbnailname = Thumbnailname::new("doc1.webp")?;
+
+        // when
+        let variants = thumbnailname.variants(&[1, 2]);
+
+        // then
+        let expected = vec![
+            Thumbnailname::new("doc1.webp")?,
+            Thumbnailname::new("doc1@2x.webp")?,
+            Thumbnailname::new("doc1.preview.webp")?,
+            Thumbnailname::new("doc1.preview@2x.webp")?,
+        ];
+        assert_eq!(variants, expected);
+
+        Ok(())
+    }
 }
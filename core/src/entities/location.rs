@@ -2,14 +2,17 @@
 //!
 //! This is medium agnostic, so the particular way the documents are read is part of the
 //! implementation.
-use crate::entities::extension::Ext;
-use crate::result::GeneralErr;
+use crate::entities::extension::{is_archive_ext, Ext, SNIFF_LEN};
+use crate::entities::file::Filename;
+use crate::result::{FileTypeErr, GeneralErr};
 
 use base64::engine::general_purpose::STANDARD as b64;
 use base64::Engine;
 use fake::{Dummy, Fake};
 use std::convert::TryFrom;
 use std::fmt::Display;
+use std::fs::File;
+use std::io::Read;
 use std::path::{Path, PathBuf};
 use tracing::instrument;
 
@@ -69,7 +72,7 @@ impl SafePathBuf {
     pub fn ext(&self) -> Result<Ext, GeneralErr> {
         let path = &self.0;
         match path.extension() {
-            Some(ext) => Ok(Ext::try_from(ext.to_str().unwrap())?),
+            Some(ext) => Ext::try_from(ext.to_str().ok_or(GeneralErr::InvalidExtension)?),
             None => Err(GeneralErr::InvalidExtension),
         }
     }
@@ -85,15 +88,26 @@ impl SafePathBuf {
         self.0.is_file()
     }
 
+    /// Archives are accepted as well, their members are the documents.
     pub fn has_valid_ext(&self) -> bool {
-        let path = &self.0;
-        let Some(extension) = path.extension() else {
-            return false;
-        };
-        match extension.to_str() {
-            Some("png" | "jpg" | "jpeg" | "webp" | "pdf") => true,
-            Some(_) | None => false,
-        }
+        self.ext().is_ok() || self.is_archive()
+    }
+
+    pub fn is_archive(&self) -> bool {
+        self.0
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map_or(false, is_archive_ext)
+    }
+
+    /// Checks if the content of the file is of the type claimed by its extension. Only the
+    /// beginning of the file is read.
+    pub fn verify_content(&self) -> Result<(), FileTypeErr> {
+        let mut head = Vec::new();
+        File::open(&self.0)?
+            .take(SNIFF_LEN as u64)
+            .read_to_end(&mut head)?;
+        Filename::from(self).verify(&head)
     }
 
     // TODO: Cover this with tests
@@ -103,11 +117,6 @@ impl SafePathBuf {
         b64.decode(dir_name).is_ok()
     }
 
-    // TODO: Cover this with tests
-    pub fn rel_stem(&self) -> String {
-        format!("{}/{}", self.parent_name(), self.filestem())
-    }
-
     // TODO: Cover this with tests
     pub fn rel_path(&self) -> String {
         format!("{}/{}", self.parent_name(), self.filename())
@@ -184,7 +193,10 @@ mod test {
     #[test]
     fn has_supported_extension_returns_true_for_supported_extensions() {
         // given
-        let supported_extensions = vec!["png", "jpg", "jpeg", "webp", "pdf"];
+        let supported_extensions = vec![
+            "png", "jpg", "jpeg", "webp", "tif", "tiff", "heic", "heif", "bmp", "gif", "pdf",
+            "docx", "odt", "xlsx", "rtf", "txt", "md", "html", "eml", "zip",
+        ];
 
         for test_case in supported_extensions {
             // when
@@ -3,14 +3,23 @@ mod real;
 #[cfg(test)]
 mod test;
 
+use crate::use_cases::auth::AuthFailure;
+use crate::use_cases::users::UserDirectory;
+
 use std::fmt::Display;
 
 use fake::{Dummy, Fake};
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome, Request};
+use serde::{Deserialize, Serialize};
 
 #[cfg(test)]
 pub use test::FAKE_USER_EMAIL;
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Default, Hash, Dummy)]
+#[derive(
+    Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Default, Hash, Dummy, Serialize, Deserialize,
+)]
+#[serde(transparent)]
 pub struct User {
     pub email: String,
 }
@@ -28,3 +37,35 @@ impl Display for User {
         write!(f, "{}", self.email)
     }
 }
+
+/// User authenticated by the [`User`] request guard.
+///
+/// It's kept in the request's local cache, so other guards don't need to authenticate again.
+#[derive(Debug)]
+pub struct Authenticated(pub Option<User>);
+
+/// Authenticated user who is allowed to manage other users.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Admin(pub User);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Admin {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let user = match req.guard::<User>().await {
+            Outcome::Success(user) => user,
+            Outcome::Failure((status, _)) => return Outcome::Failure((status, ())),
+            Outcome::Forward(f) => return Outcome::Forward(f),
+        };
+        match req.rocket().state::<UserDirectory>() {
+            Some(directory) if directory.is_admin(&user) => Outcome::Success(Admin(user)),
+            _ => {
+                req.local_cache(|| {
+                    AuthFailure(Some(format!("User '{user}' is not an administrator.")))
+                });
+                Outcome::Failure((Status::Forbidden, ()))
+            }
+        }
+    }
+}
@@ -1,11 +1,12 @@
 use crate::entities::location::SafePathBuf;
-use crate::entities::user::User;
-use crate::result::UserConvErr;
+use crate::entities::user::{Authenticated, User};
+use crate::result::{AuthErr, RateLimitErr, UserConvErr};
+use crate::use_cases::auth::{Auth, AuthFailure, Credentials};
+use crate::use_cases::throttle::{client_ip, RateLimiter, RetryAfter};
+use crate::use_cases::users::UserDirectory;
 
-use async_once_cell::OnceCell;
 use base64::engine::general_purpose::STANDARD as b64;
 use base64::Engine;
-use jwks_client::keyset::KeyStore;
 use rocket::http::Status;
 use rocket::request::{FromRequest, Outcome, Request};
 use std::convert::TryFrom;
@@ -23,36 +24,53 @@ impl TryFrom<&SafePathBuf> for User {
 
 #[rocket::async_trait]
 impl<'r> FromRequest<'r> for User {
-    type Error = UserConvErr;
+    type Error = AuthErr;
 
     async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
-        let token = req.headers().get("authorization").next();
-        if token.is_none() {
-            return Outcome::Failure((Status::Unauthorized, UserConvErr::MissingToken));
-        }
-        let token = token.unwrap(); // can unwrap, because checked earlier
-        let key_store = key_store().await;
-        match key_store.verify(token) {
-            Ok(jwt) => {
-                if let Some(email) = jwt.payload().get_str("email") {
-                    Outcome::Success(User::new(email))
-                } else {
-                    Outcome::Failure((Status::BadRequest, UserConvErr::InvalidIdToken))
+        match authenticate(req).await {
+            Ok(user) => {
+                req.local_cache(|| Authenticated(Some(user.clone())));
+                Outcome::Success(user)
+            }
+            Err(e) => {
+                req.local_cache(|| AuthFailure(Some(e.to_string())));
+                match &e {
+                    AuthErr::Throttled(throttled) => {
+                        req.local_cache(|| RetryAfter(throttled.retry_after()));
+                    }
+                    e if e.status() == Status::Unauthorized => {
+                        if let Some(limiter) = req.rocket().state::<RateLimiter>() {
+                            limiter.auth_failed(client_ip(req));
+                        }
+                    }
+                    _ => {}
                 }
+                Outcome::Failure((e.status(), e))
             }
-            Err(_) => Outcome::Failure((Status::Unauthorized, UserConvErr::TokenVerification)),
         }
     }
 }
 
-async fn key_store() -> &'static KeyStore {
-    static INSTANCE: OnceCell<KeyStore> = OnceCell::new();
-
-    INSTANCE
-        .get_or_init(async {
-            KeyStore::new_from("https://www.googleapis.com/oauth2/v3/certs".into())
-                .await
-                .expect("failed to create key store")
-        })
-        .await
+async fn authenticate(req: &Request<'_>) -> Result<User, AuthErr> {
+    let header = req
+        .headers()
+        .get_one("authorization")
+        .ok_or(AuthErr::MissingCredentials)?;
+    let limiter = req
+        .rocket()
+        .state::<RateLimiter>()
+        .ok_or(RateLimitErr::NotConfigured)?;
+    limiter.check_auth(client_ip(req))?;
+    let credentials = Credentials::parse(header)?;
+    let auth = req.rocket().state::<Auth>().ok_or(AuthErr::NotConfigured)?;
+    let user = auth
+        .authenticate(&credentials)
+        .await?
+        .ok_or(AuthErr::UnknownCredentials)?;
+    let directory = req
+        .rocket()
+        .state::<UserDirectory>()
+        .ok_or(AuthErr::NotConfigured)?;
+    directory.admit(&user)?;
+    Ok(user)
 }
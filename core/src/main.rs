@@ -2,6 +2,7 @@
 
 use crate::configuration::factories::{config_loader, config_resolver, Runtime};
 use crate::configuration::telemetry::init_tracing;
+use crate::data_providers::tls;
 use crate::result::SetupErr;
 use crate::startup::rocket;
 
@@ -23,6 +24,10 @@ mod testingtools;
 async fn main() -> Result<(), SetupErr> {
     init_tracing();
     let cfg = config_resolver(config_loader()).handle_config(path_override())?;
+    cfg.check_auth()?;
+    if let Some(tls) = tls::prepare(&cfg)? {
+        println!("TLS certificate SHA-256 fingerprint: {}", tls.fingerprint);
+    }
     let _rocket = rocket(Runtime::new(cfg)?).launch().await?;
 
     Ok(())
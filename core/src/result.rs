@@ -1,3 +1,4 @@
+use crate::entities::extension::Ext;
 use crate::entities::user::User;
 
 use rocket::{http::Status, response::Responder};
@@ -29,6 +30,9 @@ pub enum ThumbnailReadErr {
 
     #[error("Incorrect file name.")]
     WrongName(#[from] WrongNameErr),
+
+    #[error("Thumbnail is not accessible by the user.")]
+    NoAccess,
 }
 
 #[derive(Debug, Error)]
@@ -43,7 +47,9 @@ pub enum WrongNameErr {
 impl<'r, 'o: 'r> Responder<'r, 'o> for ThumbnailReadErr {
     fn respond_to(self, _request: &'r rocket::Request<'_>) -> rocket::response::Result<'o> {
         Err(match self {
+            // NOTE: not found, so it's not revealed that other user has such file
             Self::Load(FsErr::Io(e)) if e.kind() == NotFound => Status::NotFound,
+            Self::NoAccess => Status::NotFound,
             Self::Unexpected(_) | Self::Load(_) => Status::InternalServerError,
             Self::WrongName(_) => Status::UnprocessableEntity,
         })
@@ -72,18 +78,49 @@ pub enum DocumentReadErr {
 
     #[error("Incorrect file name.")]
     WrongFilename(#[from] WrongNameErr),
+
+    #[error("Document is not accessible by the user.")]
+    NoAccess,
+
+    #[error(transparent)]
+    Page(#[from] PageErr),
 }
 
 impl<'r, 'o: 'r> Responder<'r, 'o> for DocumentReadErr {
     fn respond_to(self, _request: &'r rocket::Request<'_>) -> rocket::response::Result<'o> {
         Err(match self {
             Self::Load(FsErr::Io(e)) if e.kind() == NotFound => Status::NotFound,
-            Self::Unexpected(_) | Self::Load(_) => Status::InternalServerError,
+            Self::NoAccess | Self::Page(PageErr::NotFound(_)) => Status::NotFound,
+            Self::Unexpected(_) | Self::Load(_) | Self::Page(_) => Status::InternalServerError,
             Self::WrongFilename(_) => Status::UnprocessableEntity,
         })
     }
 }
 
+#[derive(Debug, Error)]
+pub enum PageErr {
+    #[error("Page {0} does not exist.")]
+    NotFound(u32),
+
+    #[error("Failed to load PDF document: '{0}'.")]
+    Pdf(#[from] cairo::glib::error::Error),
+
+    #[error("Failed to render the page: '{0}'.")]
+    Rendering(#[from] cairo::Error),
+
+    #[error("Failed to write the page: '{0}'.")]
+    Writing(#[from] cairo::IoError),
+
+    #[error("Failed to convert the image: '{0}'.")]
+    Image(#[from] image::ImageError),
+
+    #[error("Failed to read text of the document: '{0}'.")]
+    Text(#[from] ExtractorErr),
+
+    #[error("Failed to decode the image: '{0}'.")]
+    Decoding(#[from] DecodingErr),
+}
+
 #[derive(Debug, Error)]
 pub enum CipherErr {
     #[error("Failed to decrypt.")]
@@ -92,13 +129,80 @@ pub enum CipherErr {
 
 #[derive(Debug, Error)]
 pub enum DocumentSaveErr {
+    #[error(transparent)]
+    Quota(#[from] QuotaErr),
+
+    #[error(transparent)]
+    FileType(#[from] FileTypeErr),
+
     #[error(transparent)]
     Unexpected(#[from] anyhow::Error),
 }
 
 impl<'r, 'o: 'r> Responder<'r, 'o> for DocumentSaveErr {
+    fn respond_to(self, request: &'r rocket::Request<'_>) -> rocket::response::Result<'o> {
+        match self {
+            Self::Quota(e) => e.respond_to(request),
+            Self::FileType(e) => e.respond_to(request),
+            Self::Unexpected(_) => Err(Status::new(500)),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum FileTypeErr {
+    #[error("Content of the file looks like {detected}, not {claimed}.")]
+    Mismatch { claimed: Ext, detected: Ext },
+
+    #[error("Content of the file is not recognized as {0}.")]
+    Unrecognized(Ext),
+
+    #[error("Content of the file is not recognized as ZIP.")]
+    NotArchive,
+
+    #[error("File '{0}' has unsupported extension.")]
+    Unsupported(String),
+
+    #[error("Failed to read the file: '{0}'.")]
+    Io(#[from] std::io::Error),
+}
+
+/// The reason is returned to the client, so the user knows why the document was rejected.
+impl<'r, 'o: 'r> Responder<'r, 'o> for FileTypeErr {
+    fn respond_to(self, request: &'r rocket::Request<'_>) -> rocket::response::Result<'o> {
+        (Status::UnsupportedMediaType, self.to_string()).respond_to(request)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum QuotaErr {
+    #[error("Failed to make IO operation: '{0}'.")]
+    Io(#[from] std::io::Error),
+
+    #[error("Document is larger than the limit of {0} bytes.")]
+    DocumentTooLarge(u64),
+
+    #[error("Limit of {0} documents reached.")]
+    DocsLimitReached(u64),
+
+    #[error("Limit of {0} bytes of storage reached.")]
+    BytesLimitReached(u64),
+}
+
+impl QuotaErr {
+    /// Tells if the error is caused by exceeding the quota, and not by failure to check it.
+    pub fn is_exceeded(&self) -> bool {
+        !matches!(self, Self::Io(_))
+    }
+}
+
+impl<'r, 'o: 'r> Responder<'r, 'o> for QuotaErr {
     fn respond_to(self, _request: &'r rocket::Request<'_>) -> rocket::response::Result<'o> {
-        Err(Status::new(500))
+        Err(match self {
+            Self::DocumentTooLarge(_) => Status::PayloadTooLarge,
+            Self::DocsLimitReached(_) | Self::BytesLimitReached(_) => Status::InsufficientStorage,
+            Self::Io(_) => Status::InternalServerError,
+        })
     }
 }
 
@@ -120,10 +224,210 @@ impl<'r, 'o: 'r> Responder<'r, 'o> for SearchErr {
     }
 }
 
+#[derive(Debug, Error)]
+pub enum SharingErr {
+    #[error("Failed to make IO operation: '{0}'.")]
+    Io(#[from] std::io::Error),
+
+    #[error("Failed to read or write shares: '{0}'.")]
+    Serialization(#[from] serde_json::Error),
+
+    #[error("Document '{0}' not found.")]
+    DocumentNotFound(String),
+
+    #[error("Document can't be shared with its owner.")]
+    SharedWithOwner,
+
+    #[error("Failed to search shared documents.")]
+    Search(#[from] SearchErr),
+}
+
+impl<'r, 'o: 'r> Responder<'r, 'o> for SharingErr {
+    fn respond_to(self, _request: &'r rocket::Request<'_>) -> rocket::response::Result<'o> {
+        Err(match self {
+            Self::DocumentNotFound(_) => Status::NotFound,
+            Self::SharedWithOwner => Status::UnprocessableEntity,
+            Self::Io(_) | Self::Serialization(_) | Self::Search(_) => Status::InternalServerError,
+        })
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum LinkErr {
+    #[error("Failed to make IO operation: '{0}'.")]
+    Io(#[from] std::io::Error),
+
+    #[error("Failed to read or write links: '{0}'.")]
+    Serialization(#[from] serde_json::Error),
+
+    #[error("Link not found or its signature is invalid.")]
+    NotFound,
+
+    #[error("Link has expired.")]
+    Expired,
+
+    #[error("Single-use link was already used.")]
+    AlreadyUsed,
+
+    #[error("Link was revoked.")]
+    Revoked,
+
+    #[error("Document '{0}' not found.")]
+    DocumentNotFound(String),
+
+    #[error("Link can't be valid longer than {0} seconds.")]
+    TtlTooLong(u64),
+
+    #[error("Owner of the link can't use the server: '{0}'.")]
+    OwnerInactive(#[from] UsersErr),
+
+    #[error("Failed to load document.")]
+    Load(#[from] FsErr),
+
+    #[error(transparent)]
+    Unexpected(#[from] anyhow::Error),
+}
+
+impl<'r, 'o: 'r> Responder<'r, 'o> for LinkErr {
+    fn respond_to(self, _request: &'r rocket::Request<'_>) -> rocket::response::Result<'o> {
+        Err(match self {
+            Self::NotFound | Self::DocumentNotFound(_) => Status::NotFound,
+            Self::Load(FsErr::Io(e)) if e.kind() == NotFound => Status::NotFound,
+            Self::Expired | Self::AlreadyUsed | Self::Revoked => Status::Gone,
+            Self::OwnerInactive(e) if e.is_rejection() => Status::Gone,
+            Self::TtlTooLong(_) => Status::UnprocessableEntity,
+            Self::Io(_)
+            | Self::Serialization(_)
+            | Self::Load(_)
+            | Self::OwnerInactive(_)
+            | Self::Unexpected(_) => Status::InternalServerError,
+        })
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum AuditErr {
+    #[error("Failed to make IO operation: '{0}'.")]
+    Io(#[from] std::io::Error),
+
+    #[error("Failed to read or write audit records: '{0}'.")]
+    Serialization(#[from] serde_json::Error),
+
+    #[error("Audit record {0} was modified or removed.")]
+    Tampered(u64),
+
+    #[error("Audit record {0} was not fully written and it was cut off.")]
+    Torn(u64),
+}
+
+impl<'r, 'o: 'r> Responder<'r, 'o> for AuditErr {
+    fn respond_to(self, _request: &'r rocket::Request<'_>) -> rocket::response::Result<'o> {
+        Err(Status::InternalServerError)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum RateLimitErr {
+    #[error("Too many requests, retry after {0} seconds.")]
+    TooManyRequests(u64),
+
+    #[error("Too many failed authentications, retry after {0} seconds.")]
+    TooManyAuthFailures(u64),
+
+    #[error("Rate limits are not configured.")]
+    NotConfigured,
+}
+
+impl RateLimitErr {
+    /// Seconds after which the request can be sent again.
+    pub fn retry_after(&self) -> Option<u64> {
+        match self {
+            Self::TooManyRequests(secs) | Self::TooManyAuthFailures(secs) => Some(*secs),
+            Self::NotConfigured => None,
+        }
+    }
+
+    pub fn status(&self) -> Status {
+        match self {
+            Self::TooManyRequests(_) | Self::TooManyAuthFailures(_) => Status::TooManyRequests,
+            Self::NotConfigured => Status::InternalServerError,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum UsersErr {
+    #[error("Failed to make IO operation: '{0}'.")]
+    Io(#[from] std::io::Error),
+
+    #[error("Failed to read or write accounts: '{0}'.")]
+    Serialization(#[from] serde_json::Error),
+
+    #[error("Account of '{0}' waits for the approval of an administrator.")]
+    NotApproved(String),
+
+    #[error("Account of '{0}' was denied.")]
+    Denied(String),
+
+    #[error("Account of '{0}' is disabled.")]
+    Disabled(String),
+
+    #[error("Administrator '{0}' can't be managed, change the configuration instead.")]
+    Admin(String),
+
+    #[error("User '{0}' not found.")]
+    NotFound(String),
+
+    #[error("Failed to remove index of the user.")]
+    Index(#[from] IndexerErr),
+
+    #[error("Failed to remove files of the user.")]
+    Fs(#[from] FsErr),
+
+    #[error("Failed to remove shares of the user.")]
+    Sharing(#[from] SharingErr),
+
+    #[error("Failed to revoke links of the user.")]
+    Link(#[from] LinkErr),
+}
+
+impl UsersErr {
+    /// Tells if the account of the user doesn't allow to use the server.
+    pub fn is_rejection(&self) -> bool {
+        matches!(
+            self,
+            Self::NotApproved(_) | Self::Denied(_) | Self::Disabled(_)
+        )
+    }
+}
+
+impl<'r, 'o: 'r> Responder<'r, 'o> for UsersErr {
+    fn respond_to(self, _request: &'r rocket::Request<'_>) -> rocket::response::Result<'o> {
+        Err(match self {
+            Self::NotFound(_) => Status::NotFound,
+            Self::Admin(_) => Status::UnprocessableEntity,
+            e if e.is_rejection() => Status::Forbidden,
+            _ => Status::InternalServerError,
+        })
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum ExtractorErr {
-    #[error("Error when converting to utf8.")]
-    PdfExtraction(#[from] pdf_extract::OutputError),
+    #[error("Failed to load PDF document.")]
+    Pdf(#[from] cairo::glib::error::Error),
+
+    #[error("Failed to render PDF page.")]
+    PageRendering(#[from] cairo::Error),
+
+    #[error("Failed to write rendered PDF page.")]
+    PageWriting(#[from] cairo::IoError),
+
+    #[error("Failed to preprocess the image.")]
+    Preprocessing(#[from] image::ImageError),
+
+    #[error("Failed to make IO operation.")]
+    Io(#[from] std::io::Error),
 
     #[error("Error when converting to User.")]
     UserConversion(#[from] UserConvErr),
@@ -140,6 +444,12 @@ pub enum ExtractorErr {
     #[error("Error when setting the image.")]
     SettingImage(#[from] leptess::leptonica::PixError),
 
+    #[error("Error when setting Tesseract variable.")]
+    SettingVariable(#[from] leptess::tesseract::TessSetVariableError),
+
+    #[error("Error when reading languages requested for the document.")]
+    Ocr(#[from] OcrErr),
+
     #[error("Error when converting to utf8.")]
     Utf8(#[from] std::str::Utf8Error),
 
@@ -148,6 +458,91 @@ pub enum ExtractorErr {
 
     #[error("Invalid file extension")]
     InvalidExtension(#[from] GeneralErr),
+
+    #[error("Failed to read the document archive.")]
+    Archive(#[from] zip::result::ZipError),
+
+    #[error("Failed to parse XML of the document.")]
+    Xml(#[from] quick_xml::Error),
+
+    #[error("Failed to read the spreadsheet.")]
+    Spreadsheet(#[from] calamine::XlsxError),
+
+    #[error("Part '{0}' of the document is too large or suspiciously well compressed.")]
+    TooLarge(String),
+
+    #[error("Failed to parse the email.")]
+    Email,
+
+    #[error("Failed to decode the image.")]
+    Decoding(#[from] DecodingErr),
+}
+
+#[derive(Debug, Error)]
+pub enum DecodingErr {
+    #[error("Failed to decode TIFF: '{0}'.")]
+    Tiff(#[from] tiff::TiffError),
+
+    #[error("Failed to decode HEIC: '{0}'.")]
+    Heic(#[from] libheif_rs::HeifError),
+
+    #[error("Failed to decode the image: '{0}'.")]
+    Image(#[from] image::ImageError),
+
+    #[error("Colors of the image are not supported: '{0}'.")]
+    UnsupportedColor(String),
+
+    #[error("Failed to decode TIFF with leptonica.")]
+    Leptonica,
+}
+
+#[derive(Debug, Error)]
+pub enum OcrErr {
+    #[error("Failed to make IO operation: '{0}'.")]
+    Io(#[from] std::io::Error),
+
+    #[error("Failed to read or write OCR languages: '{0}'.")]
+    Serialization(#[from] serde_json::Error),
+
+    #[error("Invalid OCR languages: '{0}'. Expected Tesseract language codes, e.g. 'pol+eng'.")]
+    InvalidLanguages(String),
+}
+
+#[derive(Debug, Error)]
+pub enum ScannerErr {
+    #[error("Failed to make IO operation: '{0}'.")]
+    Io(#[from] std::io::Error),
+
+    #[error("Failed to read or write the photo: '{0}'.")]
+    Image(#[from] image::ImageError),
+}
+
+/// The reason is recorded in the audit log, when the archive is rejected.
+#[derive(Debug, Error)]
+pub enum UnpackerErr {
+    #[error("Failed to make IO operation: '{0}'.")]
+    Io(#[from] std::io::Error),
+
+    #[error("Failed to read ZIP archive: '{0}'.")]
+    Zip(#[from] zip::result::ZipError),
+
+    #[error("Failed to parse the email.")]
+    Email,
+
+    #[error("Archive contains more than {0} documents.")]
+    TooManyMembers(u32),
+
+    #[error("Document '{0}' in the archive is too large.")]
+    MemberTooLarge(String),
+
+    #[error("Archive unpacks to more than {0} bytes.")]
+    ArchiveTooLarge(u64),
+
+    #[error("Document '{0}' in the archive is suspiciously well compressed.")]
+    Bomb(String),
+
+    #[error("Documents in the archive don't fit in the quota of the user.")]
+    QuotaExceeded,
 }
 
 #[derive(Debug, Error)]
@@ -175,6 +570,21 @@ pub enum ThumbnailerErr {
 
     #[error("Failed to make filesystem operation")]
     Fs(#[from] FsErr),
+
+    #[error("Failed to read text of the document.")]
+    Text(#[from] ExtractorErr),
+
+    #[error("Failed to decode the image.")]
+    Decoding(#[from] DecodingErr),
+
+    #[error("Failed to convert the image.")]
+    Image(#[from] image::ImageError),
+
+    #[error("Image has no pages.")]
+    NoPages,
+
+    #[error("Failed to create thumbnail name.")]
+    WrongName(#[from] WrongNameErr),
 }
 
 #[derive(Debug, Error)]
@@ -202,6 +612,18 @@ pub enum MoverErr {
 
     #[error("Failed to make filesystem operation: '{0}'.")]
     Fs(#[from] FsErr),
+
+    #[error("Error when converting to User.")]
+    UserConversion(#[from] UserConvErr),
+
+    #[error("Failed to check quota.")]
+    Quota(#[from] QuotaErr),
+
+    #[error("Failed to check account of the user.")]
+    Users(#[from] UsersErr),
+
+    #[error("Failed to unpack documents: '{0}'.")]
+    Unpacker(#[from] UnpackerErr),
 }
 
 #[derive(Debug, Error)]
@@ -211,18 +633,111 @@ pub enum UserConvErr {
 
     #[error("Invalid utf characters.")]
     Utf8(#[from] std::string::FromUtf8Error),
+}
 
-    #[cfg(not(test))]
+#[derive(Debug, Error)]
+pub enum AuthErr {
     #[error("No authorization header found.")]
-    MissingToken,
+    MissingCredentials,
+
+    #[error("Credentials are not in expected format.")]
+    MalformedCredentials,
+
+    #[error("Failed to decode credentials from base64.")]
+    Decode(#[from] base64::DecodeError),
+
+    #[error("Invalid utf characters in credentials.")]
+    Utf8(#[from] std::string::FromUtf8Error),
+
+    #[error("Credentials are not accepted by any of the configured providers.")]
+    UnknownCredentials,
+
+    #[error("Token has expired.")]
+    TokenExpired,
+
+    #[error("Token is not valid yet.")]
+    TokenNotYetValid,
+
+    #[error("Token was issued for other application.")]
+    InvalidAudience,
+
+    #[error("Token was issued by unexpected issuer.")]
+    InvalidIssuer,
+
+    #[error("Token signature is invalid.")]
+    InvalidSignature,
+
+    #[error("Token could not be verified: '{0}'.")]
+    InvalidToken(jsonwebtoken::errors::Error),
+
+    #[error("Token is signed with unknown key '{0}'.")]
+    UnknownKey(String),
+
+    #[error("Token is signed with not allowed algorithm '{0}'.")]
+    AlgorithmNotAllowed(String),
+
+    #[error("Missing 'email' claim in the token.")]
+    MissingEmail,
 
-    #[cfg(not(test))]
-    #[error("Missing 'email' field in IdToken.")]
-    InvalidIdToken,
+    #[error("Email domain of '{0}' is not allowed.")]
+    DomainNotAllowed(String),
 
-    #[cfg(not(test))]
-    #[error("Token could not be verified by identity provider.")]
-    TokenVerification,
+    /// Returned both for unknown users and wrong passwords, so the existing users can't be
+    /// discovered.
+    #[error("Invalid username or password.")]
+    InvalidCredentials,
+
+    #[error("Invalid password hash in the configuration.")]
+    PasswordHash(argon2::password_hash::Error),
+
+    #[error("Failed to fetch keys of identity provider.")]
+    KeysFetch(#[from] reqwest::Error),
+
+    #[error("Authentication is not configured.")]
+    NotConfigured,
+
+    #[error(transparent)]
+    Account(#[from] UsersErr),
+
+    #[error(transparent)]
+    Throttled(#[from] RateLimitErr),
+}
+
+impl AuthErr {
+    /// Status returned to the client when the authentication fails.
+    pub fn status(&self) -> Status {
+        match self {
+            Self::DomainNotAllowed(_) => Status::Forbidden,
+            Self::PasswordHash(_) | Self::NotConfigured => Status::InternalServerError,
+            Self::KeysFetch(_) => Status::ServiceUnavailable,
+            Self::Account(e) if e.is_rejection() => Status::Forbidden,
+            Self::Account(_) => Status::InternalServerError,
+            Self::Throttled(e) => e.status(),
+            _ => Status::Unauthorized,
+        }
+    }
+}
+
+impl From<jsonwebtoken::errors::Error> for AuthErr {
+    fn from(e: jsonwebtoken::errors::Error) -> Self {
+        use jsonwebtoken::errors::ErrorKind;
+        match e.kind() {
+            ErrorKind::ExpiredSignature => Self::TokenExpired,
+            ErrorKind::ImmatureSignature => Self::TokenNotYetValid,
+            ErrorKind::InvalidAudience => Self::InvalidAudience,
+            ErrorKind::InvalidIssuer => Self::InvalidIssuer,
+            ErrorKind::InvalidSignature => Self::InvalidSignature,
+            _ => Self::InvalidToken(e),
+        }
+    }
+}
+
+// NOTE: `argon2::password_hash::Error` doesn't implement `std::error::Error` without `std`
+// feature, so it can't be used with `#[from]`
+impl From<argon2::password_hash::Error> for AuthErr {
+    fn from(e: argon2::password_hash::Error) -> Self {
+        Self::PasswordHash(e)
+    }
 }
 
 #[derive(Debug, Error)]
@@ -255,6 +770,51 @@ pub enum EncrypterErr {
     AllOrNothing,
 }
 
+#[derive(Debug, Error)]
+pub enum CollectorErr {
+    #[error("Error when using bus.")]
+    Bus(#[from] BusErr),
+}
+
+#[derive(Debug, Error)]
+pub enum AuditorErr {
+    #[error("Error when using bus.")]
+    Bus(#[from] BusErr),
+}
+
+#[derive(Debug, Error)]
+pub enum ReconcilerErr {
+    #[error("Error when using bus.")]
+    Bus(#[from] BusErr),
+
+    #[error("Failed to make IO operation.")]
+    Io(#[from] std::io::Error),
+
+    #[error("Failed to read index.")]
+    Search(#[from] SearchErr),
+
+    #[error("Failed to make filesystem operation: '{0}'.")]
+    Fs(#[from] FsErr),
+
+    #[error("Failed to decrypt document.")]
+    Cipher(#[from] CipherErr),
+}
+
+#[derive(Debug, Error)]
+pub enum MetricsErr {
+    #[error("Failed to collect metrics.")]
+    Prometheus(#[from] prometheus::Error),
+
+    #[error("Invalid utf characters.")]
+    Utf8(#[from] std::string::FromUtf8Error),
+}
+
+impl<'r, 'o: 'r> Responder<'r, 'o> for MetricsErr {
+    fn respond_to(self, _request: &'r rocket::Request<'_>) -> rocket::response::Result<'o> {
+        Err(Status::InternalServerError)
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum EventReceiverErr {
     #[error("Failed to create watcher.")]
@@ -274,6 +834,12 @@ pub enum StateErr {
 
     #[error("Failed to make IO operation.")]
     Io(#[from] std::io::Error),
+
+    #[error("Failed to read index directory.")]
+    OpenIndexDirectory(#[from] tantivy::directory::error::OpenDirectoryError),
+
+    #[error("Failed to open index.")]
+    OpenIndex(#[from] tantivy::TantivyError),
 }
 
 #[derive(Debug, Error)]
@@ -325,6 +891,24 @@ pub enum ConfigurationErr {
 
     #[error("Invalid config path: '{0}'")]
     InvalidConfigPath(String),
+
+    #[error("Audience of the tokens issued by '{0}' is not configured, set 'audience' of this provider in the 'auth' section to the client id of the application.")]
+    MissingAudience(String),
+}
+
+#[derive(Debug, Error)]
+pub enum TlsErr {
+    #[error("Failed to make IO operation: '{0}'.")]
+    Io(#[from] std::io::Error),
+
+    #[error("Failed to generate self-signed certificate.")]
+    Generation(#[from] rcgen::RcgenError),
+
+    #[error("TLS file '{0}' is missing and generating self-signed certificate is disabled.")]
+    Missing(String),
+
+    #[error("File '{0}' doesn't contain PEM encoded certificate.")]
+    InvalidCertificate(String),
 }
 
 #[derive(Debug, Error)]
@@ -362,9 +946,30 @@ pub enum SetupErr {
     #[error("Failed to run indexer.")]
     Indexer(#[from] IndexerErr),
 
-    #[error("Failed to get configuration.")]
+    #[error("Failed to setup metrics.")]
+    Metrics(#[from] MetricsErr),
+
+    #[error("Failed to load shares.")]
+    Sharing(#[from] SharingErr),
+
+    #[error("Failed to load links.")]
+    Link(#[from] LinkErr),
+
+    #[error("Failed to open audit log.")]
+    Audit(#[from] AuditErr),
+
+    #[error("Failed to load accounts.")]
+    Users(#[from] UsersErr),
+
+    #[error("Failed to load OCR languages.")]
+    Ocr(#[from] OcrErr),
+
+    #[error("Failed to get configuration: '{0}'.")]
     Configuration(#[from] ConfigurationErr),
 
+    #[error("Failed to prepare TLS certificate.")]
+    Tls(#[from] TlsErr),
+
     #[error("Failed to launch Rocket.")]
     Rocket(#[from] rocket::Error),
 }
@@ -2,30 +2,70 @@
 
 use crate::configuration::factories::Runtime;
 use crate::data_providers::server::{
-    all_thumbnails, document, receive_document, search, thumbnail,
+    all_thumbnails, audit, create_link, document, document_page, forbidden, health, links, metrics,
+    open_link, ready, receive_document, remove_user, revoke_link, search, set_user_status, share,
+    shares, thumbnail, too_many_requests, unauthorized, unshare, usage, users,
 };
 use crate::result::SetupErr;
+use crate::use_cases::audit::AuditTrail;
 use crate::use_cases::cipher::CipherReader;
+use crate::use_cases::config::Config;
+use crate::use_cases::health::HealthCheck;
+use crate::use_cases::links::PublicLinks;
+use crate::use_cases::quota::Quotas;
+use crate::use_cases::services::auditor::Auditor;
+use crate::use_cases::services::collector::MetricsCollector;
 use crate::use_cases::services::encrypter::Encrypter;
-use crate::use_cases::services::extractor::TxtExtractor;
+use crate::use_cases::services::extractor::{TimedExtractorFactory, TxtExtractor};
 use crate::use_cases::services::indexer::Indexer;
 use crate::use_cases::services::mover::DocumentMover;
+use crate::use_cases::services::reconciler::Reconciler;
 use crate::use_cases::services::thumbnailer::ThumbnailGenerator;
 use crate::use_cases::services::watcher::FileWatcher;
+use crate::use_cases::sharing::{DocAccess, ShareRevoker};
 use crate::use_cases::state::StateReader;
+use crate::use_cases::supervisor::Supervisor;
+use crate::use_cases::throttle::RateLimiter;
+use crate::use_cases::users::{UserDirectory, UserRemover};
 
-use rocket::{routes, Build, Rocket};
-use tracing::{debug, instrument};
+use rocket::fairing::AdHoc;
+use rocket::figment::Figment;
+use rocket::tokio::task::spawn_blocking;
+use rocket::{catchers, routes, Build, Rocket};
+use std::sync::Arc;
+use tracing::{debug, error, instrument};
 
 #[must_use]
 #[instrument(skip(ctx))]
 pub fn rocket(ctx: Runtime) -> Rocket<Build> {
     let fs = ctx.fs.clone();
     let cfg = ctx.cfg.clone();
-    let (state_reader, cipher_reader) = setup_core(ctx).expect("failed to setup core");
+    let recorder = ctx.metrics.clone();
+    let auth = ctx.auth.clone();
+    let sharing = ctx.sharing.clone();
+    let public_links = PublicLinks::new(ctx.links.clone(), ctx.link_signer.clone(), &ctx.cfg);
+    let trail = AuditTrail::new(ctx.audit.clone());
+    let quotas = Quotas::new(ctx.meter.clone(), &ctx.cfg);
+    let figment = figment(&ctx.cfg);
+    let limiter = RateLimiter::new(&ctx.cfg);
+    let ocr_overrides = ctx.ocr_overrides.clone();
+    let pages = ctx.pages.clone();
+    let directory = UserDirectory::new(ctx.accounts.clone(), &ctx.cfg);
+    let remover = UserRemover::new(
+        directory.clone(),
+        ctx.cfg.clone(),
+        ctx.fs.clone(),
+        ctx.state.writer(),
+        sharing.clone(),
+        ctx.links.clone(),
+    );
+    let (state_reader, cipher_reader, supervisor, health_check) =
+        setup_core(ctx).expect("failed to setup core");
+    let access = DocAccess::new(sharing.clone(), state_reader.clone());
 
     debug!("starting server...");
     rocket::build()
+        .configure(figment)
         .mount(
             "/",
             routes![
@@ -33,16 +73,81 @@ pub fn rocket(ctx: Runtime) -> Rocket<Build> {
                 thumbnail,
                 all_thumbnails,
                 document,
-                receive_document
+                document_page,
+                receive_document,
+                share,
+                unshare,
+                shares,
+                create_link,
+                links,
+                revoke_link,
+                open_link,
+                audit,
+                usage,
+                users,
+                set_user_status,
+                remove_user,
+                health,
+                ready,
+                metrics
             ],
         )
+        .register("/", catchers![unauthorized, forbidden, too_many_requests])
         .manage(state_reader)
         .manage(cipher_reader)
         .manage(fs)
         .manage(cfg)
+        .manage(supervisor)
+        .manage(health_check)
+        .manage(recorder)
+        .manage(auth)
+        .manage(sharing)
+        .manage(access)
+        .manage(public_links)
+        .manage(trail)
+        .manage(quotas)
+        .manage(directory)
+        .manage(remover)
+        .manage(limiter)
+        .manage(ocr_overrides)
+        .manage(pages)
+        .attach(AdHoc::on_shutdown("Services shutdown", |rocket| {
+            Box::pin(async move {
+                let Some(supervisor) = rocket.state::<Arc<Supervisor>>().cloned() else {
+                    return;
+                };
+                if let Err(e) = spawn_blocking(move || supervisor.shutdown()).await {
+                    error!("failed to shutdown services: '{}'", e);
+                }
+            })
+        }))
 }
 
-fn setup_core(ctx: Runtime) -> Result<(StateReader, CipherReader), SetupErr> {
+/// Rocket settings coming from the [`Config`]. The certificate has to be prepared before the
+/// launch, see [`crate::data_providers::tls::prepare`].
+fn figment(cfg: &Config) -> Figment {
+    let server = &cfg.server;
+    let figment = rocket::Config::figment()
+        .merge(("address", server.address))
+        .merge(("port", server.port))
+        .merge(("limits.json", json_limit(cfg)));
+    if !server.tls.enabled {
+        return figment;
+    }
+    let (cert, key) = cfg.tls_paths();
+    figment.merge(("tls.certs", cert)).merge(("tls.key", key))
+}
+
+/// Uploaded documents are encoded with base64 and sent inside of JSON, so the JSON limit needs to
+/// be larger than the limit of uploaded document. Quota check gives more meaningful error then.
+fn json_limit(cfg: &Config) -> u64 {
+    const JSON_OVERHEAD: u64 = 64 * 1024;
+    (cfg.quota.max_upload_bytes + 2) / 3 * 4 + JSON_OVERHEAD
+}
+
+type Core = (StateReader, CipherReader, Arc<Supervisor>, HealthCheck);
+
+fn setup_core(ctx: Runtime) -> Result<Core, SetupErr> {
     let Runtime {
         cfg,
         bus,
@@ -52,21 +157,52 @@ fn setup_core(ctx: Runtime) -> Result<(StateReader, CipherReader), SetupErr> {
         extractor_factory,
         state,
         cipher,
+        metrics,
+        audit,
+        meter,
+        scanner,
+        unpacker,
+        ocr_overrides,
+        accounts,
+        sharing,
+        links,
+        ..
     } = ctx;
 
     let watcher = FileWatcher::new(bus.clone());
     let document_mover = DocumentMover::new(cfg.clone(), bus.clone())?;
-    let thumbnail_generator = ThumbnailGenerator::new(cfg, bus.clone())?;
+    let thumbnail_generator = ThumbnailGenerator::new(cfg.clone(), bus.clone())?;
     let extractor = TxtExtractor::new(bus.clone())?;
     let indexer = Indexer::new(bus.clone())?;
-    let encrypter = Encrypter::new(bus);
+    let encrypter = Encrypter::new(bus.clone());
+    let collector = MetricsCollector::new(bus.clone());
+    let auditor = Auditor::new(bus.clone());
+    let reconciler = Reconciler::new(cfg.clone(), bus.clone());
+    let supervisor = Arc::new(Supervisor::new(bus));
+
+    supervisor.supervise_detached("watcher", watcher.run(event_watcher, fs.clone()));
+    let quotas = Quotas::new(meter, &cfg);
+    let directory = UserDirectory::new(accounts, &cfg);
+    let revoker = ShareRevoker::new(sharing, links);
+    supervisor.supervise(
+        "mover",
+        document_mover.run(fs.clone(), quotas, directory, revoker, scanner, unpacker),
+    );
+    supervisor.supervise(
+        "thumbnailer",
+        thumbnail_generator.run(thumbnailer_factory, fs.clone()),
+    );
+    let extractor_factory = TimedExtractorFactory::wrap(extractor_factory, metrics.clone());
+    supervisor.supervise("extractor", extractor.run(extractor_factory, ocr_overrides));
+    supervisor.supervise("indexer", indexer.run(state.writer()));
+    supervisor.supervise("encrypter", encrypter.run(cipher.writer()));
+    supervisor.supervise("collector", collector.run(metrics));
+    supervisor.supervise("auditor", auditor.run(AuditTrail::new(audit)));
+
+    // NOTE: reconciliation runs once, after all the services are listening on the bus
+    reconciler.run(state.reader(), cipher.reader(), fs);
 
-    watcher.run(event_watcher);
-    document_mover.run(fs.clone());
-    thumbnail_generator.run(thumbnailer_factory, fs);
-    extractor.run(extractor_factory);
-    indexer.run(state.writer());
-    encrypter.run(cipher.writer());
+    let health_check = HealthCheck::new(cfg, cipher.reader(), cipher.writer(), supervisor.clone());
 
-    Ok((state.reader(), cipher.reader()))
+    Ok((state.reader(), cipher.reader(), supervisor, health_check))
 }
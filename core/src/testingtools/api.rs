@@ -17,15 +17,31 @@ pub fn doc<S: Into<String>>(name: S) -> SafePathBuf {
 
 pub struct ApiResponse {
     pub status: Status,
+    pub headers: Vec<(String, String)>,
     pub body: String,
 }
 
+impl ApiResponse {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
 impl TryFrom<LocalResponse<'_>> for ApiResponse {
     type Error = anyhow::Error;
 
     fn try_from(mut res: LocalResponse<'_>) -> Result<Self, Self::Error> {
+        let headers = res
+            .headers()
+            .iter()
+            .map(|header| (header.name().to_string(), header.value().to_string()))
+            .collect();
         Ok(ApiResponse {
             status: res.status(),
+            headers,
             body: res.read_body()?,
         })
     }
@@ -1,24 +1,34 @@
 use crate::configuration::factories::{fs, state, Runtime};
+use crate::data_providers::audit::AUDIT_FILE;
+use crate::data_providers::ocr::JsonOverrideStore;
+use crate::entities::file::Filename;
 use crate::entities::location::SafePathBuf;
+use crate::entities::user::{User, FAKE_USER_EMAIL};
 use crate::startup::rocket;
 use crate::testingtools::api::ApiResponse;
 use crate::testingtools::services::encrypter::{
-    failing as failing_cipher, tracked as tracked_cipher, CipherSpies,
+    failing as failing_cipher, noop as noop_cipher, tracked as tracked_cipher, CipherSpies,
 };
 use crate::testingtools::services::fs::{failing as failing_fs, tracked as tracked_fs, FsSpies};
 use crate::testingtools::services::state::{tracked, StateSpies};
 use crate::testingtools::TestConfig;
 use crate::use_cases::cipher::Cipher;
+use crate::use_cases::config::{QuotaConfig, RateLimitConfig};
 use crate::use_cases::fs::Fs;
+use crate::use_cases::ocr::OcrLanguages;
 use crate::use_cases::state::State;
+use crate::use_cases::users::Accounts;
 
 use anyhow::Result;
 use base64::engine::general_purpose::STANDARD as b64;
 use base64::Engine;
+use rocket::http::Header;
 use rocket::local::blocking::Client;
 use rocket::serde::json::json;
 use std::convert::TryInto;
 use std::fs;
+use std::net::SocketAddr;
+use std::path::PathBuf;
 use tracing::debug;
 use urlencoding::encode;
 
@@ -92,13 +102,33 @@ impl App {
         self.get(format!("/search?q={}", encode(&q)))
     }
 
+    /// Searches from the `remote` address, claiming to be `real_ip` in the `X-Real-IP` header.
+    pub fn search_from<S: Into<String>>(
+        &self,
+        q: S,
+        remote: SocketAddr,
+        real_ip: &str,
+    ) -> Result<ApiResponse> {
+        let q = q.into();
+        self.client
+            .get(format!("/search?q={}", encode(&q)))
+            .remote(remote)
+            .header(Header::new("X-Real-IP", real_ip.to_string()))
+            .dispatch()
+            .try_into()
+    }
+
     fn get<S: Into<String>>(&self, url: S) -> Result<ApiResponse> {
         self.client.get(url.into()).dispatch().try_into()
     }
 
     pub fn upload_doc(&self, path: &SafePathBuf) -> Result<ApiResponse> {
+        self.upload_doc_as(path, &path.filename())
+    }
+
+    /// Uploads the document under another name, e.g. with misleading extension.
+    pub fn upload_doc_as(&self, path: &SafePathBuf, filename: &str) -> Result<ApiResponse> {
         let body = b64.encode(fs::read(path)?);
-        let filename = path.filename();
         self.client
             .post("/document/upload")
             .body(
@@ -112,20 +142,166 @@ impl App {
             .try_into()
     }
 
+    /// Uploads the document requesting OCR `languages` other than the configured ones.
+    pub fn upload_doc_in(&self, path: &SafePathBuf, languages: &str) -> Result<ApiResponse> {
+        let body = b64.encode(fs::read(path)?);
+        let filename = path.filename();
+        self.client
+            .post("/document/upload")
+            .body(
+                json!({
+                    "filename": filename,
+                    "body": body,
+                    "languages": languages
+                })
+                .to_string(),
+            )
+            .dispatch()
+            .try_into()
+    }
+
     pub fn get_doc<S: Into<String>>(&self, name: S) -> Result<ApiResponse> {
         self.get(format!("/document/{}", name.into()))
     }
 
+    pub fn get_page<S: Into<String>>(&self, name: S, number: u32) -> Result<ApiResponse> {
+        self.get(format!("/document/{}/page/{}", name.into(), number))
+    }
+
     pub fn get_thumbnail<S: Into<String>>(&self, name: S) -> Result<ApiResponse> {
         self.get(format!("/thumbnail/{}", name.into()))
     }
 
+    pub fn get_sized_thumbnail<S: Into<String>>(&self, name: S, size: &str) -> Result<ApiResponse> {
+        self.get(format!("/thumbnail/{}?size={}", name.into(), size))
+    }
+
+    pub fn get_thumbnail_at_ratio<S: Into<String>>(
+        &self,
+        name: S,
+        dpr: u32,
+    ) -> Result<ApiResponse> {
+        self.get(format!("/thumbnail/{}?dpr={}", name.into(), dpr))
+    }
+
+    pub fn get_shared_doc<S: Into<String>>(&self, name: S, owner: &str) -> Result<ApiResponse> {
+        self.get(format!("/document/{}?owner={}", name.into(), encode(owner)))
+    }
+
+    pub fn share_doc<S: Into<String>>(&self, name: S, grantee: &str) -> Result<ApiResponse> {
+        self.client
+            .post("/share")
+            .body(share_body(name, grantee))
+            .dispatch()
+            .try_into()
+    }
+
+    pub fn unshare_doc<S: Into<String>>(&self, name: S, grantee: &str) -> Result<ApiResponse> {
+        self.client
+            .delete("/share")
+            .body(share_body(name, grantee))
+            .dispatch()
+            .try_into()
+    }
+
+    pub fn shares(&self) -> Result<ApiResponse> {
+        self.get("/shares")
+    }
+
+    pub fn create_link<S: Into<String>>(&self, name: S, single_use: bool) -> Result<ApiResponse> {
+        self.client
+            .post("/link")
+            .body(
+                json!({
+                    "filename": name.into(),
+                    "single_use": single_use
+                })
+                .to_string(),
+            )
+            .dispatch()
+            .try_into()
+    }
+
+    pub fn usage(&self) -> Result<ApiResponse> {
+        self.get("/usage")
+    }
+
+    pub fn audit(&self) -> Result<ApiResponse> {
+        self.get("/audit")
+    }
+
+    pub fn users(&self) -> Result<ApiResponse> {
+        self.get("/admin/users")
+    }
+
+    pub fn set_user_status(&self, email: &str, status: &str) -> Result<ApiResponse> {
+        self.client
+            .put(format!("/admin/users/{}", encode(email)))
+            .body(json!({ "status": status }).to_string())
+            .dispatch()
+            .try_into()
+    }
+
+    pub fn remove_user(&self, email: &str) -> Result<ApiResponse> {
+        self.client
+            .delete(format!("/admin/users/{}", encode(email)))
+            .dispatch()
+            .try_into()
+    }
+
+    /// Opens the `url` returned when the link was created.
+    pub fn open_link<S: Into<String>>(&self, url: S) -> Result<ApiResponse> {
+        self.get(url)
+    }
+
+    /// Returns all the records of the audit log, also the ones not made by any user.
+    pub fn audit_log(&self) -> Result<Vec<serde_json::Value>> {
+        let log = fs::read_to_string(self.config.as_ref().data_dir.join(AUDIT_FILE))?;
+        Ok(log
+            .lines()
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()?)
+    }
+
+    pub fn health(&self) -> Result<ApiResponse> {
+        self.get("/health")
+    }
+
+    pub fn ready(&self) -> Result<ApiResponse> {
+        self.get("/ready")
+    }
+
+    pub fn metrics(&self) -> Result<ApiResponse> {
+        self.get("/metrics")
+    }
+
     pub fn thumbnail_exists<S: Into<String>>(&self, name: S) -> bool {
         let name = name.into();
         debug!("checking if thumbnail '{}' exists", name);
         self.config.thumbnail_path(name).exists()
     }
 
+    /// Puts the thumbnail of the test user in the thumbnails directory, bypassing the pipeline.
+    pub fn put_thumbnail<S: Into<String>>(&self, name: S, content: &str) -> Result<()> {
+        let path = self.config.thumbnail_path(name);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, content)?;
+        Ok(())
+    }
+
+    /// Returns directories with the files of the user.
+    pub fn user_dirs(&self, email: &str) -> [PathBuf; 4] {
+        self.config.as_ref().user_dirs(&User::new(email))
+    }
+
+    /// Returns OCR languages requested for the document of the user, which is not extracted yet.
+    pub fn ocr_languages(&self, name: &str) -> Result<Option<OcrLanguages>> {
+        let overrides = JsonOverrideStore::create(self.config.as_ref())?;
+        Ok(overrides.take(&User::new(FAKE_USER_EMAIL), &Filename::new(name)?)?)
+    }
+
     pub fn document_exists<S: Into<String>>(&self, name: S) -> bool {
         let name = name.into();
         debug!("checking if document '{}' exists", name);
@@ -133,6 +309,14 @@ impl App {
     }
 }
 
+fn share_body<S: Into<String>>(name: S, grantee: &str) -> String {
+    json!({
+        "filename": name.into(),
+        "grantee": grantee
+    })
+    .to_string()
+}
+
 pub struct AppBuilder {
     config: Option<TestConfig>,
     ctx: Option<Runtime>,
@@ -165,6 +349,37 @@ impl AppBuilder {
         self
     }
 
+    pub fn with_noop_cipher(mut self) -> Self {
+        let ctx = self.ctx.as_mut().unwrap();
+        ctx.with_cipher(noop_cipher());
+        self
+    }
+
+    pub fn with_quota(mut self, quota: QuotaConfig) -> Self {
+        let ctx = self.ctx.as_mut().unwrap();
+        ctx.cfg.quota = quota;
+        self
+    }
+
+    pub fn with_rate_limit(mut self, rate_limit: RateLimitConfig) -> Self {
+        let ctx = self.ctx.as_mut().unwrap();
+        ctx.cfg.rate_limit = rate_limit;
+        self
+    }
+
+    /// Makes the user of the requests an administrator.
+    pub fn with_admin(mut self) -> Self {
+        let ctx = self.ctx.as_mut().unwrap();
+        ctx.cfg.users.admins = vec![FAKE_USER_EMAIL.into()];
+        self
+    }
+
+    pub fn with_accounts(mut self, accounts: Accounts) -> Self {
+        let ctx = self.ctx.as_mut().unwrap();
+        ctx.accounts = accounts;
+        self
+    }
+
     pub fn with_tracked_fs(mut self) -> Self {
         let (fs_spies, tracked_fs) = tracked_fs(fs());
         let ctx = self.ctx.as_mut().unwrap();
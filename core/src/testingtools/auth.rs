@@ -0,0 +1,78 @@
+use anyhow::Result;
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use tracing::{debug, error};
+
+pub const TEST_ISSUER: &str = "https://issuer.test";
+
+const TEST_KEY_ID: &str = "test-key";
+
+/// Signs the `claims` with the key which is served by [`JwksStub`].
+pub fn sign(claims: &serde_json::Value) -> Result<String> {
+    sign_with_kid(TEST_KEY_ID, claims)
+}
+
+/// Signs the `claims` with the test key, but with different key id in the header.
+pub fn sign_with_kid(kid: &str, claims: &serde_json::Value) -> Result<String> {
+    let key = EncodingKey::from_rsa_pem(&fs::read("res/auth/test-key.pem")?)?;
+    let mut header = Header::new(Algorithm::RS256);
+    header.kid = Some(kid.into());
+    Ok(encode(&header, claims, &key)?)
+}
+
+/// Local HTTP server serving the JWKS with the public part of the test key.
+pub struct JwksStub {
+    port: u16,
+    requests: Arc<AtomicUsize>,
+}
+
+impl JwksStub {
+    pub fn start() -> Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let port = listener.local_addr()?.port();
+        let jwks = fs::read_to_string("res/auth/test-jwks.json")?;
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                counter.fetch_add(1, Ordering::SeqCst);
+                if let Err(e) = respond(stream, &jwks) {
+                    error!("jwks stub failed to respond: '{}'", e);
+                }
+            }
+        });
+        debug!("jwks stub listening on port {}", port);
+        Ok(Self { port, requests })
+    }
+
+    pub fn url(&self) -> String {
+        format!("http://127.0.0.1:{}/certs", self.port)
+    }
+
+    /// Returns number of times the keys were fetched.
+    pub fn requests(&self) -> usize {
+        self.requests.load(Ordering::SeqCst)
+    }
+}
+
+fn respond(mut stream: TcpStream, body: &str) -> Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut line = String::new();
+    // read the request until the empty line ending the headers
+    while reader.read_line(&mut line)? > 0 && line != "\r\n" {
+        line.clear();
+    }
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    )?;
+    stream.flush()?;
+    Ok(())
+}
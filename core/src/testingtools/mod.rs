@@ -1,6 +1,10 @@
+use crate::entities::extension::Ext;
 use crate::entities::file::{Filename, Thumbnailname};
 use crate::entities::user::{User, FAKE_USER_EMAIL};
-use crate::use_cases::config::Config;
+use crate::use_cases::config::{
+    ArchivesConfig, Config, LinksConfig, OcrConfig, QuotaConfig, RateLimitConfig, ScannerConfig,
+    ServerConfig, ThumbnailsConfig, UsersConfig, WatcherConfig,
+};
 
 use anyhow::Result;
 use rocket::serde::Serialize;
@@ -15,6 +19,7 @@ use tracing::{debug, error};
 
 pub mod api;
 pub mod app;
+pub mod auth;
 pub mod services;
 pub mod unit;
 
@@ -38,6 +43,36 @@ pub fn thumbnails_dir_path() -> Result<TempDir> {
     Ok(tempfile::tempdir()?)
 }
 
+pub fn data_dir_path() -> Result<TempDir> {
+    debug!("creating data directory");
+    Ok(tempfile::tempdir()?)
+}
+
+/// ZIP archive without any members.
+pub const EMPTY_ZIP: [u8; 22] = *b"PK\x05\x06\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0";
+
+/// Minimal content recognized as the document of `ext` type, followed by some text.
+pub fn sample_content(ext: &Ext) -> Vec<u8> {
+    let signature: &[u8] = match ext {
+        Ext::Png => b"\x89PNG\r\n\x1a\n",
+        Ext::Jpg => b"\xff\xd8\xff\xe0",
+        Ext::Webp => b"RIFF\0\0\0\0WEBPVP8 ",
+        Ext::Tiff => b"II*\0",
+        Ext::Heic => b"\0\0\0\x18ftypheic",
+        Ext::Bmp => b"BM\0\0\0\0\0\0\0\0\0\0\0\0\x28\0\0\0",
+        Ext::Gif => b"GIF89a",
+        Ext::Pdf => b"%PDF-1.4\n",
+        Ext::Docx => b"PK\x03\x04word/document.xml",
+        Ext::Odt => b"PK\x03\x04mimetypeapplication/vnd.oasis.opendocument.text",
+        Ext::Xlsx => b"PK\x03\x04xl/workbook.xml",
+        Ext::Rtf => b"{\\rtf1 ",
+        Ext::Txt | Ext::Md | Ext::Html | Ext::Eml => b"",
+    };
+    let mut content = signature.to_vec();
+    content.extend_from_slice(b"Some text of the document.\n");
+    content
+}
+
 pub struct Spy {
     rx: Receiver<()>,
 }
@@ -59,6 +94,7 @@ pub struct TestConfig {
     docs_dir: TempDir,
     thumbnails_dir: TempDir,
     index_dir: TempDir,
+    data_dir: TempDir,
 }
 
 impl TestConfig {
@@ -67,6 +103,7 @@ impl TestConfig {
         let docs_dir = docs_dir_path()?;
         let thumbnails_dir = thumbnails_dir_path()?;
         let index_dir = index_dir_path()?;
+        let data_dir = data_dir_path()?;
         Ok(Self {
             // NOTE: This weird 'config in config' is here because:
             // 1. I can't drop `TestConfig` - because it holds TempDir.
@@ -80,11 +117,25 @@ impl TestConfig {
                 docs_dir: docs_dir.path().to_path_buf(),
                 thumbnails_dir: thumbnails_dir.path().to_path_buf(),
                 index_dir: index_dir.path().to_path_buf(),
+                data_dir: data_dir.path().to_path_buf(),
+                remove_orphan_thumbnails: false,
+                watcher: WatcherConfig::default(),
+                links: LinksConfig::default(),
+                quota: QuotaConfig::default(),
+                users: UsersConfig::default(),
+                rate_limit: RateLimitConfig::default(),
+                server: ServerConfig::default(),
+                ocr: OcrConfig::default(),
+                scanner: ScannerConfig::default(),
+                archives: ArchivesConfig::default(),
+                thumbnails: ThumbnailsConfig::default(),
+                auth: Config::default().auth,
             },
             watched_dir,
             docs_dir,
             thumbnails_dir,
             index_dir,
+            data_dir,
         })
     }
 
@@ -118,6 +169,7 @@ impl Serialize for TestConfig {
         state.serialize_field("watched_dir", self.watched_dir.path())?;
         state.serialize_field("thumbnails_dir", self.thumbnails_dir.path())?;
         state.serialize_field("index_dir", self.index_dir.path())?;
+        state.serialize_field("data_dir", self.data_dir.path())?;
         state.end()
     }
 }
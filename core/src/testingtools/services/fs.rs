@@ -36,6 +36,10 @@ impl Filesystem for FailingFs {
     fn mv_file(&self, _from: &SafePathBuf, _to: &Path) -> Result<(), FsErr> {
         Err(FsErr::Test)
     }
+
+    fn rm_dirs(&self, _dirs: &[PathBuf]) -> Result<(), FsErr> {
+        Err(FsErr::Test)
+    }
 }
 
 pub fn tracked(fs: Fs) -> (FsSpies, Fs) {
@@ -98,6 +102,11 @@ impl Filesystem for TrackedFs {
         self.mv_file_tx.signal();
         res
     }
+
+    #[instrument(skip(self))]
+    fn rm_dirs(&self, dirs: &[PathBuf]) -> Result<(), FsErr> {
+        self.fs.rm_dirs(dirs)
+    }
 }
 
 pub struct FsSpies {
@@ -176,4 +185,10 @@ impl Filesystem for NoOpFs {
         // nothing to do
         Ok(())
     }
+
+    #[instrument(skip(self))]
+    fn rm_dirs(&self, dirs: &[PathBuf]) -> Result<(), FsErr> {
+        // nothing to do
+        Ok(())
+    }
 }
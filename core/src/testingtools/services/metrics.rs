@@ -0,0 +1,84 @@
+use crate::entities::extension::Ext;
+use crate::result::MetricsErr;
+use crate::use_cases::metrics::{EncryptionTarget, Extraction, Metrics, MetricsRecorder, Stage};
+
+use anyhow::Result;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+pub fn tracked() -> (MetricsSpies, Metrics) {
+    TrackedMetrics::create()
+}
+
+pub struct TrackedMetrics {
+    processed_tx: Mutex<Sender<(Stage, usize)>>,
+    extracted_tx: Mutex<Sender<(Ext, Extraction)>>,
+    encryption_tx: Mutex<Sender<EncryptionTarget>>,
+}
+
+impl TrackedMetrics {
+    fn create() -> (MetricsSpies, Metrics) {
+        let (processed_tx, processed_rx) = channel();
+        let (extracted_tx, extracted_rx) = channel();
+        let (encryption_tx, encryption_rx) = channel();
+        (
+            MetricsSpies {
+                processed_rx,
+                extracted_rx,
+                encryption_rx,
+            },
+            Arc::new(Self {
+                processed_tx: Mutex::new(processed_tx),
+                extracted_tx: Mutex::new(extracted_tx),
+                encryption_tx: Mutex::new(encryption_tx),
+            }),
+        )
+    }
+}
+
+impl MetricsRecorder for TrackedMetrics {
+    fn docs_processed(&self, stage: Stage, count: usize) {
+        let tx = self.processed_tx.lock().expect("poisoned mutex");
+        // NOTE: see `MutexExt::signal` for why the error is ignored
+        let _ = tx.send((stage, count));
+    }
+
+    fn extraction_finished(&self, ext: &Ext, extraction: Extraction, _took: Duration) {
+        let tx = self.extracted_tx.lock().expect("poisoned mutex");
+        let _ = tx.send((ext.clone(), extraction));
+    }
+
+    fn encryption_failed(&self, target: EncryptionTarget) {
+        let tx = self.encryption_tx.lock().expect("poisoned mutex");
+        let _ = tx.send(target);
+    }
+
+    fn search_finished(&self, _took: Duration) {
+        // nothing to track
+    }
+
+    fn render(&self) -> Result<String, MetricsErr> {
+        Ok(String::new())
+    }
+}
+
+pub struct MetricsSpies {
+    processed_rx: Receiver<(Stage, usize)>,
+    extracted_rx: Receiver<(Ext, Extraction)>,
+    encryption_rx: Receiver<EncryptionTarget>,
+}
+
+impl MetricsSpies {
+    pub fn docs_processed(&self) -> Result<(Stage, usize)> {
+        Ok(self.processed_rx.recv_timeout(Duration::from_secs(30))?)
+    }
+
+    pub fn extraction_finished(&self) -> Result<(Ext, Extraction)> {
+        Ok(self.extracted_rx.recv_timeout(Duration::from_secs(30))?)
+    }
+
+    pub fn encryption_failed(&self) -> Result<EncryptionTarget> {
+        Ok(self.encryption_rx.recv_timeout(Duration::from_secs(30))?)
+    }
+}
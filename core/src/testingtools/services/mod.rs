@@ -1,5 +1,13 @@
+pub mod audit;
 pub mod encrypter;
 pub mod extractor;
 pub mod fs;
+pub mod metrics;
+pub mod ocr;
+pub mod quota;
+pub mod scanner;
+pub mod sharing;
 pub mod state;
 pub mod thumbnailer;
+pub mod unpacker;
+pub mod users;
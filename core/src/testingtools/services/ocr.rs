@@ -0,0 +1,37 @@
+use crate::entities::file::Filename;
+use crate::entities::user::User;
+use crate::result::OcrErr;
+use crate::use_cases::ocr::{LanguageOverrides, OcrLanguages, OverrideStore};
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Keeps the requested languages in memory only.
+pub fn stub() -> LanguageOverrides {
+    OverridesStub::make()
+}
+
+struct OverridesStub {
+    overrides: Mutex<HashMap<(User, Filename), OcrLanguages>>,
+}
+
+impl OverridesStub {
+    fn make() -> LanguageOverrides {
+        Arc::new(Self {
+            overrides: Mutex::new(HashMap::new()),
+        })
+    }
+}
+
+impl OverrideStore for OverridesStub {
+    fn set(&self, user: &User, filename: &Filename, languages: OcrLanguages) -> Result<(), OcrErr> {
+        let mut overrides = self.overrides.lock().expect("poisoned mutex");
+        overrides.insert((user.clone(), filename.clone()), languages);
+        Ok(())
+    }
+
+    fn take(&self, user: &User, filename: &Filename) -> Result<Option<OcrLanguages>, OcrErr> {
+        let mut overrides = self.overrides.lock().expect("poisoned mutex");
+        Ok(overrides.remove(&(user.clone(), filename.clone())))
+    }
+}
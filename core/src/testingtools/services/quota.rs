@@ -0,0 +1,54 @@
+use crate::entities::file::Filename;
+use crate::entities::user::User;
+use crate::result::QuotaErr;
+use crate::use_cases::config::{Config, QuotaConfig};
+use crate::use_cases::quota::{Meter, Quotas, Usage, UsageMeter};
+
+use std::sync::Arc;
+
+pub fn stub(usage: Usage, existing: &[&str]) -> Meter {
+    MeterStub::make(usage, existing)
+}
+
+/// Quotas which are never exceeded.
+pub fn unlimited() -> Quotas {
+    Quotas::new(stub(Usage::default(), &[]), &Config::default())
+}
+
+/// Quotas which are already exceeded, so no new document fits in them.
+pub fn exceeded() -> Quotas {
+    let cfg = Config {
+        quota: QuotaConfig {
+            max_docs: 0,
+            ..QuotaConfig::default()
+        },
+        ..Config::default()
+    };
+    let usage = Usage {
+        docs: 1,
+        ..Usage::default()
+    };
+    Quotas::new(stub(usage, &[]), &cfg)
+}
+
+struct MeterStub {
+    usage: Usage,
+    existing: Vec<String>,
+}
+
+impl MeterStub {
+    fn make(usage: Usage, existing: &[&str]) -> Meter {
+        let existing = existing.iter().map(ToString::to_string).collect();
+        Arc::new(Self { usage, existing })
+    }
+}
+
+impl UsageMeter for MeterStub {
+    fn usage(&self, _user: &User) -> Result<Usage, QuotaErr> {
+        Ok(self.usage.clone())
+    }
+
+    fn has_doc(&self, _user: &User, filename: &Filename) -> bool {
+        self.existing.contains(&filename.to_string())
+    }
+}
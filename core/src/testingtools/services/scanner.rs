@@ -0,0 +1,73 @@
+use crate::entities::location::SafePathBuf;
+use crate::result::ScannerErr;
+use crate::testingtools::{pipe, MutexExt, Spy, Tx};
+use crate::use_cases::scanner::{PageScanner, Scanner};
+
+use std::sync::Arc;
+use tracing::instrument;
+
+/// Scanner which never finds the page, so documents are left as they are.
+pub fn noop() -> Scanner {
+    NoOpScanner::make()
+}
+
+pub struct NoOpScanner;
+
+impl NoOpScanner {
+    fn make() -> Scanner {
+        Arc::new(Self)
+    }
+}
+
+impl PageScanner for NoOpScanner {
+    #[instrument(skip(self))]
+    fn flatten(&self, _path: &SafePathBuf) -> Result<bool, ScannerErr> {
+        // nothing to do
+        Ok(false)
+    }
+}
+
+pub fn tracked(scanner: Scanner) -> (ScannerSpies, Scanner) {
+    TrackedScanner::wrap(scanner)
+}
+
+pub struct TrackedScanner {
+    scanner: Scanner,
+    flatten_tx: Tx,
+}
+
+impl TrackedScanner {
+    fn wrap(scanner: Scanner) -> (ScannerSpies, Scanner) {
+        let (flatten_tx, flatten_spy) = pipe();
+        (
+            ScannerSpies::new(flatten_spy),
+            Arc::new(Self {
+                scanner,
+                flatten_tx,
+            }),
+        )
+    }
+}
+
+impl PageScanner for TrackedScanner {
+    #[instrument(skip(self))]
+    fn flatten(&self, path: &SafePathBuf) -> Result<bool, ScannerErr> {
+        let res = self.scanner.flatten(path);
+        self.flatten_tx.signal();
+        res
+    }
+}
+
+pub struct ScannerSpies {
+    flatten_spy: Spy,
+}
+
+impl ScannerSpies {
+    fn new(flatten_spy: Spy) -> Self {
+        Self { flatten_spy }
+    }
+
+    pub fn flatten_called(&self) -> bool {
+        self.flatten_spy.method_called()
+    }
+}
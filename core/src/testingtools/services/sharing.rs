@@ -0,0 +1,51 @@
+use crate::entities::user::User;
+use crate::result::SharingErr;
+use crate::use_cases::sharing::{Grant, ShareStore, Sharing};
+
+use std::sync::Arc;
+
+pub fn stub(grants: Vec<Grant>) -> Sharing {
+    SharingStub::make(grants)
+}
+
+struct SharingStub {
+    grants: Vec<Grant>,
+}
+
+impl SharingStub {
+    fn make(grants: Vec<Grant>) -> Sharing {
+        Arc::new(Self { grants })
+    }
+}
+
+impl ShareStore for SharingStub {
+    fn grant(&self, _grant: Grant) -> Result<(), SharingErr> {
+        unimplemented!()
+    }
+
+    fn revoke(&self, _grant: &Grant) -> Result<bool, SharingErr> {
+        unimplemented!()
+    }
+
+    fn granted_by(&self, owner: &User) -> Result<Vec<Grant>, SharingErr> {
+        Ok(self
+            .grants
+            .iter()
+            .filter(|grant| &grant.owner == owner)
+            .cloned()
+            .collect())
+    }
+
+    fn shared_with(&self, grantee: &User) -> Result<Vec<Grant>, SharingErr> {
+        Ok(self
+            .grants
+            .iter()
+            .filter(|grant| &grant.grantee == grantee)
+            .cloned()
+            .collect())
+    }
+
+    fn forget(&self, _user: &User) -> Result<(), SharingErr> {
+        unimplemented!()
+    }
+}
@@ -1,13 +1,16 @@
 use crate::entities::document::DocDetails;
+use crate::entities::file::Filename;
 use crate::entities::location::Location;
 use crate::entities::user::User;
 use crate::result::{BusErr, IndexerErr, SearchErr};
 use crate::testingtools::{pipe, MutexExt, Spy, Tx};
 use crate::use_cases::state::{
-    AppState, AppStateReader, AppStateWriter, SearchResult, State, StateReader, StateWriter,
+    AppState, AppStateReader, AppStateWriter, SearchEntry, SearchResult, State, StateReader,
+    StateWriter,
 };
 
 use anyhow::{anyhow, Result};
+use std::collections::HashSet;
 use std::sync::Arc;
 use tracing::instrument;
 
@@ -71,6 +74,15 @@ impl AppStateReader for TrackedStateReader {
         self.reader.search(user, q)
     }
 
+    fn search_in(
+        &self,
+        user: User,
+        q: String,
+        filenames: &HashSet<String>,
+    ) -> Result<SearchResult, SearchErr> {
+        self.reader.search_in(user, q, filenames)
+    }
+
     fn all_docs(&self, user: User) -> Result<SearchResult, SearchErr> {
         self.reader.all_docs(user)
     }
@@ -106,6 +118,18 @@ impl AppStateWriter for TrackedStateWriter {
         self.delete_tx.signal();
         res
     }
+
+    #[instrument(skip(self))]
+    fn delete_doc(&self, user: &User, filename: &Filename) -> Result<(), IndexerErr> {
+        let res = self.writer.delete_doc(user, filename);
+        self.delete_tx.signal();
+        res
+    }
+
+    #[instrument(skip(self))]
+    fn remove_user(&self, user: &User) -> Result<(), IndexerErr> {
+        self.writer.remove_user(user)
+    }
 }
 
 pub struct StateSpies {
@@ -187,6 +211,15 @@ impl AppStateReader for WorkingStateReader {
         Err(SearchErr::MissingIndex("error".into()))
     }
 
+    fn search_in(
+        &self,
+        _user: User,
+        _q: String,
+        _filenames: &HashSet<String>,
+    ) -> Result<SearchResult, SearchErr> {
+        Err(SearchErr::MissingIndex("error".into()))
+    }
+
     fn all_docs(&self, _user: User) -> Result<SearchResult, SearchErr> {
         Err(SearchErr::MissingIndex("error".into()))
     }
@@ -208,6 +241,14 @@ impl AppStateWriter for WorkingStateWriter {
     fn delete(&self, _loc: &Location) -> Result<(), IndexerErr> {
         Ok(())
     }
+
+    fn delete_doc(&self, _user: &User, _filename: &Filename) -> Result<(), IndexerErr> {
+        Ok(())
+    }
+
+    fn remove_user(&self, _user: &User) -> Result<(), IndexerErr> {
+        Ok(())
+    }
 }
 
 pub fn failing() -> State {
@@ -251,6 +292,15 @@ impl AppStateReader for FailingStateReader {
         Err(SearchErr::MissingIndex("error".into()))
     }
 
+    fn search_in(
+        &self,
+        _user: User,
+        _q: String,
+        _filenames: &HashSet<String>,
+    ) -> Result<SearchResult, SearchErr> {
+        Err(SearchErr::MissingIndex("error".into()))
+    }
+
     fn all_docs(&self, _user: User) -> Result<SearchResult, SearchErr> {
         Err(SearchErr::MissingIndex("error".into()))
     }
@@ -272,6 +322,14 @@ impl AppStateWriter for FailingStateWriter {
     fn delete(&self, _loc: &Location) -> Result<(), IndexerErr> {
         unimplemented!()
     }
+
+    fn delete_doc(&self, _user: &User, _filename: &Filename) -> Result<(), IndexerErr> {
+        unimplemented!()
+    }
+
+    fn remove_user(&self, _user: &User) -> Result<(), IndexerErr> {
+        unimplemented!()
+    }
 }
 
 pub fn noop() -> State {
@@ -316,6 +374,16 @@ impl AppStateReader for NoOpStateReader {
         Ok(Vec::new().into())
     }
 
+    fn search_in(
+        &self,
+        _user: User,
+        _q: String,
+        _filenames: &HashSet<String>,
+    ) -> Result<SearchResult, SearchErr> {
+        // nothing to do
+        Ok(Vec::new().into())
+    }
+
     fn all_docs(&self, _user: User) -> Result<SearchResult, SearchErr> {
         // nothing to do
         Ok(Vec::new().into())
@@ -340,4 +408,57 @@ impl AppStateWriter for NoOpStateWriter {
         // nothing to do here
         Ok(())
     }
+
+    fn delete_doc(&self, _user: &User, _filename: &Filename) -> Result<(), IndexerErr> {
+        // nothing to do here
+        Ok(())
+    }
+
+    fn remove_user(&self, _user: &User) -> Result<(), IndexerErr> {
+        // nothing to do here
+        Ok(())
+    }
+}
+
+/// Returns all the documents of the user, whatever the query is.
+pub fn stub(docs: Vec<(User, Vec<SearchEntry>)>) -> StateReader {
+    StateReaderStub::make(docs)
+}
+
+struct StateReaderStub {
+    docs: Vec<(User, Vec<SearchEntry>)>,
+}
+
+impl StateReaderStub {
+    fn make(docs: Vec<(User, Vec<SearchEntry>)>) -> StateReader {
+        Arc::new(Self { docs })
+    }
+
+    fn docs_of(&self, user: &User) -> SearchResult {
+        self.docs
+            .iter()
+            .filter(|(owner, _)| owner == user)
+            .flat_map(|(_, entries)| entries.iter().cloned())
+            .collect::<Vec<SearchEntry>>()
+            .into()
+    }
+}
+
+impl AppStateReader for StateReaderStub {
+    fn search(&self, user: User, _q: String) -> Result<SearchResult, SearchErr> {
+        Ok(self.docs_of(&user))
+    }
+
+    fn search_in(
+        &self,
+        user: User,
+        _q: String,
+        filenames: &HashSet<String>,
+    ) -> Result<SearchResult, SearchErr> {
+        Ok(self.docs_of(&user).only(filenames))
+    }
+
+    fn all_docs(&self, user: User) -> Result<SearchResult, SearchErr> {
+        Ok(self.docs_of(&user))
+    }
 }
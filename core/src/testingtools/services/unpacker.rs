@@ -0,0 +1,143 @@
+use crate::entities::location::SafePathBuf;
+use crate::result::UnpackerErr;
+use crate::testingtools::{pipe, MutexExt, Spy, Tx};
+use crate::use_cases::quota::Allowance;
+use crate::use_cases::unpacker::{DocsUnpacker, Unpacker};
+
+use std::path::Path;
+use std::sync::Arc;
+use tracing::instrument;
+
+/// Unpacker which finds nothing to unpack.
+pub fn noop() -> Unpacker {
+    NoOpUnpacker::make()
+}
+
+pub struct NoOpUnpacker;
+
+impl NoOpUnpacker {
+    fn make() -> Unpacker {
+        Arc::new(Self)
+    }
+}
+
+impl DocsUnpacker for NoOpUnpacker {
+    #[instrument(skip(self))]
+    fn unpack(
+        &self,
+        _archive: &SafePathBuf,
+        _dir: &Path,
+        _allowance: Allowance,
+    ) -> Result<Vec<SafePathBuf>, UnpackerErr> {
+        // nothing to do
+        Ok(Vec::new())
+    }
+}
+
+/// Unpacker which finds the documents of the given names, without writing them anywhere.
+pub fn found(names: &[&str]) -> Unpacker {
+    FoundUnpacker::make(names)
+}
+
+pub struct FoundUnpacker {
+    names: Vec<String>,
+}
+
+impl FoundUnpacker {
+    fn make(names: &[&str]) -> Unpacker {
+        Arc::new(Self {
+            names: names.iter().map(|name| (*name).to_string()).collect(),
+        })
+    }
+}
+
+impl DocsUnpacker for FoundUnpacker {
+    #[instrument(skip(self))]
+    fn unpack(
+        &self,
+        _archive: &SafePathBuf,
+        dir: &Path,
+        _allowance: Allowance,
+    ) -> Result<Vec<SafePathBuf>, UnpackerErr> {
+        Ok(self
+            .names
+            .iter()
+            .map(|name| SafePathBuf::new(dir.join(name)))
+            .collect())
+    }
+}
+
+/// Unpacker which always rejects the archive.
+pub fn failing() -> Unpacker {
+    FailingUnpacker::make()
+}
+
+pub struct FailingUnpacker;
+
+impl FailingUnpacker {
+    fn make() -> Unpacker {
+        Arc::new(Self)
+    }
+}
+
+impl DocsUnpacker for FailingUnpacker {
+    #[instrument(skip(self))]
+    fn unpack(
+        &self,
+        _archive: &SafePathBuf,
+        _dir: &Path,
+        _allowance: Allowance,
+    ) -> Result<Vec<SafePathBuf>, UnpackerErr> {
+        Err(UnpackerErr::TooManyMembers(0))
+    }
+}
+
+pub fn tracked(unpacker: Unpacker) -> (UnpackerSpies, Unpacker) {
+    TrackedUnpacker::wrap(unpacker)
+}
+
+pub struct TrackedUnpacker {
+    unpacker: Unpacker,
+    unpack_tx: Tx,
+}
+
+impl TrackedUnpacker {
+    fn wrap(unpacker: Unpacker) -> (UnpackerSpies, Unpacker) {
+        let (unpack_tx, unpack_spy) = pipe();
+        (
+            UnpackerSpies::new(unpack_spy),
+            Arc::new(Self {
+                unpacker,
+                unpack_tx,
+            }),
+        )
+    }
+}
+
+impl DocsUnpacker for TrackedUnpacker {
+    #[instrument(skip(self))]
+    fn unpack(
+        &self,
+        archive: &SafePathBuf,
+        dir: &Path,
+        allowance: Allowance,
+    ) -> Result<Vec<SafePathBuf>, UnpackerErr> {
+        let res = self.unpacker.unpack(archive, dir, allowance);
+        self.unpack_tx.signal();
+        res
+    }
+}
+
+pub struct UnpackerSpies {
+    unpack_spy: Spy,
+}
+
+impl UnpackerSpies {
+    fn new(unpack_spy: Spy) -> Self {
+        Self { unpack_spy }
+    }
+
+    pub fn unpack_called(&self) -> bool {
+        self.unpack_spy.method_called()
+    }
+}
@@ -0,0 +1,62 @@
+use crate::entities::user::User;
+use crate::result::UsersErr;
+use crate::use_cases::config::Config;
+use crate::use_cases::users::{Account, AccountStore, Accounts, UserDirectory};
+
+use std::sync::{Arc, Mutex};
+
+/// Keeps the accounts in memory only.
+pub fn stub(accounts: Vec<Account>) -> Accounts {
+    AccountsStub::make(accounts)
+}
+
+/// Directory with the `accounts`, where users without account are active.
+pub fn directory(accounts: Vec<Account>) -> UserDirectory {
+    UserDirectory::new(stub(accounts), &Config::default())
+}
+
+struct AccountsStub {
+    accounts: Mutex<Vec<Account>>,
+}
+
+impl AccountsStub {
+    fn make(accounts: Vec<Account>) -> Accounts {
+        Arc::new(Self {
+            accounts: Mutex::new(accounts),
+        })
+    }
+}
+
+impl AccountStore for AccountsStub {
+    fn get(&self, user: &User) -> Result<Option<Account>, UsersErr> {
+        let accounts = self.accounts.lock().expect("poisoned mutex");
+        Ok(accounts.iter().find(|a| &a.user == user).cloned())
+    }
+
+    fn get_or_add(&self, account: Account) -> Result<Account, UsersErr> {
+        let mut accounts = self.accounts.lock().expect("poisoned mutex");
+        if let Some(existing) = accounts.iter().find(|a| a.user == account.user) {
+            return Ok(existing.clone());
+        }
+        accounts.push(account.clone());
+        Ok(account)
+    }
+
+    fn put(&self, account: Account) -> Result<(), UsersErr> {
+        let mut accounts = self.accounts.lock().expect("poisoned mutex");
+        accounts.retain(|a| a.user != account.user);
+        accounts.push(account);
+        Ok(())
+    }
+
+    fn all(&self) -> Result<Vec<Account>, UsersErr> {
+        Ok(self.accounts.lock().expect("poisoned mutex").clone())
+    }
+
+    fn remove(&self, user: &User) -> Result<bool, UsersErr> {
+        let mut accounts = self.accounts.lock().expect("poisoned mutex");
+        let count = accounts.len();
+        accounts.retain(|a| &a.user != user);
+        Ok(accounts.len() != count)
+    }
+}
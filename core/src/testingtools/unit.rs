@@ -160,8 +160,7 @@ impl TestShim {
     pub fn dst_doc_location(&self) -> Location {
         let docs_dir = self.config.docs_dir.path();
         let src_path = &self.test_file.path;
-        let filename = src_path.filename();
-        Location::FS(vec![docs_dir.join(filename).into()])
+        Location::FS(vec![docs_dir.join(src_path.rel_path()).into()])
     }
 
     pub fn trigger_indexer(&mut self, details: Vec<DocDetails>) -> Result<()> {
@@ -0,0 +1,272 @@
+//! Audit log of the access to the documents and their modifications.
+//!
+//! Records are chained - each of them contains the hash of the previous one - so removing or
+//! modifying a record in the middle of the log breaks the chain and can be detected.
+use crate::entities::user::User;
+use crate::result::AuditErr;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt::{Display, Write};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{debug, error};
+
+pub type AuditLog = Arc<dyn AuditStore>;
+
+/// Hash used as the previous hash of the first record.
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Append-only storage of the audit records.
+pub trait AuditStore: Sync + Send {
+    /// Appends the entry at the end of the log, chaining it with the last record.
+    fn append(&self, entry: AuditEntry) -> Result<AuditRecord, AuditErr>;
+    /// Returns records of the actions made by the `user` or made on their documents, without
+    /// reading the records of other users.
+    fn records_of(&self, user: &User) -> Result<Vec<AuditRecord>, AuditErr>;
+    /// Checks if the chain of records is intact. Returns [`AuditErr::Tampered`] with the
+    /// number of the first broken record otherwise, or [`AuditErr::Torn`] when the last record
+    /// wasn't fully written.
+    fn verify(&self) -> Result<(), AuditErr>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Search,
+    ListThumbnails,
+    View,
+    Download,
+    Upload,
+    Delete,
+    Unpack,
+    Share,
+    Unshare,
+    ListShares,
+    CreateLink,
+    ListLinks,
+    RevokeLink,
+    OpenLink,
+    ListUsers,
+    SetUserStatus,
+    RemoveUser,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Success,
+    Failure,
+}
+
+/// Single action of the user.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// User making the action. Missing when the document was opened with a public link.
+    pub user: Option<User>,
+    /// Owner of the document, when it's not the user making the action.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<User>,
+    pub action: Action,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub document: Option<String>,
+    pub outcome: Outcome,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+impl AuditEntry {
+    pub fn new(user: &User, action: Action) -> Self {
+        Self {
+            user: Some(user.clone()),
+            owner: None,
+            action,
+            document: None,
+            outcome: Outcome::Success,
+            reason: None,
+        }
+    }
+
+    /// Entry of the action made without logging in.
+    pub fn anonymous(action: Action) -> Self {
+        Self {
+            user: None,
+            ..Self::new(&User::default(), action)
+        }
+    }
+
+    pub fn document<S: Display>(mut self, document: S) -> Self {
+        self.document = Some(document.to_string());
+        self
+    }
+
+    /// Sets the owner of the document, if it's other user than the one making the action.
+    pub fn owner(mut self, owner: &User) -> Self {
+        if self.user.as_ref() != Some(owner) {
+            self.owner = Some(owner.clone());
+        }
+        self
+    }
+
+    pub fn reason<S: Display>(mut self, reason: S) -> Self {
+        self.reason = Some(reason.to_string());
+        self
+    }
+
+    pub fn failed<S: Display>(mut self, reason: S) -> Self {
+        self.outcome = Outcome::Failure;
+        self.reason(reason)
+    }
+
+    /// Marks the entry as failed when the `res` is an error, with the error as the reason.
+    pub fn outcome<T, E: Display>(self, res: &Result<T, E>) -> Self {
+        match res {
+            Ok(_) => self,
+            Err(e) => self.failed(e),
+        }
+    }
+
+    /// Users concerned by the entry: the one making the action and the owner of the document.
+    pub fn users(&self) -> impl Iterator<Item = &User> {
+        self.user.iter().chain(&self.owner)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    /// Number of the record in the log, starting from 0.
+    pub seq: u64,
+    /// Unix timestamp, in seconds.
+    pub at: u64,
+    #[serde(flatten)]
+    pub entry: AuditEntry,
+    pub prev_hash: String,
+    pub hash: String,
+}
+
+impl AuditRecord {
+    pub fn new(seq: u64, entry: AuditEntry, prev_hash: String) -> Result<Self, AuditErr> {
+        let mut record = Self {
+            seq,
+            at: now(),
+            entry,
+            prev_hash,
+            hash: String::new(),
+        };
+        record.hash = record.digest()?;
+        Ok(record)
+    }
+
+    /// Hash of everything in the record except the hash itself.
+    pub fn digest(&self) -> Result<String, AuditErr> {
+        let mut hasher = Sha256::new();
+        hasher.update(format!("{}|{}|{}|", self.seq, self.at, self.prev_hash));
+        hasher.update(serde_json::to_vec(&self.entry)?);
+        Ok(hasher.finalize().iter().fold(String::new(), |mut hex, b| {
+            let _ = write!(hex, "{b:02x}");
+            hex
+        }))
+    }
+
+    /// Tells if the record follows the `prev` one and wasn't modified.
+    pub fn follows(&self, prev: Option<&AuditRecord>) -> Result<bool, AuditErr> {
+        let (seq, prev_hash) = prev.map_or((0, GENESIS_HASH), |p| (p.seq + 1, p.hash.as_str()));
+        Ok(self.seq == seq && self.prev_hash == prev_hash && self.hash == self.digest()?)
+    }
+}
+
+/// Writes the entries to the audit log.
+///
+/// Failure to write the entry is logged, but it doesn't fail the action which is audited.
+#[derive(Clone)]
+pub struct AuditTrail {
+    log: AuditLog,
+}
+
+impl AuditTrail {
+    pub fn new(log: AuditLog) -> Self {
+        Self { log }
+    }
+
+    pub fn record(&self, entry: AuditEntry) {
+        match self.log.append(entry) {
+            Ok(record) => debug!("audit record {} written", record.seq),
+            Err(e) => error!("failed to write audit record: '{}'", e),
+        }
+    }
+
+    pub fn records_of(&self, user: &User) -> Result<Vec<AuditRecord>, AuditErr> {
+        self.log.records_of(user)
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use anyhow::Result;
+
+    #[test]
+    fn modified_record_does_not_follow_previous_one() -> Result<()> {
+        // given
+        let first = AuditRecord::new(
+            0,
+            AuditEntry::new(&User::new("me@email.com"), Action::Upload),
+            GENESIS_HASH.into(),
+        )?;
+        let mut second = AuditRecord::new(
+            1,
+            AuditEntry::new(&User::new("me@email.com"), Action::Download).document("a.pdf"),
+            first.hash.clone(),
+        )?;
+        let intact = second.follows(Some(&first))?;
+
+        // when
+        second.entry.document = Some("b.pdf".into());
+
+        // then
+        assert!(first.follows(None)?);
+        assert!(intact);
+        assert!(!second.follows(Some(&first))?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn failed_action_is_recorded_with_reason() {
+        // given
+        let res: std::result::Result<(), AuditErr> = Err(AuditErr::Tampered(1));
+
+        // when
+        let entry = AuditEntry::new(&User::new("me@email.com"), Action::View).outcome(&res);
+
+        // then
+        assert_eq!(entry.outcome, Outcome::Failure);
+        assert_eq!(
+            entry.reason,
+            Some("Audit record 1 was modified or removed.".into())
+        );
+    }
+
+    #[test]
+    fn owner_is_kept_only_when_other_than_user() {
+        // given
+        let me = User::new("me@email.com");
+        let owner = User::new("owner@email.com");
+
+        // when
+        let own = AuditEntry::new(&me, Action::Download).owner(&me);
+        let shared = AuditEntry::new(&me, Action::Download).owner(&owner);
+
+        // then
+        assert_eq!(own.owner, None);
+        assert_eq!(shared.owner, Some(owner.clone()));
+        assert_eq!(shared.users().collect::<Vec<_>>(), vec![&me, &owner]);
+    }
+}
@@ -0,0 +1,170 @@
+//! Abstraction for establishing the identity of the user sending the request.
+use crate::entities::user::User;
+use crate::result::AuthErr;
+
+use base64::engine::general_purpose::STANDARD as b64;
+use base64::Engine;
+use std::sync::Arc;
+
+pub type Auth = Arc<dyn Authenticator>;
+
+/// Verifies credentials sent by the client.
+///
+/// The way the credentials are verified is the implementation detail. It can be a token issued
+/// by the identity provider, a token stored in the configuration or a password.
+#[rocket::async_trait]
+pub trait Authenticator: Sync + Send {
+    /// Returns the user owning the credentials.
+    ///
+    /// Returns `Ok(None)` when the credentials are not handled by this authenticator, so the next
+    /// one can be used.
+    async fn authenticate(&self, credentials: &Credentials) -> Result<Option<User>, AuthErr>;
+}
+
+/// Credentials passed in the `authorization` header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Credentials {
+    /// Token, e.g. ID token of the identity provider or static API token.
+    Token(String),
+    /// Username and password sent using HTTP Basic authentication.
+    Basic { username: String, password: String },
+}
+
+/// Reason of the failed authentication.
+///
+/// It's kept in the request's local cache, so it can be sent back to the client.
+#[derive(Debug, Default)]
+pub struct AuthFailure(pub Option<String>);
+
+impl Credentials {
+    /// Parses value of the `authorization` header.
+    ///
+    /// Supports `Bearer` and `Basic` schemes. Value without a scheme is treated as a token, because
+    /// that's how older clients send it.
+    pub fn parse<S: AsRef<str>>(header: S) -> Result<Self, AuthErr> {
+        let header = header.as_ref().trim();
+        match header.split_once(' ') {
+            Some((scheme, token)) if scheme.eq_ignore_ascii_case("bearer") => Self::token(token),
+            Some((scheme, encoded)) if scheme.eq_ignore_ascii_case("basic") => Self::basic(encoded),
+            Some(_) => Err(AuthErr::MalformedCredentials),
+            None if is_scheme(header) => Err(AuthErr::MalformedCredentials),
+            None => Self::token(header),
+        }
+    }
+
+    fn token(token: &str) -> Result<Self, AuthErr> {
+        let token = token.trim();
+        if token.is_empty() {
+            return Err(AuthErr::MalformedCredentials);
+        }
+        Ok(Self::Token(token.to_string()))
+    }
+
+    fn basic(encoded: &str) -> Result<Self, AuthErr> {
+        let decoded = String::from_utf8(b64.decode(encoded.trim())?)?;
+        let Some((username, password)) = decoded.split_once(':') else {
+            return Err(AuthErr::MalformedCredentials);
+        };
+        Ok(Self::Basic {
+            username: username.into(),
+            password: password.into(),
+        })
+    }
+}
+
+/// Checks if the header contains only the name of the scheme, without credentials.
+fn is_scheme(header: &str) -> bool {
+    header.eq_ignore_ascii_case("bearer") || header.eq_ignore_ascii_case("basic")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use anyhow::Result;
+    use claim::assert_matches;
+
+    #[test]
+    fn header_without_scheme_is_parsed_as_token() -> Result<()> {
+        // given
+        let header = "some.jwt.token";
+
+        // when
+        let credentials = Credentials::parse(header)?;
+
+        // then
+        assert_eq!(credentials, Credentials::Token("some.jwt.token".into()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn basic_header_is_parsed_as_username_and_password() -> Result<()> {
+        // given
+        let header = format!("Basic {}", b64.encode("some@email.com:pass:word"));
+
+        // when
+        let credentials = Credentials::parse(header)?;
+
+        // then
+        assert_eq!(
+            credentials,
+            Credentials::Basic {
+                username: "some@email.com".into(),
+                password: "pass:word".into()
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn basic_header_without_password_is_rejected() {
+        // given
+        let header = format!("Basic {}", b64.encode("some@email.com"));
+
+        // when
+        let res = Credentials::parse(header);
+
+        // then
+        assert_matches!(res, Err(AuthErr::MalformedCredentials));
+    }
+
+    #[test]
+    fn bearer_header_is_parsed_as_token() -> Result<()> {
+        // given
+        let header = "bearer some.jwt.token";
+
+        // when
+        let credentials = Credentials::parse(header)?;
+
+        // then
+        assert_eq!(credentials, Credentials::Token("some.jwt.token".into()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn bearer_header_without_token_is_rejected() {
+        // given
+        let header = "Bearer ";
+
+        // when
+        let res = Credentials::parse(header);
+
+        // then
+        assert_matches!(res, Err(AuthErr::MalformedCredentials));
+    }
+
+    #[test]
+    fn unknown_scheme_is_rejected() {
+        // given
+        let header = "Digest username=\"some@email.com\"";
+
+        // when
+        let res = Credentials::parse(header);
+
+        // then
+        assert_matches!(res, Err(AuthErr::MalformedCredentials));
+    }
+}
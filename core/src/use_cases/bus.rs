@@ -3,6 +3,7 @@
 //! The events represent new files of particular document, appearing in the system, which are going
 //! to be indexed by dox' core.
 use crate::entities::document::DocDetails;
+use crate::entities::file::Filename;
 use crate::entities::location::Location;
 use crate::result::BusErr;
 
@@ -43,12 +44,26 @@ pub enum BusEvent {
     /// Represents new documents appearing in the system. External event.
     NewDocs(Location),
 
+    /// Represents document renamed in the watched directory. External event.
+    ///
+    /// The document stored under the old name needs to be removed, the one under `to` is
+    /// processed as a new one.
+    DocsRenamed { from: Filename, to: Location },
+
     /// Published when text extraction is finished.
     DataExtracted(Vec<DocDetails>),
 
     /// Published when document was moved to correct location.
     DocsMoved(Location),
 
+    /// Published when documents were removed from the watched directory without processing,
+    /// together with the reason, e.g. because the quota of the user was exceeded.
+    DocsRejected(Location, String),
+
+    /// Published when documents were unpacked from the archive or from the attachments of the
+    /// email named `from`. The documents are published as moved separately.
+    DocsUnpacked { from: Filename, to: Location },
+
     /// Published when thumbnail generation is finished.
     ThumbnailMade(Location),
 
@@ -75,4 +90,8 @@ pub enum BusEvent {
 
     /// Published when document processing is finished.
     PipelineFinished,
+
+    /// Published when the application is going down, for each service in turn. The named service
+    /// should finish its work and stop.
+    Shutdown(&'static str),
 }
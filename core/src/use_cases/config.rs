@@ -10,7 +10,9 @@ use base64::engine::general_purpose::STANDARD as b64;
 use base64::Engine;
 use serde::{Deserialize, Serialize};
 use std::fmt::Display;
+use std::net::{IpAddr, Ipv4Addr};
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 pub type CfgResolver = Box<dyn ConfigResolver>;
 
@@ -51,6 +53,36 @@ pub struct Config {
     pub docs_dir: PathBuf,
     pub thumbnails_dir: PathBuf,
     pub index_dir: PathBuf,
+    /// Application data other than documents and the index, e.g. documents shared between users.
+    #[serde(default = "data_dir_default")]
+    pub data_dir: PathBuf,
+    /// Removes thumbnails without corresponding document during startup. When disabled, such
+    /// thumbnails are only reported.
+    #[serde(default)]
+    pub remove_orphan_thumbnails: bool,
+    #[serde(default)]
+    pub watcher: WatcherConfig,
+    #[serde(default)]
+    pub links: LinksConfig,
+    #[serde(default)]
+    pub quota: QuotaConfig,
+    #[serde(default)]
+    pub users: UsersConfig,
+    #[serde(default)]
+    pub rate_limit: RateLimitConfig,
+    #[serde(default)]
+    pub server: ServerConfig,
+    #[serde(default)]
+    pub ocr: OcrConfig,
+    #[serde(default)]
+    pub scanner: ScannerConfig,
+    #[serde(default)]
+    pub archives: ArchivesConfig,
+    #[serde(default)]
+    pub thumbnails: ThumbnailsConfig,
+    /// Authentication providers. Credentials are checked by each of them, in the specified order.
+    #[serde(default = "auth_default")]
+    pub auth: Vec<AuthConfig>,
 }
 
 impl Config {
@@ -65,6 +97,47 @@ impl Config {
     pub fn watched_path(&self, user: &User, name: &Filename) -> PathBuf {
         self.watched_dir.join(relative_path(user, name))
     }
+
+    /// Returns directories with the files of the user - documents, thumbnails, index and
+    /// documents waiting in the watched directory, in that order.
+    pub fn user_dirs(&self, user: &User) -> [PathBuf; 4] {
+        let user_dir = b64.encode(&user.email);
+        [
+            self.docs_dir.join(&user_dir),
+            self.thumbnails_dir.join(&user_dir),
+            self.index_dir.join(&user_dir),
+            self.watched_dir.join(&user_dir),
+        ]
+    }
+
+    /// Returns paths of the TLS certificate chain and of its private key, in that order.
+    pub fn tls_paths(&self) -> (PathBuf, PathBuf) {
+        let tls = &self.server.tls;
+        let default_dir = self.data_dir.join("tls");
+        (
+            tls.cert_path
+                .clone()
+                .unwrap_or_else(|| default_dir.join("cert.pem")),
+            tls.key_path
+                .clone()
+                .unwrap_or_else(|| default_dir.join("key.pem")),
+        )
+    }
+
+    /// Fails when any OpenID Connect provider has no audience, or a blank one, as all its tokens
+    /// would be rejected and nobody could log in with it.
+    pub fn check_auth(&self) -> Result<(), ConfigurationErr> {
+        let missing = self.auth.iter().find_map(|auth| match auth {
+            AuthConfig::Oidc(oidc) => {
+                let audience = oidc.audience.as_deref().unwrap_or_default();
+                audience.trim().is_empty().then(|| oidc.issuer.clone())
+            }
+            _ => None,
+        });
+        missing.map_or(Ok(()), |issuer| {
+            Err(ConfigurationErr::MissingAudience(issuer))
+        })
+    }
 }
 
 fn relative_path<D: Display>(user: &User, filename: &D) -> String {
@@ -78,10 +151,516 @@ impl Default for Config {
             docs_dir: docs_dir_default(),
             thumbnails_dir: thumbnails_dir_default(),
             index_dir: index_dir_default(),
+            data_dir: data_dir_default(),
+            remove_orphan_thumbnails: false,
+            watcher: WatcherConfig::default(),
+            links: LinksConfig::default(),
+            quota: QuotaConfig::default(),
+            users: UsersConfig::default(),
+            rate_limit: RateLimitConfig::default(),
+            server: ServerConfig::default(),
+            ocr: OcrConfig::default(),
+            scanner: ScannerConfig::default(),
+            archives: ArchivesConfig::default(),
+            thumbnails: ThumbnailsConfig::default(),
+            auth: auth_default(),
+        }
+    }
+}
+
+/// Settings of watching for new documents.
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct WatcherConfig {
+    pub backend: WatcherBackend,
+    /// How often the polling backend scans the watched directory, in milliseconds.
+    pub poll_interval_ms: u64,
+    /// How long the size of a new file needs to stay the same before it's considered fully
+    /// written, in milliseconds.
+    pub settle_time_ms: u64,
+}
+
+impl WatcherConfig {
+    pub fn poll_interval(&self) -> Duration {
+        Duration::from_millis(self.poll_interval_ms)
+    }
+
+    pub fn settle_time(&self) -> Duration {
+        Duration::from_millis(self.settle_time_ms)
+    }
+}
+
+impl Default for WatcherConfig {
+    fn default() -> Self {
+        Self {
+            backend: WatcherBackend::default(),
+            poll_interval_ms: 2000,
+            settle_time_ms: 500,
+        }
+    }
+}
+
+/// Settings of public links to documents.
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct LinksConfig {
+    /// Validity of a link when it's not specified while creating it, in seconds.
+    pub default_ttl_secs: u64,
+    /// Longest allowed validity of a link, in seconds.
+    pub max_ttl_secs: u64,
+}
+
+impl Default for LinksConfig {
+    fn default() -> Self {
+        Self {
+            default_ttl_secs: 24 * 60 * 60,
+            max_ttl_secs: 7 * 24 * 60 * 60,
+        }
+    }
+}
+
+/// Limits of the storage used by a single user.
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct QuotaConfig {
+    /// Maximum number of documents of a user.
+    pub max_docs: u64,
+    /// Maximum size of documents, thumbnails and index of a user, in bytes.
+    pub max_bytes: u64,
+    /// Maximum size of a single uploaded document, in bytes.
+    pub max_upload_bytes: u64,
+}
+
+impl Default for QuotaConfig {
+    fn default() -> Self {
+        Self {
+            max_docs: 10_000,
+            max_bytes: 10 * 1024 * 1024 * 1024,
+            max_upload_bytes: 100 * 1024 * 1024,
+        }
+    }
+}
+
+/// Administration of the users allowed to use the server.
+#[derive(Debug, Default, PartialEq, Eq, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct UsersConfig {
+    /// Emails of the administrators. They are always allowed and can manage other users.
+    pub admins: Vec<String>,
+    /// New users need to be approved by an administrator before they can use the server. When
+    /// disabled, every user with valid credentials is approved on the first request.
+    pub require_approval: bool,
+}
+
+/// Limits of the requests, protecting the server from floods of requests and guessing of the
+/// credentials.
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    /// Failed authentications from a single IP address within a minute, after which its requests
+    /// are rejected until the minute passes. Reaching the limit is logged. 0 disables the limit.
+    pub max_auth_failures_per_minute: u32,
+    pub search: LimitConfig,
+    pub upload: LimitConfig,
+    /// Limit of downloaded documents, including the ones opened with public links.
+    pub download: LimitConfig,
+    /// Address of the reverse proxy in front of the server. Only requests coming from it are
+    /// attributed to the address from their `X-Real-IP` header, which can be set by anyone
+    /// otherwise.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trusted_proxy: Option<IpAddr>,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            max_auth_failures_per_minute: 20,
+            search: LimitConfig::new(60, 120, 20),
+            upload: LimitConfig::new(20, 40, 10),
+            download: LimitConfig::new(300, 600, 50),
+            trusted_proxy: None,
+        }
+    }
+}
+
+/// Rate of the requests to a single endpoint, refilled continuously like a token bucket.
+#[derive(Debug, Default, PartialEq, Eq, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct LimitConfig {
+    /// Requests per minute of a single user. 0 disables the limit.
+    pub user_per_minute: u32,
+    /// Requests per minute from a single IP address. 0 disables the limit.
+    pub ip_per_minute: u32,
+    /// Requests which can be sent at once, before the rate applies.
+    pub burst: u32,
+}
+
+impl LimitConfig {
+    pub fn new(user_per_minute: u32, ip_per_minute: u32, burst: u32) -> Self {
+        Self {
+            user_per_minute,
+            ip_per_minute,
+            burst,
+        }
+    }
+}
+
+/// Network settings of the HTTP server.
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct ServerConfig {
+    pub address: IpAddr,
+    pub port: u16,
+    pub tls: TlsConfig,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            address: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            port: 8000,
+            tls: TlsConfig::default(),
+        }
+    }
+}
+
+/// Encryption of the connections. Without it, credentials and documents are sent in plain text.
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct TlsConfig {
+    pub enabled: bool,
+    /// PEM file with the certificate chain. `tls/cert.pem` in the data directory when missing.
+    pub cert_path: Option<PathBuf>,
+    /// PEM file with the private key. `tls/key.pem` in the data directory when missing.
+    pub key_path: Option<PathBuf>,
+    /// Generates self-signed certificate when there is none yet. Its fingerprint is printed
+    /// during startup, so the client can pin it.
+    pub self_signed: bool,
+}
+
+impl Default for TlsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            cert_path: None,
+            key_path: None,
+            self_signed: true,
+        }
+    }
+}
+
+/// Settings of the text recognition in images.
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct OcrConfig {
+    /// Tesseract languages used to recognize the text, e.g. `["pol", "eng"]`. Each of them needs
+    /// its `.traineddata` file. Uploaded document can request other languages.
+    pub languages: Vec<String>,
+    /// Directory with `.traineddata` files. Default location of Tesseract is used when missing.
+    pub tessdata_dir: Option<PathBuf>,
+    /// Tesseract page segmentation mode, see `tesseract --help-psm`.
+    pub page_segmentation_mode: u8,
+    /// Resolution assumed for images which don't specify it. 0 leaves it to Tesseract.
+    pub dpi: u16,
+    pub preprocessing: PreprocessingConfig,
+}
+
+impl OcrConfig {
+    /// Returns the languages in the format expected by Tesseract, e.g. `pol+eng`.
+    pub fn languages(&self) -> String {
+        self.languages.join("+")
+    }
+}
+
+impl Default for OcrConfig {
+    fn default() -> Self {
+        Self {
+            languages: vec!["pol".into()],
+            tessdata_dir: None,
+            page_segmentation_mode: 3,
+            dpi: 300,
+            preprocessing: PreprocessingConfig::default(),
         }
     }
 }
 
+/// Cleanup of the images before the text recognition. Phone photos of documents are often
+/// rotated, skewed, shadowed and small, which lowers the accuracy of OCR.
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct PreprocessingConfig {
+    /// Disables all the steps below. Images are passed to Tesseract as they are.
+    pub enabled: bool,
+    /// Rotates the image according to its EXIF orientation.
+    pub exif_rotation: bool,
+    /// Straightens the lines of the text.
+    pub deskew: bool,
+    /// Largest skew corrected, in degrees.
+    pub max_skew_degrees: u8,
+    /// Removes the noise with a median filter.
+    pub denoise: bool,
+    /// Converts the image to black and white, comparing each pixel with its neighbourhood, so
+    /// shadows don't turn into black areas.
+    pub binarize: bool,
+    /// Radius of the neighbourhood used by the binarization, in pixels.
+    pub binarization_radius: u32,
+    /// Images narrower than an A4 page at this resolution are upscaled. 0 disables upscaling.
+    pub target_dpi: u16,
+}
+
+impl Default for PreprocessingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            exif_rotation: true,
+            deskew: true,
+            max_skew_degrees: 10,
+            denoise: true,
+            binarize: true,
+            binarization_radius: 15,
+            target_dpi: 300,
+        }
+    }
+}
+
+/// Detection of the page on the photos of the documents. Found page is cut out of the background
+/// and its perspective is corrected, so the stored image looks like a scan.
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct ScannerConfig {
+    pub enabled: bool,
+    /// Smallest part of the photo, in percents, which can be taken for the page. Smaller shapes
+    /// are ignored, e.g. a business card lying on the document.
+    pub min_page_area: u8,
+    /// Page covering larger part of the photo, in percents, is left as it is. Such images are
+    /// usually scans already.
+    pub max_page_area: u8,
+}
+
+impl Default for ScannerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_page_area: 20,
+            max_page_area: 95,
+        }
+    }
+}
+
+/// Unpacking of ZIP archives and of the attachments of emails. Archives come from untrusted
+/// sources, so the limits protect the disk against archives unpacking to huge sizes (zip bombs).
+/// Archive exceeding any of them is rejected as a whole.
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct ArchivesConfig {
+    pub enabled: bool,
+    /// Most documents unpacked from a single archive.
+    pub max_members: u32,
+    /// Largest unpacked document, in bytes.
+    pub max_member_bytes: u64,
+    /// Most bytes unpacked from a single archive, all documents together.
+    pub max_total_bytes: u64,
+    /// Highest allowed ratio of the unpacked size of a document to its packed size.
+    pub max_ratio: u64,
+}
+
+impl Default for ArchivesConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_members: 1000,
+            max_member_bytes: 100 * 1024 * 1024,
+            max_total_bytes: 1024 * 1024 * 1024,
+            max_ratio: 100,
+        }
+    }
+}
+
+/// Thumbnails of the documents. Images and PDF documents have a small thumbnail shown in the list
+/// of documents and a larger one shown as its preview, see
+/// [`ThumbnailSize`](crate::entities::file::ThumbnailSize). Both are rendered also for high density
+/// screens. PDF documents have the thumbnail of their cover page.
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct ThumbnailsConfig {
+    pub format: ThumbnailFormat,
+    /// Quality of the lossy compression, from 1 (smallest files) to 100 (best looking).
+    pub quality: u8,
+    /// Longest side of the thumbnails shown in the list of documents, in pixels.
+    pub tile_px: u32,
+    /// Longest side of the thumbnails shown as the preview of the document, in pixels.
+    pub preview_px: u32,
+    /// Width of the box the cover page of PDF is fitted into, keeping its proportions, in CSS
+    /// pixels.
+    pub pdf_width: u32,
+    /// Height of the box the cover page of PDF is fitted into, in CSS pixels.
+    pub pdf_height: u32,
+    /// Device pixel ratios, other than 1, for which additional thumbnails are rendered.
+    pub pixel_ratios: Vec<u32>,
+    /// Number of the PDF page used as the thumbnail, starting from 1. The last page is used when
+    /// the document is shorter.
+    pub cover_page: u32,
+}
+
+impl Default for ThumbnailsConfig {
+    fn default() -> Self {
+        Self {
+            format: ThumbnailFormat::default(),
+            quality: 80,
+            tile_px: 320,
+            preview_px: 1280,
+            pdf_width: 320,
+            pdf_height: 452,
+            pixel_ratios: vec![2],
+            cover_page: 1,
+        }
+    }
+}
+
+impl ThumbnailsConfig {
+    /// Device pixel ratios to render the thumbnails for, the standard one first.
+    pub fn ratios(&self) -> Vec<u32> {
+        let mut ratios = vec![1];
+        for &ratio in &self.pixel_ratios {
+            if ratio > 1 && !ratios.contains(&ratio) {
+                ratios.push(ratio);
+            }
+        }
+        ratios
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Deserialize, Serialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum ThumbnailFormat {
+    #[default]
+    Webp,
+    Jpeg,
+}
+
+impl ThumbnailFormat {
+    /// Extension of the thumbnails in this format.
+    pub fn ext(self) -> &'static str {
+        match self {
+            ThumbnailFormat::Webp => "webp",
+            ThumbnailFormat::Jpeg => "jpg",
+        }
+    }
+}
+
+/// Mechanism used to detect changes in the watched directory.
+#[derive(Debug, Default, PartialEq, Eq, Deserialize, Serialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum WatcherBackend {
+    /// Uses notifications of the operating system (e.g. inotify).
+    #[default]
+    Native,
+    /// Periodically scans the watched directory. Works on network shares (NFS, SMB) and bind
+    /// mounts, where native notifications are often not delivered.
+    Polling,
+    /// Uses both mechanisms at the same time.
+    Both,
+}
+
+impl WatcherBackend {
+    pub fn is_native(self) -> bool {
+        matches!(self, Self::Native | Self::Both)
+    }
+
+    pub fn is_polling(self) -> bool {
+        matches!(self, Self::Polling | Self::Both)
+    }
+}
+
+/// Source of the user's identity.
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize, Clone)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum AuthConfig {
+    /// ID tokens issued by an OpenID Connect provider.
+    Oidc(OidcConfig),
+    /// Static API tokens, e.g. for scripts.
+    Tokens(TokensConfig),
+    /// Users with passwords stored in the configuration, sent using HTTP Basic authentication.
+    Local(LocalConfig),
+}
+
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize, Clone)]
+pub struct OidcConfig {
+    /// Expected `iss` claim of the token.
+    pub issuer: String,
+    /// Location of the keys used to verify signatures of the tokens.
+    pub jwks_url: String,
+    /// Expected `aud` claim of the token, i.e. client id of the application. The application
+    /// doesn't start until it's configured, otherwise tokens issued for any application would be
+    /// accepted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audience: Option<String>,
+    /// Domains of emails which are allowed to log in. All domains are allowed when empty.
+    #[serde(default)]
+    pub allowed_domains: Vec<String>,
+    /// How long fetched keys are used before they are fetched again, in seconds.
+    #[serde(default = "jwks_cache_ttl_default")]
+    pub jwks_cache_ttl_secs: u64,
+    /// Tolerated difference between clocks of the server and the provider, in seconds.
+    #[serde(default = "clock_skew_default")]
+    pub clock_skew_secs: u64,
+}
+
+impl OidcConfig {
+    pub fn jwks_cache_ttl(&self) -> Duration {
+        Duration::from_secs(self.jwks_cache_ttl_secs)
+    }
+
+    pub fn google() -> Self {
+        Self {
+            issuer: "https://accounts.google.com".into(),
+            jwks_url: "https://www.googleapis.com/oauth2/v3/certs".into(),
+            audience: None,
+            allowed_domains: Vec::new(),
+            jwks_cache_ttl_secs: jwks_cache_ttl_default(),
+            clock_skew_secs: clock_skew_default(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize, Clone)]
+pub struct TokensConfig {
+    pub tokens: Vec<ApiToken>,
+}
+
+/// Token is not stored in the configuration, only its SHA-256 hash (hex encoded).
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize, Clone)]
+pub struct ApiToken {
+    pub email: String,
+    pub sha256: String,
+}
+
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize, Clone)]
+pub struct LocalConfig {
+    pub users: Vec<LocalUser>,
+}
+
+/// Password is stored as Argon2 hash in PHC string format.
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize, Clone)]
+pub struct LocalUser {
+    pub email: String,
+    pub password_hash: String,
+}
+
+/// Google accounts are used, as before the providers were configurable. Client id of the
+/// application is not known, so the application doesn't start until `audience` is set.
+fn auth_default() -> Vec<AuthConfig> {
+    vec![AuthConfig::Oidc(OidcConfig::google())]
+}
+
+fn jwks_cache_ttl_default() -> u64 {
+    3600
+}
+
+fn clock_skew_default() -> u64 {
+    60
+}
+
 impl AsRef<Config> for Config {
     fn as_ref(&self) -> &Config {
         self
@@ -106,6 +685,12 @@ fn thumbnails_dir_default() -> PathBuf {
         .join("dox/thumbnails")
 }
 
+fn data_dir_default() -> PathBuf {
+    dirs::data_dir()
+        .expect("failed to read system data path")
+        .join("dox/data")
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -122,6 +707,101 @@ mod test {
             docs_dir: dirs::data_dir().unwrap().join("dox/docs"),
             thumbnails_dir: dirs::data_dir().unwrap().join("dox/thumbnails"),
             index_dir: dirs::data_dir().unwrap().join("dox/index"),
+            data_dir: dirs::data_dir().unwrap().join("dox/data"),
+            remove_orphan_thumbnails: false,
+            watcher: WatcherConfig {
+                backend: WatcherBackend::Native,
+                poll_interval_ms: 2000,
+                settle_time_ms: 500,
+            },
+            links: LinksConfig {
+                default_ttl_secs: 86400,
+                max_ttl_secs: 604_800,
+            },
+            quota: QuotaConfig {
+                max_docs: 10_000,
+                max_bytes: 10_737_418_240,
+                max_upload_bytes: 104_857_600,
+            },
+            users: UsersConfig {
+                admins: Vec::new(),
+                require_approval: false,
+            },
+            rate_limit: RateLimitConfig {
+                max_auth_failures_per_minute: 20,
+                search: LimitConfig {
+                    user_per_minute: 60,
+                    ip_per_minute: 120,
+                    burst: 20,
+                },
+                upload: LimitConfig {
+                    user_per_minute: 20,
+                    ip_per_minute: 40,
+                    burst: 10,
+                },
+                download: LimitConfig {
+                    user_per_minute: 300,
+                    ip_per_minute: 600,
+                    burst: 50,
+                },
+                trusted_proxy: None,
+            },
+            server: ServerConfig {
+                address: IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)),
+                port: 8000,
+                tls: TlsConfig {
+                    enabled: false,
+                    cert_path: None,
+                    key_path: None,
+                    self_signed: true,
+                },
+            },
+            ocr: OcrConfig {
+                languages: vec!["pol".into()],
+                tessdata_dir: None,
+                page_segmentation_mode: 3,
+                dpi: 300,
+                preprocessing: PreprocessingConfig {
+                    enabled: true,
+                    exif_rotation: true,
+                    deskew: true,
+                    max_skew_degrees: 10,
+                    denoise: true,
+                    binarize: true,
+                    binarization_radius: 15,
+                    target_dpi: 300,
+                },
+            },
+            scanner: ScannerConfig {
+                enabled: true,
+                min_page_area: 20,
+                max_page_area: 95,
+            },
+            archives: ArchivesConfig {
+                enabled: true,
+                max_members: 1000,
+                max_member_bytes: 104_857_600,
+                max_total_bytes: 1_073_741_824,
+                max_ratio: 100,
+            },
+            thumbnails: ThumbnailsConfig {
+                format: ThumbnailFormat::Webp,
+                quality: 80,
+                tile_px: 320,
+                preview_px: 1280,
+                pdf_width: 320,
+                pdf_height: 452,
+                pixel_ratios: vec![2],
+                cover_page: 1,
+            },
+            auth: vec![AuthConfig::Oidc(OidcConfig {
+                issuer: "https://accounts.google.com".into(),
+                jwks_url: "https://www.googleapis.com/oauth2/v3/certs".into(),
+                audience: None,
+                allowed_domains: Vec::new(),
+                jwks_cache_ttl_secs: 3600,
+                clock_skew_secs: 60,
+            })],
         };
 
         // when
@@ -193,4 +873,60 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn auth_without_audience_is_rejected() -> Result<()> {
+        // given
+        let without = Config::default();
+        let blank = Config {
+            auth: vec![AuthConfig::Oidc(OidcConfig {
+                audience: Some(" ".into()),
+                ..OidcConfig::google()
+            })],
+            ..Config::default()
+        };
+        let with = Config {
+            auth: vec![AuthConfig::Oidc(OidcConfig {
+                audience: Some("dox".into()),
+                ..OidcConfig::google()
+            })],
+            ..Config::default()
+        };
+
+        // when
+        let rejected = without.check_auth();
+        let rejected_blank = blank.check_auth();
+        let accepted = with.check_auth();
+
+        // then
+        assert!(
+            matches!(rejected, Err(ConfigurationErr::MissingAudience(issuer)) if issuer == "https://accounts.google.com")
+        );
+        assert!(matches!(
+            rejected_blank,
+            Err(ConfigurationErr::MissingAudience(_))
+        ));
+        assert!(accepted.is_ok());
+
+        Ok(())
+    }
+
+    #[test]
+    fn tls_paths_default_to_data_dir() -> Result<()> {
+        // given
+        let data_dir = tempdir()?;
+        let config = Config {
+            data_dir: data_dir.path().to_path_buf(),
+            ..Default::default()
+        };
+
+        // when
+        let (cert, key) = config.tls_paths();
+
+        // then
+        assert_eq!(cert, data_dir.path().join("tls/cert.pem"));
+        assert_eq!(key, data_dir.path().join("tls/key.pem"));
+
+        Ok(())
+    }
 }
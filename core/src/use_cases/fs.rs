@@ -23,4 +23,8 @@ pub trait Filesystem: Sync + Send {
 
     /// Moves file from `from` path to `to` path.
     fn mv_file(&self, from: &SafePathBuf, to: &Path) -> Result<(), FsErr>;
+
+    /// Removes directories with all their content. Either all of them are removed, or none.
+    /// Directories which don't exist are skipped.
+    fn rm_dirs(&self, dirs: &[PathBuf]) -> Result<(), FsErr>;
 }
@@ -0,0 +1,186 @@
+//! Checks whether the application is able to do its job.
+use crate::use_cases::cipher::{CipherReader, CipherWriter};
+use crate::use_cases::config::Config;
+use crate::use_cases::supervisor::Supervisor;
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use tracing::{instrument, warn};
+
+const KEY_PROBE: &[u8] = b"dox";
+
+pub struct HealthCheck {
+    cfg: Config,
+    cipher_reader: CipherReader,
+    cipher_writer: CipherWriter,
+    supervisor: Arc<Supervisor>,
+}
+
+impl HealthCheck {
+    pub fn new(
+        cfg: Config,
+        cipher_reader: CipherReader,
+        cipher_writer: CipherWriter,
+        supervisor: Arc<Supervisor>,
+    ) -> Self {
+        Self {
+            cfg,
+            cipher_reader,
+            cipher_writer,
+            supervisor,
+        }
+    }
+
+    /// Checks if threads of all the services are alive.
+    #[instrument(skip(self))]
+    pub fn liveness(&self) -> HealthReport {
+        let mut report = HealthReport::new();
+        for (name, alive) in self.supervisor.status() {
+            report.check(format!("service:{name}"), alive);
+        }
+        report
+    }
+
+    /// Checks if the services are alive and if all the resources needed to process and serve
+    /// documents are available.
+    #[instrument(skip(self))]
+    pub fn readiness(&self) -> HealthReport {
+        let mut report = self.liveness();
+        report.check("watched_dir", is_writable_dir(&self.cfg.watched_dir));
+        report.check("docs_dir", is_writable_dir(&self.cfg.docs_dir));
+        report.check("thumbnails_dir", is_writable_dir(&self.cfg.thumbnails_dir));
+        report.check("index_dir", index_dirs_valid(&self.cfg.index_dir));
+        report.check("key", self.key_available());
+        report
+    }
+
+    fn key_available(&self) -> bool {
+        self.cipher_writer
+            .encrypt(KEY_PROBE)
+            .and_then(|encrypted| self.cipher_reader.decrypt(&encrypted))
+            .is_ok()
+    }
+}
+
+fn is_writable_dir(path: &Path) -> bool {
+    fs::metadata(path).map_or(false, |m| m.is_dir() && !m.permissions().readonly())
+}
+
+/// Index directory holds one directory per user.
+fn index_dirs_valid(index_dir: &Path) -> bool {
+    if !is_writable_dir(index_dir) {
+        return false;
+    }
+    let Ok(entries) = fs::read_dir(index_dir) else {
+        return false;
+    };
+    entries
+        .map(|entry| entry.map(|e| e.path()))
+        .all(|path| path.map_or(false, |p| is_writable_dir(&p)))
+}
+
+/// Result of all the checks along with the overall status.
+#[derive(Debug, Serialize, PartialEq, Eq)]
+pub struct HealthReport {
+    healthy: bool,
+    checks: BTreeMap<String, bool>,
+}
+
+impl HealthReport {
+    fn new() -> Self {
+        Self {
+            healthy: true,
+            checks: BTreeMap::new(),
+        }
+    }
+
+    fn check<S: Into<String>>(&mut self, name: S, passed: bool) {
+        let name = name.into();
+        if !passed {
+            warn!("health check '{}' failed", name);
+        }
+        self.healthy &= passed;
+        self.checks.insert(name, passed);
+    }
+
+    pub fn is_healthy(&self) -> bool {
+        self.healthy
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::configuration::factories::event_bus;
+    use crate::configuration::telemetry::init_tracing;
+    use crate::testingtools::services::encrypter::{failing, working};
+    use crate::testingtools::TestConfig;
+    use crate::use_cases::cipher::Cipher;
+
+    use anyhow::Result;
+
+    fn health_check(cfg: &TestConfig, cipher: &Cipher) -> Result<HealthCheck> {
+        Ok(HealthCheck::new(
+            cfg.into(),
+            cipher.reader(),
+            cipher.writer(),
+            Arc::new(Supervisor::new(event_bus()?)),
+        ))
+    }
+
+    #[test]
+    fn readiness_passes_when_all_resources_are_available() -> Result<()> {
+        // given
+        init_tracing();
+        let cfg = TestConfig::new()?;
+        let check = health_check(&cfg, &working())?;
+
+        // when
+        let report = check.readiness();
+
+        // then
+        assert!(report.is_healthy());
+
+        Ok(())
+    }
+
+    #[test]
+    fn readiness_fails_when_thumbnails_dir_is_missing() -> Result<()> {
+        // given
+        init_tracing();
+        let cfg = TestConfig::new()?;
+        let config: Config = (&cfg).into();
+        fs::remove_dir_all(&config.thumbnails_dir)?;
+        let check = health_check(&cfg, &working())?;
+
+        // when
+        let report = check.readiness();
+
+        // then
+        assert!(!report.is_healthy());
+        assert_eq!(report.checks.get("thumbnails_dir"), Some(&false));
+
+        Ok(())
+    }
+
+    #[test]
+    fn readiness_fails_when_key_is_not_available() -> Result<()> {
+        // given
+        init_tracing();
+        let cfg = TestConfig::new()?;
+        let check = health_check(&cfg, &failing())?;
+
+        // when
+        let report = check.readiness();
+
+        // then
+        assert!(!report.is_healthy());
+        assert_eq!(report.checks.get("key"), Some(&false));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,396 @@
+//! Public links to documents, which can be opened without logging in.
+//!
+//! The token of the link is signed, so it can't be forged or its expiration time changed. Links
+//! are also kept by the server, so they can be revoked and each access to them is recorded.
+use crate::entities::file::Filename;
+use crate::entities::user::User;
+use crate::result::LinkErr;
+use crate::use_cases::config::{Config, LinksConfig};
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD as b64url;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{debug, instrument, warn};
+
+pub type Links = Arc<dyn LinkStore>;
+pub type Signer = Arc<dyn LinkSigner>;
+
+/// Number of the last accesses kept with the link, the older ones are dropped.
+pub const MAX_ACCESSES: usize = 100;
+
+/// Keeps all the links created by the users.
+pub trait LinkStore: Sync + Send {
+    fn add(&self, link: ShareLink) -> Result<(), LinkErr>;
+    fn get(&self, id: &str) -> Result<Option<ShareLink>, LinkErr>;
+    /// Returns all the links of the `owner`, including expired and revoked ones.
+    fn links_of(&self, owner: &User) -> Result<Vec<ShareLink>, LinkErr>;
+    /// Marks the link as revoked. Returns `false` when the `owner` has no such link.
+    fn revoke(&self, owner: &User, id: &str) -> Result<bool, LinkErr>;
+    /// Returns the link when it can be opened at `now`. The access is recorded only when it's
+    /// denied, otherwise it's recorded with [`LinkStore::record`], once the document is read.
+    fn open(&self, id: &str, ip: Option<String>, now: u64) -> Result<ShareLink, LinkErr>;
+    /// Records the access to the opened link. Single-use link is marked as used when the
+    /// document was returned, or [`LinkErr::AlreadyUsed`] is returned when it was used in the
+    /// meantime, so it's returned at most once.
+    fn record(&self, id: &str, access: LinkAccess) -> Result<(), LinkErr>;
+}
+
+/// Signs the tokens of the links.
+pub trait LinkSigner: Sync + Send {
+    /// Returns signature of the `payload`, safe to be used in the URL.
+    fn sign(&self, payload: &str) -> String;
+    fn verify(&self, payload: &str, signature: &str) -> bool;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShareLink {
+    pub id: String,
+    pub owner: User,
+    pub filename: Filename,
+    /// Unix timestamp, in seconds.
+    pub created_at: u64,
+    /// Unix timestamp, in seconds.
+    pub expires_at: u64,
+    pub single_use: bool,
+    #[serde(default)]
+    pub used: bool,
+    #[serde(default)]
+    pub revoked: bool,
+    #[serde(default)]
+    pub accesses: Vec<LinkAccess>,
+}
+
+impl ShareLink {
+    /// Checks if the link can be opened at `now`.
+    pub fn check(&self, now: u64) -> Result<(), LinkErr> {
+        if self.revoked {
+            Err(LinkErr::Revoked)
+        } else if now >= self.expires_at {
+            Err(LinkErr::Expired)
+        } else if self.single_use && self.used {
+            Err(LinkErr::AlreadyUsed)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Adds the access to the history, dropping the oldest ones above [`MAX_ACCESSES`].
+    pub fn add_access(&mut self, access: LinkAccess) {
+        self.accesses.push(access);
+        let excess = self.accesses.len().saturating_sub(MAX_ACCESSES);
+        self.accesses.drain(..excess);
+    }
+}
+
+/// Single attempt to open the link.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LinkAccess {
+    /// Unix timestamp, in seconds.
+    pub at: u64,
+    pub ip: Option<String>,
+    /// Tells if the document was returned.
+    pub granted: bool,
+}
+
+/// Link returned to the owner after it's created.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MintedLink {
+    pub id: String,
+    pub url: String,
+    pub expires_at: u64,
+}
+
+/// Creates and opens public links.
+pub struct PublicLinks {
+    store: Links,
+    signer: Signer,
+    cfg: LinksConfig,
+}
+
+impl PublicLinks {
+    pub fn new(store: Links, signer: Signer, cfg: &Config) -> Self {
+        Self {
+            store,
+            signer,
+            cfg: cfg.links.clone(),
+        }
+    }
+
+    /// Creates link valid for `ttl_secs` seconds, or for the configured default time when it's
+    /// not passed.
+    #[instrument(skip(self))]
+    pub fn mint(
+        &self,
+        owner: User,
+        filename: Filename,
+        ttl_secs: Option<u64>,
+        single_use: bool,
+    ) -> Result<MintedLink, LinkErr> {
+        let ttl_secs = ttl_secs.unwrap_or(self.cfg.default_ttl_secs);
+        if ttl_secs > self.cfg.max_ttl_secs {
+            return Err(LinkErr::TtlTooLong(self.cfg.max_ttl_secs));
+        }
+        let now = now();
+        let link = ShareLink {
+            id: b64url.encode(rand::random::<[u8; 16]>()),
+            owner,
+            filename,
+            created_at: now,
+            expires_at: now + ttl_secs,
+            single_use,
+            used: false,
+            revoked: false,
+            accesses: Vec::new(),
+        };
+        let minted = MintedLink {
+            id: link.id.clone(),
+            url: format!("/link/{}", self.token(&link)),
+            expires_at: link.expires_at,
+        };
+        self.store.add(link)?;
+        debug!("created link '{}'", minted.id);
+        Ok(minted)
+    }
+
+    /// Returns the document of the link, read with `read`, when the token is valid. The access
+    /// is recorded whether it's granted or not, and single-use link is used only when the document
+    /// was read.
+    #[instrument(skip(self, token, read))]
+    pub fn open<T, E, F>(&self, token: &str, ip: Option<String>, read: F) -> Result<T, E>
+    where
+        F: FnOnce(&ShareLink) -> Result<T, E>,
+        E: From<LinkErr>,
+    {
+        let Some(id) = self.verify(token) else {
+            warn!(
+                "link with invalid signature opened from '{}'",
+                ip.as_deref().unwrap_or("unknown")
+            );
+            return Err(LinkErr::NotFound.into());
+        };
+        let now = now();
+        let link = self.store.open(id, ip.clone(), now)?;
+        let res = read(&link);
+        let access = LinkAccess {
+            at: now,
+            ip,
+            granted: res.is_ok(),
+        };
+        self.store.record(id, access)?;
+        res
+    }
+
+    /// Returns the link of the token without opening it.
+    pub fn find(&self, token: &str) -> Result<ShareLink, LinkErr> {
+        let Some(id) = self.verify(token) else {
+            return Err(LinkErr::NotFound);
+        };
+        self.store.get(id)?.ok_or(LinkErr::NotFound)
+    }
+
+    pub fn links_of(&self, owner: &User) -> Result<Vec<ShareLink>, LinkErr> {
+        self.store.links_of(owner)
+    }
+
+    pub fn revoke(&self, owner: &User, id: &str) -> Result<bool, LinkErr> {
+        self.store.revoke(owner, id)
+    }
+
+    fn token(&self, link: &ShareLink) -> String {
+        let payload = format!("{}.{}", link.id, link.expires_at);
+        let signature = self.signer.sign(&payload);
+        format!("{payload}.{signature}")
+    }
+
+    /// Returns id of the link when the token is signed correctly.
+    fn verify<'a>(&self, token: &'a str) -> Option<&'a str> {
+        let (payload, signature) = token.rsplit_once('.')?;
+        if !self.signer.verify(payload, signature) {
+            return None;
+        }
+        payload.split_once('.').map(|(id, _)| id)
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::configuration::factories::{link_signer, links};
+    use crate::configuration::telemetry::init_tracing;
+    use crate::testingtools::TestConfig;
+
+    use anyhow::Result;
+    use claim::assert_matches;
+
+    fn public_links(cfg: &TestConfig) -> Result<PublicLinks> {
+        let cfg = cfg.as_ref();
+        Ok(PublicLinks::new(links(cfg)?, link_signer(cfg)?, cfg))
+    }
+
+    fn token(minted: &MintedLink) -> &str {
+        minted.url.trim_start_matches("/link/")
+    }
+
+    fn open(links: &PublicLinks, token: &str, ip: Option<String>) -> Result<ShareLink, LinkErr> {
+        links.open(token, ip, |link| Ok(link.clone()))
+    }
+
+    fn mint(links: &PublicLinks, ttl_secs: Option<u64>, single_use: bool) -> Result<MintedLink> {
+        Ok(links.mint(
+            User::new("owner@email.com"),
+            Filename::new("doc.pdf")?,
+            ttl_secs,
+            single_use,
+        )?)
+    }
+
+    #[test]
+    fn opened_link_returns_document_and_records_access() -> Result<()> {
+        // given
+        init_tracing();
+        let cfg = TestConfig::new()?;
+        let links = public_links(&cfg)?;
+        let minted = mint(&links, None, false)?;
+
+        // when
+        let link = open(&links, token(&minted), Some("10.0.0.1".into()))?;
+
+        // then
+        assert_eq!(link.filename, Filename::new("doc.pdf")?);
+        assert_eq!(link.owner, User::new("owner@email.com"));
+        let recorded = links.links_of(&User::new("owner@email.com"))?;
+        assert_eq!(recorded[0].accesses.len(), 1);
+        assert_eq!(recorded[0].accesses[0].ip, Some("10.0.0.1".into()));
+        assert!(recorded[0].accesses[0].granted);
+
+        Ok(())
+    }
+
+    #[test]
+    fn single_use_link_can_be_opened_once() -> Result<()> {
+        // given
+        init_tracing();
+        let cfg = TestConfig::new()?;
+        let links = public_links(&cfg)?;
+        let minted = mint(&links, None, true)?;
+
+        // when
+        let first = open(&links, token(&minted), None);
+        let second = open(&links, token(&minted), None);
+
+        // then
+        assert!(first.is_ok());
+        assert_matches!(second, Err(LinkErr::AlreadyUsed));
+        let recorded = links.links_of(&User::new("owner@email.com"))?;
+        assert_eq!(recorded[0].accesses.len(), 2);
+        assert!(!recorded[0].accesses[1].granted);
+
+        Ok(())
+    }
+
+    #[test]
+    fn single_use_link_is_not_used_when_document_can_not_be_read() -> Result<()> {
+        // given
+        init_tracing();
+        let cfg = TestConfig::new()?;
+        let links = public_links(&cfg)?;
+        let minted = mint(&links, None, true)?;
+        let failed: Result<ShareLink, LinkErr> = links.open(token(&minted), None, |_| {
+            Err(LinkErr::DocumentNotFound("doc.pdf".into()))
+        });
+
+        // when
+        let res = open(&links, token(&minted), None);
+
+        // then
+        assert_matches!(failed, Err(LinkErr::DocumentNotFound(_)));
+        assert!(res.is_ok());
+        let recorded = links.links_of(&User::new("owner@email.com"))?;
+        assert!(!recorded[0].accesses[0].granted);
+        assert!(recorded[0].accesses[1].granted);
+        assert!(recorded[0].used);
+
+        Ok(())
+    }
+
+    #[test]
+    fn link_with_changed_expiration_time_is_rejected() -> Result<()> {
+        // given
+        init_tracing();
+        let cfg = TestConfig::new()?;
+        let links = public_links(&cfg)?;
+        let minted = mint(&links, None, false)?;
+        let (_, signature) = token(&minted).rsplit_once('.').unwrap_or_default();
+        let forged = format!("{}.{}.{}", minted.id, minted.expires_at + 3600, signature);
+
+        // when
+        let res = open(&links, &forged, None);
+
+        // then
+        assert_matches!(res, Err(LinkErr::NotFound));
+
+        Ok(())
+    }
+
+    #[test]
+    fn revoked_link_can_not_be_opened() -> Result<()> {
+        // given
+        init_tracing();
+        let cfg = TestConfig::new()?;
+        let links = public_links(&cfg)?;
+        let minted = mint(&links, None, false)?;
+
+        // when
+        let revoked = links.revoke(&User::new("owner@email.com"), &minted.id)?;
+        let res = open(&links, token(&minted), None);
+
+        // then
+        assert!(revoked);
+        assert_matches!(res, Err(LinkErr::Revoked));
+
+        Ok(())
+    }
+
+    #[test]
+    fn expired_link_can_not_be_opened() -> Result<()> {
+        // given
+        init_tracing();
+        let cfg = TestConfig::new()?;
+        let links = public_links(&cfg)?;
+        let minted = mint(&links, Some(0), false)?;
+
+        // when
+        let res = open(&links, token(&minted), None);
+
+        // then
+        assert_matches!(res, Err(LinkErr::Expired));
+
+        Ok(())
+    }
+
+    #[test]
+    fn link_can_not_be_valid_longer_than_configured_maximum() -> Result<()> {
+        // given
+        init_tracing();
+        let cfg = TestConfig::new()?;
+        let links = public_links(&cfg)?;
+        let max_ttl = cfg.as_ref().links.max_ttl_secs;
+
+        // when
+        let res = mint(&links, Some(max_ttl + 1), false);
+
+        // then
+        assert!(res.is_err());
+
+        Ok(())
+    }
+}
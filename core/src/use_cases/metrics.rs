@@ -0,0 +1,78 @@
+//! Abstraction for collecting metrics of the application.
+//!
+//! The format in which the metrics are exposed is the implementation detail. See
+//! [`PrometheusMetrics`](crate::data_providers::metrics::PrometheusMetrics).
+use crate::entities::extension::Ext;
+use crate::result::MetricsErr;
+
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+pub type Metrics = Arc<dyn MetricsRecorder>;
+
+/// Records measurements of the document processing.
+pub trait MetricsRecorder: Sync + Send {
+    /// Counts documents which reached particular processing [`Stage`].
+    fn docs_processed(&self, stage: Stage, count: usize);
+
+    /// Records how long the text extraction of a document with [`Ext`] took. Text recognized
+    /// with OCR is recorded apart from the text read from the document.
+    fn extraction_finished(&self, ext: &Ext, extraction: Extraction, took: Duration);
+
+    /// Counts failed encryptions of documents or thumbnails.
+    fn encryption_failed(&self, target: EncryptionTarget);
+
+    /// Records how long the search took.
+    fn search_finished(&self, took: Duration);
+
+    /// Returns all collected metrics in a text form.
+    fn render(&self) -> Result<String, MetricsErr>;
+}
+
+/// Stage of the document processing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    Received,
+    Moved,
+    Thumbnailed,
+    Extracted,
+    Indexed,
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Stage::Received => write!(f, "received"),
+            Stage::Moved => write!(f, "moved"),
+            Stage::Thumbnailed => write!(f, "thumbnailed"),
+            Stage::Extracted => write!(f, "extracted"),
+            Stage::Indexed => write!(f, "indexed"),
+        }
+    }
+}
+
+/// How the text of the document was extracted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Extraction {
+    /// Text was read from the document.
+    Text,
+    /// Text was recognized in the images of the document.
+    Ocr,
+}
+
+/// Kind of the file which failed to be encrypted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EncryptionTarget {
+    Document,
+    Thumbnail,
+}
+
+impl fmt::Display for EncryptionTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EncryptionTarget::Document => write!(f, "document"),
+            EncryptionTarget::Thumbnail => write!(f, "thumbnail"),
+        }
+    }
+}
@@ -1,8 +1,22 @@
+pub mod audit;
+pub mod auth;
 pub mod bus;
 pub mod cipher;
 pub mod config;
 pub mod fs;
+pub mod health;
+pub mod links;
+pub mod metrics;
+pub mod ocr;
+pub mod pages;
+pub mod quota;
 pub mod receiver;
+pub mod scanner;
+pub mod sharing;
 pub mod state;
+pub mod supervisor;
+pub mod throttle;
+pub mod unpacker;
+pub mod users;
 
 pub mod services;
@@ -0,0 +1,101 @@
+//! Languages used to recognize the text of the documents.
+//!
+//! Documents are recognized using the languages from [`OcrConfig`], unless other languages were
+//! requested during the upload of the document. Requested languages are kept until the text of
+//! the document is extracted.
+//!
+//! [`OcrConfig`]: crate::use_cases::config::OcrConfig
+use crate::entities::file::Filename;
+use crate::entities::location::Location;
+use crate::entities::user::User;
+use crate::result::OcrErr;
+
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::fmt::Display;
+use std::sync::Arc;
+use tracing::warn;
+
+pub type LanguageOverrides = Arc<dyn OverrideStore>;
+
+/// Keeps the languages requested for the documents which are not extracted yet.
+pub trait OverrideStore: Sync + Send {
+    /// Requests the languages for the document, replacing previous request.
+    fn set(&self, user: &User, filename: &Filename, languages: OcrLanguages) -> Result<(), OcrErr>;
+    /// Returns the languages requested for the document and forgets them.
+    fn take(&self, user: &User, filename: &Filename) -> Result<Option<OcrLanguages>, OcrErr>;
+
+    /// Forgets the languages requested for the documents which won't be extracted, e.g. rejected
+    /// ones, so they are not applied to other documents uploaded later under the same name.
+    fn forget(&self, loc: &Location) {
+        let Location::FS(paths) = loc;
+        for path in paths {
+            let Ok(user) = User::try_from(path) else {
+                continue;
+            };
+            if let Err(e) = self.take(&user, &Filename::from(path)) {
+                warn!("failed to forget OCR languages of '{}': '{}'", path, e);
+            }
+        }
+    }
+}
+
+/// Tesseract language codes joined with `+`, e.g. `pol+eng`.
+///
+/// Codes are used to find the `.traineddata` files, so only letters, digits and `_` are allowed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OcrLanguages(String);
+
+impl OcrLanguages {
+    pub fn new<S: Into<String>>(languages: S) -> Result<Self, OcrErr> {
+        let languages = languages.into();
+        let valid = languages.split('+').all(|code| {
+            !code.is_empty() && code.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        });
+        if !valid {
+            return Err(OcrErr::InvalidLanguages(languages));
+        }
+        Ok(Self(languages))
+    }
+}
+
+impl AsRef<str> for OcrLanguages {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Display for OcrLanguages {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use claim::{assert_err, assert_ok};
+
+    #[test]
+    fn languages_are_validated() {
+        // given
+        let valid = ["pol", "pol+eng", "chi_sim+eng"];
+        let invalid = ["", "pol+", "../pol", "pol eng", "pol+eng/x"];
+
+        for languages in valid {
+            // when
+            let res = OcrLanguages::new(languages);
+
+            // then
+            assert_ok!(res);
+        }
+        for languages in invalid {
+            // when
+            let res = OcrLanguages::new(languages);
+
+            // then
+            assert_err!(res);
+        }
+    }
+}
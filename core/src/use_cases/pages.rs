@@ -0,0 +1,15 @@
+//! Images of single pages of the documents.
+//!
+//! Search tells which pages of the document matched, so the client can show just these pages
+//! instead of downloading the whole document.
+use crate::entities::extension::Ext;
+use crate::result::PageErr;
+
+use std::sync::Arc;
+
+pub type Pages = Arc<dyn PageRenderer>;
+
+pub trait PageRenderer: Sync + Send {
+    /// Renders page `number`, counted from 1, of the document with `ext` extension as PNG.
+    fn render(&self, doc: &[u8], ext: &Ext, number: u32) -> Result<Vec<u8>, PageErr>;
+}
@@ -0,0 +1,269 @@
+//! Limits of the storage used by the users.
+//!
+//! Storage used by the user includes their documents, thumbnails and index, as well as the
+//! documents which are waiting for processing in the watched directory.
+use crate::entities::file::Filename;
+use crate::entities::user::User;
+use crate::result::QuotaErr;
+use crate::use_cases::config::{Config, QuotaConfig};
+
+use serde::Serialize;
+use std::sync::Arc;
+use tracing::{debug, instrument};
+
+pub type Meter = Arc<dyn UsageMeter>;
+
+/// Measures the storage used by the user.
+pub trait UsageMeter: Sync + Send {
+    fn usage(&self, user: &User) -> Result<Usage, QuotaErr>;
+    /// Tells if the user already has a document with this name.
+    fn has_doc(&self, user: &User, filename: &Filename) -> bool;
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct Usage {
+    pub docs: u64,
+    pub docs_bytes: u64,
+    pub thumbnails_bytes: u64,
+    pub index_bytes: u64,
+    /// Documents in the watched directory, not processed yet. Documents replacing already
+    /// existing ones are not counted.
+    pub pending_docs: u64,
+    pub pending_bytes: u64,
+}
+
+impl Usage {
+    pub fn total_docs(&self) -> u64 {
+        self.docs + self.pending_docs
+    }
+
+    pub fn total_bytes(&self) -> u64 {
+        self.docs_bytes + self.thumbnails_bytes + self.index_bytes + self.pending_bytes
+    }
+}
+
+/// Usage of the storage together with the limits.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UsageReport {
+    #[serde(flatten)]
+    pub usage: Usage,
+    pub total_docs: u64,
+    pub total_bytes: u64,
+    #[serde(flatten)]
+    pub limits: QuotaConfig,
+}
+
+/// Documents and bytes which the user can still store.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Allowance {
+    pub docs: u64,
+    pub bytes: u64,
+}
+
+impl Allowance {
+    pub fn unlimited() -> Self {
+        Self {
+            docs: u64::MAX,
+            bytes: u64::MAX,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Quotas {
+    meter: Meter,
+    limits: QuotaConfig,
+}
+
+impl Quotas {
+    pub fn new(meter: Meter, cfg: &Config) -> Self {
+        Self {
+            meter,
+            limits: cfg.quota.clone(),
+        }
+    }
+
+    pub fn usage(&self, user: &User) -> Result<UsageReport, QuotaErr> {
+        let usage = self.meter.usage(user)?;
+        Ok(UsageReport {
+            total_docs: usage.total_docs(),
+            total_bytes: usage.total_bytes(),
+            usage,
+            limits: self.limits.clone(),
+        })
+    }
+
+    /// Checks if the document of `size` bytes can be uploaded by the user.
+    #[instrument(skip(self))]
+    pub fn check_upload(
+        &self,
+        user: &User,
+        filename: &Filename,
+        size: u64,
+    ) -> Result<(), QuotaErr> {
+        if size > self.limits.max_upload_bytes {
+            return Err(QuotaErr::DocumentTooLarge(self.limits.max_upload_bytes));
+        }
+        let usage = self.meter.usage(user)?;
+        let new_docs = u64::from(!self.meter.has_doc(user, filename));
+        self.check(usage.total_docs() + new_docs, usage.total_bytes() + size)
+    }
+
+    /// Checks if the storage already used by the user, including documents waiting in the
+    /// watched directory, fits in the limits.
+    #[instrument(skip(self))]
+    pub fn check_stored(&self, user: &User) -> Result<(), QuotaErr> {
+        let usage = self.meter.usage(user)?;
+        self.check(usage.total_docs(), usage.total_bytes())
+    }
+
+    /// Documents and bytes left to the user before reaching the limits, used to store the
+    /// documents unpacked from archives.
+    #[instrument(skip(self))]
+    pub fn allowance(&self, user: &User) -> Result<Allowance, QuotaErr> {
+        let usage = self.meter.usage(user)?;
+        Ok(Allowance {
+            docs: self.limits.max_docs.saturating_sub(usage.total_docs()),
+            bytes: self.limits.max_bytes.saturating_sub(usage.total_bytes()),
+        })
+    }
+
+    fn check(&self, docs: u64, bytes: u64) -> Result<(), QuotaErr> {
+        debug!("checking {} docs and {} bytes against quota", docs, bytes);
+        if docs > self.limits.max_docs {
+            Err(QuotaErr::DocsLimitReached(self.limits.max_docs))
+        } else if bytes > self.limits.max_bytes {
+            Err(QuotaErr::BytesLimitReached(self.limits.max_bytes))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::testingtools::services::quota::stub;
+
+    use anyhow::Result;
+    use claim::{assert_matches, assert_ok};
+
+    fn quotas(usage: Usage, existing: &[&str], limits: QuotaConfig) -> Quotas {
+        let cfg = Config {
+            quota: limits,
+            ..Config::default()
+        };
+        Quotas::new(stub(usage, existing), &cfg)
+    }
+
+    fn limits(max_docs: u64, max_bytes: u64, max_upload_bytes: u64) -> QuotaConfig {
+        QuotaConfig {
+            max_docs,
+            max_bytes,
+            max_upload_bytes,
+        }
+    }
+
+    fn usage(docs: u64, docs_bytes: u64) -> Usage {
+        Usage {
+            docs,
+            docs_bytes,
+            ..Usage::default()
+        }
+    }
+
+    #[test]
+    fn too_large_document_is_rejected() -> Result<()> {
+        // given
+        let quotas = quotas(Usage::default(), &[], limits(10, 1000, 100));
+
+        // when
+        let res = quotas.check_upload(&User::new("me@email.com"), &Filename::new("a.pdf")?, 101);
+
+        // then
+        assert_matches!(res, Err(QuotaErr::DocumentTooLarge(100)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn document_over_the_limit_of_docs_is_rejected_unless_it_replaces_existing_one() -> Result<()> {
+        // given
+        let quotas = quotas(usage(10, 100), &["a.pdf"], limits(10, 1000, 100));
+        let user = User::new("me@email.com");
+
+        // when
+        let new = quotas.check_upload(&user, &Filename::new("b.pdf")?, 1);
+        let replacing = quotas.check_upload(&user, &Filename::new("a.pdf")?, 1);
+
+        // then
+        assert_matches!(new, Err(QuotaErr::DocsLimitReached(10)));
+        assert_ok!(replacing);
+
+        Ok(())
+    }
+
+    #[test]
+    fn document_over_the_limit_of_bytes_is_rejected() -> Result<()> {
+        // given
+        let quotas = quotas(usage(1, 950), &[], limits(10, 1000, 100));
+
+        // when
+        let res = quotas.check_upload(&User::new("me@email.com"), &Filename::new("a.pdf")?, 51);
+
+        // then
+        assert_matches!(res, Err(QuotaErr::BytesLimitReached(1000)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn allowance_is_what_is_left_of_the_limits() -> Result<()> {
+        // given
+        let user = User::new("me@email.com");
+        let within = quotas(usage(4, 900), &[], limits(10, 1000, 100));
+        let over = quotas(usage(12, 1100), &[], limits(10, 1000, 100));
+
+        // when
+        let left = within.allowance(&user)?;
+        let nothing = over.allowance(&user)?;
+
+        // then
+        assert_eq!(
+            left,
+            Allowance {
+                docs: 6,
+                bytes: 100
+            }
+        );
+        assert_eq!(nothing, Allowance { docs: 0, bytes: 0 });
+
+        Ok(())
+    }
+
+    #[test]
+    fn usage_is_reported_together_with_limits() -> Result<()> {
+        // given
+        let used = Usage {
+            docs: 2,
+            docs_bytes: 200,
+            thumbnails_bytes: 20,
+            index_bytes: 10,
+            pending_docs: 1,
+            pending_bytes: 100,
+        };
+        let quotas = quotas(used.clone(), &[], limits(10, 1000, 100));
+
+        // when
+        let report = quotas.usage(&User::new("me@email.com"))?;
+
+        // then
+        assert_eq!(report.usage, used);
+        assert_eq!(report.total_docs, 3);
+        assert_eq!(report.total_bytes, 330);
+        assert_eq!(report.limits, limits(10, 1000, 100));
+
+        Ok(())
+    }
+}
@@ -1,7 +1,9 @@
+use crate::entities::file::Filename;
 use crate::entities::location::SafePathBuf;
 use crate::result::EventReceiverErr;
 
 use std::fmt::Display;
+use std::path::PathBuf;
 
 pub type EventRecv = Box<dyn EventReceiver>;
 
@@ -13,7 +15,27 @@ pub trait EventReceiver: Send {
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum DocsEvent {
+    /// New document appeared.
     Created(SafePathBuf),
+
+    /// Content of the document changed.
+    Modified(SafePathBuf),
+
+    /// Document was renamed within the same user directory.
+    Renamed {
+        from: Filename,
+        to: SafePathBuf,
+    },
+
+    /// Document disappeared. The path doesn't exist anymore, so it's not a [`SafePathBuf`].
+    Removed(PathBuf),
+
+    /// Content of the document is not of the type claimed by its extension.
+    Rejected {
+        path: SafePathBuf,
+        reason: String,
+    },
+
     Other,
 }
 
@@ -24,6 +46,10 @@ impl Display for DocsEvent {
             "{}",
             match self {
                 DocsEvent::Created(_) => "Created",
+                DocsEvent::Modified(_) => "Modified",
+                DocsEvent::Renamed { .. } => "Renamed",
+                DocsEvent::Removed(_) => "Removed",
+                DocsEvent::Rejected { .. } => "Rejected",
                 DocsEvent::Other => "Other",
             }
         )
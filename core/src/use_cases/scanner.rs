@@ -0,0 +1,18 @@
+//! Correction of the photos of the documents.
+//!
+//! Photos taken with the phone include the background around the page, and the page itself is
+//! seen at an angle. The page is found on the photo and flattened, so the stored document, its
+//! thumbnail and its recognized text are the same as if the document was scanned.
+use crate::entities::location::SafePathBuf;
+use crate::result::ScannerErr;
+
+use std::sync::Arc;
+
+pub type Scanner = Arc<dyn PageScanner>;
+
+pub trait PageScanner: Sync + Send {
+    /// Replaces the photo with the flattened page found on it. Returns `false` when there is
+    /// nothing to correct, e.g. the page was not found or the file is not a photo. The file is
+    /// left intact then.
+    fn flatten(&self, path: &SafePathBuf) -> Result<bool, ScannerErr>;
+}
@@ -0,0 +1,173 @@
+use crate::entities::location::{Location, SafePathBuf};
+use crate::entities::user::User;
+use crate::result::AuditorErr;
+use crate::use_cases::audit::{Action, AuditEntry, AuditTrail};
+use crate::use_cases::bus::{BusEvent, EventBus};
+use crate::use_cases::supervisor::{spawn_service, ServiceHandle};
+
+use std::convert::TryFrom;
+use tracing::{instrument, trace, warn};
+
+type Result<T> = std::result::Result<T, AuditorErr>;
+
+/// Observes the bus and records removals of the documents made by the pipeline.
+///
+/// Actions of the users are recorded by the server, but documents are also removed without
+/// user's request - when they are renamed in the watched directory, rejected because of exceeded
+/// quota or when their encryption fails. Documents unpacked from archives are recorded as well,
+/// together with the archive they come from.
+pub struct Auditor {
+    bus: EventBus,
+}
+
+impl Auditor {
+    pub fn new(bus: EventBus) -> Self {
+        Self { bus }
+    }
+
+    #[instrument(skip(self, trail))]
+    pub fn run(self, trail: AuditTrail) -> ServiceHandle {
+        let sub = self.bus.subscriber();
+        spawn_service("auditor", move || -> Result<()> {
+            loop {
+                match sub.recv()? {
+                    BusEvent::DocumentEncryptionFailed(loc) => {
+                        record_removals(&trail, &loc, "document encryption failed");
+                    }
+                    BusEvent::ThumbnailEncryptionFailed(loc) => {
+                        record_removals(&trail, &loc, "thumbnail encryption failed");
+                    }
+                    BusEvent::DocsRejected(loc, reason) => {
+                        record_removals(&trail, &loc, &reason);
+                    }
+                    BusEvent::DocsRenamed { from, to } => {
+                        let Location::FS(paths) = &to;
+                        for path in paths {
+                            let reason = format!("renamed to '{}'", path.filename());
+                            record(&trail, path, from.to_string(), &reason);
+                        }
+                    }
+                    BusEvent::DocsUnpacked { from, to } => {
+                        let Location::FS(paths) = &to;
+                        for path in paths {
+                            let reason = format!("unpacked from '{from}'");
+                            record_action(&trail, path, Action::Unpack, &reason);
+                        }
+                    }
+                    BusEvent::Shutdown("auditor") => return Ok(()),
+                    e => trace!("event not supported in auditor: '{:?}'", e),
+                }
+            }
+        })
+    }
+}
+
+fn record_removals(trail: &AuditTrail, loc: &Location, reason: &str) {
+    let Location::FS(paths) = loc;
+    for path in paths {
+        record(trail, path, path.filename(), reason);
+    }
+}
+
+fn record(trail: &AuditTrail, path: &SafePathBuf, document: String, reason: &str) {
+    match User::try_from(path) {
+        Ok(user) => trail.record(
+            AuditEntry::new(&user, Action::Delete)
+                .document(document)
+                .reason(reason),
+        ),
+        Err(e) => warn!("can't audit removal of '{}': '{}'", path, e),
+    }
+}
+
+fn record_action(trail: &AuditTrail, path: &SafePathBuf, action: Action, reason: &str) {
+    match User::try_from(path) {
+        Ok(user) => trail.record(
+            AuditEntry::new(&user, action)
+                .document(path.filename())
+                .reason(reason),
+        ),
+        Err(e) => warn!("can't audit '{:?}' of '{}': '{}'", action, path, e),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::configuration::telemetry::init_tracing;
+    use crate::entities::file::Filename;
+    use crate::entities::user::FAKE_USER_EMAIL;
+    use crate::testingtools::services::audit::tracked;
+    use crate::testingtools::unit::create_test_shim;
+
+    use anyhow::Result;
+
+    #[test]
+    fn removal_after_failed_encryption_is_recorded() -> Result<()> {
+        // given
+        init_tracing();
+        let (spies, log) = tracked();
+        let mut shim = create_test_shim()?;
+        Auditor::new(shim.bus()).run(AuditTrail::new(log));
+        let Location::FS(paths) = shim.test_location();
+
+        // when
+        shim.send_events(&[BusEvent::DocumentEncryptionFailed(shim.test_location())])?;
+
+        // then
+        let entry = spies.appended()?;
+        assert_eq!(entry.user, Some(User::new(FAKE_USER_EMAIL)));
+        assert_eq!(entry.action, Action::Delete);
+        assert_eq!(entry.document, Some(paths[0].filename()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn removal_of_old_name_of_renamed_document_is_recorded() -> Result<()> {
+        // given
+        init_tracing();
+        let (spies, log) = tracked();
+        let mut shim = create_test_shim()?;
+        Auditor::new(shim.bus()).run(AuditTrail::new(log));
+        let from = Filename::new("old-name.jpg")?;
+
+        // when
+        shim.send_events(&[BusEvent::DocsRenamed {
+            from,
+            to: shim.test_location(),
+        }])?;
+
+        // then
+        let entry = spies.appended()?;
+        assert_eq!(entry.action, Action::Delete);
+        assert_eq!(entry.document, Some("old-name.jpg".into()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn archive_of_unpacked_document_is_recorded() -> Result<()> {
+        // given
+        init_tracing();
+        let (spies, log) = tracked();
+        let mut shim = create_test_shim()?;
+        Auditor::new(shim.bus()).run(AuditTrail::new(log));
+        let Location::FS(paths) = shim.test_location();
+
+        // when
+        shim.send_events(&[BusEvent::DocsUnpacked {
+            from: Filename::new("scans.zip")?,
+            to: shim.test_location(),
+        }])?;
+
+        // then
+        let entry = spies.appended()?;
+        assert_eq!(entry.action, Action::Unpack);
+        assert_eq!(entry.document, Some(paths[0].filename()));
+        assert_eq!(entry.reason, Some("unpacked from 'scans.zip'".into()));
+
+        Ok(())
+    }
+}
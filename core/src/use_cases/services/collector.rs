@@ -0,0 +1,107 @@
+use crate::entities::location::Location;
+use crate::result::CollectorErr;
+use crate::use_cases::bus::{BusEvent, EventBus};
+use crate::use_cases::metrics::{EncryptionTarget, Metrics, Stage};
+use crate::use_cases::supervisor::{spawn_service, ServiceHandle};
+
+use tracing::{instrument, trace};
+
+type Result<T> = std::result::Result<T, CollectorErr>;
+
+/// Observes the bus and counts documents passing through the pipeline.
+pub struct MetricsCollector {
+    bus: EventBus,
+}
+
+impl MetricsCollector {
+    pub fn new(bus: EventBus) -> Self {
+        Self { bus }
+    }
+
+    #[instrument(skip(self, metrics))]
+    pub fn run(self, metrics: Metrics) -> ServiceHandle {
+        let sub = self.bus.subscriber();
+        spawn_service("collector", move || -> Result<()> {
+            loop {
+                match sub.recv()? {
+                    BusEvent::NewDocs(loc) => metrics.docs_processed(Stage::Received, count(&loc)),
+                    BusEvent::DocsMoved(loc) => metrics.docs_processed(Stage::Moved, count(&loc)),
+                    BusEvent::ThumbnailMade(loc) => {
+                        metrics.docs_processed(Stage::Thumbnailed, count(&loc));
+                    }
+                    BusEvent::DataExtracted(details) => {
+                        metrics.docs_processed(Stage::Extracted, details.len());
+                    }
+                    BusEvent::Indexed(details) => {
+                        metrics.docs_processed(Stage::Indexed, details.len());
+                    }
+                    BusEvent::DocumentEncryptionFailed(_) => {
+                        metrics.encryption_failed(EncryptionTarget::Document);
+                    }
+                    BusEvent::ThumbnailEncryptionFailed(_) => {
+                        metrics.encryption_failed(EncryptionTarget::Thumbnail);
+                    }
+                    BusEvent::Shutdown("collector") => return Ok(()),
+                    e => trace!("event not supported in collector: '{:?}'", e),
+                }
+            }
+        })
+    }
+}
+
+fn count(loc: &Location) -> usize {
+    let Location::FS(paths) = loc;
+    paths.len()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::configuration::telemetry::init_tracing;
+    use crate::entities::document::DocDetails;
+    use crate::testingtools::services::metrics::tracked;
+    use crate::testingtools::unit::create_test_shim;
+
+    use anyhow::Result;
+    use fake::{Fake, Faker};
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn indexed_docs_are_counted() -> Result<()> {
+        // given
+        init_tracing();
+        let (spies, metrics) = tracked();
+        let mut shim = create_test_shim()?;
+        MetricsCollector::new(shim.bus()).run(metrics);
+        thread::sleep(Duration::from_secs(1)); // allow to start collector
+        let details: Vec<DocDetails> = vec![Faker.fake(), Faker.fake()];
+
+        // when
+        shim.send_events(&[BusEvent::Indexed(details)])?;
+
+        // then
+        assert_eq!(spies.docs_processed()?, (Stage::Indexed, 2));
+
+        Ok(())
+    }
+
+    #[test]
+    fn document_encryption_failure_is_counted() -> Result<()> {
+        // given
+        init_tracing();
+        let (spies, metrics) = tracked();
+        let mut shim = create_test_shim()?;
+        MetricsCollector::new(shim.bus()).run(metrics);
+        thread::sleep(Duration::from_secs(1)); // allow to start collector
+
+        // when
+        shim.trigger_document_encryption_failure()?;
+
+        // then
+        assert_eq!(spies.encryption_failed()?, EncryptionTarget::Document);
+
+        Ok(())
+    }
+}
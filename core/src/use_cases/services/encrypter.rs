@@ -2,10 +2,10 @@ use crate::entities::location::{Location, SafePathBuf};
 use crate::result::EncrypterErr;
 use crate::use_cases::bus::{BusEvent, EventBus};
 use crate::use_cases::cipher::CipherWriter;
+use crate::use_cases::supervisor::{spawn_service, ServiceHandle};
 
 use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
 use std::fs;
-use std::thread;
 use tracing::{debug, error, instrument, trace, warn};
 
 type Result<T> = std::result::Result<T, EncrypterErr>;
@@ -20,12 +20,12 @@ impl Encrypter {
     }
 
     #[instrument(skip(self, cipher))]
-    pub fn run(self, cipher: CipherWriter) {
+    pub fn run(self, cipher: CipherWriter) -> ServiceHandle {
         let sub = self.bus.subscriber();
+        let publ = self.bus.publisher();
         // TODO: improve tracing of threads somehow. Currently, it's hard to debug because threads
         // do not appear as separate tracing's scopes
-        thread::spawn(move || -> Result<()> {
-            let publ = self.bus.publisher();
+        spawn_service("encrypter", move || -> Result<()> {
             loop {
                 let ev = sub.recv()?;
                 match ev.clone() {
@@ -38,10 +38,11 @@ impl Encrypter {
                         error!("encryption failed");
                         publ.send(pick_response(&ev, location))?;
                     }
+                    BusEvent::Shutdown("encrypter") => return Ok(()),
                     e => trace!("event not supported in encrypter: '{:?}'", e),
                 }
             }
-        });
+        })
     }
 }
 
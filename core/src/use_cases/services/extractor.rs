@@ -4,9 +4,11 @@ use crate::entities::extension::Ext;
 use crate::entities::location::Location;
 use crate::result::ExtractorErr;
 use crate::use_cases::bus::{BusEvent, EventBus, EventPublisher};
+use crate::use_cases::metrics::{Extraction, Metrics};
+use crate::use_cases::ocr::LanguageOverrides;
+use crate::use_cases::supervisor::{spawn_service, ServiceHandle, ServicePool};
 
-use rayon::{ThreadPool, ThreadPoolBuilder};
-use std::thread;
+use std::time::Instant;
 use tracing::{debug, error, instrument, trace, warn};
 
 pub type ExtractorCreator = Box<dyn ExtractorFactory>;
@@ -15,46 +17,59 @@ type Result<T> = std::result::Result<T, ExtractorErr>;
 
 pub struct TxtExtractor {
     bus: EventBus,
-    tp: ThreadPool,
+    tp: ServicePool,
 }
 
 impl TxtExtractor {
     pub fn new(bus: EventBus) -> Result<Self> {
-        let tp = ThreadPoolBuilder::new().num_threads(4).build()?;
+        let tp = ServicePool::new(4)?;
         Ok(Self { bus, tp })
     }
 
-    #[instrument(skip(self, factory))]
-    pub fn run(self, factory: ExtractorCreator) {
-        thread::spawn(move || -> Result<()> {
-            let sub = self.bus.subscriber();
+    /// OCR languages requested for the documents which are rejected, or which extraction fails,
+    /// are forgotten in `overrides`.
+    #[instrument(skip(self, factory, overrides))]
+    pub fn run(self, factory: ExtractorCreator, overrides: LanguageOverrides) -> ServiceHandle {
+        let sub = self.bus.subscriber();
+        spawn_service("extractor", move || -> Result<()> {
             loop {
                 match sub.recv()? {
-                    BusEvent::DocsMoved(loc) => self.extract_data(loc, &factory)?,
+                    BusEvent::DocsMoved(loc) => self.extract_data(loc, &factory, &overrides)?,
+                    BusEvent::DocsRejected(loc, _) => overrides.forget(&loc),
+                    BusEvent::Shutdown("extractor") => break,
                     e => trace!("event not supported in TxtExtractor: '{:?}'", e),
                 }
             }
-        });
+            self.tp.drain();
+            Ok(())
+        })
     }
 
-    fn extract_data(&self, loc: Location, factory: &ExtractorCreator) -> Result<()> {
+    fn extract_data(
+        &self,
+        loc: Location,
+        factory: &ExtractorCreator,
+        overrides: &LanguageOverrides,
+    ) -> Result<()> {
         debug!("NewDocs in: '{:?}', starting extraction", loc);
         let extractor = factory.make(&loc.extension()?);
         let publ = self.bus.publisher();
+        let overrides = overrides.clone();
         self.tp.spawn(move || {
-            if let Err(e) = extract(loc, &extractor, &publ) {
+            if let Err(e) = extract(&loc, &extractor, &publ) {
                 error!("extraction failed: '{}'", e);
+                overrides.forget(&loc);
             }
         });
         Ok(())
     }
 }
 
-fn extract(loc: Location, extr: &Extractor, publ: &EventPublisher) -> Result<()> {
-    publ.send(BusEvent::DataExtracted(extr.extract_data(&loc)?))?;
+fn extract(loc: &Location, extr: &Extractor, publ: &EventPublisher) -> Result<()> {
+    publ.send(BusEvent::DataExtracted(extr.extract_data(loc)?))?;
     debug!("extraction finished");
     debug!("sending encryption request for: '{:?}'", loc);
-    publ.send(BusEvent::EncryptDocument(loc))?;
+    publ.send(BusEvent::EncryptDocument(loc.clone()))?;
     Ok(())
 }
 
@@ -70,18 +85,69 @@ pub trait ExtractorFactory: Sync + Send {
     fn make(&self, ext: &Ext) -> Extractor;
 }
 
+/// Measures how long the extraction takes for extractors created by the wrapped factory.
+pub struct TimedExtractorFactory {
+    factory: ExtractorCreator,
+    metrics: Metrics,
+}
+
+impl TimedExtractorFactory {
+    pub fn wrap(factory: ExtractorCreator, metrics: Metrics) -> ExtractorCreator {
+        Box::new(Self { factory, metrics })
+    }
+}
+
+impl ExtractorFactory for TimedExtractorFactory {
+    fn make(&self, ext: &Ext) -> Extractor {
+        Box::new(TimedExtractor {
+            extractor: self.factory.make(ext),
+            ext: ext.clone(),
+            metrics: self.metrics.clone(),
+        })
+    }
+}
+
+struct TimedExtractor {
+    extractor: Extractor,
+    ext: Ext,
+    metrics: Metrics,
+}
+
+impl DataExtractor for TimedExtractor {
+    fn extract_data(&self, location: &Location) -> Result<Vec<DocDetails>> {
+        let start = Instant::now();
+        let res = self.extractor.extract_data(location)?;
+        // NOTE: only recognized text has a confidence, e.g. PDFs are recognized when they have no
+        // text layer
+        let extraction = if res.iter().any(|doc| doc.confidence.is_some()) {
+            Extraction::Ocr
+        } else {
+            Extraction::Text
+        };
+        self.metrics
+            .extraction_finished(&self.ext, extraction, start.elapsed());
+        Ok(res)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
 
     use crate::configuration::telemetry::init_tracing;
+    use crate::entities::file::Filename;
+    use crate::entities::user::{User, FAKE_USER_EMAIL};
     use crate::testingtools::services::extractor::{
         factory, failing, noop, stub, tracked, working,
     };
+    use crate::testingtools::services::metrics::tracked as tracked_metrics;
+    use crate::testingtools::services::ocr::stub as ocr_stub;
     use crate::testingtools::unit::create_test_shim;
+    use crate::use_cases::ocr::OcrLanguages;
 
     use anyhow::Result;
     use fake::{Fake, Faker};
+    use std::thread;
     use std::time::Duration;
 
     #[test]
@@ -91,7 +157,7 @@ mod test {
         let (extractor_spies, extractor) = tracked(working());
         let factory_stub = factory(vec![extractor]);
         let mut shim = create_test_shim()?;
-        TxtExtractor::new(shim.bus())?.run(factory_stub);
+        TxtExtractor::new(shim.bus())?.run(factory_stub, ocr_stub());
         thread::sleep(Duration::from_secs(1)); // allow to start extractor
 
         // when
@@ -110,7 +176,7 @@ mod test {
         let docs_details: Vec<DocDetails> = Faker.fake();
         let factory_stub = factory(vec![stub(docs_details.clone())]);
         let mut shim = create_test_shim()?;
-        TxtExtractor::new(shim.bus())?.run(factory_stub);
+        TxtExtractor::new(shim.bus())?.run(factory_stub, ocr_stub());
         thread::sleep(Duration::from_secs(1)); // allow to start extractor
 
         // when
@@ -129,7 +195,7 @@ mod test {
         init_tracing();
         let factory_stub = factory(vec![stub(Faker.fake())]);
         let mut shim = create_test_shim()?;
-        TxtExtractor::new(shim.bus())?.run(factory_stub);
+        TxtExtractor::new(shim.bus())?.run(factory_stub, ocr_stub());
         thread::sleep(Duration::from_secs(1)); // allow to start extractor
 
         // when
@@ -151,7 +217,7 @@ mod test {
         let (extractor_spies, extractor) = tracked(failing());
         let factory_stub = factory(vec![extractor]);
         let mut shim = create_test_shim()?;
-        TxtExtractor::new(shim.bus())?.run(factory_stub);
+        TxtExtractor::new(shim.bus())?.run(factory_stub, ocr_stub());
         thread::sleep(Duration::from_secs(1)); // allow to start extractor
 
         // when
@@ -166,6 +232,52 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn languages_are_forgotten_when_extraction_fails() -> Result<()> {
+        // given
+        init_tracing();
+        let factory_stub = factory(vec![failing()]);
+        let overrides = ocr_stub();
+        let user = User::new(FAKE_USER_EMAIL);
+        let filename = Filename::new("some-file.jpg")?;
+        overrides.set(&user, &filename, OcrLanguages::new("eng")?)?;
+        let mut shim = create_test_shim()?;
+        TxtExtractor::new(shim.bus())?.run(factory_stub, overrides.clone());
+        thread::sleep(Duration::from_secs(1)); // allow to start extractor
+
+        // when
+        shim.trigger_extractor()?;
+        thread::sleep(Duration::from_secs(1)); // allow to extract
+
+        // then
+        assert_eq!(overrides.take(&user, &filename)?, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn languages_of_rejected_documents_are_forgotten() -> Result<()> {
+        // given
+        init_tracing();
+        let overrides = ocr_stub();
+        let user = User::new(FAKE_USER_EMAIL);
+        let filename = Filename::new("some-file.jpg")?;
+        overrides.set(&user, &filename, OcrLanguages::new("eng")?)?;
+        let mut shim = create_test_shim()?;
+        TxtExtractor::new(shim.bus())?.run(factory(vec![noop()]), overrides.clone());
+        thread::sleep(Duration::from_secs(1)); // allow to start extractor
+        let event = BusEvent::DocsRejected(shim.test_location(), "quota exceeded".into());
+
+        // when
+        shim.send_events(&[event])?;
+        thread::sleep(Duration::from_secs(1)); // allow to handle the event
+
+        // then
+        assert_eq!(overrides.take(&user, &filename)?, None);
+
+        Ok(())
+    }
+
     #[test]
     fn extractor_ignores_other_bus_events() -> Result<()> {
         // given
@@ -182,7 +294,7 @@ mod test {
             BusEvent::PipelineFinished,
         ];
         let mut shim = create_test_shim()?;
-        TxtExtractor::new(shim.bus())?.run(factory_stub);
+        TxtExtractor::new(shim.bus())?.run(factory_stub, ocr_stub());
 
         // when
         shim.send_events(&ignored_events)?;
@@ -206,7 +318,7 @@ mod test {
         let (extractor_spies2, extractor2) = tracked(failing());
         let factory_stub = factory(vec![extractor1, extractor2]);
         let mut shim = create_test_shim()?;
-        TxtExtractor::new(shim.bus())?.run(factory_stub);
+        TxtExtractor::new(shim.bus())?.run(factory_stub, ocr_stub());
         thread::sleep(Duration::from_secs(1)); // allow to start extractor
 
         shim.trigger_extractor()?;
@@ -220,4 +332,58 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn timed_extractor_factory_records_extraction_time() -> Result<()> {
+        // given
+        init_tracing();
+        let (metrics_spies, metrics) = tracked_metrics();
+        let doc = DocDetails {
+            confidence: None,
+            ..Faker.fake()
+        };
+        let factory_stub = factory(vec![stub(vec![doc])]);
+        let factory = TimedExtractorFactory::wrap(factory_stub, metrics);
+        let mut shim = create_test_shim()?;
+        TxtExtractor::new(shim.bus())?.run(factory, ocr_stub());
+        thread::sleep(Duration::from_secs(1)); // allow to start extractor
+
+        // when
+        shim.trigger_extractor()?;
+
+        // then
+        assert_eq!(
+            metrics_spies.extraction_finished()?,
+            (Ext::Jpg, Extraction::Text)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn recognized_text_is_recorded_as_ocr() -> Result<()> {
+        // given
+        init_tracing();
+        let (metrics_spies, metrics) = tracked_metrics();
+        let doc = DocDetails {
+            confidence: Some(90),
+            ..Faker.fake()
+        };
+        let factory_stub = factory(vec![stub(vec![doc])]);
+        let factory = TimedExtractorFactory::wrap(factory_stub, metrics);
+        let mut shim = create_test_shim()?;
+        TxtExtractor::new(shim.bus())?.run(factory, ocr_stub());
+        thread::sleep(Duration::from_secs(1)); // allow to start extractor
+
+        // when
+        shim.trigger_extractor()?;
+
+        // then
+        assert_eq!(
+            metrics_spies.extraction_finished()?,
+            (Ext::Jpg, Extraction::Ocr)
+        );
+
+        Ok(())
+    }
 }
@@ -1,18 +1,20 @@
 use crate::entities::document::DocDetails;
+use crate::entities::file::Filename;
 use crate::entities::location::Location;
+use crate::entities::user::User;
 use crate::result::IndexerErr;
 use crate::use_cases::bus::{BusEvent, EventBus, EventPublisher};
 use crate::use_cases::state::StateWriter;
+use crate::use_cases::supervisor::{spawn_service, ServiceHandle, ServicePool};
 
-use rayon::{ThreadPool, ThreadPoolBuilder};
-use std::thread;
+use std::convert::TryInto;
 use tracing::{debug, error, instrument, trace, warn};
 
 type Result<T> = std::result::Result<T, IndexerErr>;
 
 pub struct Indexer {
     bus: EventBus,
-    tp: ThreadPool,
+    tp: ServicePool,
 }
 
 impl Indexer {
@@ -20,22 +22,28 @@ impl Indexer {
         // TODO: think about num_threads
         // TODO: should threadpool be shared between services?
         // TODO: should threadpool have it's own abstraction here?
-        let tp = ThreadPoolBuilder::new().num_threads(4).build()?;
+        let tp = ServicePool::new(4)?;
         Ok(Self { bus, tp })
     }
 
     #[instrument(skip(self, state))]
-    pub fn run(self, state: StateWriter) {
+    pub fn run(self, state: StateWriter) -> ServiceHandle {
         let sub = self.bus.subscriber();
-        thread::spawn(move || -> Result<()> {
+        spawn_service("indexer", move || -> Result<()> {
             loop {
                 match sub.recv()? {
                     BusEvent::DataExtracted(doc_details) => self.index(doc_details, state.clone()),
                     BusEvent::DocumentEncryptionFailed(loc) => self.cleanup(loc, state.clone()),
+                    BusEvent::DocsRenamed { from, to } => self.rename(from, to, state.clone()),
+                    BusEvent::Shutdown("indexer") => break,
                     e => trace!("event not supported in indexer: '{:?}'", e),
                 }
             }
-        });
+            // NOTE: every indexing job commits its writes, so waiting for the jobs is enough to
+            // not lose any pending index writes
+            self.tp.drain();
+            Ok(())
+        })
     }
 
     #[instrument(skip(self, state))]
@@ -58,6 +66,18 @@ impl Indexer {
             }
         });
     }
+
+    /// Only the old name is removed here. The document under the new name goes through the
+    /// whole pipeline and is indexed as a new one.
+    #[instrument(skip(self, state))]
+    fn rename(&self, from: Filename, to: Location, state: StateWriter) {
+        debug!("document renamed, removing index data of '{}'", from);
+        self.tp.spawn(move || {
+            if let Err(e) = remove_renamed(&from, &to, &state) {
+                error!("failed to remove renamed doc '{}': '{}'", from, e);
+            }
+        });
+    }
 }
 
 #[instrument(skip(state, publ))]
@@ -69,6 +89,16 @@ fn index(doc_details: &[DocDetails], state: &StateWriter, publ: EventPublisher)
     Ok(())
 }
 
+#[instrument(skip(state))]
+fn remove_renamed(from: &Filename, to: &Location, state: &StateWriter) -> Result<()> {
+    let Location::FS(paths) = to;
+    for path in paths {
+        let user: User = path.try_into()?;
+        state.delete_doc(&user, from)?;
+    }
+    Ok(())
+}
+
 #[instrument(skip(state, publ))]
 fn cleanup(loc: &Location, state: &StateWriter, publ: EventPublisher) -> Result<()> {
     state.delete(loc)?;
@@ -232,4 +262,25 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn state_removes_old_name_when_docs_renamed_event_appears() -> Result<()> {
+        // given
+        init_tracing();
+        let (state_spies, state) = tracked(&noop());
+        let mut shim = create_test_shim()?;
+        Indexer::new(shim.bus())?.run(state.writer());
+        let event = BusEvent::DocsRenamed {
+            from: Filename::new("old-name.jpg")?,
+            to: shim.test_location(),
+        };
+
+        // when
+        shim.send_events(&[event])?;
+
+        // then
+        assert!(state_spies.delete_called());
+
+        Ok(())
+    }
 }
@@ -1,6 +1,9 @@
+pub mod auditor;
+pub mod collector;
 pub mod encrypter;
 pub mod extractor;
 pub mod indexer;
 pub mod mover;
+pub mod reconciler;
 pub mod thumbnailer;
 pub mod watcher;
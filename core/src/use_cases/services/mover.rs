@@ -1,13 +1,21 @@
 //! Abstraction for moving received document to correct place.
+use crate::entities::extension::Ext;
+use crate::entities::file::{Filename, Thumbnailname};
 use crate::entities::location::{Location, SafePathBuf};
+use crate::entities::user::User;
 use crate::result::MoverErr;
 use crate::use_cases::bus::{BusEvent, EventBus, EventPublisher};
 use crate::use_cases::config::Config;
 use crate::use_cases::fs::Fs;
-
-use rayon::{ThreadPool, ThreadPoolBuilder};
+use crate::use_cases::quota::Quotas;
+use crate::use_cases::scanner::Scanner;
+use crate::use_cases::sharing::ShareRevoker;
+use crate::use_cases::supervisor::{spawn_service, ServiceHandle, ServicePool};
+use crate::use_cases::unpacker::Unpacker;
+use crate::use_cases::users::UserDirectory;
+
+use std::convert::TryFrom;
 use std::path::PathBuf;
-use std::thread;
 use tracing::{debug, error, instrument, trace, warn};
 
 type Result<T> = std::result::Result<T, MoverErr>;
@@ -15,74 +23,253 @@ type Result<T> = std::result::Result<T, MoverErr>;
 pub struct DocumentMover {
     cfg: Config,
     bus: EventBus,
-    tp: ThreadPool,
+    tp: ServicePool,
 }
 
 impl DocumentMover {
     pub fn new<C: Into<Config>>(cfg: C, bus: EventBus) -> Result<Self> {
         let cfg = cfg.into();
-        let tp = ThreadPoolBuilder::new().num_threads(4).build()?;
+        let tp = ServicePool::new(4)?;
         Ok(Self { cfg, bus, tp })
     }
 
-    #[instrument(skip(self, fs))]
-    pub fn run(self, fs: Fs) {
-        thread::spawn(move || -> Result<()> {
-            let sub = self.bus.subscriber();
+    /// Documents of users who exceeded their quota, or whose accounts aren't active in the
+    /// `directory`, are removed from the watched directory instead of being moved. Photos are
+    /// flattened with the `scanner` after they are moved.
+    ///
+    /// Archives are replaced by the documents unpacked from them with the `unpacker`. Emails are
+    /// kept, and their attachments are unpacked next to them.
+    ///
+    /// Grants and links to the documents which are renamed or removed are revoked with the
+    /// `revoker`.
+    #[instrument(skip(self, fs, quotas, directory, revoker, scanner, unpacker))]
+    pub fn run(
+        self,
+        fs: Fs,
+        quotas: Quotas,
+        directory: UserDirectory,
+        revoker: ShareRevoker,
+        scanner: Scanner,
+        unpacker: Unpacker,
+    ) -> ServiceHandle {
+        let sub = self.bus.subscriber();
+        let tools = Tools {
+            fs,
+            quotas,
+            directory,
+            revoker,
+            scanner,
+            unpacker,
+        };
+        spawn_service("mover", move || -> Result<()> {
             loop {
                 match sub.recv()? {
-                    BusEvent::NewDocs(loc) => self.move_doc(loc, &fs),
-                    BusEvent::DocumentEncryptionFailed(loc) => self.cleanup(loc, &fs),
+                    BusEvent::NewDocs(loc) => self.move_doc(loc, &tools),
+                    BusEvent::DocsRenamed { from, to } => self.rename_doc(&from, to, &tools),
+                    BusEvent::DocumentEncryptionFailed(loc) => self.cleanup(loc, &tools),
+                    BusEvent::Shutdown("mover") => break,
                     e => trace!("event not supported in DocumentMover: '{:?}'", e),
                 }
             }
-        });
+            self.tp.drain();
+            Ok(())
+        })
     }
 
-    #[instrument(skip(self, fs))]
-    fn move_doc(&self, loc: Location, fs: &Fs) {
+    #[instrument(skip(self, tools))]
+    fn move_doc(&self, loc: Location, tools: &Tools) {
         debug!("NewDocs in: '{:?}', moving to correct location", loc);
         let publ = self.bus.publisher();
         let dir = self.cfg.docs_dir.clone();
-        let fs = fs.clone();
+        let tools = tools.clone();
         self.tp.spawn(move || {
-            if let Err(e) = move_document(&loc, &fs, &dir, publ) {
+            if let Err(e) = move_document(&loc, &tools, &dir, &publ) {
                 error!("failed to move doc: '{}'", e);
             }
         });
     }
 
-    #[instrument(skip(self, fs))]
-    fn cleanup(&self, loc: Location, fs: &Fs) {
+    /// Removes the document stored under the old name and moves the renamed one as a new document.
+    #[instrument(skip(self, tools))]
+    fn rename_doc(&self, from: &Filename, to: Location, tools: &Tools) {
+        if let Err(e) = remove_renamed(from, &to, tools, &self.cfg) {
+            error!("failed to remove renamed doc '{}': '{}'", from, e);
+        }
+        self.move_doc(to, tools);
+    }
+
+    #[instrument(skip(self, tools))]
+    fn cleanup(&self, loc: Location, tools: &Tools) {
         debug!("pipeline failed, removing document");
-        let fs = fs.clone();
+        let tools = tools.clone();
         self.tp.spawn(move || {
-            if let Err(e) = remove_document(&loc, &fs) {
+            if let Err(e) = remove_document(&loc, &tools) {
                 error!("failed to remove document '{:?}': '{}'", loc, e);
             }
         });
     }
 }
 
-#[instrument(skip(fs, publ))]
-fn move_document(loc: &Location, fs: &Fs, dir: &PathBuf, publ: EventPublisher) -> Result<()> {
+/// Services used while moving the documents.
+#[derive(Clone)]
+struct Tools {
+    fs: Fs,
+    quotas: Quotas,
+    directory: UserDirectory,
+    revoker: ShareRevoker,
+    scanner: Scanner,
+    unpacker: Unpacker,
+}
+
+#[instrument(skip(tools, publ))]
+fn move_document(
+    loc: &Location,
+    tools: &Tools,
+    dir: &PathBuf,
+    publ: &EventPublisher,
+) -> Result<()> {
+    let Tools {
+        fs,
+        quotas,
+        directory,
+        scanner,
+        unpacker,
+        ..
+    } = tools;
     let Location::FS(paths) = loc;
     let mut dst_paths = Vec::new();
     for path in paths {
-        let dst_path = dir.join(path.filename());
+        let user = User::try_from(path)?;
+        if let Err(e) = directory.check_active(&user) {
+            if !e.is_rejection() {
+                return Err(e.into());
+            }
+            warn!("account not active, removing '{}': '{}'", path, e);
+            fs.rm_file(path)?;
+            let rejected = Location::FS(vec![path.clone()]);
+            publ.send(BusEvent::DocsRejected(rejected, e.to_string()))?;
+            continue;
+        }
+        if let Err(e) = quotas.check_stored(&user) {
+            if !e.is_exceeded() {
+                return Err(e.into());
+            }
+            warn!("quota exceeded, removing '{}': '{}'", path, e);
+            fs.rm_file(path)?;
+            let rejected = Location::FS(vec![path.clone()]);
+            publ.send(BusEvent::DocsRejected(rejected, e.to_string()))?;
+            continue;
+        }
+        let dst_path = dir.join(path.rel_path());
         fs.mv_file(path, &dst_path)?;
-        dst_paths.push(SafePathBuf::new(dst_path));
+        let dst_path = SafePathBuf::new(dst_path);
+        if dst_path.is_archive() {
+            // NOTE: archive is not a document, only the documents unpacked from it are kept
+            let res = unpack(&dst_path, unpacker, quotas, scanner, publ);
+            fs.rm_file(&dst_path)?;
+            if let Err(e) = res {
+                warn!("rejecting archive '{}': '{}'", path, e);
+                let rejected = Location::FS(vec![path.clone()]);
+                publ.send(BusEvent::DocsRejected(rejected, e.to_string()))?;
+            }
+            continue;
+        }
+        flatten(scanner, &dst_path);
+        if matches!(dst_path.ext(), Ok(Ext::Eml)) {
+            if let Err(e) = unpack(&dst_path, unpacker, quotas, scanner, publ) {
+                warn!("failed to unpack attachments of '{}': '{}'", dst_path, e);
+            }
+        }
+        dst_paths.push(dst_path);
     }
     debug!("moving finished");
-    publ.send(BusEvent::DocsMoved(Location::FS(dst_paths)))?;
+    if !dst_paths.is_empty() {
+        publ.send(BusEvent::DocsMoved(Location::FS(dst_paths)))?;
+    }
+    Ok(())
+}
+
+/// Documents are unpacked next to the `source` and each of them is published as a separate
+/// document, after the link to the `source` is published. Unpacked documents are counted against
+/// the quota of the user, like any other document.
+fn unpack(
+    source: &SafePathBuf,
+    unpacker: &Unpacker,
+    quotas: &Quotas,
+    scanner: &Scanner,
+    publ: &EventPublisher,
+) -> Result<()> {
+    let allowance = quotas.allowance(&User::try_from(source)?)?;
+    let docs = unpacker.unpack(source, source.parent(), allowance)?;
+    if docs.is_empty() {
+        return Ok(());
+    }
+    debug!("unpacked {} documents from '{}'", docs.len(), source);
+    publ.send(BusEvent::DocsUnpacked {
+        from: Filename::from(source),
+        to: Location::FS(docs.clone()),
+    })?;
+    for doc in docs {
+        flatten(scanner, &doc);
+        publ.send(BusEvent::DocsMoved(Location::FS(vec![doc])))?;
+    }
+    Ok(())
+}
+
+/// The photo is kept as it is when flattening fails, the document is still usable.
+fn flatten(scanner: &Scanner, path: &SafePathBuf) {
+    match scanner.flatten(path) {
+        Ok(true) => debug!("photo '{}' flattened", path),
+        Ok(false) => trace!("nothing to flatten in '{}'", path),
+        Err(e) => warn!("failed to flatten '{}': '{}'", path, e),
+    }
+}
+
+#[instrument(skip(tools))]
+fn remove_renamed(from: &Filename, to: &Location, tools: &Tools, cfg: &Config) -> Result<()> {
+    let Tools { fs, revoker, .. } = tools;
+    let Location::FS(paths) = to;
+    for path in paths {
+        revoker.revoke(&User::try_from(path)?, from);
+        let old_path = cfg.docs_dir.join(path.parent_name()).join(from.to_string());
+        if old_path.exists() {
+            debug!("removing renamed doc: '{}'", old_path.display());
+            fs.rm_file(&SafePathBuf::new(old_path))?;
+        }
+        let thumbnails_dir = cfg.thumbnails_dir.join(path.parent_name());
+        for thumbnail in old_thumbnails(from, cfg)? {
+            let old_path = thumbnails_dir.join(thumbnail.to_string());
+            if old_path.exists() {
+                debug!(
+                    "removing thumbnail of renamed doc: '{}'",
+                    old_path.display()
+                );
+                fs.rm_file(&SafePathBuf::new(old_path))?;
+            }
+        }
+    }
     Ok(())
 }
 
-#[instrument(skip(fs))]
-fn remove_document(loc: &Location, fs: &Fs) -> Result<()> {
+/// Thumbnails which could have been made for the document under its old name, in each size and
+/// pixel ratio. Documents rendered from text have PNG thumbnails, others the configured format.
+fn old_thumbnails(from: &Filename, cfg: &Config) -> Result<Vec<Thumbnailname>> {
+    let mut thumbnails = Vec::new();
+    for ext in [cfg.thumbnails.format.ext(), "png"] {
+        let thumbnail = Thumbnailname::of(from.clone(), ext)?;
+        thumbnails.extend(thumbnail.variants(&cfg.thumbnails.pixel_ratios));
+    }
+    Ok(thumbnails)
+}
+
+#[instrument(skip(tools))]
+fn remove_document(loc: &Location, tools: &Tools) -> Result<()> {
     let Location::FS(paths) = loc;
     for path in paths {
-        fs.rm_file(path)?;
+        tools.fs.rm_file(path)?;
+        tools
+            .revoker
+            .revoke(&User::try_from(path)?, &Filename::from(path));
     }
     Ok(())
 }
@@ -91,22 +278,48 @@ fn remove_document(loc: &Location, fs: &Fs) -> Result<()> {
 mod test {
     use super::*;
 
+    use crate::configuration::factories::{fs as local_fs, links, sharing};
     use crate::configuration::telemetry::init_tracing;
+    use crate::entities::user::FAKE_USER_EMAIL;
     use crate::testingtools::services::fs::{failing, noop, tracked};
+    use crate::testingtools::services::quota::{exceeded, unlimited};
+    use crate::testingtools::services::scanner::{self, noop as noop_scanner};
+    use crate::testingtools::services::unpacker::{
+        self, failing as failing_unpacker, found, noop as noop_unpacker,
+    };
+    use crate::testingtools::services::users::directory;
     use crate::testingtools::unit::create_test_shim;
     use crate::testingtools::TestConfig;
+    use crate::use_cases::links::ShareLink;
+    use crate::use_cases::sharing::Grant;
+    use crate::use_cases::users::{Account, AccountStatus};
 
     use anyhow::Result;
     use fake::{Fake, Faker};
+    use std::thread;
     use std::time::Duration;
 
+    fn revoker(cfg: &TestConfig) -> Result<ShareRevoker> {
+        Ok(ShareRevoker::new(
+            sharing(cfg.as_ref())?,
+            links(cfg.as_ref())?,
+        ))
+    }
+
     #[test]
     fn fs_is_used_to_move_document() -> Result<()> {
         // given
         init_tracing();
         let (fs_spies, fs) = tracked(noop());
         let mut shim = create_test_shim()?;
-        DocumentMover::new(TestConfig::new()?, shim.bus())?.run(fs);
+        DocumentMover::new(TestConfig::new()?, shim.bus())?.run(
+            fs,
+            unlimited(),
+            directory(Vec::new()),
+            revoker(shim.config())?,
+            noop_scanner(),
+            noop_unpacker(),
+        );
         thread::sleep(Duration::from_secs(1)); // allow to start DocumentMover
 
         // when
@@ -118,12 +331,44 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn moved_document_is_flattened() -> Result<()> {
+        // given
+        init_tracing();
+        let (scanner_spies, scanner) = scanner::tracked(noop_scanner(), noop_unpacker());
+        let mut shim = create_test_shim()?;
+        DocumentMover::new(shim.config(), shim.bus())?.run(
+            noop(),
+            unlimited(),
+            directory(Vec::new()),
+            revoker(shim.config())?,
+            scanner,
+            noop_unpacker(),
+        );
+        thread::sleep(Duration::from_secs(1)); // allow to start DocumentMover
+
+        // when
+        shim.trigger_mover()?;
+
+        // then
+        assert!(scanner_spies.flatten_called());
+
+        Ok(())
+    }
+
     #[test]
     fn docs_moved_event_appears_on_success() -> Result<()> {
         // given
         init_tracing();
         let mut shim = create_test_shim()?;
-        DocumentMover::new(shim.config(), shim.bus())?.run(noop());
+        DocumentMover::new(shim.config(), shim.bus())?.run(
+            noop(),
+            unlimited(),
+            directory(Vec::new()),
+            revoker(shim.config())?,
+            noop_scanner(),
+            noop_unpacker(),
+        );
         thread::sleep(Duration::from_secs(1)); // allow to start DocumentMover
 
         // when
@@ -143,7 +388,14 @@ mod test {
         init_tracing();
         let (fs_spies, fs) = tracked(failing());
         let mut shim = create_test_shim()?;
-        DocumentMover::new(Config::default(), shim.bus())?.run(fs);
+        DocumentMover::new(Config::default(), shim.bus())?.run(
+            fs,
+            unlimited(),
+            directory(Vec::new()),
+            revoker(shim.config())?,
+            noop_scanner(),
+            noop_unpacker(),
+        );
         thread::sleep(Duration::from_secs(1)); // allow to start DocumentMover
 
         // when
@@ -158,6 +410,70 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn document_is_removed_instead_of_moved_when_quota_is_exceeded() -> Result<()> {
+        // given
+        init_tracing();
+        let (fs_spies, fs) = tracked(noop());
+        let mut shim = create_test_shim()?;
+        DocumentMover::new(shim.config(), shim.bus())?.run(
+            fs,
+            exceeded(),
+            directory(Vec::new()),
+            revoker(shim.config())?,
+            noop_scanner(),
+            noop_unpacker(),
+        );
+        thread::sleep(Duration::from_secs(1)); // allow to start DocumentMover
+
+        // when
+        shim.trigger_mover()?;
+
+        shim.ignore_event()?; // ignore NewDocs event
+
+        // then
+        assert!(fs_spies.rm_file_called());
+        assert!(matches!(
+            shim.recv_event()?,
+            BusEvent::DocsRejected(loc, _) if loc == shim.test_location()
+        ));
+        assert!(shim.no_events_on_bus());
+
+        Ok(())
+    }
+
+    #[test]
+    fn document_is_removed_instead_of_moved_when_account_is_disabled() -> Result<()> {
+        // given
+        init_tracing();
+        let (fs_spies, fs) = tracked(noop());
+        let mut shim = create_test_shim()?;
+        let disabled = Account::new(User::new(FAKE_USER_EMAIL), AccountStatus::Disabled);
+        DocumentMover::new(shim.config(), shim.bus())?.run(
+            fs,
+            unlimited(),
+            directory(vec![disabled]),
+            revoker(shim.config())?,
+            noop_scanner(),
+            noop_unpacker(),
+        );
+        thread::sleep(Duration::from_secs(1)); // allow to start DocumentMover
+
+        // when
+        shim.trigger_mover()?;
+
+        shim.ignore_event()?; // ignore NewDocs event
+
+        // then
+        assert!(fs_spies.rm_file_called());
+        assert!(matches!(
+            shim.recv_event()?,
+            BusEvent::DocsRejected(loc, _) if loc == shim.test_location()
+        ));
+
+        Ok(())
+    }
+
     #[test]
     fn mover_ignores_other_bus_events() -> Result<()> {
         // given
@@ -175,7 +491,14 @@ mod test {
             BusEvent::ThumbnailEncryptionFailed(Faker.fake()),
             BusEvent::PipelineFinished,
         ];
-        DocumentMover::new(Config::default(), shim.bus())?.run(noop());
+        DocumentMover::new(Config::default(), shim.bus())?.run(
+            noop(),
+            unlimited(),
+            directory(Vec::new()),
+            revoker(shim.config())?,
+            noop_scanner(),
+            noop_unpacker(),
+        );
 
         // when
         shim.send_events(&ignored_events)?;
@@ -196,7 +519,14 @@ mod test {
         init_tracing();
         let (fs_spies, fs) = tracked(failing());
         let mut shim = create_test_shim()?;
-        DocumentMover::new(Config::default(), shim.bus())?.run(fs);
+        DocumentMover::new(Config::default(), shim.bus())?.run(
+            fs,
+            unlimited(),
+            directory(Vec::new()),
+            revoker(shim.config())?,
+            noop_scanner(),
+            noop_unpacker(),
+        );
         thread::sleep(Duration::from_secs(1)); // allow to start DocumentMover
 
         shim.trigger_mover()?;
@@ -217,7 +547,14 @@ mod test {
         init_tracing();
         let (fs_spies, fs) = tracked(noop());
         let mut shim = create_test_shim()?;
-        DocumentMover::new(Config::default(), shim.bus())?.run(fs);
+        DocumentMover::new(Config::default(), shim.bus())?.run(
+            fs,
+            unlimited(),
+            directory(Vec::new()),
+            revoker(shim.config())?,
+            noop_scanner(),
+            noop_unpacker(),
+        );
         thread::sleep(Duration::from_secs(1)); // allow to start DocumentMover
 
         // when
@@ -228,4 +565,270 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn renamed_document_is_moved_as_new_one() -> Result<()> {
+        // given
+        init_tracing();
+        let mut shim = create_test_shim()?;
+        DocumentMover::new(shim.config(), shim.bus())?.run(
+            noop(),
+            unlimited(),
+            directory(Vec::new()),
+            revoker(shim.config())?,
+            noop_scanner(),
+            noop_unpacker(),
+        );
+        thread::sleep(Duration::from_secs(1)); // allow to start DocumentMover
+        let event = BusEvent::DocsRenamed {
+            from: Filename::new("old-name.jpg")?,
+            to: shim.test_location(),
+        };
+
+        // when
+        shim.send_events(&[event])?;
+
+        shim.ignore_event()?; // ignore DocsRenamed event
+
+        // then
+        assert!(shim.event_on_bus(&BusEvent::DocsMoved(shim.dst_doc_location()))?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn document_stored_under_old_name_is_removed_when_renamed() -> Result<()> {
+        // given
+        init_tracing();
+        let (fs_spies, fs) = tracked(noop());
+        let mut shim = create_test_shim()?;
+        let old_path = shim.config().doc_path("old-name.jpg");
+        std::fs::create_dir_all(old_path.parent().unwrap())?;
+        std::fs::write(&old_path, "anything")?;
+        DocumentMover::new(shim.config(), shim.bus())?.run(
+            fs,
+            unlimited(),
+            directory(Vec::new()),
+            revoker(shim.config())?,
+            noop_scanner(),
+            noop_unpacker(),
+        );
+        thread::sleep(Duration::from_secs(1)); // allow to start DocumentMover
+        let event = BusEvent::DocsRenamed {
+            from: Filename::new("old-name.jpg")?,
+            to: shim.test_location(),
+        };
+
+        // when
+        shim.send_events(&[event])?;
+
+        // then
+        assert!(fs_spies.rm_file_called());
+        assert!(fs_spies.mv_file_called());
+
+        Ok(())
+    }
+
+    #[test]
+    fn thumbnails_of_old_name_are_removed_when_renamed() -> Result<()> {
+        // given
+        init_tracing();
+        let mut shim = create_test_shim()?;
+        let thumbnails = [
+            shim.config().thumbnail_path("old-name.jpg.webp"),
+            shim.config().thumbnail_path("old-name.jpg@2x.webp"),
+            shim.config().thumbnail_path("old-name.jpg.preview.webp"),
+        ];
+        let other = shim.config().thumbnail_path("other-name.jpg.webp");
+        std::fs::create_dir_all(other.parent().unwrap())?;
+        for path in thumbnails.iter().chain(Some(&other)) {
+            std::fs::write(path, "anything")?;
+        }
+        DocumentMover::new(shim.config(), shim.bus())?.run(
+            local_fs(),
+            unlimited(),
+            directory(Vec::new()),
+            revoker(shim.config())?,
+            noop_scanner(),
+            noop_unpacker(),
+        );
+        thread::sleep(Duration::from_secs(1)); // allow to start DocumentMover
+        let event = BusEvent::DocsRenamed {
+            from: Filename::new("old-name.jpg")?,
+            to: shim.test_location(),
+        };
+
+        // when
+        shim.send_events(&[event])?;
+        thread::sleep(Duration::from_secs(1)); // allow to remove thumbnails
+
+        // then
+        assert!(thumbnails.iter().all(|path| !path.exists()));
+        assert!(other.exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn grants_and_links_to_old_name_are_revoked_when_renamed() -> Result<()> {
+        // given
+        init_tracing();
+        let mut shim = create_test_shim()?;
+        let (sharing, links) = (
+            sharing(shim.config().as_ref())?,
+            links(shim.config().as_ref())?,
+        );
+        let owner = User::new(FAKE_USER_EMAIL);
+        let old_name = Filename::new("old-name.jpg")?;
+        let grant = Grant::new(
+            owner.clone(),
+            User::new("other@email.com"),
+            old_name.clone(),
+        );
+        sharing.grant(grant)?;
+        links.add(ShareLink {
+            id: "link-id".into(),
+            owner: owner.clone(),
+            filename: old_name.clone(),
+            created_at: 100,
+            expires_at: u64::MAX,
+            single_use: false,
+            used: false,
+            revoked: false,
+            accesses: Vec::new(),
+        })?;
+        DocumentMover::new(shim.config(), shim.bus())?.run(
+            noop(),
+            unlimited(),
+            directory(Vec::new()),
+            ShareRevoker::new(sharing.clone(), links.clone()),
+            noop_scanner(),
+            noop_unpacker(),
+        );
+        thread::sleep(Duration::from_secs(1)); // allow to start DocumentMover
+        let event = BusEvent::DocsRenamed {
+            from: old_name,
+            to: shim.test_location(),
+        };
+
+        // when
+        shim.send_events(&[event])?;
+        thread::sleep(Duration::from_secs(1)); // allow to revoke grants and links
+
+        // then
+        assert!(sharing.granted_by(&owner)?.is_empty());
+        assert!(links.links_of(&owner)?.iter().all(|link| link.revoked));
+
+        Ok(())
+    }
+
+    #[test]
+    fn archive_is_replaced_by_unpacked_documents() -> Result<()> {
+        // given
+        init_tracing();
+        let (fs_spies, fs) = tracked(noop());
+        let mut shim = create_test_shim()?;
+        let unpacker = found(&["scans-invoice.pdf"]);
+        DocumentMover::new(shim.config(), shim.bus())?.run(
+            fs,
+            unlimited(),
+            directory(Vec::new()),
+            revoker(shim.config())?,
+            noop_scanner(),
+            unpacker,
+        );
+        thread::sleep(Duration::from_secs(1)); // allow to start DocumentMover
+        let archive = sibling(&shim.test_location(), "scans.zip");
+        let Location::FS(dst) = shim.dst_doc_location();
+        let unpacked = dst[0].parent().join("scans-invoice.pdf");
+
+        // when
+        shim.send_events(&[BusEvent::NewDocs(archive)])?;
+
+        shim.ignore_event()?; // ignore NewDocs event
+
+        // then
+        assert_eq!(
+            shim.recv_event()?,
+            BusEvent::DocsUnpacked {
+                from: Filename::new("scans.zip")?,
+                to: Location::FS(vec![unpacked.clone().into()])
+            }
+        );
+        assert_eq!(
+            shim.recv_event()?,
+            BusEvent::DocsMoved(Location::FS(vec![unpacked.into()]))
+        );
+        assert!(fs_spies.rm_file_called());
+        assert!(shim.no_events_on_bus());
+
+        Ok(())
+    }
+
+    #[test]
+    fn archive_is_rejected_when_unpacking_fails() -> Result<()> {
+        // given
+        init_tracing();
+        let mut shim = create_test_shim()?;
+        let unpacker = failing_unpacker();
+        DocumentMover::new(shim.config(), shim.bus())?.run(
+            noop(),
+            unlimited(),
+            directory(Vec::new()),
+            revoker(shim.config())?,
+            noop_scanner(),
+            unpacker,
+        );
+        thread::sleep(Duration::from_secs(1)); // allow to start DocumentMover
+        let archive = sibling(&shim.test_location(), "scans.zip");
+
+        // when
+        shim.send_events(&[BusEvent::NewDocs(archive.clone())])?;
+
+        shim.ignore_event()?; // ignore NewDocs event
+
+        // then
+        assert!(matches!(
+            shim.recv_event()?,
+            BusEvent::DocsRejected(loc, _) if loc == archive
+        ));
+        assert!(shim.no_events_on_bus());
+
+        Ok(())
+    }
+
+    #[test]
+    fn attachments_of_email_are_unpacked_next_to_it() -> Result<()> {
+        // given
+        init_tracing();
+        let (unpacker_spies, unpacker) = unpacker::tracked(noop_unpacker());
+        let mut shim = create_test_shim()?;
+        DocumentMover::new(shim.config(), shim.bus())?.run(
+            noop(),
+            unlimited(),
+            directory(Vec::new()),
+            revoker(shim.config())?,
+            noop_scanner(),
+            unpacker,
+        );
+        thread::sleep(Duration::from_secs(1)); // allow to start DocumentMover
+        let email = sibling(&shim.test_location(), "invoice.eml");
+
+        // when
+        shim.send_events(&[BusEvent::NewDocs(email)])?;
+
+        shim.ignore_event()?; // ignore NewDocs event
+
+        // then
+        assert!(unpacker_spies.unpack_called());
+        assert!(matches!(shim.recv_event()?, BusEvent::DocsMoved(_)));
+
+        Ok(())
+    }
+
+    /// Location of other file in the same user directory.
+    fn sibling(loc: &Location, name: &str) -> Location {
+        let Location::FS(paths) = loc;
+        Location::FS(vec![paths[0].parent().join(name).into()])
+    }
 }
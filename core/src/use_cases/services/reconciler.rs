@@ -0,0 +1,499 @@
+//! Brings the index in sync with the files on the disk after the application starts.
+use crate::entities::file::{Filename, Thumbnailname};
+use crate::entities::location::{Location, SafePathBuf};
+use crate::entities::user::User;
+use crate::result::ReconcilerErr;
+use crate::use_cases::bus::{BusEvent, EventBus, EventPublisher};
+use crate::use_cases::cipher::CipherReader;
+use crate::use_cases::config::Config;
+use crate::use_cases::fs::Fs;
+use crate::use_cases::state::StateReader;
+use crate::use_cases::supervisor::ServiceHandle;
+
+use base64::engine::general_purpose::STANDARD as b64;
+use base64::Engine;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use tracing::{debug, error, info, instrument, warn};
+
+type Result<T> = std::result::Result<T, ReconcilerErr>;
+
+/// Handles the files which changed while the application was down.
+///
+/// It runs once, after all other services are started:
+/// 1. Thumbnails not referenced by any indexed document are reported, and removed if
+///    [`Config::remove_orphan_thumbnails`] is set. Thumbnails of the documents which are still on
+///    the disk are kept, as these documents are being processed, or will be.
+/// 2. Every supported file left in the watched directory is published as [`BusEvent::NewDocs`].
+///    Files with content not matching their extension are removed and published as
+///    [`BusEvent::DocsRejected`], like the ones found by the watcher.
+/// 3. Every document from the docs directory which is missing in the user's index is decrypted
+///    into the watched directory, so it goes through the whole pipeline again, like a new document.
+pub struct Reconciler {
+    cfg: Config,
+    bus: EventBus,
+}
+
+impl Reconciler {
+    pub fn new<C: Into<Config>>(cfg: C, bus: EventBus) -> Self {
+        let cfg = cfg.into();
+        Self { cfg, bus }
+    }
+
+    #[instrument(skip(self, state, cipher, fs))]
+    pub fn run(self, state: StateReader, cipher: CipherReader, fs: Fs) -> ServiceHandle {
+        thread::Builder::new()
+            .name("reconciler".into())
+            .spawn(move || match self.reconcile(&state, &cipher, &fs) {
+                Ok(report) => info!("reconciliation finished: {:?}", report),
+                Err(e) => error!("reconciliation failed: '{}'", e),
+            })
+            .expect("failed to spawn reconciler thread")
+    }
+
+    fn reconcile(&self, state: &StateReader, cipher: &CipherReader, fs: &Fs) -> Result<Report> {
+        let publ = self.bus.publisher();
+        let mut report = Report::default();
+        // NOTE: thumbnails are made before the documents are indexed, so orphans are looked for
+        // before any document is queued
+        self.find_orphans(state, fs, &mut report)?;
+        self.queue_unprocessed(&publ, fs, &mut report)?;
+        self.reindex_missing(state, cipher, fs, &mut report)?;
+        Ok(report)
+    }
+
+    fn queue_unprocessed(&self, publ: &EventPublisher, fs: &Fs, report: &mut Report) -> Result<()> {
+        for (_, watched_dir) in user_dirs(&self.cfg.watched_dir)? {
+            for path in files(&watched_dir)? {
+                if !path.has_valid_ext() {
+                    warn!("skipping unsupported file: '{}'", path);
+                    continue;
+                }
+                if let Err(e) = path.verify_content() {
+                    warn!("rejecting unprocessed file '{}': '{}'", path, e);
+                    fs.rm_file(&path)?;
+                    publ.send(BusEvent::DocsRejected(
+                        Location::FS(vec![path]),
+                        e.to_string(),
+                    ))?;
+                    report.rejected += 1;
+                    continue;
+                }
+                debug!("queueing unprocessed file: '{}'", path);
+                publ.send(BusEvent::NewDocs(Location::FS(vec![path])))?;
+                report.queued += 1;
+            }
+        }
+        Ok(())
+    }
+
+    fn reindex_missing(
+        &self,
+        state: &StateReader,
+        cipher: &CipherReader,
+        fs: &Fs,
+        report: &mut Report,
+    ) -> Result<()> {
+        for (user, docs_dir) in user_dirs(&self.cfg.docs_dir)? {
+            let indexed = state.all_docs(user)?.filenames();
+            for doc in files(&docs_dir)? {
+                if indexed.contains(&doc.filename()) {
+                    continue;
+                }
+                match reindex(&doc, &self.cfg.watched_dir, cipher, fs) {
+                    Ok(()) => report.reindexed += 1,
+                    Err(e) => {
+                        warn!("failed to re-index '{}': '{}'", doc, e);
+                        report.failed += 1;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn find_orphans(&self, state: &StateReader, fs: &Fs, report: &mut Report) -> Result<()> {
+        for (user, thumbnails_dir) in user_dirs(&self.cfg.thumbnails_dir)? {
+            let in_flight = self.in_flight(&user)?;
+            let indexed = variants(
+                &state.all_docs(user)?.thumbnails(),
+                &self.cfg.thumbnails.pixel_ratios,
+            );
+            for thumbnail in files(&thumbnails_dir)? {
+                let name = thumbnail.filename();
+                if !indexed.contains(&name) && !in_flight.contains(&name) {
+                    self.handle_orphan(&thumbnail, fs, report)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Names of the thumbnails which can be made for the documents of the `user` which are on the
+    /// disk, in the docs or the watched directory, but may be not indexed yet.
+    fn in_flight(&self, user: &User) -> Result<HashSet<String>> {
+        let [docs_dir, _, _, watched_dir] = self.cfg.user_dirs(user);
+        let mut names = HashSet::new();
+        for doc in files(&docs_dir)?.iter().chain(&files(&watched_dir)?) {
+            for ext in [self.cfg.thumbnails.format.ext(), "png"] {
+                let Ok(thumbnail) = Thumbnailname::of(Filename::from(doc), ext) else {
+                    continue;
+                };
+                names.extend(
+                    thumbnail
+                        .variants(&self.cfg.thumbnails.pixel_ratios)
+                        .iter()
+                        .map(ToString::to_string),
+                );
+            }
+        }
+        Ok(names)
+    }
+
+    fn handle_orphan(&self, thumbnail: &SafePathBuf, fs: &Fs, report: &mut Report) -> Result<()> {
+        report.orphans += 1;
+        if !self.cfg.remove_orphan_thumbnails {
+            warn!("found orphan thumbnail: '{}'", thumbnail);
+            return Ok(());
+        }
+        warn!("removing orphan thumbnail: '{}'", thumbnail);
+        fs.rm_file(thumbnail)?;
+        report.removed_orphans += 1;
+        Ok(())
+    }
+}
+
+/// Documents are encrypted at rest, so they need to be decrypted before they can be processed
+/// again. The decrypted copy is written to the watched directory and picked up by the watcher, the
+/// encrypted original is kept until the copy replaces it. The pipeline encrypts it back when it's
+/// done.
+fn reindex(doc: &SafePathBuf, watched_dir: &Path, cipher: &CipherReader, fs: &Fs) -> Result<()> {
+    let copy = watched_dir.join(doc.rel_path());
+    if copy.exists() {
+        debug!("'{}' is already waiting for processing", doc);
+        return Ok(());
+    }
+    let path: &PathBuf = doc.as_ref();
+    let decrypted = cipher.decrypt(&fs.load(path.clone())?)?;
+    fs.save(copy, &decrypted)?;
+    debug!("re-indexing '{}'", doc);
+    Ok(())
+}
+
+/// Names of the files of the thumbnails, which are stored separately for each size and pixel
+/// ratio.
+fn variants(thumbnails: &HashSet<String>, ratios: &[u32]) -> HashSet<String> {
+    thumbnails
+        .iter()
+        .filter_map(|name| Thumbnailname::new(name.as_str()).ok())
+        .flat_map(|name| name.variants(ratios))
+        .map(|name| name.to_string())
+        .collect()
+}
+
+/// Returns directories of users placed directly under `root`.
+fn user_dirs(root: &Path) -> Result<Vec<(User, PathBuf)>> {
+    if !root.exists() {
+        return Ok(Vec::new());
+    }
+    let mut dirs = Vec::new();
+    for entry in fs::read_dir(root)? {
+        let path = entry?.path();
+        if !path.is_dir() {
+            continue;
+        }
+        match dir_owner(&path) {
+            Some(user) => dirs.push((user, path)),
+            None => warn!("'{}' is not a user directory", path.display()),
+        }
+    }
+    Ok(dirs)
+}
+
+fn dir_owner(dir: &Path) -> Option<User> {
+    let dir_name = dir.file_name()?.to_str()?;
+    let email = String::from_utf8(b64.decode(dir_name).ok()?).ok()?;
+    Some(User::new(email))
+}
+
+fn files(dir: &Path) -> Result<Vec<SafePathBuf>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file() {
+            files.push(SafePathBuf::new(path));
+        }
+    }
+    Ok(files)
+}
+
+/// Summary of the reconciliation.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Report {
+    queued: usize,
+    rejected: usize,
+    reindexed: usize,
+    failed: usize,
+    orphans: usize,
+    removed_orphans: usize,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::configuration::factories::{fs as local_fs, state};
+    use crate::configuration::telemetry::init_tracing;
+    use crate::entities::document::DocDetails;
+    use crate::entities::file::{Filename, Thumbnailname};
+    use crate::entities::user::FAKE_USER_EMAIL;
+    use crate::testingtools::services::encrypter::{failing, noop};
+    use crate::testingtools::unit::create_test_shim;
+    use crate::testingtools::TestConfig;
+
+    use anyhow::Result;
+
+    fn mk_user_file<P: AsRef<Path>>(root: P, name: &str) -> Result<SafePathBuf> {
+        let user_dir = root.as_ref().join(b64.encode(FAKE_USER_EMAIL));
+        fs::create_dir_all(&user_dir)?;
+        let path = user_dir.join(name);
+        fs::write(&path, "anything")?;
+        Ok(SafePathBuf::new(path))
+    }
+
+    #[test]
+    fn files_left_in_watched_dir_are_published_as_new_docs() -> Result<()> {
+        // given
+        init_tracing();
+        let shim = create_test_shim()?;
+        let cfg = TestConfig::new()?;
+        let config: Config = (&cfg).into();
+        let path = mk_user_file(&config.watched_dir, "doc1.pdf")?;
+        fs::write(&path, "%PDF-1.4 anything")?;
+        let state = state(&cfg)?;
+        let reconciler = Reconciler::new(config, shim.bus());
+
+        // when
+        let report = reconciler.reconcile(&state.reader(), &noop().reader(), &local_fs())?;
+
+        // then
+        assert_eq!(report.queued, 1);
+        assert_eq!(
+            shim.recv_event()?,
+            BusEvent::NewDocs(Location::FS(vec![path]))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn files_left_in_watched_dir_with_mismatched_content_are_rejected() -> Result<()> {
+        // given
+        init_tracing();
+        let shim = create_test_shim()?;
+        let cfg = TestConfig::new()?;
+        let config: Config = (&cfg).into();
+        let path = mk_user_file(&config.watched_dir, "doc1.pdf")?;
+        fs::write(&path, [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'])?;
+        let state = state(&cfg)?;
+        let reconciler = Reconciler::new(config, shim.bus());
+
+        // when
+        let report = reconciler.reconcile(&state.reader(), &noop().reader(), &local_fs())?;
+
+        // then
+        assert_eq!(report.queued, 0);
+        assert_eq!(report.rejected, 1);
+        assert!(!path.is_file());
+        assert!(matches!(
+            shim.recv_event()?,
+            BusEvent::DocsRejected(Location::FS(paths), _) if paths == vec![path]
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn documents_missing_in_index_are_reindexed() -> Result<()> {
+        // given
+        init_tracing();
+        let shim = create_test_shim()?;
+        let cfg = TestConfig::new()?;
+        let config: Config = (&cfg).into();
+        let doc = mk_user_file(&config.docs_dir, "doc1.pdf")?;
+        let copy = config.watched_dir.join(doc.rel_path());
+        let state = state(&cfg)?;
+        let reconciler = Reconciler::new(config, shim.bus());
+
+        // when
+        let report = reconciler.reconcile(&state.reader(), &noop().reader(), &local_fs())?;
+
+        // then
+        assert_eq!(report.reindexed, 1);
+        assert_eq!(fs::read(&copy)?, b"anything");
+        assert!(doc.is_file());
+        assert!(shim.no_events_on_bus());
+
+        Ok(())
+    }
+
+    #[test]
+    fn documents_which_can_not_be_decrypted_are_reported() -> Result<()> {
+        // given
+        init_tracing();
+        let shim = create_test_shim()?;
+        let cfg = TestConfig::new()?;
+        let config: Config = (&cfg).into();
+        mk_user_file(&config.docs_dir, "doc1.pdf")?;
+        let state = state(&cfg)?;
+        let reconciler = Reconciler::new(config, shim.bus());
+
+        // when
+        let report = reconciler.reconcile(&state.reader(), &failing().reader(), &local_fs())?;
+
+        // then
+        assert_eq!(report.failed, 1);
+        assert!(shim.no_events_on_bus());
+
+        Ok(())
+    }
+
+    #[test]
+    fn indexed_documents_are_left_untouched() -> Result<()> {
+        // given
+        init_tracing();
+        let shim = create_test_shim()?;
+        let cfg = TestConfig::new()?;
+        let config: Config = (&cfg).into();
+        mk_user_file(&config.docs_dir, "doc1.pdf")?;
+        let thumbnail = mk_user_file(&config.thumbnails_dir, "doc1.png")?;
+        let state = state(&cfg)?;
+        state.writer().index(&[DocDetails::new(
+            Filename::new("doc1.pdf")?,
+            "body",
+            Thumbnailname::new("doc1.png")?,
+            User::new(FAKE_USER_EMAIL),
+        )])?;
+        let reconciler = Reconciler::new(config, shim.bus());
+
+        // when
+        let report = reconciler.reconcile(&state.reader(), &noop().reader(), &local_fs())?;
+
+        // then
+        assert_eq!(report, Report::default());
+        assert!(thumbnail.is_file());
+        assert!(shim.no_events_on_bus());
+
+        Ok(())
+    }
+
+    #[test]
+    fn other_variants_of_indexed_thumbnails_are_not_orphans() -> Result<()> {
+        // given
+        init_tracing();
+        let shim = create_test_shim()?;
+        let cfg = TestConfig::new()?;
+        let config: Config = (&cfg).into();
+        mk_user_file(&config.docs_dir, "doc1.png")?;
+        mk_user_file(&config.thumbnails_dir, "doc1.webp")?;
+        mk_user_file(&config.thumbnails_dir, "doc1@2x.webp")?;
+        let preview = mk_user_file(&config.thumbnails_dir, "doc1.preview.webp")?;
+        let state = state(&cfg)?;
+        state.writer().index(&[DocDetails::new(
+            Filename::new("doc1.png")?,
+            "body",
+            Thumbnailname::new("doc1.webp")?,
+            User::new(FAKE_USER_EMAIL),
+        )])?;
+        let reconciler = Reconciler::new(config, shim.bus());
+
+        // when
+        let report = reconciler.reconcile(&state.reader(), &noop().reader(), &local_fs())?;
+
+        // then
+        assert_eq!(report.orphans, 0);
+        assert!(preview.is_file());
+
+        Ok(())
+    }
+
+    #[test]
+    fn thumbnails_of_documents_not_indexed_yet_are_not_orphans() -> Result<()> {
+        // given
+        init_tracing();
+        let shim = create_test_shim()?;
+        let cfg = TestConfig::new()?;
+        let config = Config {
+            remove_orphan_thumbnails: true,
+            ..Config::from(&cfg)
+        };
+        let path = mk_user_file(&config.watched_dir, "doc1.pdf")?;
+        fs::write(&path, "%PDF-1.4 anything")?;
+        let ext = config.thumbnails.format.ext();
+        let thumbnail = mk_user_file(&config.thumbnails_dir, &format!("doc1.pdf.{ext}"))?;
+        let preview = mk_user_file(&config.thumbnails_dir, &format!("doc1.pdf.preview.{ext}"))?;
+        let state = state(&cfg)?;
+        let reconciler = Reconciler::new(config, shim.bus());
+
+        // when
+        let report = reconciler.reconcile(&state.reader(), &noop().reader(), &local_fs())?;
+
+        // then
+        assert_eq!(report.orphans, 0);
+        assert!(thumbnail.is_file());
+        assert!(preview.is_file());
+
+        Ok(())
+    }
+
+    #[test]
+    fn orphan_thumbnails_are_only_reported_by_default() -> Result<()> {
+        // given
+        init_tracing();
+        let shim = create_test_shim()?;
+        let cfg = TestConfig::new()?;
+        let config: Config = (&cfg).into();
+        let thumbnail = mk_user_file(&config.thumbnails_dir, "doc1.png")?;
+        let state = state(&cfg)?;
+        let reconciler = Reconciler::new(config, shim.bus());
+
+        // when
+        let report = reconciler.reconcile(&state.reader(), &noop().reader(), &local_fs())?;
+
+        // then
+        assert_eq!(report.orphans, 1);
+        assert_eq!(report.removed_orphans, 0);
+        assert!(thumbnail.is_file());
+
+        Ok(())
+    }
+
+    #[test]
+    fn orphan_thumbnails_are_removed_when_enabled() -> Result<()> {
+        // given
+        init_tracing();
+        let shim = create_test_shim()?;
+        let cfg = TestConfig::new()?;
+        let config = Config {
+            remove_orphan_thumbnails: true,
+            ..Config::from(&cfg)
+        };
+        let thumbnail = mk_user_file(&config.thumbnails_dir, "doc1.png")?;
+        let state = state(&cfg)?;
+        let reconciler = Reconciler::new(config, shim.bus());
+
+        // when
+        let report = reconciler.reconcile(&state.reader(), &noop().reader(), &local_fs())?;
+
+        // then
+        assert_eq!(report.removed_orphans, 1);
+        assert!(!thumbnail.is_file());
+
+        Ok(())
+    }
+}
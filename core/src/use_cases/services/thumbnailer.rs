@@ -5,10 +5,9 @@ use crate::result::ThumbnailerErr;
 use crate::use_cases::bus::{BusEvent, EventBus, EventPublisher};
 use crate::use_cases::config::Config;
 use crate::use_cases::fs::Fs;
+use crate::use_cases::supervisor::{spawn_service, ServiceHandle, ServicePool};
 
-use rayon::{ThreadPool, ThreadPoolBuilder};
 use std::path::{Path, PathBuf};
-use std::thread;
 use tracing::{debug, error, instrument, trace, warn};
 
 pub type ThumbnailerCreator = Box<dyn ThumbnailerFactory>;
@@ -18,28 +17,31 @@ type Result<T> = std::result::Result<T, ThumbnailerErr>;
 pub struct ThumbnailGenerator {
     cfg: Config,
     bus: EventBus,
-    tp: ThreadPool,
+    tp: ServicePool,
 }
 
 impl ThumbnailGenerator {
     pub fn new<C: Into<Config>>(cfg: C, bus: EventBus) -> Result<Self> {
         let cfg = cfg.into();
-        let tp = ThreadPoolBuilder::new().num_threads(4).build()?;
+        let tp = ServicePool::new(4)?;
         Ok(Self { cfg, bus, tp })
     }
 
     #[instrument(skip(self, factory, fs))]
-    pub fn run(self, factory: ThumbnailerCreator, fs: Fs) {
-        thread::spawn(move || -> Result<()> {
-            let sub = self.bus.subscriber();
+    pub fn run(self, factory: ThumbnailerCreator, fs: Fs) -> ServiceHandle {
+        let sub = self.bus.subscriber();
+        spawn_service("thumbnailer", move || -> Result<()> {
             loop {
                 match sub.recv()? {
                     BusEvent::DocsMoved(loc) => self.do_thumbnail(loc, &factory)?,
                     BusEvent::ThumbnailEncryptionFailed(loc) => self.cleanup(loc, &fs),
+                    BusEvent::Shutdown("thumbnailer") => break,
                     e => trace!("event not supported in ThumbnailGenerator: '{:?}'", e),
                 }
             }
-        });
+            self.tp.drain();
+            Ok(())
+        })
     }
 
     #[instrument(skip(self, factory))]
@@ -120,6 +122,7 @@ mod test {
 
     use anyhow::Result;
     use fake::{Fake, Faker};
+    use std::thread;
     use std::time::Duration;
 
     #[test]
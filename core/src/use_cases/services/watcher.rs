@@ -1,18 +1,23 @@
 use crate::entities::location::Location;
 use crate::result::WatcherErr;
 use crate::use_cases::bus::{BusEvent, EventBus};
+use crate::use_cases::fs::Fs;
 use crate::use_cases::receiver::{DocsEvent, EventRecv};
+use crate::use_cases::supervisor::{spawn_service, ServiceHandle};
 
-use std::thread;
-use tracing::{debug, trace};
+use tracing::{debug, trace, warn};
 
 type Result<T> = std::result::Result<T, WatcherErr>;
 
 /// Watches for the event comming from [`Watcher`] and publishes appropriate event on the event bus.
 ///
 /// It then spawns new thread in which it receives events from [`Watcher`]. If the event is
-/// [`WatcherEvent::Created`], then [`Event::NewDocs`] is created out of it and published on the
-/// bus.
+/// [`DocsEvent::Created`] or [`DocsEvent::Modified`], then [`BusEvent::NewDocs`] is created out of
+/// it and published on the bus, so the document is (re)extracted. [`DocsEvent::Renamed`] is
+/// published as [`BusEvent::DocsRenamed`], so the index can drop the old name.
+///
+/// Documents in [`DocsEvent::Rejected`] are removed from the watched directory, with `fs`, and
+/// published as [`BusEvent::DocsRejected`], so the rejection is recorded.
 #[derive(Debug)]
 pub struct FileWatcher {
     bus: EventBus,
@@ -23,11 +28,11 @@ impl FileWatcher {
         Self { bus }
     }
 
-    pub fn run(self, receiver: EventRecv) {
+    pub fn run(self, receiver: EventRecv, fs: Fs) -> ServiceHandle {
         debug!("spawning watching thread");
-        thread::spawn(move || -> Result<()> {
+        let publ = self.bus.publisher();
+        spawn_service("watcher", move || -> Result<()> {
             debug!("watching thread spawned");
-            let publ = self.bus.publisher();
             loop {
                 trace!("waiting for event from watcher");
                 match receiver.recv() {
@@ -35,11 +40,32 @@ impl FileWatcher {
                         debug!("got create file event on path: '{:?}'", path);
                         publ.send(BusEvent::NewDocs(Location::FS(vec![path])))?;
                     }
+                    Ok(DocsEvent::Modified(path)) => {
+                        debug!("got modify file event on path: '{:?}'", path);
+                        publ.send(BusEvent::NewDocs(Location::FS(vec![path])))?;
+                    }
+                    Ok(DocsEvent::Renamed { from, to }) => {
+                        debug!("got rename file event from '{}' to '{:?}'", from, to);
+                        let to = Location::FS(vec![to]);
+                        publ.send(BusEvent::DocsRenamed { from, to })?;
+                    }
+                    Ok(DocsEvent::Removed(path)) => {
+                        // documents are moved out of the watched directory once they are
+                        // received, so removals are expected and there is nothing to update
+                        trace!("path removed from watched dir: '{:?}'", path);
+                    }
+                    Ok(DocsEvent::Rejected { path, reason }) => {
+                        debug!("got rejected file event on path: '{:?}'", path);
+                        if let Err(e) = fs.rm_file(&path) {
+                            warn!("failed to remove rejected file '{}': '{}'", path, e);
+                        }
+                        publ.send(BusEvent::DocsRejected(Location::FS(vec![path]), reason))?;
+                    }
                     Ok(e) => trace!("event not supported in Watcher: '{}'", e),
                     Err(e) => trace!("watcher error: {:?}", e),
                 }
             }
-        });
+        })
     }
 }
 
@@ -48,7 +74,9 @@ mod test {
     use super::*;
 
     use crate::configuration::telemetry::init_tracing;
+    use crate::entities::file::Filename;
     use crate::result::EventReceiverErr;
+    use crate::testingtools::services::fs::{noop, tracked};
     use crate::testingtools::unit::create_test_shim;
     use crate::use_cases::bus::BusEvent;
     use crate::use_cases::receiver::EventReceiver;
@@ -62,7 +90,7 @@ mod test {
         init_tracing();
         let mut shim = create_test_shim()?;
         let mock_event_receiver = MockEventReceiver::new(shim.rx());
-        FileWatcher::new(shim.bus()).run(mock_event_receiver);
+        FileWatcher::new(shim.bus()).run(mock_event_receiver, noop());
 
         // when
         shim.trigger_watcher()?;
@@ -73,13 +101,97 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn modified_docs_event_puts_new_docs_event_on_bus() -> Result<()> {
+        // given
+        init_tracing();
+        let mut shim = create_test_shim()?;
+        let mock_event_receiver = MockEventReceiver::new(shim.rx());
+        FileWatcher::new(shim.bus()).run(mock_event_receiver, noop());
+        let Location::FS(paths) = shim.test_location();
+
+        // when
+        shim.mk_docs_event(DocsEvent::Modified(paths[0].clone()))?;
+
+        // then
+        assert!(shim.event_on_bus(&BusEvent::NewDocs(shim.test_location()))?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn renamed_docs_event_puts_docs_renamed_event_on_bus() -> Result<()> {
+        // given
+        init_tracing();
+        let mut shim = create_test_shim()?;
+        let mock_event_receiver = MockEventReceiver::new(shim.rx());
+        FileWatcher::new(shim.bus()).run(mock_event_receiver, noop());
+        let Location::FS(paths) = shim.test_location();
+        let from = Filename::new("old-name.jpg")?;
+
+        // when
+        shim.mk_docs_event(DocsEvent::Renamed {
+            from: from.clone(),
+            to: paths[0].clone(),
+        })?;
+
+        // then
+        assert!(shim.event_on_bus(&BusEvent::DocsRenamed {
+            from,
+            to: shim.test_location()
+        })?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn removed_docs_event_is_ignored() -> Result<()> {
+        // given
+        init_tracing();
+        let mut shim = create_test_shim()?;
+        let mock_event_receiver = MockEventReceiver::new(shim.rx());
+        FileWatcher::new(shim.bus()).run(mock_event_receiver, noop());
+
+        // when
+        shim.mk_docs_event(DocsEvent::Removed("/some/path.jpg".into()))?;
+
+        // then
+        assert!(shim.no_events_on_bus());
+
+        Ok(())
+    }
+
+    #[test]
+    fn rejected_docs_event_removes_file_and_puts_docs_rejected_event_on_bus() -> Result<()> {
+        // given
+        init_tracing();
+        let mut shim = create_test_shim()?;
+        let mock_event_receiver = MockEventReceiver::new(shim.rx());
+        let (fs_spies, fs) = tracked(noop());
+        FileWatcher::new(shim.bus()).run(mock_event_receiver, fs);
+        let Location::FS(paths) = shim.test_location();
+        let reason = "Content of the file looks like PDF, not JPG.".to_string();
+
+        // when
+        shim.mk_docs_event(DocsEvent::Rejected {
+            path: paths[0].clone(),
+            reason: reason.clone(),
+        })?;
+
+        // then
+        assert!(shim.event_on_bus(&BusEvent::DocsRejected(shim.test_location(), reason))?);
+        assert!(fs_spies.rm_file_called());
+
+        Ok(())
+    }
+
     #[test]
     fn other_docs_event_is_ignored() -> Result<()> {
         // given
         init_tracing();
         let mut shim = create_test_shim()?;
         let mock_event_receiver = MockEventReceiver::new(shim.rx());
-        FileWatcher::new(shim.bus()).run(mock_event_receiver);
+        FileWatcher::new(shim.bus()).run(mock_event_receiver, noop());
 
         // when
         shim.mk_docs_event(DocsEvent::Other)?;
@@ -99,7 +211,7 @@ mod test {
         let watcher = FileWatcher::new(shim.bus());
 
         // when
-        watcher.run(erroneous_event_receiver); // error ignored here
+        watcher.run(erroneous_event_receiver, noop()); // error ignored here
 
         // then
         assert!(shim.no_events_on_bus());
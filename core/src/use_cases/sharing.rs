@@ -0,0 +1,266 @@
+//! Abstraction for sharing documents between users.
+//!
+//! The owner of a document grants other user a read access to it. Shared documents are returned
+//! in the searches of the grantee and can be downloaded by them.
+use crate::entities::file::{Filename, Thumbnailname};
+use crate::entities::user::User;
+use crate::result::{LinkErr, SharingErr};
+use crate::use_cases::links::Links;
+use crate::use_cases::state::{SearchResult, StateReader};
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Arc;
+use tracing::{debug, instrument, warn};
+
+pub type Sharing = Arc<dyn ShareStore>;
+
+/// Keeps the list of documents shared between users.
+pub trait ShareStore: Sync + Send {
+    /// Grants read access. Granting already granted access does nothing.
+    fn grant(&self, grant: Grant) -> Result<(), SharingErr>;
+    /// Revokes read access. Returns `false` if there was no such grant.
+    fn revoke(&self, grant: &Grant) -> Result<bool, SharingErr>;
+    /// Returns the documents shared by the `owner`.
+    fn granted_by(&self, owner: &User) -> Result<Vec<Grant>, SharingErr>;
+    /// Returns the documents shared with the `grantee`.
+    fn shared_with(&self, grantee: &User) -> Result<Vec<Grant>, SharingErr>;
+    /// Revokes all the grants given by or to the `user`.
+    fn forget(&self, user: &User) -> Result<(), SharingErr>;
+}
+
+/// Read access to a single document.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Grant {
+    pub owner: User,
+    pub grantee: User,
+    pub filename: Filename,
+}
+
+impl Grant {
+    pub fn new(owner: User, grantee: User, filename: Filename) -> Self {
+        Self {
+            owner,
+            grantee,
+            filename,
+        }
+    }
+}
+
+/// Decides which documents can be read by the user - their own and the ones shared with them.
+pub struct DocAccess {
+    sharing: Sharing,
+    state: StateReader,
+}
+
+impl DocAccess {
+    pub fn new(sharing: Sharing, state: StateReader) -> Self {
+        Self { sharing, state }
+    }
+
+    /// Searches documents of the user and documents shared with them. Shared documents are
+    /// marked with the email of their owner.
+    #[instrument(skip(self))]
+    pub fn search(&self, user: &User, q: &str) -> Result<SearchResult, SharingErr> {
+        let mut res = self.state.search(user.clone(), q.to_string())?;
+        for (owner, filenames) in self.shared_by_owner(user)? {
+            let shared = self
+                .state
+                .search_in(owner.clone(), q.to_string(), &filenames)?;
+            res.extend(shared.shared(&owner, &filenames));
+        }
+        Ok(res)
+    }
+
+    pub fn can_read_doc(
+        &self,
+        user: &User,
+        owner: &User,
+        filename: &Filename,
+    ) -> Result<bool, SharingErr> {
+        if user == owner {
+            return Ok(true);
+        }
+        let grant = Grant::new(owner.clone(), user.clone(), filename.clone());
+        Ok(self.sharing.shared_with(user)?.contains(&grant))
+    }
+
+    /// Thumbnail can be read when the document it was made of can be read.
+    pub fn can_read_thumbnail(
+        &self,
+        user: &User,
+        owner: &User,
+        thumbnail: &Thumbnailname,
+    ) -> Result<bool, SharingErr> {
+        if user == owner {
+            return Ok(true);
+        }
+        let Some(filenames) = self.shared_by_owner(user)?.remove(owner) else {
+            return Ok(false);
+        };
+        let shared = self
+            .state
+            .all_docs(owner.clone())?
+            .shared(owner, &filenames);
+        debug!(
+            "thumbnails shared by '{}': '{:?}'",
+            owner,
+            shared.thumbnails()
+        );
+        Ok(shared.thumbnails().contains(&thumbnail.to_string()))
+    }
+
+    fn shared_by_owner(&self, user: &User) -> Result<BTreeMap<User, HashSet<String>>, SharingErr> {
+        let mut owners: BTreeMap<User, HashSet<String>> = BTreeMap::new();
+        for grant in self.sharing.shared_with(user)? {
+            owners
+                .entry(grant.owner)
+                .or_default()
+                .insert(grant.filename.to_string());
+        }
+        Ok(owners)
+    }
+}
+
+/// Revokes the grants and the links to the documents which were removed or renamed, so they don't
+/// give access to other documents stored later under the same names.
+#[derive(Clone)]
+pub struct ShareRevoker {
+    sharing: Sharing,
+    links: Links,
+}
+
+impl ShareRevoker {
+    pub fn new(sharing: Sharing, links: Links) -> Self {
+        Self { sharing, links }
+    }
+
+    /// Failures are only logged, the document is gone either way.
+    #[instrument(skip(self))]
+    pub fn revoke(&self, owner: &User, filename: &Filename) {
+        if let Err(e) = self.revoke_grants(owner, filename) {
+            warn!("failed to revoke grants to '{}': '{}'", filename, e);
+        }
+        if let Err(e) = self.revoke_links(owner, filename) {
+            warn!("failed to revoke links to '{}': '{}'", filename, e);
+        }
+    }
+
+    fn revoke_grants(&self, owner: &User, filename: &Filename) -> Result<(), SharingErr> {
+        for grant in self.sharing.granted_by(owner)? {
+            if &grant.filename == filename {
+                debug!("revoking grant for '{}'", grant.grantee);
+                self.sharing.revoke(&grant)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn revoke_links(&self, owner: &User, filename: &Filename) -> Result<(), LinkErr> {
+        for link in self.links.links_of(owner)? {
+            if &link.filename == filename && !link.revoked {
+                debug!("revoking link '{}'", link.id);
+                self.links.revoke(owner, &link.id)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::testingtools::services::{sharing, state};
+    use crate::use_cases::state::SearchEntry;
+
+    use anyhow::Result;
+
+    fn grant(owner: &str, grantee: &str, filename: &str) -> Result<Grant> {
+        Ok(Grant::new(
+            User::new(owner),
+            User::new(grantee),
+            Filename::new(filename)?,
+        ))
+    }
+
+    fn entry(filename: &str, thumbnail: &str) -> SearchEntry {
+        SearchEntry::new((filename.into(), thumbnail.into()))
+    }
+
+    #[test]
+    fn search_returns_only_shared_documents_of_other_users() -> Result<()> {
+        // given
+        let sharing = sharing::stub(vec![grant("owner@email.com", "me@email.com", "a.pdf")?]);
+        let state = state::stub(vec![
+            (
+                User::new("me@email.com"),
+                vec![entry("mine.pdf", "mine.png")],
+            ),
+            (
+                User::new("owner@email.com"),
+                vec![entry("a.pdf", "a.png"), entry("b.pdf", "b.png")],
+            ),
+        ]);
+        let access = DocAccess::new(sharing, state);
+
+        // when
+        let res = access.search(&User::new("me@email.com"), "query")?;
+
+        // then
+        assert_eq!(
+            res,
+            vec![
+                entry("mine.pdf", "mine.png"),
+                entry("a.pdf", "a.png").shared_by(&User::new("owner@email.com")),
+            ]
+            .into()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn user_can_read_own_and_shared_documents_only() -> Result<()> {
+        // given
+        let sharing = sharing::stub(vec![grant("owner@email.com", "me@email.com", "a.pdf")?]);
+        let access = DocAccess::new(sharing, state::stub(Vec::new()));
+        let me = User::new("me@email.com");
+        let owner = User::new("owner@email.com");
+
+        // when
+        let own = access.can_read_doc(&me, &me, &Filename::new("mine.pdf")?)?;
+        let shared = access.can_read_doc(&me, &owner, &Filename::new("a.pdf")?)?;
+        let not_shared = access.can_read_doc(&me, &owner, &Filename::new("b.pdf")?)?;
+
+        // then
+        assert!(own);
+        assert!(shared);
+        assert!(!not_shared);
+
+        Ok(())
+    }
+
+    #[test]
+    fn thumbnail_is_readable_when_its_document_is_shared() -> Result<()> {
+        // given
+        let sharing = sharing::stub(vec![grant("owner@email.com", "me@email.com", "a.pdf")?]);
+        let state = state::stub(vec![(
+            User::new("owner@email.com"),
+            vec![entry("a.pdf", "a.png"), entry("b.pdf", "b.png")],
+        )]);
+        let access = DocAccess::new(sharing, state);
+        let me = User::new("me@email.com");
+        let owner = User::new("owner@email.com");
+
+        // when
+        let shared = access.can_read_thumbnail(&me, &owner, &Thumbnailname::new("a.png")?)?;
+        let not_shared = access.can_read_thumbnail(&me, &owner, &Thumbnailname::new("b.png")?)?;
+
+        // then
+        assert!(shared);
+        assert!(!not_shared);
+
+        Ok(())
+    }
+}
@@ -1,5 +1,6 @@
 //! Abstraction for indexing and searching documents.
 use crate::entities::document::DocDetails;
+use crate::entities::file::Filename;
 use crate::entities::location::Location;
 use crate::entities::user::User;
 use crate::result::{IndexerErr, SearchErr};
@@ -21,16 +22,30 @@ pub trait AppState: Send {
 pub trait AppStateReader: Sync + Send {
     /// Returns list of documents mathing passed query.
     fn search(&self, user: User, q: String) -> Result<SearchResult, SearchErr>;
+    /// Returns list of documents matching passed query, searching only the documents with given
+    /// `filenames`.
+    fn search_in(
+        &self,
+        user: User,
+        q: String,
+        filenames: &HashSet<String>,
+    ) -> Result<SearchResult, SearchErr>;
     /// Returns list of all indexed documents.
     fn all_docs(&self, user: User) -> Result<SearchResult, SearchErr>;
 }
 
 /// Allows to index documents.
 pub trait AppStateWriter: Sync + Send {
-    /// Indexes documents.
+    /// Indexes documents. Already indexed document with the same name is replaced.
     fn index(&self, docs_details: &[DocDetails]) -> Result<(), IndexerErr>;
 
     fn delete(&self, loc: &Location) -> Result<(), IndexerErr>;
+
+    /// Removes document of the `user` with given name, if it was indexed.
+    fn delete_doc(&self, user: &User, filename: &Filename) -> Result<(), IndexerErr>;
+
+    /// Closes the index of the `user`. Files of the index are removed separately.
+    fn remove_user(&self, user: &User) -> Result<(), IndexerErr>;
 }
 
 /// Holds list of basic document details.
@@ -39,6 +54,53 @@ pub struct SearchResult {
     entries: HashSet<SearchEntry>,
 }
 
+impl SearchResult {
+    /// Returns names of all the documents in the result.
+    pub fn filenames(&self) -> HashSet<String> {
+        self.entries.iter().map(|e| e.filename.clone()).collect()
+    }
+
+    /// Returns names of all the thumbnails in the result.
+    pub fn thumbnails(&self) -> HashSet<String> {
+        self.entries.iter().map(|e| e.thumbnail.clone()).collect()
+    }
+
+    /// Keeps only the documents with given `filenames`.
+    #[must_use]
+    pub fn only(self, filenames: &HashSet<String>) -> Self {
+        self.entries
+            .into_iter()
+            .filter(|e| filenames.contains(&e.filename))
+            .collect::<Vec<SearchEntry>>()
+            .into()
+    }
+
+    /// Keeps only the documents shared by the `owner` and marks them as shared.
+    #[must_use]
+    pub fn shared(self, owner: &User, filenames: &HashSet<String>) -> Self {
+        self.only(filenames)
+            .entries
+            .into_iter()
+            .map(|e| e.shared_by(owner))
+            .collect::<Vec<SearchEntry>>()
+            .into()
+    }
+
+    pub fn extend(&mut self, other: SearchResult) {
+        self.entries.extend(other.entries);
+    }
+
+    /// Forgets the matching pages, e.g. when all the documents are listed.
+    #[must_use]
+    pub fn without_pages(self) -> Self {
+        self.entries
+            .into_iter()
+            .map(|e| e.pages(Vec::new()))
+            .collect::<Vec<SearchEntry>>()
+            .into()
+    }
+}
+
 impl From<Vec<SearchEntry>> for SearchResult {
     fn from(entries: Vec<SearchEntry>) -> Self {
         Self {
@@ -48,10 +110,16 @@ impl From<Vec<SearchEntry>> for SearchResult {
 }
 
 /// Basic document details.
-#[derive(Debug, Serialize, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Serialize, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Clone)]
 pub struct SearchEntry {
     filename: String,
     thumbnail: String,
+    /// Numbers of the pages matching the query, in ascending order.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pages: Vec<u32>,
+    /// Email of the owner, when the document is shared with the user.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    shared_by: Option<String>,
 }
 
 impl SearchEntry {
@@ -59,6 +127,21 @@ impl SearchEntry {
         Self {
             filename,
             thumbnail,
+            pages: Vec::new(),
+            shared_by: None,
+        }
+    }
+
+    #[must_use]
+    pub fn pages(self, pages: Vec<u32>) -> Self {
+        Self { pages, ..self }
+    }
+
+    #[must_use]
+    pub fn shared_by(self, owner: &User) -> Self {
+        Self {
+            shared_by: Some(owner.email.clone()),
+            ..self
         }
     }
 }
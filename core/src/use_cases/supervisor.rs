@@ -0,0 +1,465 @@
+//! Owns the threads of all the services and manages their lifecycle.
+//!
+//! Every service runs its loop in a thread spawned via [`spawn_service`]. When the loop fails or
+//! panics, it's restarted, waiting longer after each failure in a row. A service which keeps
+//! failing is given up, so it's reported as dead. The [`Supervisor`] keeps handles of those
+//! threads and, on shutdown, stops the services one by one, in the order of the pipeline.
+use crate::use_cases::bus::{BusEvent, EventBus};
+
+use rayon::{ThreadPool, ThreadPoolBuildError, ThreadPoolBuilder};
+use std::fmt::Display;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use tracing::{debug, error, instrument, warn};
+
+pub type ServiceHandle = JoinHandle<()>;
+
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Spawns a thread running the `service_loop`.
+///
+/// The loop is expected to return `Ok(())` only when the service was asked to stop. In every other
+/// case - when it returns an error or panics - the failure is logged and the loop is started
+/// again, according to the [`RestartPolicy`].
+pub fn spawn_service<F, E>(name: &'static str, service_loop: F) -> ServiceHandle
+where
+    F: FnMut() -> Result<(), E> + Send + 'static,
+    E: Display,
+{
+    spawn_with_policy(name, RestartPolicy::default(), service_loop)
+}
+
+fn spawn_with_policy<F, E>(
+    name: &'static str,
+    policy: RestartPolicy,
+    mut service_loop: F,
+) -> ServiceHandle
+where
+    F: FnMut() -> Result<(), E> + Send + 'static,
+    E: Display,
+{
+    thread::Builder::new()
+        .name(name.into())
+        .spawn(move || {
+            let mut failures = 0;
+            loop {
+                let started = Instant::now();
+                match panic::catch_unwind(AssertUnwindSafe(&mut service_loop)) {
+                    Ok(Ok(())) => {
+                        debug!("service '{}' stopped", name);
+                        break;
+                    }
+                    Ok(Err(e)) => error!("service '{}' failed: '{}'", name, e),
+                    Err(_) => error!("service '{}' panicked", name),
+                }
+                if started.elapsed() >= policy.stable_run {
+                    failures = 0;
+                }
+                failures += 1;
+                if failures > policy.max_restarts {
+                    error!(
+                        "service '{}' failed {} times in a row, giving up",
+                        name, failures
+                    );
+                    break;
+                }
+                let delay = policy.delay(failures);
+                warn!("restarting service '{}' in {:?}", name, delay);
+                thread::sleep(delay);
+            }
+        })
+        .expect("failed to spawn service thread")
+}
+
+/// Tells how long to wait before the failed service is restarted, and when to give up.
+#[derive(Debug, Clone, Copy)]
+struct RestartPolicy {
+    /// Delay after the first failure, doubled after each next failure in a row.
+    delay: Duration,
+    max_delay: Duration,
+    /// Number of failures in a row after which the service is not restarted anymore.
+    max_restarts: u32,
+    /// Failure of the service which ran at least that long is not counted as in a row.
+    stable_run: Duration,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+            max_restarts: 10,
+            stable_run: Duration::from_secs(300),
+        }
+    }
+}
+
+impl RestartPolicy {
+    fn delay(&self, failures: u32) -> Duration {
+        let factor = 2_u32.saturating_pow(failures.saturating_sub(1));
+        self.delay.saturating_mul(factor).min(self.max_delay)
+    }
+}
+
+/// Keeps track of all running services.
+#[derive(Debug)]
+pub struct Supervisor {
+    bus: EventBus,
+    services: Mutex<Vec<Service>>,
+}
+
+impl Supervisor {
+    pub fn new(bus: EventBus) -> Self {
+        Self {
+            bus,
+            services: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Registers service which stops when [`BusEvent::Shutdown`] with its `name` appears on the
+    /// bus. Services are stopped in the order they were registered, so they should be registered
+    /// in the order of the pipeline.
+    pub fn supervise(&self, name: &'static str, handle: ServiceHandle) {
+        self.register(Service::new(name, handle, true));
+    }
+
+    /// Registers service which is not listening on the bus, so it can't be stopped gracefully.
+    ///
+    /// It's the case of the services fed by external sources, like the file watcher which is
+    /// blocked waiting for the filesystem events. They are left running until the process exits.
+    pub fn supervise_detached(&self, name: &'static str, handle: ServiceHandle) {
+        self.register(Service::new(name, handle, false));
+    }
+
+    /// Returns names of the registered services along with the information if they are alive.
+    pub fn status(&self) -> Vec<(&'static str, bool)> {
+        let services = self.services.lock().expect("poisoned mutex");
+        services
+            .iter()
+            .map(|s| (s.name, !s.handle.is_finished()))
+            .collect()
+    }
+
+    fn register(&self, service: Service) {
+        let mut services = self.services.lock().expect("poisoned mutex");
+        services.push(service);
+    }
+
+    /// Stops graceful services one by one, in the order they were registered. Each of them gets
+    /// [`BusEvent::Shutdown`] only after the previous one finished its in-flight work, so the
+    /// events it published are not lost.
+    #[instrument(skip(self))]
+    pub fn shutdown(&self) {
+        debug!("shutting down services");
+        let services = {
+            let mut services = self.services.lock().expect("poisoned mutex");
+            services.drain(..).collect::<Vec<_>>()
+        };
+        let publ = self.bus.publisher();
+        let deadline = Instant::now() + SHUTDOWN_TIMEOUT;
+        for service in services.into_iter().filter(|s| s.graceful) {
+            if let Err(e) = publ.send(BusEvent::Shutdown(service.name)) {
+                error!("failed to publish shutdown of '{}': '{}'", service.name, e);
+                continue;
+            }
+            service.wait_until(deadline);
+        }
+        debug!("all services stopped");
+    }
+}
+
+#[derive(Debug)]
+struct Service {
+    name: &'static str,
+    handle: ServiceHandle,
+    graceful: bool,
+}
+
+impl Service {
+    fn new(name: &'static str, handle: ServiceHandle, graceful: bool) -> Self {
+        Self {
+            name,
+            handle,
+            graceful,
+        }
+    }
+
+    fn wait_until(self, deadline: Instant) {
+        while !self.handle.is_finished() {
+            if Instant::now() > deadline {
+                warn!("service '{}' didn't stop in time", self.name);
+                return;
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+        if self.handle.join().is_err() {
+            error!("failed to join service '{}'", self.name);
+        }
+    }
+}
+
+/// Thread pool which knows how many jobs are still in progress.
+///
+/// It allows the service to wait for all the spawned jobs to finish before it stops.
+pub struct ServicePool {
+    tp: ThreadPool,
+    in_flight: Arc<InFlight>,
+}
+
+impl ServicePool {
+    pub fn new(num_threads: usize) -> Result<Self, ThreadPoolBuildError> {
+        Ok(Self {
+            tp: ThreadPoolBuilder::new().num_threads(num_threads).build()?,
+            in_flight: Arc::new(InFlight::default()),
+        })
+    }
+
+    pub fn spawn<F>(&self, job: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        let guard = JobGuard::new(self.in_flight.clone());
+        self.tp.spawn(move || {
+            let _guard = guard; // released even if the job panics
+            job();
+        });
+    }
+
+    /// Blocks until all the spawned jobs are finished.
+    pub fn drain(&self) {
+        let (lock, cvar) = (&self.in_flight.count, &self.in_flight.cvar);
+        let mut count = lock.lock().expect("poisoned mutex");
+        while *count > 0 {
+            count = cvar.wait(count).expect("poisoned mutex");
+        }
+    }
+}
+
+#[derive(Default)]
+struct InFlight {
+    count: Mutex<usize>,
+    cvar: Condvar,
+}
+
+struct JobGuard {
+    in_flight: Arc<InFlight>,
+}
+
+impl JobGuard {
+    fn new(in_flight: Arc<InFlight>) -> Self {
+        *in_flight.count.lock().expect("poisoned mutex") += 1;
+        Self { in_flight }
+    }
+}
+
+impl Drop for JobGuard {
+    fn drop(&mut self) {
+        let mut count = self.in_flight.count.lock().expect("poisoned mutex");
+        *count -= 1;
+        self.in_flight.cvar.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::configuration::factories::event_bus;
+    use crate::configuration::telemetry::init_tracing;
+    use crate::testingtools::unit::SubscriberExt;
+
+    use anyhow::{anyhow, Result};
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+    #[test]
+    fn failing_service_loop_is_restarted() -> Result<()> {
+        // given
+        init_tracing();
+        let runs = Arc::new(AtomicUsize::new(0));
+        let counter = runs.clone();
+
+        // when
+        let handle = spawn_service("test", move || -> Result<()> {
+            if counter.fetch_add(1, Ordering::SeqCst) < 2 {
+                return Err(anyhow!("failure"));
+            }
+            Ok(())
+        });
+        handle.join().unwrap();
+
+        // then
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn panicking_service_loop_is_restarted() -> Result<()> {
+        // given
+        init_tracing();
+        let panicked = Arc::new(AtomicBool::new(false));
+        let flag = panicked.clone();
+
+        // when
+        let handle = spawn_service("test", move || -> Result<()> {
+            if !flag.swap(true, Ordering::SeqCst) {
+                panic!("service panicked");
+            }
+            Ok(())
+        });
+
+        // then
+        assert!(handle.join().is_ok());
+        assert!(panicked.load(Ordering::SeqCst));
+
+        Ok(())
+    }
+
+    #[test]
+    fn restart_delay_grows_up_to_limit() {
+        // given
+        let policy = RestartPolicy::default();
+
+        // when
+        let delays = [1, 2, 3, 10, 40].map(|failures| policy.delay(failures));
+
+        // then
+        assert_eq!(delays, [1, 2, 4, 60, 60].map(Duration::from_secs));
+    }
+
+    #[test]
+    fn service_failing_too_many_times_in_row_is_given_up() -> Result<()> {
+        // given
+        init_tracing();
+        let runs = Arc::new(AtomicUsize::new(0));
+        let counter = runs.clone();
+        let policy = RestartPolicy {
+            delay: Duration::from_millis(10),
+            max_restarts: 3,
+            ..RestartPolicy::default()
+        };
+
+        // when
+        let handle = spawn_with_policy("test", policy, move || -> Result<()> {
+            counter.fetch_add(1, Ordering::SeqCst);
+            Err(anyhow!("failure"))
+        });
+        handle.join().unwrap();
+
+        // then
+        assert_eq!(runs.load(Ordering::SeqCst), 4);
+
+        Ok(())
+    }
+
+    #[test]
+    fn services_are_stopped_in_registration_order() -> Result<()> {
+        // given
+        init_tracing();
+        let bus = event_bus()?;
+        let sub = bus.subscriber();
+        let (first_sub, second_sub) = (bus.subscriber(), bus.subscriber());
+        let publ = bus.publisher();
+        let supervisor = Supervisor::new(bus);
+        let first = spawn_service("first", move || -> Result<()> {
+            while first_sub.recv()? != BusEvent::Shutdown("first") {}
+            // NOTE: in-flight work finished after the shutdown was requested
+            publ.send(BusEvent::PipelineFinished)?;
+            Ok(())
+        });
+        let second = spawn_service("second", move || -> Result<()> {
+            while second_sub.recv()? != BusEvent::Shutdown("second") {}
+            Ok(())
+        });
+        supervisor.supervise("first", first);
+        supervisor.supervise("second", second);
+
+        // when
+        supervisor.shutdown();
+
+        // then
+        let timeout = Duration::from_secs(2);
+        assert_eq!(sub.try_recv(timeout)?, BusEvent::Shutdown("first"));
+        assert_eq!(sub.try_recv(timeout)?, BusEvent::PipelineFinished);
+        assert_eq!(sub.try_recv(timeout)?, BusEvent::Shutdown("second"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn shutdown_waits_for_graceful_services() -> Result<()> {
+        // given
+        init_tracing();
+        let bus = event_bus()?;
+        let sub = bus.subscriber();
+        let stopped = Arc::new(AtomicBool::new(false));
+        let flag = stopped.clone();
+        let supervisor = Supervisor::new(bus);
+        let handle = spawn_service("test", move || -> Result<()> {
+            while sub.recv()? != BusEvent::Shutdown("test") {}
+            thread::sleep(Duration::from_secs(1));
+            flag.store(true, Ordering::SeqCst);
+            Ok(())
+        });
+        supervisor.supervise("test", handle);
+
+        // when
+        supervisor.shutdown();
+
+        // then
+        assert!(stopped.load(Ordering::SeqCst));
+
+        Ok(())
+    }
+
+    #[test]
+    fn status_reports_finished_services_as_dead() -> Result<()> {
+        // given
+        init_tracing();
+        let supervisor = Supervisor::new(event_bus()?);
+        let (tx, rx) = std::sync::mpsc::channel::<()>();
+        let running = spawn_service("running", move || -> Result<()> {
+            let _ = rx.recv(); // blocks until the sender is dropped
+            Ok(())
+        });
+        supervisor.supervise("running", running);
+        supervisor.supervise(
+            "finished",
+            spawn_service("finished", || -> Result<()> { Ok(()) }),
+        );
+        thread::sleep(Duration::from_millis(500));
+
+        // when
+        let status = supervisor.status();
+
+        // then
+        assert_eq!(status, vec![("running", true), ("finished", false)]);
+        drop(tx);
+
+        Ok(())
+    }
+
+    #[test]
+    fn drain_waits_for_all_jobs_in_service_pool() -> Result<()> {
+        // given
+        let pool = ServicePool::new(2)?;
+        let finished = Arc::new(AtomicUsize::new(0));
+        for _ in 0..4 {
+            let finished = finished.clone();
+            pool.spawn(move || {
+                thread::sleep(Duration::from_millis(500));
+                finished.fetch_add(1, Ordering::SeqCst);
+            });
+        }
+
+        // when
+        pool.drain();
+
+        // then
+        assert_eq!(finished.load(Ordering::SeqCst), 4);
+
+        Ok(())
+    }
+}
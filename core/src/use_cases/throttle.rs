@@ -0,0 +1,432 @@
+//! Limits of the rate of the requests.
+//!
+//! Each user and each IP address get their own token bucket for every limited endpoint. Request
+//! takes a token from the bucket, and the bucket is refilled continuously with the configured
+//! rate, up to the burst. Failed authentications are counted per IP address separately.
+//!
+//! The IP address is the address of the connection. The `X-Real-IP` header is used only for the
+//! requests coming from the configured trusted proxy, so clients can't change their address.
+use crate::entities::user::{Authenticated, User};
+use crate::result::RateLimitErr;
+use crate::use_cases::config::{Config, LimitConfig, RateLimitConfig};
+
+use rocket::request::{FromRequest, Outcome, Request};
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+
+/// Buckets of the clients which are full again are dropped above this number.
+const MAX_BUCKETS: usize = 10_000;
+const AUTH_FAILURES_WINDOW: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Endpoint {
+    Search,
+    Upload,
+    Download,
+}
+
+/// Marks the endpoint limited by the [`Throttle`] guard.
+pub trait Limited: Send + Sync + 'static {
+    const ENDPOINT: Endpoint;
+}
+
+pub struct Searches;
+pub struct Uploads;
+pub struct Downloads;
+
+impl Limited for Searches {
+    const ENDPOINT: Endpoint = Endpoint::Search;
+}
+
+impl Limited for Uploads {
+    const ENDPOINT: Endpoint = Endpoint::Upload;
+}
+
+impl Limited for Downloads {
+    const ENDPOINT: Endpoint = Endpoint::Download;
+}
+
+/// Seconds after which the rejected request can be sent again.
+///
+/// It's kept in the request's local cache, so it can be sent back to the client.
+#[derive(Debug, Default)]
+pub struct RetryAfter(pub Option<u64>);
+
+/// Request guard rejecting the request when the user or their IP address exceeded the limit of
+/// the endpoint `E`.
+///
+/// The user is taken from [`Authenticated`], so the guard has to be declared after the [`User`]
+/// guard. Only the IP address is limited otherwise.
+pub struct Throttle<E: Limited>(PhantomData<E>);
+
+#[rocket::async_trait]
+impl<'r, E: Limited> FromRequest<'r> for Throttle<E> {
+    type Error = RateLimitErr;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let Authenticated(user) = req.local_cache(|| Authenticated(None));
+        let res = req
+            .rocket()
+            .state::<RateLimiter>()
+            .ok_or(RateLimitErr::NotConfigured)
+            .and_then(|limiter| limiter.check(E::ENDPOINT, user.as_ref(), client_ip(req)));
+        match res {
+            Ok(()) => Outcome::Success(Self(PhantomData)),
+            Err(e) => {
+                req.local_cache(|| RetryAfter(e.retry_after()));
+                Outcome::Failure((e.status(), e))
+            }
+        }
+    }
+}
+
+/// Request guard with the IP address of the client, see [`client_ip`].
+pub struct ClientIp(pub Option<IpAddr>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ClientIp {
+    type Error = std::convert::Infallible;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(Self(client_ip(req)))
+    }
+}
+
+/// Address of the client. It's the address of the connection, unless the connection comes from
+/// the trusted proxy which passes the address of the client in the `X-Real-IP` header.
+pub fn client_ip(req: &Request<'_>) -> Option<IpAddr> {
+    let remote = req.remote().map(|addr| addr.ip());
+    let proxy = req
+        .rocket()
+        .state::<RateLimiter>()
+        .and_then(|limiter| limiter.cfg.trusted_proxy);
+    match (remote, proxy) {
+        (Some(remote), Some(proxy)) if remote == proxy => req.real_ip().or(Some(remote)),
+        _ => remote,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Client {
+    User(User),
+    Ip(IpAddr),
+}
+
+pub struct RateLimiter {
+    cfg: RateLimitConfig,
+    buckets: Mutex<HashMap<(Endpoint, Client), TokenBucket>>,
+    auth_failures: Mutex<HashMap<IpAddr, FailureWindow>>,
+}
+
+impl RateLimiter {
+    pub fn new(cfg: &Config) -> Self {
+        Self {
+            cfg: cfg.rate_limit.clone(),
+            buckets: Mutex::new(HashMap::new()),
+            auth_failures: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Takes a token from the buckets of the user and of the IP address.
+    pub fn check(
+        &self,
+        endpoint: Endpoint,
+        user: Option<&User>,
+        ip: Option<IpAddr>,
+    ) -> Result<(), RateLimitErr> {
+        self.check_at(endpoint, user, ip, Instant::now())
+    }
+
+    fn check_at(
+        &self,
+        endpoint: Endpoint,
+        user: Option<&User>,
+        ip: Option<IpAddr>,
+        now: Instant,
+    ) -> Result<(), RateLimitErr> {
+        let mut buckets = self.buckets.lock().expect("poisoned mutex");
+        if buckets.len() > MAX_BUCKETS {
+            buckets.retain(
+                |(endpoint, client), bucket| match self.rate(*endpoint, client) {
+                    Some(rate) => !bucket.is_full(rate, now),
+                    None => false,
+                },
+            );
+        }
+        // NOTE: IP address goes first, so flood of the requests with invalid credentials doesn't
+        // use up the tokens of the user
+        let clients = ip
+            .map(Client::Ip)
+            .into_iter()
+            .chain(user.cloned().map(Client::User));
+        for client in clients {
+            let Some(rate) = self.rate(endpoint, &client) else {
+                continue;
+            };
+            let bucket = buckets
+                .entry((endpoint, client.clone()))
+                .or_insert_with(|| TokenBucket::full(rate, now));
+            if let Err(wait) = bucket.take(rate, now) {
+                debug!("{:?} limit exceeded by {:?}", endpoint, client);
+                return Err(RateLimitErr::TooManyRequests(whole_secs(wait)));
+            }
+        }
+        Ok(())
+    }
+
+    fn rate(&self, endpoint: Endpoint, client: &Client) -> Option<Rate> {
+        let limit = match endpoint {
+            Endpoint::Search => &self.cfg.search,
+            Endpoint::Upload => &self.cfg.upload,
+            Endpoint::Download => &self.cfg.download,
+        };
+        Rate::new(limit, client)
+    }
+
+    /// Rejects the IP address which failed to authenticate too many times recently.
+    pub fn check_auth(&self, ip: Option<IpAddr>) -> Result<(), RateLimitErr> {
+        self.check_auth_at(ip, Instant::now())
+    }
+
+    fn check_auth_at(&self, ip: Option<IpAddr>, now: Instant) -> Result<(), RateLimitErr> {
+        let max = self.cfg.max_auth_failures_per_minute;
+        let Some(ip) = ip.filter(|_| max > 0) else {
+            return Ok(());
+        };
+        let failures = self.auth_failures.lock().expect("poisoned mutex");
+        match failures.get(&ip) {
+            Some(window) if window.is_open(now) && window.count >= max => Err(
+                RateLimitErr::TooManyAuthFailures(whole_secs(window.remaining(now))),
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    /// Counts the failed authentication. Logs the spike of the failures from the IP address.
+    pub fn auth_failed(&self, ip: Option<IpAddr>) {
+        self.auth_failed_at(ip, Instant::now());
+    }
+
+    fn auth_failed_at(&self, ip: Option<IpAddr>, now: Instant) {
+        let max = self.cfg.max_auth_failures_per_minute;
+        let Some(ip) = ip.filter(|_| max > 0) else {
+            return;
+        };
+        let mut failures = self.auth_failures.lock().expect("poisoned mutex");
+        if failures.len() > MAX_BUCKETS {
+            failures.retain(|_, window| window.is_open(now));
+        }
+        let window = failures
+            .entry(ip)
+            .or_insert_with(|| FailureWindow::new(now));
+        if !window.is_open(now) {
+            *window = FailureWindow::new(now);
+        }
+        window.count += 1;
+        if window.count == max {
+            warn!(
+                "{} failed authentications from '{}' within a minute, rejecting it for {}s",
+                window.count,
+                ip,
+                whole_secs(window.remaining(now))
+            );
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Rate {
+    per_sec: f64,
+    burst: f64,
+}
+
+impl Rate {
+    /// Returns `None` when the client is not limited.
+    fn new(limit: &LimitConfig, client: &Client) -> Option<Self> {
+        let per_minute = match client {
+            Client::User(_) => limit.user_per_minute,
+            Client::Ip(_) => limit.ip_per_minute,
+        };
+        if per_minute == 0 {
+            return None;
+        }
+        Some(Self {
+            per_sec: f64::from(per_minute) / 60.0,
+            burst: f64::from(limit.burst.max(1)),
+        })
+    }
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    fn full(rate: Rate, now: Instant) -> Self {
+        Self {
+            tokens: rate.burst,
+            updated: now,
+        }
+    }
+
+    fn tokens_at(&self, rate: Rate, now: Instant) -> f64 {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        (self.tokens + elapsed * rate.per_sec).min(rate.burst)
+    }
+
+    fn is_full(&self, rate: Rate, now: Instant) -> bool {
+        self.tokens_at(rate, now) >= rate.burst
+    }
+
+    /// Takes a token from the bucket. Returns the time after which the token is available
+    /// otherwise.
+    fn take(&mut self, rate: Rate, now: Instant) -> Result<(), Duration> {
+        self.tokens = self.tokens_at(rate, now);
+        self.updated = now;
+        if self.tokens < 1.0 {
+            return Err(Duration::from_secs_f64((1.0 - self.tokens) / rate.per_sec));
+        }
+        self.tokens -= 1.0;
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct FailureWindow {
+    started: Instant,
+    count: u32,
+}
+
+impl FailureWindow {
+    fn new(now: Instant) -> Self {
+        Self {
+            started: now,
+            count: 0,
+        }
+    }
+
+    fn is_open(&self, now: Instant) -> bool {
+        now.saturating_duration_since(self.started) < AUTH_FAILURES_WINDOW
+    }
+
+    fn remaining(&self, now: Instant) -> Duration {
+        AUTH_FAILURES_WINDOW.saturating_sub(now.saturating_duration_since(self.started))
+    }
+}
+
+/// Rounds up to whole seconds, which are used in the `Retry-After` header.
+fn whole_secs(duration: Duration) -> u64 {
+    let secs = duration.as_secs() + u64::from(duration.subsec_nanos() > 0);
+    secs.max(1)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use claim::{assert_matches, assert_ok};
+    use std::net::Ipv4Addr;
+
+    fn limiter(search: LimitConfig, max_auth_failures_per_minute: u32) -> RateLimiter {
+        let cfg = Config {
+            rate_limit: RateLimitConfig {
+                max_auth_failures_per_minute,
+                search,
+                ..RateLimitConfig::default()
+            },
+            ..Config::default()
+        };
+        RateLimiter::new(&cfg)
+    }
+
+    fn ip() -> Option<IpAddr> {
+        Some(IpAddr::V4(Ipv4Addr::LOCALHOST))
+    }
+
+    #[test]
+    fn requests_over_the_burst_are_rejected_until_bucket_is_refilled() {
+        // given
+        let limiter = limiter(LimitConfig::new(60, 0, 2), 0);
+        let user = User::new("me@email.com");
+        let now = Instant::now();
+
+        // when
+        let first = limiter.check_at(Endpoint::Search, Some(&user), None, now);
+        let second = limiter.check_at(Endpoint::Search, Some(&user), None, now);
+        let third = limiter.check_at(Endpoint::Search, Some(&user), None, now);
+        let refilled = limiter.check_at(
+            Endpoint::Search,
+            Some(&user),
+            None,
+            now + Duration::from_secs(1),
+        );
+
+        // then
+        assert_ok!(first);
+        assert_ok!(second);
+        assert_matches!(third, Err(RateLimitErr::TooManyRequests(1)));
+        assert_ok!(refilled);
+    }
+
+    #[test]
+    fn users_and_ip_addresses_have_separate_buckets() {
+        // given
+        let limiter = limiter(LimitConfig::new(1, 1, 1), 0);
+        let me = User::new("me@email.com");
+        let other = User::new("other@email.com");
+        let now = Instant::now();
+        assert_ok!(limiter.check_at(Endpoint::Search, Some(&me), None, now));
+
+        // when
+        let other_user = limiter.check_at(Endpoint::Search, Some(&other), None, now);
+        let other_endpoint = limiter.check_at(Endpoint::Upload, Some(&me), None, now);
+        let from_ip = limiter.check_at(Endpoint::Search, None, ip(), now);
+        let same_user = limiter.check_at(Endpoint::Search, Some(&me), None, now);
+
+        // then
+        assert_ok!(other_user);
+        assert_ok!(other_endpoint);
+        assert_ok!(from_ip);
+        assert_matches!(same_user, Err(RateLimitErr::TooManyRequests(60)));
+    }
+
+    #[test]
+    fn disabled_limit_is_never_exceeded() {
+        // given
+        let limiter = limiter(LimitConfig::new(0, 0, 1), 0);
+        let user = User::new("me@email.com");
+        let now = Instant::now();
+
+        // when
+        let results: Vec<_> = (0..10)
+            .map(|_| limiter.check_at(Endpoint::Search, Some(&user), ip(), now))
+            .collect();
+
+        // then
+        assert!(results.iter().all(Result::is_ok));
+    }
+
+    #[test]
+    fn ip_address_is_rejected_after_too_many_failed_authentications() {
+        // given
+        let limiter = limiter(LimitConfig::default(), 2);
+        let now = Instant::now();
+        limiter.auth_failed_at(ip(), now);
+        assert_ok!(limiter.check_auth_at(ip(), now));
+        limiter.auth_failed_at(ip(), now);
+
+        // when
+        let rejected = limiter.check_auth_at(ip(), now + Duration::from_secs(30));
+        let after_minute = limiter.check_auth_at(ip(), now + Duration::from_secs(60));
+
+        // then
+        assert_matches!(rejected, Err(RateLimitErr::TooManyAuthFailures(30)));
+        assert_ok!(after_minute);
+    }
+}
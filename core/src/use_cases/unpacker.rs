@@ -0,0 +1,26 @@
+//! Unpacking of the documents sent together, in ZIP archives or as attachments of emails.
+//!
+//! Each supported document is stored next to the archive and processed as a separate document.
+//! Names of the unpacked documents start with the name of the archive, so they can be told apart.
+use crate::entities::location::SafePathBuf;
+use crate::result::UnpackerErr;
+use crate::use_cases::quota::Allowance;
+
+use std::path::Path;
+use std::sync::Arc;
+
+pub type Unpacker = Arc<dyn DocsUnpacker>;
+
+pub trait DocsUnpacker: Sync + Send {
+    /// Writes supported members of the ZIP archive, or supported attachments of the email, into
+    /// `dir`. Returns paths of the written documents, empty when there is nothing to unpack.
+    ///
+    /// Nothing is written when the archive breaks any of the limits, or when the documents don't
+    /// fit in the `allowance` left to the user.
+    fn unpack(
+        &self,
+        archive: &SafePathBuf,
+        dir: &Path,
+        allowance: Allowance,
+    ) -> Result<Vec<SafePathBuf>, UnpackerErr>;
+}
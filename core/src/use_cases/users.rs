@@ -0,0 +1,334 @@
+//! Accounts of the users and their administration.
+//!
+//! Valid credentials are not enough to use the server - the account of the user has to be active
+//! too. Accounts are created on the first request of the user. They are active right away, unless
+//! [`UsersConfig::require_approval`] is enabled, in which case they wait for an administrator.
+//! Administrators can also approve or deny emails in advance, disable accounts and remove users
+//! together with all their data.
+//!
+//! [`UsersConfig::require_approval`]: crate::use_cases::config::UsersConfig::require_approval
+use crate::entities::user::User;
+use crate::result::UsersErr;
+use crate::use_cases::config::Config;
+use crate::use_cases::fs::Fs;
+use crate::use_cases::links::Links;
+use crate::use_cases::sharing::Sharing;
+use crate::use_cases::state::StateWriter;
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{debug, instrument};
+
+pub type Accounts = Arc<dyn AccountStore>;
+
+/// Keeps the accounts of the users.
+pub trait AccountStore: Sync + Send {
+    fn get(&self, user: &User) -> Result<Option<Account>, UsersErr>;
+    /// Returns the account of the user. The `account` is added first, when the user has none.
+    fn get_or_add(&self, account: Account) -> Result<Account, UsersErr>;
+    /// Adds the account, or replaces existing account of the same user.
+    fn put(&self, account: Account) -> Result<(), UsersErr>;
+    fn all(&self) -> Result<Vec<Account>, UsersErr>;
+    /// Removes the account. Returns `false` when there was no such account.
+    fn remove(&self, user: &User) -> Result<bool, UsersErr>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountStatus {
+    /// Waits for the approval of an administrator.
+    Pending,
+    Active,
+    Denied,
+    Disabled,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Account {
+    pub user: User,
+    pub status: AccountStatus,
+    /// Unix timestamp of the last change of the status, in seconds.
+    pub changed_at: u64,
+}
+
+impl Account {
+    pub fn new(user: User, status: AccountStatus) -> Self {
+        Self {
+            user,
+            status,
+            changed_at: now(),
+        }
+    }
+
+    fn check(&self) -> Result<(), UsersErr> {
+        let email = self.user.email.clone();
+        match self.status {
+            AccountStatus::Active => Ok(()),
+            AccountStatus::Pending => Err(UsersErr::NotApproved(email)),
+            AccountStatus::Denied => Err(UsersErr::Denied(email)),
+            AccountStatus::Disabled => Err(UsersErr::Disabled(email)),
+        }
+    }
+}
+
+/// Decides which users can use the server and which of them are administrators.
+///
+/// Administrators come from the configuration, so they can't be locked out by other
+/// administrators.
+#[derive(Clone)]
+pub struct UserDirectory {
+    accounts: Accounts,
+    admins: HashSet<User>,
+    require_approval: bool,
+}
+
+impl UserDirectory {
+    pub fn new(accounts: Accounts, cfg: &Config) -> Self {
+        Self {
+            accounts,
+            admins: cfg.users.admins.iter().map(User::new).collect(),
+            require_approval: cfg.users.require_approval,
+        }
+    }
+
+    pub fn is_admin(&self, user: &User) -> bool {
+        self.admins.contains(user)
+    }
+
+    /// Checks if the authenticated user can use the server. Creates the account of a new user.
+    #[instrument(skip(self))]
+    pub fn admit(&self, user: &User) -> Result<(), UsersErr> {
+        let admin = self.is_admin(user);
+        let status = if admin || !self.require_approval {
+            AccountStatus::Active
+        } else {
+            AccountStatus::Pending
+        };
+        let account = self
+            .accounts
+            .get_or_add(Account::new(user.clone(), status))?;
+        if admin {
+            return Ok(());
+        }
+        account.check()
+    }
+
+    /// Checks if the `user` can use the server, without creating their account. Used for the
+    /// actions made for the user when they are not logged in, e.g. opening their links or
+    /// ingesting files from their watched directory.
+    pub fn check_active(&self, user: &User) -> Result<(), UsersErr> {
+        if self.is_admin(user) {
+            return Ok(());
+        }
+        match self.accounts.get(user)? {
+            Some(account) => account.check(),
+            None if self.require_approval => Err(UsersErr::NotApproved(user.email.clone())),
+            None => Ok(()),
+        }
+    }
+
+    pub fn accounts(&self) -> Result<Vec<Account>, UsersErr> {
+        let mut accounts = self.accounts.all()?;
+        accounts.sort_by(|a, b| a.user.cmp(&b.user));
+        Ok(accounts)
+    }
+
+    /// Sets the status of the account. Account is created when the user has none yet, so emails
+    /// can be approved or denied before the users log in for the first time.
+    #[instrument(skip(self))]
+    pub fn set_status(&self, user: &User, status: AccountStatus) -> Result<Account, UsersErr> {
+        if self.is_admin(user) {
+            return Err(UsersErr::Admin(user.email.clone()));
+        }
+        let account = Account::new(user.clone(), status);
+        self.accounts.put(account.clone())?;
+        Ok(account)
+    }
+}
+
+/// Removes users together with their documents, thumbnails, index, shares and links.
+pub struct UserRemover {
+    directory: UserDirectory,
+    cfg: Config,
+    fs: Fs,
+    state: StateWriter,
+    sharing: Sharing,
+    links: Links,
+}
+
+impl UserRemover {
+    pub fn new(
+        directory: UserDirectory,
+        cfg: Config,
+        fs: Fs,
+        state: StateWriter,
+        sharing: Sharing,
+        links: Links,
+    ) -> Self {
+        Self {
+            directory,
+            cfg,
+            fs,
+            state,
+            sharing,
+            links,
+        }
+    }
+
+    /// Removes the user. Files of the user are removed all at once - when any of them can't be
+    /// removed, the user is left untouched.
+    ///
+    /// Removed user can log in again, like any new user. Deny their account to prevent that.
+    #[instrument(skip(self))]
+    pub fn remove(&self, user: &User) -> Result<(), UsersErr> {
+        if self.directory.is_admin(user) {
+            return Err(UsersErr::Admin(user.email.clone()));
+        }
+        let accounts = &self.directory.accounts;
+        let dirs = self.cfg.user_dirs(user);
+        let account = accounts.get(user)?;
+        if account.is_none() && !dirs.iter().any(|dir| dir.exists()) {
+            return Err(UsersErr::NotFound(user.email.clone()));
+        }
+        // NOTE: the user can't upload new documents while the old ones are removed
+        accounts.put(Account::new(user.clone(), AccountStatus::Disabled))?;
+        if let Err(e) = self.fs.rm_dirs(&dirs) {
+            match account {
+                Some(account) => accounts.put(account)?,
+                None => {
+                    accounts.remove(user)?;
+                }
+            }
+            return Err(e.into());
+        }
+        self.state.remove_user(user)?;
+        self.sharing.forget(user)?;
+        for link in self.links.links_of(user)? {
+            self.links.revoke(user, &link.id)?;
+        }
+        accounts.remove(user)?;
+        debug!("user '{}' removed", user);
+        Ok(())
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::testingtools::services::users::stub;
+    use crate::use_cases::config::UsersConfig;
+
+    use anyhow::Result;
+    use claim::{assert_matches, assert_ok};
+
+    fn directory(accounts: Vec<Account>, require_approval: bool) -> UserDirectory {
+        let cfg = Config {
+            users: UsersConfig {
+                admins: vec!["admin@email.com".into()],
+                require_approval,
+            },
+            ..Config::default()
+        };
+        UserDirectory::new(stub(accounts), &cfg)
+    }
+
+    #[test]
+    fn new_user_is_admitted_when_approval_is_not_required() -> Result<()> {
+        // given
+        let directory = directory(Vec::new(), false);
+        let user = User::new("me@email.com");
+
+        // when
+        let res = directory.admit(&user);
+
+        // then
+        assert_ok!(res);
+        assert_eq!(directory.accounts()?[0].status, AccountStatus::Active);
+
+        Ok(())
+    }
+
+    #[test]
+    fn new_user_waits_for_approval_when_it_is_required() -> Result<()> {
+        // given
+        let directory = directory(Vec::new(), true);
+        let user = User::new("me@email.com");
+
+        // when
+        let first = directory.admit(&user);
+        directory.set_status(&user, AccountStatus::Active)?;
+        let approved = directory.admit(&user);
+
+        // then
+        assert_matches!(first, Err(UsersErr::NotApproved(_)));
+        assert_ok!(approved);
+
+        Ok(())
+    }
+
+    #[test]
+    fn denied_and_disabled_users_are_not_admitted() {
+        // given
+        let denied = User::new("denied@email.com");
+        let disabled = User::new("disabled@email.com");
+        let directory = directory(
+            vec![
+                Account::new(denied.clone(), AccountStatus::Denied),
+                Account::new(disabled.clone(), AccountStatus::Disabled),
+            ],
+            false,
+        );
+
+        // when
+        let denied = directory.admit(&denied);
+        let disabled = directory.admit(&disabled);
+
+        // then
+        assert_matches!(denied, Err(UsersErr::Denied(_)));
+        assert_matches!(disabled, Err(UsersErr::Disabled(_)));
+    }
+
+    #[test]
+    fn account_is_not_created_when_checking_if_it_is_active() {
+        // given
+        let disabled = User::new("disabled@email.com");
+        let unknown = User::new("unknown@email.com");
+        let directory = directory(
+            vec![Account::new(disabled.clone(), AccountStatus::Disabled)],
+            true,
+        );
+
+        // when
+        let disabled = directory.check_active(&disabled);
+        let unknown = directory.check_active(&unknown);
+
+        // then
+        assert_matches!(disabled, Err(UsersErr::Disabled(_)));
+        assert_matches!(unknown, Err(UsersErr::NotApproved(_)));
+        assert_eq!(directory.accounts().map(|a| a.len()).ok(), Some(1));
+    }
+
+    #[test]
+    fn admin_is_always_admitted_and_can_not_be_managed() {
+        // given
+        let admin = User::new("admin@email.com");
+        let directory = directory(Vec::new(), true);
+
+        // when
+        let admitted = directory.admit(&admin);
+        let disabled = directory.set_status(&admin, AccountStatus::Disabled);
+
+        // then
+        assert_ok!(admitted);
+        assert_matches!(disabled, Err(UsersErr::Admin(_)));
+    }
+}